@@ -46,8 +46,15 @@ fn main() -> ExitCode {
         radar.product_description.height,
     );
 
-    println!("Station:      {}", radar.text_header.location);
-    println!("Product:      {} ({:?})", radar.text_header.aaa, radar.message_header.code);
+    let (station, awips) = match &radar.text_header {
+        Some(header) => (
+            header.location.as_str(),
+            header.awips.as_ref().map_or("", |awips| awips.aaa.as_str()),
+        ),
+        None => ("(no text header)", ""),
+    };
+    println!("Station:      {station}");
+    println!("Product:      {awips} ({:?})", radar.message_header.code);
     println!("Issued:       {}", radar.message_header.datetime);
     println!("Radar site:   {lat:.3}, {lon:.3} ({height} ft)");
     println!("VCP:          {}", radar.product_description.vcp);
@@ -56,14 +63,16 @@ fn main() -> ExitCode {
         Some(symbology) => {
             println!("Symbology:    {} layer(s)", symbology.layers.len());
             for (i, layer) in symbology.layers.iter().enumerate() {
-                match layer {
-                    SymPacketData::RadialDataAF1F(p) => {
-                        println!("  layer {i}: {} radials x {} bins (run-length encoded)", p.radials.len(), p.header.num_bins);
-                    }
-                    SymPacketData::DigitalRadialDataArray(p) => {
-                        println!("  layer {i}: {} radials x {} bins (digital)", p.radials.len(), p.header.num_bins);
+                for packet in layer {
+                    match packet {
+                        SymPacketData::RadialDataAF1F(p) => {
+                            println!("  layer {i}: {} radials x {} bins (run-length encoded)", p.radials.len(), p.header.num_bins);
+                        }
+                        SymPacketData::DigitalRadialDataArray(p) => {
+                            println!("  layer {i}: {} radials x {} bins (digital)", p.radials.len(), p.header.num_bins);
+                        }
+                        other => println!("  layer {i}: {other:?}"),
                     }
-                    other => println!("  layer {i}: {other:?}"),
                 }
             }
         }
@@ -0,0 +1,407 @@
+//! Assembling a new product from scratch, as opposed to editing a parsed one.
+//!
+//! [`ProductBuilder`] fills in the text header, message header and product
+//! description from a handful of declared values, and wraps the layers of
+//! packets given to it in a symbology block. Everything the encoder derives,
+//! the lengths and block offsets, is left for [`Radar::to_vec`] to compute.
+
+use chrono::{DateTime, Timelike, Utc};
+
+use crate::error_r::Error;
+use crate::level_scaling::LevelScaling;
+use crate::{
+    AwipsId, MessageCode, MessageHeader, ProductDescription, Radar, SymPacketData, SymbologyBlock,
+    SymbologyHeader, TextHeader,
+};
+
+/// Declares a product and builds it into a [`Radar`] or a complete file.
+///
+/// ```
+/// use radar::{DigitalRadialPacket, MessageCode, ProductBuilder, TextPacket};
+///
+/// let builder = ProductBuilder::new(MessageCode::DigitalHybridScanReflectivity, "KMKX")
+///     .with_position(42.968, -88.551, 1022)
+///     .with_linear_scaling(0.5, -32.0);
+/// let scaling = builder.level_scaling().unwrap();
+///
+/// let field = vec![vec![20.0_f32; 230]; 360];
+/// let bytes = builder
+///     .with_layer([
+///         DigitalRadialPacket::from_values(&field, &scaling).into(),
+///         TextPacket::new(0, 0, "TEST", Some(3)).into(),
+///     ])
+///     .to_vec()?;
+/// # Ok::<(), radar::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProductBuilder {
+    pub code: MessageCode,
+    /// Four letter ICAO identifier of the radar, e.g. `KMKX`.
+    pub site: String,
+    /// Latitude of the radar in degrees, + for north.
+    pub latitude: f64,
+    /// Longitude of the radar in degrees, + for east.
+    pub longitude: f64,
+    /// Height of the radar in feet above mean sea level.
+    pub height: i16,
+    /// Start of the volume scan the product is derived from.
+    pub volume_time: DateTime<Utc>,
+    /// When the product was generated, which is also the time of the message.
+    pub generation_time: DateTime<Utc>,
+    /// 0 = Maintenance, 1 = Clean Air, 2 = Precipitation.
+    pub operational_mode: i16,
+    /// Volume Coverage Pattern of the scan strategy.
+    pub vcp: i16,
+    /// Volume scan number, 1 to 80.
+    pub volume_scan: i16,
+    /// Elevation number within the volume scan, 0 for volume products.
+    pub elevation_num: i16,
+    /// Threshold halfwords 31-46, as written. See
+    /// [`ProductBuilder::with_linear_scaling`] for products that carry a scale
+    /// and offset there instead of a packed table.
+    pub thresholds: [i16; 16],
+    /// Scale and offset to encode into the threshold halfwords in place of
+    /// `thresholds`, as `value = level * scale + offset`.
+    pub linear_scaling: Option<(f32, f32)>,
+    /// Product-dependent halfwords (Table V numbering) to set, in order.
+    pub dependent_halfwords: Vec<(u8, i16)>,
+    pub version: u8,
+    /// WMO category digits, e.g. `53` for `SDUS53`.
+    pub wmo_category: usize,
+    /// First three characters of the AWIPS product id, e.g. `DHR`. The last
+    /// three come from `site`.
+    pub awips_category: String,
+    /// Source ID of the message header.
+    pub source: i16,
+    /// The symbology block's layers, each a list of packets.
+    pub layers: Vec<Vec<SymPacketData>>,
+}
+
+impl ProductBuilder {
+    /// A product of type `code` from `site`, generated now, with no layers.
+    ///
+    /// The position is 0, 0 until set, the version is the newest this crate
+    /// supports for `code`, and the AWIPS category is the placeholder `XXX`.
+    pub fn new(code: MessageCode, site: impl Into<String>) -> Self {
+        let now = Utc::now().with_nanosecond(0).unwrap_or_default();
+        ProductBuilder {
+            code,
+            site: site.into(),
+            latitude: 0.0,
+            longitude: 0.0,
+            height: 0,
+            volume_time: now,
+            generation_time: now,
+            operational_mode: 2,
+            vcp: 0,
+            volume_scan: 1,
+            elevation_num: 0,
+            thresholds: [0; 16],
+            linear_scaling: None,
+            dependent_halfwords: Vec::new(),
+            version: code.supported_version().unwrap_or(0),
+            wmo_category: 0,
+            awips_category: "XXX".into(),
+            source: 0,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Sets the radar's latitude and longitude in degrees and its height in
+    /// feet.
+    pub fn with_position(mut self, latitude: f64, longitude: f64, height: i16) -> Self {
+        self.latitude = latitude;
+        self.longitude = longitude;
+        self.height = height;
+        self
+    }
+
+    /// Sets the volume scan start time and the product generation time.
+    pub fn with_times(mut self, volume: DateTime<Utc>, generated: DateTime<Utc>) -> Self {
+        self.volume_time = volume;
+        self.generation_time = generated;
+        self
+    }
+
+    /// Sets the operational mode and Volume Coverage Pattern.
+    pub fn with_scan_strategy(mut self, operational_mode: i16, vcp: i16) -> Self {
+        self.operational_mode = operational_mode;
+        self.vcp = vcp;
+        self
+    }
+
+    /// Sets the elevation number within the volume scan.
+    pub fn with_elevation_number(mut self, elevation_num: i16) -> Self {
+        self.elevation_num = elevation_num;
+        self
+    }
+
+    /// Sets the threshold halfwords 31-46 verbatim.
+    pub fn with_thresholds(mut self, thresholds: [i16; 16]) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Encodes `value = level * scale + offset` into the threshold halfwords,
+    /// in whichever form Note 1 of Figure 3-6 gives for this product, so that
+    /// [`ProductDescription::level_scaling`] reads it back.
+    ///
+    /// Only products whose levels decode linearly accept this; building any
+    /// other returns [`Error::NoLinearScaling`].
+    pub fn with_linear_scaling(mut self, scale: f32, offset: f32) -> Self {
+        self.linear_scaling = Some((scale, offset));
+        self
+    }
+
+    /// Sets product-dependent halfword `n`, numbered as Table V numbers them.
+    pub fn with_dependent_halfword(mut self, n: u8, value: i16) -> Self {
+        self.dependent_halfwords.push((n, value));
+        self
+    }
+
+    /// Sets the WMO category and the first half of the AWIPS product id, as in
+    /// `SDUS53 KMKX 162134` / `DHRMKX`.
+    pub fn with_awips_id(mut self, wmo_category: usize, awips_category: impl Into<String>) -> Self {
+        self.wmo_category = wmo_category;
+        self.awips_category = awips_category.into();
+        self
+    }
+
+    /// Appends a symbology layer holding `packets`, in order.
+    pub fn with_layer(mut self, packets: impl IntoIterator<Item = SymPacketData>) -> Self {
+        self.layers.push(packets.into_iter().collect());
+        self
+    }
+
+    /// How the built product's levels will decode, for encoding a field with
+    /// the packet constructors such as [`crate::DigitalRadialPacket::from_values`].
+    ///
+    /// `None` where [`ProductDescription::level_scaling`] would be, or if the
+    /// declared linear scaling does not apply to this product.
+    pub fn level_scaling(&self) -> Option<LevelScaling> {
+        self.product_description().ok()?.level_scaling()
+    }
+
+    /// Builds the product.
+    ///
+    /// Lengths and block offsets are left at 0; [`Radar::to_vec`] computes
+    /// them as it writes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ProductType`] if `code` is not a product, and
+    /// [`Error::NoLinearScaling`] if a linear scaling was declared for a
+    /// product that does not use one.
+    pub fn build(&self) -> Result<Radar, Error> {
+        if !self.code.is_product() {
+            return Err(Error::ProductType(self.code));
+        }
+        let product_description = self.product_description()?;
+
+        let symbology = (!self.layers.is_empty()).then(|| SymbologyBlock {
+            header: SymbologyHeader {
+                divider: -1,
+                id: 1,
+                block_length: 0,
+                layers: self.layers.len() as i16,
+            },
+            layers: self.layers.clone(),
+        });
+
+        Ok(Radar {
+            text_header: Some(TextHeader {
+                sequence: None,
+                designator: "SDUS".into(),
+                xx: self.wmo_category,
+                location: self.site.clone(),
+                timestamp: self.generation_time.format("%d%H%M").to_string(),
+                indicator: None,
+                awips: Some(AwipsId {
+                    aaa: self.awips_category.clone(),
+                    bbb: self.site.chars().skip(1).collect(),
+                }),
+                line_end: "\r\r\n".into(),
+                raw: None,
+            }),
+            message_header: MessageHeader {
+                code: self.code,
+                datetime: self.generation_time,
+                length: 0,
+                source: self.source,
+                dest: 0,
+                // The header and description blocks, plus the symbology block.
+                nblocks: 2 + symbology.is_some() as i16,
+            },
+            product_description,
+            symbology,
+            graphic: None,
+            tabular: None,
+            validated: self.code.is_validated_product(),
+            layout: None,
+        })
+    }
+
+    /// Builds the product and serializes it as a complete Level 3 file.
+    ///
+    /// # Errors
+    ///
+    /// As [`ProductBuilder::build`] and [`Radar::to_vec`]; a site or AWIPS
+    /// category of the wrong length is an [`Error::FieldWidth`].
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        self.build()?.to_vec()
+    }
+
+    fn product_description(&self) -> Result<ProductDescription, Error> {
+        let mut threshold_data: Vec<u8> = self.thresholds.iter().flat_map(|t| t.to_be_bytes()).collect();
+        if let Some((scale, offset)) = self.linear_scaling {
+            let encoded = linear_threshold_bytes(self.code as i16, scale, offset)
+                .ok_or(Error::NoLinearScaling(self.code))?;
+            threshold_data[..encoded.len()].copy_from_slice(&encoded);
+        }
+
+        let (vol_scan_date, vol_scan_time) = date_and_time(self.volume_time);
+        let (product_date, product_time) = date_and_time(self.generation_time);
+        let mut description = ProductDescription {
+            divider: -1,
+            latitude: (self.latitude * 1000.0).round() as i32,
+            longitude: (self.longitude * 1000.0).round() as i32,
+            height: self.height,
+            product_code: self.code as i16,
+            operational_mode: self.operational_mode,
+            vcp: self.vcp,
+            sequence_num: 0,
+            vol_scan_num: self.volume_scan,
+            vol_scan_date,
+            vol_scan_time,
+            product_date,
+            product_time,
+            halfwords_27_28: vec![0; 4],
+            elevation_num: self.elevation_num,
+            halfwords_30: vec![0; 2],
+            threshold_data,
+            halfwords_47_53: vec![0; 14],
+            version: self.version,
+            spot_blank: 0,
+            offset_symbology: 0,
+            offset_graphic: 0,
+            offset_tabular: 0,
+        };
+        for (n, value) in &self.dependent_halfwords {
+            description.set_dependent_halfword(*n, *value);
+        }
+        Ok(description)
+    }
+}
+
+/// The 1-based day count and seconds after midnight the product description
+/// stores a time as.
+fn date_and_time(time: DateTime<Utc>) -> (i16, i32) {
+    let timestamp = time.timestamp();
+    ((timestamp.div_euclid(86_400) + 1) as i16, timestamp.rem_euclid(86_400) as i32)
+}
+
+/// The leading threshold halfwords that make
+/// [`ProductDescription::level_scaling`] decode `level * scale + offset`, for
+/// the products that decode linearly.
+fn linear_threshold_bytes(product_code: i16, scale: f32, offset: f32) -> Option<Vec<u8>> {
+    let pair = |offset: f32, scale: f32| {
+        [offset.round() as i16, scale.round() as i16]
+            .iter()
+            .flat_map(|h| h.to_be_bytes())
+            .collect()
+    };
+    match product_code {
+        32 => Some(pair(offset * 10.0, scale * 10.0)),
+        // Level 2 is the first data level, and the stored offset is its value.
+        94 | 99 | 182 | 186 => Some(pair((offset + 2.0 * scale) * 10.0, scale * 10.0)),
+        138 => Some(pair(offset * 100.0, scale * 100.0)),
+        // F = (N - OFFSET) / SCALE, in hundredths of an inch for the
+        // accumulation products.
+        159 | 161 | 163 | 167 | 168 | 170 | 172 | 173 | 174 | 175 | 176 => {
+            if scale == 0.0 {
+                return None;
+            }
+            let unit = if matches!(product_code, 170 | 172 | 173 | 174 | 175) { 0.01 } else { 1.0 };
+            let stored_scale = unit / scale;
+            let stored_offset = -offset / scale;
+            Some([stored_scale.to_be_bytes(), stored_offset.to_be_bytes()].concat())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn builder(code: MessageCode) -> ProductBuilder {
+        ProductBuilder::new(code, "KMKX")
+            .with_position(42.968, -88.551, 1022)
+            .with_times(
+                Utc.with_ymd_and_hms(2022, 9, 16, 21, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 9, 16, 21, 34, 12).unwrap(),
+            )
+    }
+
+    #[test]
+    fn declared_values_land_in_the_headers() {
+        let radar = builder(MessageCode::DigitalHybridScanReflectivity)
+            .with_awips_id(53, "DHR")
+            .with_scan_strategy(2, 212)
+            .build()
+            .unwrap();
+
+        let text_header = radar.text_header.unwrap();
+        assert_eq!(text_header.timestamp, "162134");
+        assert_eq!(text_header.awips.unwrap().bbb, "MKX");
+        let pd = &radar.product_description;
+        assert_eq!((pd.latitude, pd.longitude, pd.height), (42968, -88551, 1022));
+        assert_eq!((pd.product_code, pd.vcp), (32, 212));
+        assert_eq!((pd.vol_scan_date, pd.vol_scan_time), (19252, 77400));
+        assert_eq!(pd.product_time, 77652);
+        assert_eq!(radar.message_header.nblocks, 2);
+        assert!(radar.symbology.is_none());
+    }
+
+    /// Every linear product reads back the scale and offset it was given.
+    #[test]
+    fn linear_scaling_round_trips_through_level_scaling() {
+        for (code, scale, offset) in [
+            (MessageCode::DigitalHybridScanReflectivity, 0.5, -32.0),
+            (MessageCode::BaseVelocityDataArray, 0.5, -64.5),
+            (MessageCode::DigitalStormTotalPrecipitation, 0.01, 0.0),
+            (MessageCode::DigitalDifferentialReflectivity, 0.0625, -8.0),
+            (MessageCode::DigitalAccumulationArray, 0.01, 0.0),
+        ] {
+            let s = builder(code).with_linear_scaling(scale, offset).level_scaling().unwrap();
+            let (got_scale, got_offset) = s.linear_params().unwrap();
+            assert!((got_scale - scale).abs() < 1e-6, "{code:?} scale {got_scale}");
+            assert!((got_offset - offset).abs() < 1e-4, "{code:?} offset {got_offset}");
+        }
+    }
+
+    #[test]
+    fn linear_scaling_is_rejected_for_a_threshold_table_product() {
+        let err = builder(MessageCode::BaseReflectivity20)
+            .with_linear_scaling(1.0, 0.0)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::NoLinearScaling(MessageCode::BaseReflectivity20)));
+    }
+
+    #[test]
+    fn a_control_message_code_is_not_a_product() {
+        let err = builder(MessageCode::GeneralStatus).build().unwrap_err();
+        assert!(matches!(err, Error::ProductType(_)));
+    }
+
+    #[test]
+    fn a_bad_awips_category_fails_to_encode() {
+        let err = builder(MessageCode::BaseReflectivity20)
+            .with_awips_id(73, "N0")
+            .to_vec()
+            .unwrap_err();
+        assert!(matches!(err, Error::FieldWidth { .. }));
+    }
+}
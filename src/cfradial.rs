@@ -0,0 +1,320 @@
+//! CF/Radial 1.3 output of radial products, for Py-ART, wradlib and LROSE.
+//!
+//! The first radial data packet becomes a single sweep: one ray per radial,
+//! at the centre of the radial's azimuth span, and one gate per bin, with
+//! `range` at the centre of each bin. The decoded field is stored as `float`
+//! with a `_FillValue` wherever the bin holds no measurement, named after the
+//! product (`base_reflectivity` for product 19, and so on) and carrying the
+//! units of the product's [`crate::LevelScaling`].
+//!
+//! Products carry only the volume scan's start time, so every ray is given
+//! that time.
+
+use chrono::{DateTime, Utc};
+
+use crate::netcdf::{Dataset, Values, Variable};
+use crate::{Error, FieldCoordinates, MessageCode, Radar, Site};
+
+/// Written where a bin holds no measurement.
+const FILL_VALUE: f32 = -9999.0;
+
+/// Length of the `char` variables' string dimension.
+const STRING_LENGTH: usize = 32;
+
+impl Radar {
+    /// The first radial data packet as a CF/Radial NetCDF file (classic
+    /// format, 64-bit offsets).
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::data_field`], and [`Error::NoRadialData`] if the first data
+    /// packet is not a radial one.
+    pub fn to_cfradial(&self) -> Result<Vec<u8>, Error> {
+        let field = self.data_field()?;
+        let FieldCoordinates::Polar(geometry) = &field.coordinates else {
+            return Err(Error::NoRadialData);
+        };
+        let pd = &self.product_description;
+        let site = Site::from_description(pd);
+        let code = <MessageCode as num::FromPrimitive>::from_i16(pd.product_code).unwrap_or_default();
+        let start = volume_time(pd.vol_scan_date, pd.vol_scan_time);
+        let end = volume_time(pd.product_date, pd.product_time);
+        let iso = |time: DateTime<Utc>| time.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let station = self.text_header.as_ref().map_or("", |header| header.location.as_str());
+
+        let mut nc = Dataset::default();
+        let time = nc.dimension("time", field.rows);
+        let range = nc.dimension("range", field.cols);
+        let sweep = nc.dimension("sweep", 1);
+        let string_length = nc.dimension("string_length", STRING_LENGTH);
+
+        nc.attribute("Conventions", "CF/Radial");
+        nc.attribute("version", "1.3");
+        nc.attribute("title", format!("NEXRAD Level 3 {code}"));
+        nc.attribute("institution", "");
+        nc.attribute("references", "");
+        nc.attribute("source", "WSR-88D Level 3 product");
+        nc.attribute("history", "");
+        nc.attribute("comment", "");
+        nc.attribute("instrument_name", station);
+        nc.attribute("platform_type", "fixed");
+        nc.attribute("instrument_type", "radar");
+        nc.attribute("time_coverage_start", iso(start));
+        nc.attribute("time_coverage_end", iso(end));
+
+        let chars = |text: &str| Values::Char(padded(text));
+        nc.variable(
+            Variable::new("volume_number", &[], pd.vol_scan_num as i32).with("long_name", "data_volume_index_number"),
+        );
+        nc.variable(
+            Variable::new("time_coverage_start", &[string_length], chars(&iso(start)))
+                .with("long_name", "data_volume_start_time_utc"),
+        );
+        nc.variable(
+            Variable::new("time_coverage_end", &[string_length], chars(&iso(end)))
+                .with("long_name", "data_volume_end_time_utc"),
+        );
+        nc.variable(
+            Variable::new("latitude", &[], site.latitude)
+                .with("long_name", "latitude")
+                .with("units", "degrees_north")
+                .with("standard_name", "latitude"),
+        );
+        nc.variable(
+            Variable::new("longitude", &[], site.longitude)
+                .with("long_name", "longitude")
+                .with("units", "degrees_east")
+                .with("standard_name", "longitude"),
+        );
+        nc.variable(
+            Variable::new("altitude", &[], site.height)
+                .with("long_name", "altitude")
+                .with("units", "meters")
+                .with("positive", "up"),
+        );
+
+        let fixed_angle = geometry.radials.first().map_or(0.0, |span| span.elevation) as f32;
+        nc.variable(Variable::new("sweep_number", &[sweep], 0).with("long_name", "sweep_index_number_0_based"));
+        nc.variable(
+            Variable::new("sweep_mode", &[sweep, string_length], chars("azimuth_surveillance"))
+                .with("long_name", "scan_mode_for_sweep"),
+        );
+        nc.variable(
+            Variable::new("fixed_angle", &[sweep], fixed_angle)
+                .with("long_name", "ray_target_fixed_angle")
+                .with("units", "degrees"),
+        );
+        nc.variable(
+            Variable::new("sweep_start_ray_index", &[sweep], 0).with("long_name", "index_of_first_ray_in_sweep"),
+        );
+        nc.variable(
+            Variable::new("sweep_end_ray_index", &[sweep], field.rows as i32 - 1)
+                .with("long_name", "index_of_last_ray_in_sweep"),
+        );
+
+        nc.variable(
+            Variable::new("time", &[time], Values::Double(vec![0.0; field.rows]))
+                .with("standard_name", "time")
+                .with("long_name", "time_in_seconds_since_volume_start")
+                .with("units", format!("seconds since {}", iso(start)))
+                .with("calendar", "gregorian"),
+        );
+        let ranges = (0..field.cols).map(|bin| geometry.bin_range(bin) as f32).collect();
+        nc.variable(
+            Variable::new("range", &[range], Values::Float(ranges))
+                .with("standard_name", "projection_range_coordinate")
+                .with("long_name", "range_to_measurement_volume")
+                .with("units", "meters")
+                .with("spacing_is_constant", "true")
+                .with("meters_to_center_of_first_gate", geometry.first_bin_range as f32)
+                .with("meters_between_gates", geometry.bin_size as f32)
+                .with("axis", "radial_range_coordinate"),
+        );
+        let azimuths = geometry.radials.iter().map(|span| (span.azimuth + span.width / 2.0).rem_euclid(360.0) as f32);
+        nc.variable(
+            Variable::new("azimuth", &[time], Values::Float(azimuths.collect()))
+                .with("standard_name", "ray_azimuth_angle")
+                .with("long_name", "azimuth_angle_from_true_north")
+                .with("units", "degrees")
+                .with("axis", "radial_azimuth_coordinate"),
+        );
+        let elevations = geometry.radials.iter().map(|span| span.elevation as f32);
+        nc.variable(
+            Variable::new("elevation", &[time], Values::Float(elevations.collect()))
+                .with("standard_name", "ray_elevation_angle")
+                .with("long_name", "elevation_angle_from_horizontal_plane")
+                .with("units", "degrees")
+                .with("axis", "radial_elevation_coordinate"),
+        );
+
+        let values = field.values.iter().map(|v| if v.is_nan() { FILL_VALUE } else { *v }).collect();
+        nc.variable(
+            Variable::new(&field_name(code), &[time, range], Values::Float(values))
+                .with("long_name", code.to_string())
+                .with("units", field.units)
+                .with("_FillValue", FILL_VALUE)
+                .with("coordinates", "elevation azimuth range"),
+        );
+        Ok(nc.to_bytes())
+    }
+
+    /// Writes the product to `path` as [`Radar::to_cfradial`] encodes it.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::to_cfradial`], or [`Error::Io`] if the file cannot be
+    /// written.
+    pub fn write_cfradial<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path, self.to_cfradial()?)?;
+        Ok(())
+    }
+}
+
+/// `text` cut or padded with NULs to [`STRING_LENGTH`] bytes, cutting at a
+/// character boundary so the result stays valid UTF-8.
+fn padded(text: &str) -> String {
+    let mut end = text.len().min(STRING_LENGTH);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut padded = text[..end].to_string();
+    padded.extend(std::iter::repeat_n('\0', STRING_LENGTH - end));
+    padded
+}
+
+/// A product date (days since 1 January 1970, that day being 1) and time
+/// (seconds after midnight) as a UTC time.
+fn volume_time(date: i16, seconds: i32) -> DateTime<Utc> {
+    DateTime::from_timestamp((date as i64 - 1) * 86_400 + seconds as i64, 0).unwrap_or_default()
+}
+
+/// The field's variable name: the product's variant name in snake case,
+/// without the product number some variants carry.
+fn field_name(code: MessageCode) -> String {
+    let variant = format!("{code:?}");
+    let mut name = String::new();
+    for (i, c) in variant.trim_end_matches(|c: char| c.is_ascii_digit()).char_indices() {
+        if c.is_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netcdf::read;
+
+    const P20: &[u8] = include_bytes!("../data/sn_DS.p20-r_kmkx.last");
+
+    fn variable<'a>(dataset: &'a Dataset, name: &str) -> &'a Variable {
+        dataset.variables.iter().find(|v| v.name == name).expect("variable present")
+    }
+
+    fn attribute<'a>(attributes: &'a [(String, Values)], name: &str) -> &'a Values {
+        &attributes.iter().find(|(n, _)| n == name).expect("attribute present").1
+    }
+
+    #[test]
+    fn a_radial_product_becomes_one_sweep() {
+        let radar = Radar::from_vec(P20.to_vec()).unwrap().1;
+        let field = radar.data_field().unwrap();
+        let FieldCoordinates::Polar(geometry) = &field.coordinates else {
+            panic!("p20 is radial");
+        };
+        let dataset = read(&radar.to_cfradial().unwrap());
+
+        assert_eq!(dataset.dimensions[0], ("time".to_string(), geometry.radials.len()));
+        assert_eq!(dataset.dimensions[1], ("range".to_string(), geometry.num_bins));
+        assert_eq!(attribute(&dataset.attributes, "Conventions"), &Values::from("CF/Radial"));
+
+        let Values::Float(azimuths) = &variable(&dataset, "azimuth").values else {
+            panic!("azimuth should be float");
+        };
+        let first = &geometry.radials[0];
+        assert_eq!(azimuths[0], (first.azimuth + first.width / 2.0).rem_euclid(360.0) as f32);
+
+        let range = variable(&dataset, "range");
+        let Values::Float(ranges) = &range.values else {
+            panic!("range should be float");
+        };
+        assert_eq!(ranges[0], geometry.first_bin_range as f32);
+        assert_eq!(ranges[1] - ranges[0], geometry.bin_size as f32);
+
+        let reflectivity = variable(&dataset, "base_reflectivity");
+        assert_eq!(reflectivity.dimensions, vec![0, 1]);
+        assert_eq!(attribute(&reflectivity.attributes, "units"), &Values::from("dBZ"));
+        let Values::Float(values) = &reflectivity.values else {
+            panic!("field should be float");
+        };
+        for (written, decoded) in values.iter().zip(&field.values) {
+            if decoded.is_nan() {
+                assert_eq!(*written, FILL_VALUE);
+            } else {
+                assert_eq!(written, decoded);
+            }
+        }
+    }
+
+    /// Checks the start of the header and the field's data against the
+    /// CDF-2 layout itself, without going through [`read`], so that a
+    /// mistake shared by the writer and the reader still shows.
+    #[test]
+    fn the_file_follows_the_cdf2_layout() {
+        let radar = Radar::from_vec(P20.to_vec()).unwrap().1;
+        let field = radar.data_field().unwrap();
+        let bytes = radar.to_cfradial().unwrap();
+
+        let mut expected = b"CDF\x02".to_vec();
+        let word = |out: &mut Vec<u8>, word: u32| out.extend(word.to_be_bytes());
+        let name = |out: &mut Vec<u8>, name: &[u8]| {
+            out.extend((name.len() as u32).to_be_bytes());
+            out.extend(name);
+            out.resize(out.len().next_multiple_of(4), 0);
+        };
+        word(&mut expected, 0); // numrecs
+        word(&mut expected, 0x0A); // NC_DIMENSION
+        word(&mut expected, 4);
+        let dimensions = [(&b"time"[..], field.rows), (b"range", field.cols), (b"sweep", 1), (b"string_length", 32)];
+        for (dimension, len) in dimensions {
+            name(&mut expected, dimension);
+            word(&mut expected, len as u32);
+        }
+        word(&mut expected, 0x0C); // NC_ATTRIBUTE
+        word(&mut expected, 13);
+        name(&mut expected, b"Conventions");
+        word(&mut expected, 2); // NC_CHAR
+        name(&mut expected, b"CF/Radial");
+        assert_eq!(bytes[..expected.len()], expected[..]);
+
+        // The field is the last variable, so its float32s end the file.
+        let data = &bytes[bytes.len() - field.values.len() * 4..];
+        for (chunk, value) in data.chunks_exact(4).zip(&field.values) {
+            let expected = if value.is_nan() { FILL_VALUE } else { *value };
+            assert_eq!(chunk, expected.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn strings_are_cut_at_a_character_boundary() {
+        assert_eq!(padded("KMKX"), format!("KMKX{}", "\0".repeat(28)));
+        // 31 ASCII bytes then a two-byte character: the character is dropped.
+        let text = format!("{}é", "a".repeat(31));
+        assert_eq!(padded(&text), format!("{}\0", "a".repeat(31)));
+        assert_eq!(padded(&"é".repeat(20)).len(), STRING_LENGTH);
+    }
+
+    #[test]
+    fn products_are_named_in_snake_case() {
+        assert_eq!(field_name(MessageCode::BaseReflectivity19), "base_reflectivity");
+        assert_eq!(field_name(MessageCode::VelocityAzimuthDisplay), "velocity_azimuth_display");
+    }
+
+    #[test]
+    fn volume_times_count_days_from_one() {
+        let time = volume_time(1, 3600);
+        assert_eq!(time.to_rfc3339(), "1970-01-01T01:00:00+00:00");
+    }
+}
@@ -10,8 +10,9 @@ use serde::{Deserialize, Serialize};
 /// TABLE III MESSAGE CODES FOR PRODUCTS
 ///
 /// Every variant here corresponds to a real product code number from the
-/// ICD, but only the ones listed in [`MessageCode::is_supported_product`]
-/// can actually be parsed past the message header by this crate.
+/// ICD. Any Table III product (see [`MessageCode::is_product`]) is parsed;
+/// the ones listed in [`MessageCode::is_validated_product`] are the ones this
+/// crate's decoding has been checked against.
 #[derive(Serialize, Deserialize, Display, FromStr, PartialEq, Debug, Copy, Clone, Default)]
 #[derive(FromPrimitive, ToPrimitive)]
 pub enum MessageCode {
@@ -239,19 +240,25 @@ pub enum MessageCode {
 }
 
 impl MessageCode {
+    /// Whether this is a Table III product code at all, as opposed to one of
+    /// the Table II control messages (codes below 16) or a code this crate
+    /// does not recognise. `Radar::parse` rejects anything that is not.
+    pub fn is_product(&self) -> bool {
+        !matches!(self, MessageCode::Spare) && *self as u32 >= 16
+    }
+
     /// Whether this crate's [`crate::message_header`]/[`crate::product_description`]
-    /// parsing has been validated against this product type. `Radar::parse`
-    /// rejects any file whose product code is not in this list, even if the
-    /// message header and product description blocks themselves would
-    /// otherwise parse fine.
-    pub fn is_supported_product(&self) -> bool {
+    /// parsing has been validated against this product type. Products outside
+    /// this list are still parsed, but with a warning, and are reported
+    /// through `Radar::validated`.
+    pub fn is_validated_product(&self) -> bool {
         let supported_products: [u32;33] = [19, 20, 25, 27, 28, 30, 32, 34, 56, 78, 79, 80, 94, 99, 134, 135, 138, 159, 161, 163, 165, 169, 170, 171, 172, 173, 174, 175, 176, 177, 181, 182, 186];
         supported_products.contains(&(*self as u32))
     }
 
     /// The highest product version number this crate knows how to interpret
-    /// for this product type, or `None` if the product isn't supported at
-    /// all (see [`MessageCode::is_supported_product`]). A file whose
+    /// for this product type, or `None` if the product hasn't been validated
+    /// (see [`MessageCode::is_validated_product`]). A file whose
     /// `ProductDescription::version` exceeds this is rejected rather than
     /// parsed, since newer versions may have changed the product-dependent
     /// fields in ways this crate doesn't account for.
@@ -294,6 +301,19 @@ impl MessageCode {
         }
     }
 
+    /// The range extent of one bin of this radial product, in metres, from
+    /// the resolution column of Table III. `None` for products that are not
+    /// radial, or whose resolution is not listed here; the radial packets'
+    /// own range scale factor is all there is to go on for those.
+    pub fn bin_size(&self) -> Option<f64> {
+        match *self as u32 {
+            25 | 28 | 99 | 153..=155 | 159 | 161 | 163 | 165 | 170 | 172..=177 => Some(250.0),
+            19 | 27 | 30 | 32 | 56 | 94 | 134 | 135 => Some(1000.0),
+            20 | 78..=80 | 138 | 169 | 171 => Some(2000.0),
+            _ => None,
+        }
+    }
+
     /// Whether [`Self::color_code`] has a real color table for this product
     /// type, as opposed to falling back to a neutral gray for every level.
     pub fn has_color_table(&self) -> bool {
@@ -882,7 +902,7 @@ pub enum PacketCode {
 mod tests {
     use super::*;
 
-    /// Every code number that `is_supported_product`/`supported_version`
+    /// Every code number that `is_validated_product`/`supported_version`
     /// claim to support must round-trip through `FromPrimitive` back to the
     /// exact same code. This is a regression test for a bug where nine of
     /// these codes (including the very common Base Reflectivity/Velocity
@@ -891,7 +911,7 @@ mod tests {
     /// and every file using one of those product types was rejected as
     /// "unsupported."
     #[test]
-    fn every_declared_supported_code_has_a_matching_enum_variant() {
+    fn every_declared_validated_code_has_a_matching_enum_variant() {
         let supported_products: [u32; 33] = [
             19, 20, 25, 27, 28, 30, 32, 34, 56, 78, 79, 80, 94, 99, 134, 135, 138, 159, 161, 163,
             165, 169, 170, 171, 172, 173, 174, 175, 176, 177, 181, 182, 186,
@@ -906,8 +926,8 @@ mod tests {
                 parsed as u32
             );
             assert!(
-                parsed.is_supported_product(),
-                "code {code} round-tripped to {parsed:?} but is_supported_product() is false for it"
+                parsed.is_validated_product(),
+                "code {code} round-tripped to {parsed:?} but is_validated_product() is false for it"
             );
         }
     }
@@ -916,7 +936,28 @@ mod tests {
     fn unknown_codes_fall_back_to_spare_and_are_rejected() {
         let parsed = <MessageCode as num::FromPrimitive>::from_u32(65535).unwrap_or_default();
         assert_eq!(parsed, MessageCode::Spare);
-        assert!(!parsed.is_supported_product());
+        assert!(!parsed.is_product());
+        assert!(!parsed.is_validated_product());
+    }
+
+    /// Products outside the validated list are still products, and so are
+    /// parsed; control messages are not.
+    #[test]
+    fn unvalidated_products_are_still_products() {
+        for code in [
+            MessageCode::CompositeReflectivity0p54Nmi,
+            MessageCode::EchoTops,
+            MessageCode::VerticallyIntegratedLiquid,
+            MessageCode::StormTrackingInformation,
+            MessageCode::HailIndex,
+            MessageCode::TornadoVortexSignature,
+            MessageCode::MesocycloneDetection,
+        ] {
+            assert!(code.is_product(), "{code:?} is a Table III product");
+            assert!(!code.is_validated_product(), "{code:?} has not been validated");
+        }
+        assert!(!MessageCode::GeneralStatus.is_product());
+        assert!(!MessageCode::BiasTableMessage.is_product());
     }
 
     /// Every product the Product Specification defines a color table for
@@ -0,0 +1,413 @@
+//! One dense representation of every packet that carries data levels.
+//!
+//! Each data packet stores its levels its own way: nibble runs in `AF1F` and
+//! `BA0F`/`BA07` packets, byte runs in packet 17, one byte per bin or cell in
+//! packets 16 and 33, and XDR integers in generic Radial Components. A
+//! [`DataField`] unpacks any of them into rows of raw levels, decodes each
+//! through the product's [`LevelScaling`], and records which cells hold a
+//! measurement and which a flag.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, GenericComponent, LevelDecoding, LevelScaling, LevelThreshold, ProductDescription, RadialGeometry, Radar, Run, Site,
+    SymPacketData, ThresholdCode,
+};
+
+/// What a cell of a [`DataField`] holds.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CellMask {
+    /// A measurement; the cell's value is finite.
+    #[default]
+    Valid,
+    /// Below the product's threshold.
+    BelowThreshold,
+    /// Range folded.
+    RangeFolded,
+    /// Any other flag, a level that decodes to nothing, or a cell past the
+    /// end of a short row.
+    NoData,
+}
+
+/// Decodes a raw level, returning its value (`NaN` unless valid) and what it
+/// means.
+///
+/// Levels that do not fit a byte only occur in generic products, whose
+/// scaling, where this crate knows it, is linear.
+pub(crate) fn decode_level(level: i32, scaling: Option<&LevelScaling>) -> (f32, CellMask) {
+    let Some(scaling) = scaling else {
+        return match level {
+            0 => (f32::NAN, CellMask::BelowThreshold),
+            _ => (level as f32, CellMask::Valid),
+        };
+    };
+    let Ok(byte) = u8::try_from(level) else {
+        return match scaling.linear_params() {
+            Some((scale, offset)) if level >= 0 => (level as f32 * scale + offset, CellMask::Valid),
+            _ => (f32::NAN, CellMask::NoData),
+        };
+    };
+    if scaling.is_range_folded(byte) {
+        return (f32::NAN, CellMask::RangeFolded);
+    }
+    if let Some(value) = scaling.value(byte) {
+        return (value, CellMask::Valid);
+    }
+    let mask = match scaling.decoding() {
+        LevelDecoding::Thresholds(_) => match scaling.threshold(byte) {
+            Some(LevelThreshold::Code(ThresholdCode::BelowThreshold | ThresholdCode::Blank)) => {
+                CellMask::BelowThreshold
+            }
+            _ => CellMask::NoData,
+        },
+        _ if byte < scaling.first_data_level => CellMask::BelowThreshold,
+        _ => CellMask::NoData,
+    };
+    (f32::NAN, mask)
+}
+
+/// Where the cells of a [`DataField`] are.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum FieldCoordinates {
+    /// Rows are radials and columns are range bins.
+    Polar(RadialGeometry),
+    /// Rows run north to south and columns west to east, on the display's
+    /// I/J screen coordinates: 1/4 km units centred on the radar, I
+    /// increasing east and J south.
+    Raster {
+        /// I of the west edge of the first column.
+        i_start: f64,
+        /// J of the north edge of the first row.
+        j_start: f64,
+        /// Width of a column, in I units.
+        x_scale: f64,
+        /// Height of a row, in J units.
+        y_scale: f64,
+    },
+    /// Rows and columns of the 1/4 LFM grid that packets 17 and 18 are laid
+    /// out on, which is not centred on the radar.
+    LfmGrid,
+}
+
+/// Nominal side of a 1/4 LFM grid box, in metres, where the polar
+/// stereographic grid is true at 60°N.
+const LFM_BOX: f64 = 4762.5;
+
+/// Side of a 1/4 LFM grid box at `site`, in metres: the polar stereographic
+/// grid's boxes shrink away from 60°N as `(1 + sin 60°) / (1 + sin φ)`.
+pub(crate) fn lfm_box_size(site: &Site) -> f64 {
+    LFM_BOX * (1.0 + 60f64.to_radians().sin()) / (1.0 + site.latitude.to_radians().sin())
+}
+
+/// The data levels of one packet, as a dense `rows` by `cols` array.
+///
+/// `levels`, `values` and `mask` are row-major. A value is finite exactly
+/// where the mask is [`CellMask::Valid`]. Rows shorter than the longest are
+/// padded with level 0 cells masked as [`CellMask::NoData`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DataField {
+    pub coordinates: FieldCoordinates,
+    pub rows: usize,
+    pub cols: usize,
+    pub levels: Vec<i32>,
+    pub values: Vec<f32>,
+    pub mask: Vec<CellMask>,
+    /// The units values are in, from the product's level scaling; empty when
+    /// they are levels or the units are unknown.
+    pub units: &'static str,
+}
+
+impl DataField {
+    /// The data levels of `packet`, from the product described by `pd`, or
+    /// `None` for a packet that carries none. For a generic data packet this
+    /// is its first Radial Component.
+    pub fn from_packet(packet: &SymPacketData, pd: &ProductDescription) -> Option<DataField> {
+        let (coordinates, rows): (FieldCoordinates, Vec<Vec<i32>>) = match packet {
+            SymPacketData::RadialDataAF1F(packet) => (
+                FieldCoordinates::Polar(RadialGeometry::from_radial_packet(packet, pd)),
+                packet
+                    .radials
+                    .iter()
+                    .map(|radial| {
+                        radial
+                            .data
+                            .iter()
+                            .flat_map(|run| std::iter::repeat_n(run.color as i32, run.run as usize))
+                            .collect()
+                    })
+                    .collect(),
+            ),
+            SymPacketData::DigitalRadialDataArray(packet) => (
+                FieldCoordinates::Polar(RadialGeometry::from_digital_radial_packet(packet, pd)),
+                packet
+                    .radials
+                    .iter()
+                    .map(|radial| radial.data.iter().map(|level| *level as i32).collect())
+                    .collect(),
+            ),
+            SymPacketData::RasterData(packet) => {
+                let header = &packet.header;
+                (
+                    FieldCoordinates::Raster {
+                        i_start: header.i_start as f64,
+                        j_start: header.j_start as f64,
+                        x_scale: header.x_scale_int as f64,
+                        y_scale: header.y_scale_int as f64,
+                    },
+                    packet.rows.iter().map(|row| expand_runs(row)).collect(),
+                )
+            }
+            SymPacketData::DigitalPrecipitationDataArray(packet)
+            | SymPacketData::PrecipitationRateDataArray(packet) => (
+                FieldCoordinates::LfmGrid,
+                packet.rows.iter().map(|row| expand_runs(row)).collect(),
+            ),
+            SymPacketData::DigitalRasterDataArray(packet) => {
+                let header = &packet.header;
+                (
+                    FieldCoordinates::Raster {
+                        i_start: header.i_start as f64,
+                        j_start: header.j_start as f64,
+                        x_scale: header.j_scale as f64,
+                        y_scale: header.i_scale as f64,
+                    },
+                    packet
+                        .rows
+                        .iter()
+                        .map(|row| row.iter().map(|level| *level as i32).collect())
+                        .collect(),
+                )
+            }
+            SymPacketData::GenericData(packet) => {
+                let components = match (&packet.product_description, &packet.external_description) {
+                    (Some(description), _) => &description.components,
+                    (None, Some(description)) => &description.components,
+                    (None, None) => return None,
+                };
+                let component = components.iter().find_map(|component| match component {
+                    GenericComponent::Radial(radial) => Some(radial),
+                    _ => None,
+                })?;
+                (
+                    FieldCoordinates::Polar(RadialGeometry::from_radial_component(component, pd)),
+                    component.radials.iter().map(|radial| radial.data.clone()).collect(),
+                )
+            }
+            _ => return None,
+        };
+
+        let scaling = pd.level_scaling();
+        let cols = match &coordinates {
+            FieldCoordinates::Polar(geometry) => geometry.num_bins,
+            _ => 0,
+        }
+        .max(rows.iter().map(Vec::len).max().unwrap_or(0));
+
+        let mut field = DataField {
+            coordinates,
+            rows: rows.len(),
+            cols,
+            levels: Vec::with_capacity(rows.len() * cols),
+            values: Vec::with_capacity(rows.len() * cols),
+            mask: Vec::with_capacity(rows.len() * cols),
+            units: scaling.as_ref().map_or("", |scaling| scaling.units),
+        };
+        for row in &rows {
+            for col in 0..cols {
+                let (level, (value, mask)) = match row.get(col) {
+                    Some(level) => (*level, decode_level(*level, scaling.as_ref())),
+                    None => (0, (f32::NAN, CellMask::NoData)),
+                };
+                field.levels.push(level);
+                field.values.push(value);
+                field.mask.push(mask);
+            }
+        }
+        Some(field)
+    }
+
+    fn index(&self, row: usize, col: usize) -> Option<usize> {
+        (row < self.rows && col < self.cols).then_some(row * self.cols + col)
+    }
+
+    /// The raw level at `row`, `col`.
+    pub fn level(&self, row: usize, col: usize) -> Option<i32> {
+        self.levels.get(self.index(row, col)?).copied()
+    }
+
+    /// The decoded value at `row`, `col`, if it holds a measurement.
+    pub fn value(&self, row: usize, col: usize) -> Option<f32> {
+        let index = self.index(row, col)?;
+        (self.mask[index] == CellMask::Valid).then(|| self.values[index])
+    }
+
+    /// What the cell at `row`, `col` holds.
+    pub fn mask(&self, row: usize, col: usize) -> Option<CellMask> {
+        self.mask.get(self.index(row, col)?).copied()
+    }
+}
+
+fn expand_runs(runs: &[Run]) -> Vec<i32> {
+    runs.iter()
+        .flat_map(|run| std::iter::repeat_n(run.level as i32, run.run as usize))
+        .collect()
+}
+
+impl Radar {
+    /// The data levels of the first packet, in layer order, that carries
+    /// any: radial, raster, precipitation array, or a generic product's
+    /// Radial Component.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::NoSymbologyData`] if there is no symbology block, and
+    /// [`Error::NoDataPacket`] if no layer holds a data packet.
+    pub fn data_field(&self) -> Result<DataField, Error> {
+        let symbology = self.symbology.as_ref().ok_or(Error::NoSymbologyData)?;
+        symbology
+            .layers
+            .iter()
+            .flatten()
+            .find_map(|packet| DataField::from_packet(packet, &self.product_description))
+            .ok_or(Error::NoDataPacket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DigitalRasterHeader, DigitalRasterPacket, GenericDataHeader, GenericDataPacket, GenericProductDescription,
+        GenericRadial, PrecipArrayHeader, PrecipArrayPacket, RadialComponent, RasterPacket, RasterPacketHeader,
+    };
+
+    fn load(bytes: &[u8]) -> Radar {
+        Radar::from_vec(bytes.to_vec()).unwrap().1
+    }
+
+    #[test]
+    fn undecodable_levels_are_masked_by_meaning() {
+        assert_eq!(decode_level(7, None), (7.0, CellMask::Valid));
+        assert_eq!(decode_level(0, None).1, CellMask::BelowThreshold);
+
+        let radar = load(include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"));
+        let scaling = radar.product_description.level_scaling().unwrap();
+        assert_eq!(decode_level(0, Some(&scaling)).1, CellMask::BelowThreshold);
+        assert_eq!(decode_level(1, Some(&scaling)).1, CellMask::RangeFolded);
+        assert_eq!(decode_level(100, Some(&scaling)), (scaling.value(100).unwrap(), CellMask::Valid));
+        assert_eq!(decode_level(-1, Some(&scaling)).1, CellMask::NoData);
+    }
+
+    #[test]
+    fn a_digital_radial_product_is_a_polar_field() {
+        let radar = load(include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"));
+        let field = radar.data_field().unwrap();
+        assert_eq!((field.rows, field.cols), (360, 1200));
+        assert_eq!(field.units, "kt");
+        let FieldCoordinates::Polar(geometry) = &field.coordinates else {
+            panic!("expected polar coordinates");
+        };
+        assert_eq!(geometry.bin_size, 250.0);
+
+        let SymPacketData::DigitalRadialDataArray(packet) = &radar.symbology.as_ref().unwrap().layers[0][0] else {
+            panic!("expected a digital radial packet");
+        };
+        for (row, col) in [(0, 0), (45, 300), (359, 1199)] {
+            assert_eq!(field.level(row, col), Some(packet.radials[row].data[col] as i32));
+        }
+        for (value, mask) in field.values.iter().zip(&field.mask) {
+            assert_eq!(value.is_finite(), *mask == CellMask::Valid);
+        }
+    }
+
+    #[test]
+    fn radial_runs_expand_to_bins() {
+        let radar = load(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let field = radar.data_field().unwrap();
+        assert_eq!((field.rows, field.cols), (360, 230));
+        let sweep = radar.sweep().unwrap();
+        for row in 0..field.rows {
+            for col in 0..field.cols {
+                assert_eq!(field.level(row, col), Some(sweep.levels[row][col] as i32));
+            }
+        }
+    }
+
+    #[test]
+    fn raster_rows_are_unpacked_with_their_origin_and_scale() {
+        let run = |run, level| Run { run, level };
+        let packet = SymPacketData::RasterData(RasterPacket {
+            header: RasterPacketHeader {
+                i_start: -928,
+                j_start: -928,
+                x_scale_int: 4,
+                y_scale_int: 4,
+                num_rows: 2,
+                ..Default::default()
+            },
+            rows: vec![vec![run(2, 0), run(1, 5)], vec![run(1, 3)]],
+        });
+        let pd = ProductDescription::default();
+        let field = DataField::from_packet(&packet, &pd).unwrap();
+        assert_eq!((field.rows, field.cols), (2, 3));
+        assert_eq!(field.levels, vec![0, 0, 5, 3, 0, 0]);
+        assert_eq!(field.value(0, 2), Some(5.0));
+        assert_eq!(field.mask(0, 0), Some(CellMask::BelowThreshold));
+        assert_eq!(field.mask(1, 1), Some(CellMask::NoData));
+        assert_eq!(field.mask(2, 0), None);
+        assert_eq!(
+            field.coordinates,
+            FieldCoordinates::Raster { i_start: -928.0, j_start: -928.0, x_scale: 4.0, y_scale: 4.0 }
+        );
+
+        let digital = SymPacketData::DigitalRasterDataArray(DigitalRasterPacket {
+            header: DigitalRasterHeader { i_scale: 2, j_scale: 3, num_cells: 2, num_rows: 1, ..Default::default() },
+            rows: vec![vec![9, 0]],
+        });
+        let field = DataField::from_packet(&digital, &pd).unwrap();
+        assert_eq!(field.levels, vec![9, 0]);
+        assert!(matches!(field.coordinates, FieldCoordinates::Raster { x_scale: 3.0, y_scale: 2.0, .. }));
+
+        let precip = SymPacketData::DigitalPrecipitationDataArray(PrecipArrayPacket {
+            header: PrecipArrayHeader { num_boxes: 2, num_rows: 1, ..Default::default() },
+            rows: vec![vec![run(2, 40)]],
+        });
+        let field = DataField::from_packet(&precip, &pd).unwrap();
+        assert_eq!((field.coordinates, field.levels), (FieldCoordinates::LfmGrid, vec![40, 40]));
+    }
+
+    #[test]
+    fn a_generic_radial_component_is_a_polar_field() {
+        let component = RadialComponent {
+            bin_size: 250.0,
+            first_bin_range: 125.0,
+            radials: vec![
+                GenericRadial { azimuth: 0.0, width: 1.0, num_bins: 3, data: vec![0, 300, 2], ..Default::default() },
+                GenericRadial { azimuth: 1.0, width: 1.0, num_bins: 2, data: vec![1, 2], ..Default::default() },
+            ],
+            ..Default::default()
+        };
+        let packet = SymPacketData::GenericData(Box::new(GenericDataPacket {
+            header: GenericDataHeader::default(),
+            product_description: Some(GenericProductDescription {
+                components: vec![GenericComponent::Radial(component)],
+                ..Default::default()
+            }),
+            external_description: None,
+            raw: Vec::new(),
+        }));
+        let field = DataField::from_packet(&packet, &ProductDescription::default()).unwrap();
+        assert_eq!((field.rows, field.cols), (2, 3));
+        assert_eq!(field.levels, vec![0, 300, 2, 1, 2, 0]);
+        assert_eq!(field.value(0, 1), Some(300.0));
+        assert_eq!(field.mask(1, 2), Some(CellMask::NoData));
+    }
+
+    #[test]
+    fn a_product_without_data_packets_has_no_field() {
+        let mut radar = load(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        radar.symbology.as_mut().unwrap().layers[0].clear();
+        assert!(matches!(radar.data_field(), Err(Error::NoDataPacket)));
+        assert!(DataField::from_packet(&crate::TextPacket::new(0, 0, "A", None).into(), &radar.product_description).is_none());
+    }
+}
@@ -0,0 +1,1157 @@
+//! Typed decoding of the product-dependent halfwords of the Product
+//! Description Block: halfwords 27-28, 30 and 47-53, whose meaning Table V of
+//! the ICD defines separately for each product.
+//!
+//! Scale factors follow the resolution of the range Table V gives for each
+//! field: an elevation angle of "-1.0 to +45.0" degrees is stored in tenths, a
+//! mean-field bias of "0.01 to 99.99" in hundredths, and so on. Fields whose
+//! scaling the table leaves open are returned as stored.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::product_description::ProductDescription;
+
+/// Halfword 51 of the products that can be compressed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionMethod {
+    /// 0: the symbology block and following blocks are stored as is.
+    None,
+    /// 1: everything after the Product Description Block is bzip2 compressed.
+    Bzip2,
+    /// Any other value, which Table V does not define.
+    Unknown(i16),
+}
+
+impl CompressionMethod {
+    fn from_halfword(raw: i16) -> Self {
+        match raw {
+            0 => CompressionMethod::None,
+            1 => CompressionMethod::Bzip2,
+            other => CompressionMethod::Unknown(other),
+        }
+    }
+}
+
+/// Halfwords 51-53 of the products that can be compressed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    pub method: CompressionMethod,
+    /// Size in bytes of the product once decompressed (halfwords 52-53).
+    pub uncompressed_size: u32,
+}
+
+/// The scan type in bits 0-4 of the delta time halfword.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SupplementalScan {
+    /// 0: a regular elevation scan.
+    NonSupplemental,
+    /// 1: a Supplemental Adaptive Intra-Volume Low-Level Scan.
+    Sails,
+    /// 2: a Mid-Volume Rescan of Low-Level Elevations.
+    Mrle,
+    /// Any other value, which Table V does not define.
+    Unknown(u8),
+}
+
+/// Halfword 50 of the elevation-based products: "Delta Time / Supplemental
+/// Scan".
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeltaTime {
+    /// Bits 5-15, seconds (0 to 800).
+    pub seconds: u16,
+    /// Bits 0-4.
+    pub scan: SupplementalScan,
+}
+
+impl DeltaTime {
+    fn from_halfword(raw: i16) -> Self {
+        let raw = raw as u16;
+        let scan = match (raw & 0x1F) as u8 {
+            0 => SupplementalScan::NonSupplemental,
+            1 => SupplementalScan::Sails,
+            2 => SupplementalScan::Mrle,
+            other => SupplementalScan::Unknown(other),
+        };
+        DeltaTime {
+            seconds: raw >> 5,
+            scan,
+        }
+    }
+}
+
+/// A Julian date and time-of-day pair, as Table V stores the start and end of
+/// accumulations.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JulianTime {
+    /// Days since 1 January 1970, where 1 = 1 January 1970.
+    pub date: i16,
+    /// Minutes after midnight GMT.
+    pub minutes: i16,
+}
+
+impl JulianTime {
+    /// The date and time as UTC, or `None` for a date of zero (unset).
+    pub fn datetime(&self) -> Option<DateTime<Utc>> {
+        if self.date <= 0 {
+            return None;
+        }
+        DateTime::from_timestamp((self.date as i64 - 1) * 86_400 + self.minutes as i64 * 60, 0)
+    }
+}
+
+/// The product-dependent halfwords of a Product Description Block, decoded
+/// per Table V.
+///
+/// Products that share a layout share a variant; optional fields are the ones
+/// only some of those products define.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ProductDependentParams {
+    /// 19, 20, 94, 153, 181 and 186.
+    BaseReflectivity {
+        /// Degrees.
+        elevation_angle: f32,
+        /// dBZ.
+        max_reflectivity: i16,
+        delta_time: Option<DeltaTime>,
+        compression: Option<Compression>,
+    },
+    /// 25, 27, 93, 99, 154 and 182.
+    BaseVelocity {
+        /// Degrees.
+        elevation_angle: f32,
+        /// Knots.
+        max_negative_velocity: i16,
+        /// Knots.
+        max_positive_velocity: i16,
+        delta_time: Option<DeltaTime>,
+        /// Product 93 only: 1 or 2.
+        velocity_precision: Option<i16>,
+        compression: Option<Compression>,
+    },
+    /// 28, 30 and 155.
+    SpectrumWidth {
+        /// Degrees.
+        elevation_angle: f32,
+        /// Knots.
+        max_spectrum_width: i16,
+        delta_time: Option<DeltaTime>,
+        compression: Option<Compression>,
+    },
+    /// 132, 133 and 165: only the elevation they were generated from.
+    Elevation {
+        /// Degrees.
+        elevation_angle: f32,
+        delta_time: DeltaTime,
+        compression: Option<Compression>,
+    },
+    /// 113, Power Removed Control / Clutter Filter Control.
+    ClutterFilterControl {
+        rpg_cut_number: i16,
+        cmd_generated: bool,
+        /// Degrees.
+        elevation_angle: f32,
+        /// Minutes after midnight.
+        map_time: i16,
+        /// Julian date.
+        map_date: i16,
+        compression: Compression,
+    },
+    /// 37, 38, 97 and 98.
+    CompositeReflectivity {
+        /// Degrees, or 0 when AVSET did not terminate the volume.
+        avset_termination_angle: f32,
+        /// dBZ.
+        max_reflectivity: i16,
+        /// dB.
+        calibration_constant: f32,
+    },
+    /// 65, 66, 67 and 90.
+    LayerCompositeReflectivity {
+        /// Degrees, or 0 when AVSET did not terminate the volume.
+        avset_termination_angle: f32,
+        /// dBZ.
+        max_reflectivity: i16,
+        /// Thousands of feet.
+        layer_bottom: i16,
+        /// Thousands of feet.
+        layer_top: i16,
+        /// dB.
+        calibration_constant: f32,
+    },
+    /// 137, User Selectable Layer Composite Reflectivity.
+    UserSelectableLayer {
+        /// Thousands of feet, as requested.
+        requested_bottom: i16,
+        /// Thousands of feet, as requested.
+        requested_top: i16,
+        /// dBZ.
+        max_reflectivity: i16,
+        /// Thousands of feet, adjusted to correct request errors.
+        actual_bottom: i16,
+        /// Thousands of feet, adjusted to correct request errors.
+        actual_top: i16,
+    },
+    /// 50 and 51, the cross sections.
+    CrossSection {
+        /// Degrees.
+        azimuth_1: f32,
+        /// Nautical miles.
+        range_1: f32,
+        /// Degrees.
+        azimuth_2: f32,
+        /// Nautical miles.
+        range_2: f32,
+        /// dB, product 50 only.
+        calibration_constant: Option<f32>,
+    },
+    /// 32, Digital Hybrid Scan Reflectivity.
+    DigitalHybridScan {
+        /// dBZ.
+        max_reflectivity: i16,
+        /// Julian date of the scan.
+        scan_date: i16,
+        /// Average time of the hybrid scan, minutes after midnight.
+        scan_time: i16,
+        compression: Compression,
+    },
+    /// 193 and 195, the DQA-edited digital reflectivity products.
+    EditedReflectivity {
+        /// Degrees.
+        elevation_angle: f32,
+        /// dBZ.
+        max_reflectivity: i16,
+        edited_radials: i16,
+        /// 0, 1 or 3.
+        avset_status: i16,
+        /// Product 193 only: 0 or 1.
+        chaff_detection: Option<i16>,
+        compression: Compression,
+    },
+    /// 41, Echo Tops.
+    EchoTops {
+        /// Degrees, or 0 when AVSET did not terminate the volume.
+        avset_termination_angle: f32,
+        /// Thousands of feet.
+        max_echo_top: i16,
+    },
+    /// 135, Enhanced Echo Tops.
+    EnhancedEchoTops {
+        /// Degrees, or 0 when AVSET did not terminate the volume.
+        avset_termination_angle: f32,
+        /// Thousands of feet.
+        max_echo_top: i16,
+        edited_radials: i16,
+        /// dBZ.
+        reflectivity_threshold: i16,
+        spurious_points_removed: i16,
+        compression: Compression,
+    },
+    /// 57, Vertically Integrated Liquid.
+    Vil {
+        /// Degrees, or 0 when AVSET did not terminate the volume.
+        avset_termination_angle: f32,
+        /// kg/m².
+        max_vil: i16,
+    },
+    /// 134, High Resolution VIL.
+    DigitalVil {
+        /// Degrees, or 0 when AVSET did not terminate the volume.
+        avset_termination_angle: f32,
+        /// Digital VIL level, 0 to 254.
+        max_vil: i16,
+        edited_radials: i16,
+        compression: Compression,
+    },
+    /// 178 (icing hazard) and 179 (hail hazard) layers.
+    HazardTop {
+        /// Degrees, or 0 when AVSET did not terminate the volume.
+        avset_termination_angle: f32,
+        /// Thousands of feet.
+        max_top: i16,
+        /// Product 179 only: 0 or 1.
+        hsda_status: Option<i16>,
+        compression: Compression,
+    },
+    /// 56, Storm Relative Mean Radial Velocity.
+    StormRelativeVelocity {
+        /// Degrees.
+        elevation_angle: f32,
+        /// Knots.
+        max_negative_velocity: i16,
+        /// Knots.
+        max_positive_velocity: i16,
+        motion_source: i16,
+        /// Knots.
+        average_storm_speed: f32,
+        /// Degrees.
+        average_storm_direction: f32,
+    },
+    /// 48, VAD Wind Profile.
+    VadWindProfile {
+        /// Knots.
+        max_speed: i16,
+        /// Degrees.
+        max_speed_direction: i16,
+        /// Feet.
+        max_speed_altitude: i32,
+    },
+    /// 84, Velocity Azimuth Display.
+    VelocityAzimuthDisplay {
+        /// Thousands of feet.
+        altitude: i16,
+        /// Knots.
+        speed: i16,
+        /// Degrees.
+        direction: i16,
+        /// Degrees.
+        elevation_angle: f32,
+        /// Nautical miles.
+        slant_range: f32,
+        /// Knots.
+        rms_error: i16,
+    },
+    /// 58, Storm Tracking Information.
+    StormTracking { total_storms: i16 },
+    /// 61 and 143, the tornado vortex signature products.
+    TornadoVortexSignature {
+        /// Product 143 only, degrees.
+        elevation_angle: Option<f32>,
+        total_tvs: i16,
+        total_etvs: i16,
+        /// Product 143 only.
+        delta_time: Option<DeltaTime>,
+    },
+    /// 141, Mesocyclone Detection.
+    Mesocyclone {
+        /// Adaptation data, dBZ.
+        min_reflectivity_threshold: i16,
+        /// Adaptation data.
+        overlap_display_filter: bool,
+        /// Adaptation data, 1 to 5.
+        min_display_filter_rank: i16,
+    },
+    /// 149, Digital Mesocyclone Detection.
+    DigitalMesocyclone {
+        /// Adaptation data, dBZ.
+        min_reflectivity_threshold: i16,
+        /// Degrees.
+        elevation_angle: f32,
+        delta_time: DeltaTime,
+        compression: Compression,
+    },
+    /// 140 (gust front) and 196 (microburst), which report only counts.
+    Detections {
+        /// Product 196 only.
+        half_degree_scan_count: Option<i16>,
+        detection_count: i16,
+    },
+    /// 75, Free Text Message.
+    FreeText { rpg_id: i16 },
+    /// 78, 79 and 80, the legacy rainfall accumulations.
+    RainfallAccumulation {
+        /// Inches.
+        max_rainfall: f32,
+        /// Product 80 only.
+        begin: Option<JulianTime>,
+        end: JulianTime,
+        mean_field_bias: f32,
+        sample_size: f32,
+    },
+    /// 31, User Selectable Storm Total Precipitation.
+    UserSelectableAccumulation {
+        /// Hours.
+        end_hour: i16,
+        /// Hours.
+        time_span: i16,
+        null_product: bool,
+        /// Inches.
+        max_rainfall: f32,
+        begin: JulianTime,
+        end: JulianTime,
+        mean_field_bias: f32,
+        sample_size: f32,
+    },
+    /// 81, Hourly Digital Precipitation Array.
+    HourlyDigitalPrecipitation {
+        /// dBA, as stored.
+        max_rainfall: i16,
+        mean_field_bias: f32,
+        sample_size: f32,
+        end: JulianTime,
+    },
+    /// 138, Digital Storm Total Precipitation.
+    DigitalStormTotal {
+        begin: JulianTime,
+        mean_field_bias: f32,
+        /// Inches.
+        max_rainfall: f32,
+        end: JulianTime,
+        sample_size: f32,
+        compression: Compression,
+    },
+    /// 169-175, the dual-polarisation accumulation and difference products.
+    DualPolAccumulation {
+        /// 172 and 175: start of the accumulation.
+        begin: Option<JulianTime>,
+        /// 170: minimum time in the hourly period, minutes.
+        threshold_time: Option<i16>,
+        /// 170: total time in the hourly period, minutes.
+        total_time: Option<i16>,
+        /// 173: end time and time span, minutes.
+        time_span: Option<i16>,
+        /// Null product flag (low byte of halfword 30); absent for 174.
+        null_product: Option<u8>,
+        /// 173: missing period flag (high byte of halfword 30).
+        missing_period: Option<bool>,
+        /// Inches; the maximum accumulation difference for 174 and 175.
+        max_accumulation: f32,
+        /// Inches, 174 and 175 only.
+        min_accumulation: Option<f32>,
+        end: JulianTime,
+        mean_field_bias: Option<f32>,
+        /// 169 only.
+        sample_size: Option<f32>,
+        compression: Option<Compression>,
+    },
+    /// 176, Digital Instantaneous Precipitation Rate.
+    PrecipitationRate {
+        scan: JulianTime,
+        precipitation_detected: bool,
+        gage_bias_applied: bool,
+        /// As stored.
+        max_rate: u16,
+        /// Percent.
+        bins_filled: f32,
+        /// Degrees.
+        highest_elevation: f32,
+        mean_field_bias: f32,
+        compression: Compression,
+    },
+    /// 177 and 197, the hybrid hydrometeor and rain rate classifications.
+    HybridClassification {
+        mode_filter_size: i16,
+        /// Percent.
+        bins_filled: f32,
+        /// Degrees.
+        highest_elevation: f32,
+        /// 197 only.
+        dry_snow_multiplier: Option<f32>,
+        compression: Compression,
+    },
+    /// 144-147, 150 and 151, the snow accumulation products.
+    SnowAccumulation {
+        /// 150 and 151: ending hour.
+        end_hour: Option<i16>,
+        /// 150 and 151: hours.
+        time_span: Option<i16>,
+        /// 144-147: minutes.
+        missing_period: Option<i16>,
+        use_rca: bool,
+        /// Inches.
+        max_value: f32,
+        begin: JulianTime,
+        end: JulianTime,
+        /// Degrees.
+        max_azimuth: i16,
+        /// Nautical miles.
+        max_range: i16,
+    },
+    /// 159, 161, 163, 167 and 168, the dual-polarisation moments, and 166,
+    /// Melting Layer: a minimum and maximum for the elevation.
+    DualPolMoment {
+        /// Degrees.
+        elevation_angle: f32,
+        /// dB for 159, unitless for 161 and 167, °/km for 163, degrees for
+        /// 168, thousands of feet for 166.
+        min: f32,
+        max: f32,
+        delta_time: DeltaTime,
+        compression: Option<Compression>,
+    },
+    /// 152 and 202: only the compression fields.
+    CompressionOnly { compression: Compression },
+    /// A product Table V lists with no dependent parameters (59 and 62), or
+    /// one it does not list at all.
+    None,
+}
+
+impl ProductDependentParams {
+    /// Halfwords 51-53, for the products whose Table V entry has them.
+    pub fn compression(&self) -> Option<Compression> {
+        use ProductDependentParams as P;
+        match self {
+            P::BaseReflectivity { compression, .. }
+            | P::BaseVelocity { compression, .. }
+            | P::SpectrumWidth { compression, .. }
+            | P::Elevation { compression, .. }
+            | P::DualPolAccumulation { compression, .. }
+            | P::DualPolMoment { compression, .. } => *compression,
+            P::ClutterFilterControl { compression, .. }
+            | P::DigitalHybridScan { compression, .. }
+            | P::EditedReflectivity { compression, .. }
+            | P::EnhancedEchoTops { compression, .. }
+            | P::DigitalVil { compression, .. }
+            | P::HazardTop { compression, .. }
+            | P::DigitalMesocyclone { compression, .. }
+            | P::DigitalStormTotal { compression, .. }
+            | P::PrecipitationRate { compression, .. }
+            | P::HybridClassification { compression, .. }
+            | P::CompressionOnly { compression } => Some(*compression),
+            _ => None,
+        }
+    }
+
+    /// The elevation angle the product was generated from, degrees, for the
+    /// products whose Table V entry gives one. `None` for composites,
+    /// accumulations and the other products not tied to one elevation.
+    pub fn elevation_angle(&self) -> Option<f32> {
+        use ProductDependentParams as P;
+        match self {
+            P::BaseReflectivity { elevation_angle, .. }
+            | P::BaseVelocity { elevation_angle, .. }
+            | P::SpectrumWidth { elevation_angle, .. }
+            | P::Elevation { elevation_angle, .. }
+            | P::ClutterFilterControl { elevation_angle, .. }
+            | P::EditedReflectivity { elevation_angle, .. }
+            | P::StormRelativeVelocity { elevation_angle, .. }
+            | P::VelocityAzimuthDisplay { elevation_angle, .. }
+            | P::DigitalMesocyclone { elevation_angle, .. }
+            | P::DualPolMoment { elevation_angle, .. } => Some(*elevation_angle),
+            P::TornadoVortexSignature { elevation_angle, .. } => *elevation_angle,
+            _ => None,
+        }
+    }
+}
+
+impl ProductDescription {
+    /// Product-dependent halfword `n`, for `n` in 27-28, 30 or 47-53 (the
+    /// numbering Table V uses). `None` for any other halfword.
+    pub fn dependent_halfword(&self, n: u8) -> Option<i16> {
+        let (bytes, index) = match n {
+            27 | 28 => (&self.halfwords_27_28, n - 27),
+            30 => (&self.halfwords_30, 0),
+            47..=53 => (&self.halfwords_47_53, n - 47),
+            _ => return None,
+        };
+        let start = index as usize * 2;
+        let d = bytes.get(start..start + 2)?;
+        Some(i16::from_be_bytes([d[0], d[1]]))
+    }
+
+    /// Sets product-dependent halfword `n`, numbered as for
+    /// [`ProductDescription::dependent_halfword`]. Returns whether `n` names
+    /// one.
+    pub fn set_dependent_halfword(&mut self, n: u8, value: i16) -> bool {
+        let (bytes, index) = match n {
+            27 | 28 => (&mut self.halfwords_27_28, n - 27),
+            30 => (&mut self.halfwords_30, 0),
+            47..=53 => (&mut self.halfwords_47_53, n - 47),
+            _ => return false,
+        };
+        let start = index as usize * 2;
+        match bytes.get_mut(start..start + 2) {
+            Some(d) => {
+                d.copy_from_slice(&value.to_be_bytes());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Decodes halfwords 27-28, 30 and 47-53 per Table V for this product.
+    pub fn dependent_params(&self) -> ProductDependentParams {
+        let hw = |n: u8| self.dependent_halfword(n).unwrap_or(0);
+        let tenths = |n: u8| hw(n) as f32 / 10.0;
+        let hundredths = |n: u8| hw(n) as f32 / 100.0;
+        let delta_time = || DeltaTime::from_halfword(hw(50));
+        let compression = || Compression {
+            method: CompressionMethod::from_halfword(hw(51)),
+            uncompressed_size: ((hw(52) as u16 as u32) << 16) | hw(53) as u16 as u32,
+        };
+        let cal_constant = |msw: u8| {
+            let bits = ((hw(msw) as u16 as u32) << 16) | hw(msw + 1) as u16 as u32;
+            f32::from_bits(bits)
+        };
+        let julian = |date: u8, minutes: u8| JulianTime {
+            date: hw(date),
+            minutes: hw(minutes),
+        };
+        let code = self.product_code;
+
+        use ProductDependentParams as P;
+        match code {
+            19 | 20 | 181 | 186 => P::BaseReflectivity {
+                elevation_angle: tenths(30),
+                max_reflectivity: hw(47),
+                delta_time: None,
+                compression: None,
+            },
+            94 | 153 => P::BaseReflectivity {
+                elevation_angle: tenths(30),
+                max_reflectivity: hw(47),
+                delta_time: Some(delta_time()),
+                compression: Some(compression()),
+            },
+            25 | 27 | 182 => P::BaseVelocity {
+                elevation_angle: tenths(30),
+                max_negative_velocity: hw(47),
+                max_positive_velocity: hw(48),
+                delta_time: None,
+                velocity_precision: None,
+                compression: None,
+            },
+            93 => P::BaseVelocity {
+                elevation_angle: tenths(30),
+                max_negative_velocity: hw(47),
+                max_positive_velocity: hw(48),
+                delta_time: None,
+                velocity_precision: Some(hw(50)),
+                compression: None,
+            },
+            99 | 154 => P::BaseVelocity {
+                elevation_angle: tenths(30),
+                max_negative_velocity: hw(47),
+                max_positive_velocity: hw(48),
+                delta_time: Some(delta_time()),
+                velocity_precision: None,
+                compression: Some(compression()),
+            },
+            28 => P::SpectrumWidth {
+                elevation_angle: tenths(30),
+                max_spectrum_width: hw(47),
+                delta_time: None,
+                compression: None,
+            },
+            30 => P::SpectrumWidth {
+                elevation_angle: tenths(30),
+                max_spectrum_width: hw(47),
+                delta_time: Some(delta_time()),
+                compression: None,
+            },
+            155 => P::SpectrumWidth {
+                elevation_angle: tenths(30),
+                max_spectrum_width: hw(47),
+                delta_time: Some(delta_time()),
+                compression: Some(compression()),
+            },
+            132 | 133 | 165 => P::Elevation {
+                elevation_angle: tenths(30),
+                delta_time: delta_time(),
+                compression: (code == 165).then(compression),
+            },
+            113 => P::ClutterFilterControl {
+                rpg_cut_number: hw(27),
+                cmd_generated: hw(28) != 0,
+                elevation_angle: tenths(30),
+                map_time: hw(47),
+                map_date: hw(48),
+                compression: compression(),
+            },
+            37 | 38 | 97 | 98 => P::CompositeReflectivity {
+                avset_termination_angle: tenths(30),
+                max_reflectivity: hw(47),
+                calibration_constant: cal_constant(51),
+            },
+            65 | 66 | 67 | 90 => P::LayerCompositeReflectivity {
+                avset_termination_angle: tenths(30),
+                max_reflectivity: hw(47),
+                layer_bottom: hw(48),
+                layer_top: hw(49),
+                calibration_constant: cal_constant(51),
+            },
+            137 => P::UserSelectableLayer {
+                requested_bottom: hw(27),
+                requested_top: hw(28),
+                max_reflectivity: hw(47),
+                actual_bottom: hw(48),
+                actual_top: hw(49),
+            },
+            50 | 51 => P::CrossSection {
+                azimuth_1: tenths(47),
+                range_1: tenths(48),
+                azimuth_2: tenths(49),
+                range_2: tenths(50),
+                calibration_constant: (code == 50).then(|| cal_constant(51)),
+            },
+            32 => P::DigitalHybridScan {
+                max_reflectivity: hw(47),
+                scan_date: hw(48),
+                scan_time: hw(49),
+                compression: compression(),
+            },
+            193 | 195 => P::EditedReflectivity {
+                elevation_angle: tenths(30),
+                max_reflectivity: hw(47),
+                edited_radials: hw(48),
+                avset_status: hw(49),
+                chaff_detection: (code == 193).then(|| hw(50)),
+                compression: compression(),
+            },
+            41 => P::EchoTops {
+                avset_termination_angle: tenths(30),
+                max_echo_top: hw(47),
+            },
+            135 => P::EnhancedEchoTops {
+                avset_termination_angle: tenths(30),
+                max_echo_top: hw(47),
+                edited_radials: hw(48),
+                reflectivity_threshold: hw(49),
+                spurious_points_removed: hw(50),
+                compression: compression(),
+            },
+            57 => P::Vil {
+                avset_termination_angle: tenths(30),
+                max_vil: hw(47),
+            },
+            134 => P::DigitalVil {
+                avset_termination_angle: tenths(30),
+                max_vil: hw(47),
+                edited_radials: hw(48),
+                compression: compression(),
+            },
+            178 | 179 => P::HazardTop {
+                avset_termination_angle: tenths(30),
+                max_top: hw(47),
+                hsda_status: (code == 179).then(|| hw(48)),
+                compression: compression(),
+            },
+            56 => P::StormRelativeVelocity {
+                elevation_angle: tenths(30),
+                max_negative_velocity: hw(47),
+                max_positive_velocity: hw(48),
+                motion_source: hw(49),
+                average_storm_speed: tenths(51),
+                average_storm_direction: tenths(52),
+            },
+            48 => P::VadWindProfile {
+                max_speed: hw(47),
+                max_speed_direction: hw(48),
+                max_speed_altitude: hw(49) as i32 * 10,
+            },
+            84 => P::VelocityAzimuthDisplay {
+                altitude: hw(30),
+                speed: hw(47),
+                direction: hw(48),
+                elevation_angle: tenths(49),
+                slant_range: tenths(50),
+                rms_error: hw(51),
+            },
+            58 => P::StormTracking { total_storms: hw(47) },
+            61 => P::TornadoVortexSignature {
+                elevation_angle: None,
+                total_tvs: hw(47),
+                total_etvs: hw(48),
+                delta_time: None,
+            },
+            143 => P::TornadoVortexSignature {
+                elevation_angle: Some(tenths(30)),
+                total_tvs: hw(47),
+                total_etvs: hw(48),
+                delta_time: Some(delta_time()),
+            },
+            141 => P::Mesocyclone {
+                min_reflectivity_threshold: hw(27),
+                overlap_display_filter: hw(28) != 0,
+                min_display_filter_rank: hw(30),
+            },
+            149 => P::DigitalMesocyclone {
+                min_reflectivity_threshold: hw(27),
+                elevation_angle: tenths(30),
+                delta_time: delta_time(),
+                compression: compression(),
+            },
+            140 => P::Detections {
+                half_degree_scan_count: None,
+                detection_count: hw(49),
+            },
+            196 => P::Detections {
+                half_degree_scan_count: Some(hw(27)),
+                detection_count: hw(49),
+            },
+            75 => P::FreeText { rpg_id: hw(47) },
+            78 | 79 => P::RainfallAccumulation {
+                max_rainfall: tenths(47),
+                begin: None,
+                end: julian(50, 51),
+                mean_field_bias: hundredths(48),
+                sample_size: hundredths(49),
+            },
+            80 => P::RainfallAccumulation {
+                max_rainfall: tenths(47),
+                begin: Some(julian(48, 49)),
+                end: julian(50, 51),
+                mean_field_bias: hundredths(52),
+                sample_size: hundredths(53),
+            },
+            31 => P::UserSelectableAccumulation {
+                end_hour: hw(27),
+                time_span: hw(28),
+                null_product: hw(30) != 0,
+                max_rainfall: tenths(47),
+                begin: julian(48, 49),
+                end: julian(50, 51),
+                mean_field_bias: hundredths(52),
+                sample_size: hundredths(53),
+            },
+            81 => P::HourlyDigitalPrecipitation {
+                max_rainfall: hw(47),
+                mean_field_bias: hundredths(48),
+                sample_size: hundredths(49),
+                end: julian(50, 51),
+            },
+            138 => P::DigitalStormTotal {
+                begin: julian(27, 28),
+                mean_field_bias: hundredths(30),
+                max_rainfall: hundredths(47),
+                end: julian(48, 49),
+                sample_size: hundredths(50),
+                compression: compression(),
+            },
+            169 => P::DualPolAccumulation {
+                begin: None,
+                threshold_time: None,
+                total_time: None,
+                time_span: None,
+                null_product: Some(hw(30) as u8),
+                missing_period: None,
+                max_accumulation: tenths(47),
+                min_accumulation: None,
+                end: julian(48, 49),
+                mean_field_bias: Some(hundredths(50)),
+                sample_size: Some(hundredths(51)),
+                compression: None,
+            },
+            170 => P::DualPolAccumulation {
+                begin: None,
+                threshold_time: Some(hw(27)),
+                total_time: Some(hw(28)),
+                time_span: None,
+                null_product: Some(hw(30) as u8),
+                missing_period: None,
+                max_accumulation: tenths(47),
+                min_accumulation: None,
+                end: julian(48, 49),
+                mean_field_bias: Some(hundredths(50)),
+                sample_size: None,
+                compression: Some(compression()),
+            },
+            171 | 172 => P::DualPolAccumulation {
+                begin: Some(julian(27, 28)),
+                threshold_time: None,
+                total_time: None,
+                time_span: None,
+                null_product: Some(hw(30) as u8),
+                missing_period: None,
+                max_accumulation: tenths(47),
+                min_accumulation: None,
+                end: julian(48, 49),
+                mean_field_bias: Some(hundredths(50)),
+                sample_size: None,
+                compression: Some(compression()),
+            },
+            // Halfword 27 is the end time and 48 the end date; halfword 49
+            // holds a start time, but with no start date of its own.
+            173 => P::DualPolAccumulation {
+                begin: None,
+                threshold_time: None,
+                total_time: None,
+                time_span: Some(hw(28)),
+                null_product: Some(hw(30) as u8),
+                missing_period: Some((hw(30) as u16 >> 8) != 0),
+                max_accumulation: tenths(47),
+                min_accumulation: None,
+                end: julian(48, 27),
+                mean_field_bias: Some(hundredths(50)),
+                sample_size: None,
+                compression: Some(compression()),
+            },
+            174 | 175 => P::DualPolAccumulation {
+                begin: (code == 175).then(|| julian(27, 28)),
+                threshold_time: None,
+                total_time: None,
+                time_span: None,
+                null_product: (code == 175).then(|| hw(30) as u8),
+                missing_period: None,
+                max_accumulation: tenths(47),
+                min_accumulation: Some(tenths(50)),
+                end: julian(48, 49),
+                mean_field_bias: None,
+                sample_size: None,
+                compression: Some(compression()),
+            },
+            176 => P::PrecipitationRate {
+                scan: julian(27, 28),
+                precipitation_detected: (hw(30) as u16 >> 8) != 0,
+                gage_bias_applied: (hw(30) & 0xFF) != 0,
+                max_rate: hw(47) as u16,
+                bins_filled: hundredths(48),
+                highest_elevation: tenths(49),
+                mean_field_bias: hundredths(50),
+                compression: compression(),
+            },
+            177 | 197 => P::HybridClassification {
+                mode_filter_size: hw(47),
+                bins_filled: hundredths(48),
+                highest_elevation: tenths(49),
+                dry_snow_multiplier: (code == 197).then(|| tenths(50)),
+                compression: compression(),
+            },
+            144..=147 => {
+                // The resolution of the maximum grows with the accumulation
+                // period: 0.001 in for 144 up to 0.1 in for 147.
+                let scale = match code {
+                    144 => 1000.0,
+                    145 | 146 => 100.0,
+                    _ => 10.0,
+                };
+                P::SnowAccumulation {
+                    end_hour: None,
+                    time_span: None,
+                    missing_period: Some(hw(27)),
+                    use_rca: hw(30) != 0,
+                    max_value: hw(47) as f32 / scale,
+                    begin: julian(48, 49),
+                    end: julian(50, 51),
+                    max_azimuth: hw(52),
+                    max_range: hw(53),
+                }
+            }
+            150 | 151 => {
+                // Halfword 30 is 0, 1, 256 or 257: the high byte is the "use
+                // high scale" flag, which coarsens the maximum by ten.
+                let flags = hw(30) as u16;
+                let high_scale = flags >> 8 != 0;
+                let scale = match (code, high_scale) {
+                    (150, false) => 1000.0,
+                    (150, true) | (151, false) => 100.0,
+                    _ => 10.0,
+                };
+                P::SnowAccumulation {
+                    end_hour: Some(hw(27)),
+                    time_span: Some(hw(28)),
+                    missing_period: None,
+                    use_rca: flags & 0xFF != 0,
+                    max_value: hw(47) as f32 / scale,
+                    begin: julian(48, 49),
+                    end: julian(50, 51),
+                    max_azimuth: hw(52),
+                    max_range: hw(53),
+                }
+            }
+            159 | 161 | 163 | 166 | 167 | 168 => {
+                let scale = match code {
+                    159 => 10.0,
+                    161 | 163 | 167 => 100.0,
+                    _ => 1.0,
+                };
+                P::DualPolMoment {
+                    elevation_angle: tenths(30),
+                    min: hw(47) as f32 / scale,
+                    max: hw(48) as f32 / scale,
+                    delta_time: delta_time(),
+                    compression: (code != 166).then(compression),
+                }
+            }
+            152 | 202 => P::CompressionOnly {
+                compression: compression(),
+            },
+            _ => P::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A product description whose dependent halfwords are the given
+    /// `(halfword, value)` pairs.
+    fn description(product_code: i16, halfwords: &[(u8, i16)]) -> ProductDescription {
+        let mut pd = ProductDescription {
+            product_code,
+            halfwords_27_28: vec![0; 4],
+            halfwords_30: vec![0; 2],
+            halfwords_47_53: vec![0; 14],
+            ..Default::default()
+        };
+        for &(n, value) in halfwords {
+            assert!(pd.set_dependent_halfword(n, value));
+        }
+        pd
+    }
+
+    #[test]
+    fn setting_a_halfword_reads_back() {
+        let mut pd = description(94, &[]);
+        assert!(pd.set_dependent_halfword(30, -5));
+        assert!(pd.set_dependent_halfword(53, 1234));
+        assert!(!pd.set_dependent_halfword(29, 1));
+        assert_eq!(pd.dependent_halfword(30), Some(-5));
+        assert_eq!(pd.dependent_halfword(53), Some(1234));
+    }
+
+    #[test]
+    fn compression_is_only_reported_where_table_v_has_it() {
+        let dhr = description(32, &[(51, 1), (52, 1), (53, 20012)]);
+        assert_eq!(
+            dhr.dependent_params().compression(),
+            Some(Compression {
+                method: CompressionMethod::Bzip2,
+                uncompressed_size: 85548,
+            })
+        );
+        // Product 56 uses halfword 51 for the average storm speed.
+        assert_eq!(description(56, &[(51, 1)]).dependent_params().compression(), None);
+    }
+
+    #[test]
+    fn elevation_angle_is_only_reported_for_single_elevation_products() {
+        assert_eq!(description(94, &[(30, 15)]).dependent_params().elevation_angle(), Some(1.5));
+        assert_eq!(description(32, &[(30, 15)]).dependent_params().elevation_angle(), None);
+    }
+
+    #[test]
+    fn dependent_halfword_uses_table_v_numbering() {
+        let pd = description(94, &[(27, 1), (28, 2), (30, 3), (47, 4), (53, 10)]);
+        assert_eq!(pd.dependent_halfword(27), Some(1));
+        assert_eq!(pd.dependent_halfword(28), Some(2));
+        assert_eq!(pd.dependent_halfword(30), Some(3));
+        assert_eq!(pd.dependent_halfword(47), Some(4));
+        assert_eq!(pd.dependent_halfword(53), Some(10));
+        assert_eq!(pd.dependent_halfword(29), None);
+        assert_eq!(pd.dependent_halfword(54), None);
+    }
+
+    #[test]
+    fn decodes_a_super_res_reflectivity_product() {
+        // 0.5 deg, 63 dBZ, 12 s into a SAILS cut, bzip2 to 70000 bytes.
+        let pd = description(
+            153,
+            &[(30, 5), (47, 63), (50, (12 << 5) | 1), (51, 1), (52, 1), (53, 4464)],
+        );
+        assert_eq!(
+            pd.dependent_params(),
+            ProductDependentParams::BaseReflectivity {
+                elevation_angle: 0.5,
+                max_reflectivity: 63,
+                delta_time: Some(DeltaTime {
+                    seconds: 12,
+                    scan: SupplementalScan::Sails,
+                }),
+                compression: Some(Compression {
+                    method: CompressionMethod::Bzip2,
+                    uncompressed_size: 70000,
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_the_mrle_scan_flag() {
+        let pd = description(99, &[(47, -50), (48, 60), (50, 2)]);
+        match pd.dependent_params() {
+            ProductDependentParams::BaseVelocity {
+                max_negative_velocity,
+                max_positive_velocity,
+                delta_time: Some(delta),
+                ..
+            } => {
+                assert_eq!((max_negative_velocity, max_positive_velocity), (-50, 60));
+                assert_eq!(delta.scan, SupplementalScan::Mrle);
+                assert_eq!(delta.seconds, 0);
+            }
+            other => panic!("expected base velocity, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decodes_an_accumulation_with_begin_and_end_times() {
+        let pd = description(
+            80,
+            &[(47, 125), (48, 19000), (49, 60), (50, 19001), (51, 90), (52, 105), (53, 1234)],
+        );
+        match pd.dependent_params() {
+            ProductDependentParams::RainfallAccumulation {
+                max_rainfall,
+                begin: Some(begin),
+                end,
+                mean_field_bias,
+                sample_size,
+            } => {
+                assert_eq!(max_rainfall, 12.5);
+                assert_eq!(begin, JulianTime { date: 19000, minutes: 60 });
+                assert_eq!(end.minutes, 90);
+                assert!((mean_field_bias - 1.05).abs() < 1e-6);
+                assert!((sample_size - 12.34).abs() < 1e-4);
+                assert_eq!(
+                    end.datetime().unwrap().to_rfc3339(),
+                    "2022-01-08T01:30:00+00:00"
+                );
+            }
+            other => panic!("expected a rainfall accumulation, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decodes_the_calibration_constant_as_a_float() {
+        let bits = (-31.5f32).to_bits();
+        let pd = description(
+            37,
+            &[(47, 58), (51, (bits >> 16) as i16), (52, bits as u16 as i16)],
+        );
+        assert_eq!(
+            pd.dependent_params(),
+            ProductDependentParams::CompositeReflectivity {
+                avset_termination_angle: 0.0,
+                max_reflectivity: 58,
+                calibration_constant: -31.5,
+            }
+        );
+    }
+
+    #[test]
+    fn snow_high_scale_flag_coarsens_the_maximum() {
+        let low = description(150, &[(30, 1), (47, 1234)]).dependent_params();
+        let high = description(150, &[(30, 257), (47, 1234)]).dependent_params();
+        match (low, high) {
+            (
+                ProductDependentParams::SnowAccumulation { max_value: low, use_rca: true, .. },
+                ProductDependentParams::SnowAccumulation { max_value: high, use_rca: true, .. },
+            ) => {
+                assert!((low - 1.234).abs() < 1e-6);
+                assert!((high - 12.34).abs() < 1e-5);
+            }
+            other => panic!("expected snow accumulations, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn products_without_dependent_params_decode_to_none() {
+        assert_eq!(description(59, &[]).dependent_params(), ProductDependentParams::None);
+        assert_eq!(description(62, &[]).dependent_params(), ProductDependentParams::None);
+    }
+
+    /// The bundled product 32 fixture reports a 56 dBZ maximum and is bzip2
+    /// compressed.
+    #[test]
+    fn decodes_the_reference_dhr_product() {
+        let file = include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last");
+        let (rest, _) = crate::text_header(file).unwrap();
+        let (rest, _) = crate::message_header(rest).unwrap();
+        let pd = crate::product_description(rest).unwrap().1;
+
+        match pd.dependent_params() {
+            ProductDependentParams::DigitalHybridScan {
+                max_reflectivity,
+                compression,
+                ..
+            } => {
+                assert_eq!(max_reflectivity, 56);
+                assert_eq!(compression.method, CompressionMethod::Bzip2);
+                assert!(compression.uncompressed_size > 0);
+            }
+            other => panic!("expected DHR params, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Structured records of the anomalies a parse tolerates, and the options that
+//! decide which of them are tolerated at all.
+//!
+//! The block and packet parsers are plain `nom` functions, so rather than
+//! threading a collector through every signature they report into a
+//! per-thread sink that [`crate::Radar::from_vec_with`] installs for the
+//! duration of a parse. Outside of one, [`report`] only logs. The same sink
+//! gives a failed parse's [`ParseError`] its block, layer, packet and offset.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::codes::PacketCode;
+use crate::error_r::ParseError;
+
+/// How much a [`Diagnostic`] calls the decoded product into question.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something unusual that loses no data, such as zero padding after the
+    /// last packet of a layer, or a product that has not been validated.
+    Warning,
+    /// Something that means part of the product was skipped or is likely
+    /// decoded wrongly, such as a block divider mismatch or undecoded bytes.
+    /// [`ParseOptions::strict`] rejects a file with any of these.
+    Error,
+}
+
+/// The part of the file being parsed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Block {
+    /// The text header, message header and product description.
+    #[default]
+    Header,
+    /// The Product Symbology Block (ID 1).
+    Symbology,
+    /// The Graphic Alphanumeric Block (ID 2).
+    Graphic,
+    /// The Tabular Alphanumeric Block (ID 3).
+    Tabular,
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Block::Header => "header",
+            Block::Symbology => "symbology block",
+            Block::Graphic => "graphic alphanumeric block",
+            Block::Tabular => "tabular alphanumeric block",
+        })
+    }
+}
+
+/// One anomaly found while parsing.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub block: Block,
+    /// Index of the symbology layer, when in the symbology block.
+    pub layer: Option<usize>,
+    /// The packet being decoded, when inside one.
+    pub packet_code: Option<PacketCode>,
+    /// Byte offset from the start of the file, counting the part after the
+    /// product description as it is once decompressed.
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} in the {}", self.severity, self.block)?;
+        if let Some(layer) = self.layer {
+            write!(f, ", layer {layer}")?;
+        }
+        if let Some(code) = self.packet_code {
+            write!(f, ", packet {code:?}")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, ", byte {offset}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// How tolerant [`crate::Radar::from_vec_with`] should be.
+///
+/// `Default` gives the behaviour of [`crate::Radar::from_vec`]: lenient,
+/// unvalidated products accepted, newer product versions rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Fail on the first [`Severity::Error`] diagnostic instead of returning
+    /// it alongside the product.
+    pub strict: bool,
+    /// Parse a validated product even when its version is newer than this
+    /// crate supports, with a warning, instead of failing.
+    pub allow_newer_versions: bool,
+    /// Parse products outside [`crate::MessageCode::is_validated_product`],
+    /// with a warning, instead of failing.
+    pub allow_unvalidated: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParseOptions {
+    /// Options matching [`crate::Radar::from_vec`].
+    pub fn new() -> Self {
+        ParseOptions {
+            strict: false,
+            allow_newer_versions: false,
+            allow_unvalidated: true,
+        }
+    }
+
+    /// Fails the parse on the first [`Severity::Error`] diagnostic.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Accepts product versions newer than this crate supports.
+    pub fn allow_newer_versions(mut self) -> Self {
+        self.allow_newer_versions = true;
+        self
+    }
+
+    /// Rejects products this crate has not been validated against.
+    pub fn reject_unvalidated(mut self) -> Self {
+        self.allow_unvalidated = false;
+        self
+    }
+}
+
+/// Where a buffer being parsed sits in the file.
+struct Region {
+    start: usize,
+    len: usize,
+    file_offset: usize,
+}
+
+#[derive(Default)]
+struct Sink {
+    regions: Vec<Region>,
+    block: Block,
+    layer: Option<usize>,
+    packet_code: Option<PacketCode>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+thread_local! {
+    static SINK: RefCell<Option<Sink>> = const { RefCell::new(None) };
+    /// Where the last [`failure`] was raised, and why. Kept outside the sink
+    /// so that a packet parser called directly still explains itself.
+    static FAILURE: RefCell<Option<(usize, String)>> = const { RefCell::new(None) };
+}
+
+/// Runs `parse` with a sink installed, returning what it reported.
+///
+/// `regions` maps each buffer the parse reads from to its offset in the file,
+/// so that diagnostics can be placed by the slice they were raised on.
+pub(crate) fn collect<R>(regions: &[(&[u8], usize)], parse: impl FnOnce() -> R) -> (R, Vec<Diagnostic>) {
+    let sink = Sink {
+        regions: regions
+            .iter()
+            .map(|(buffer, file_offset)| Region {
+                start: buffer.as_ptr() as usize,
+                len: buffer.len(),
+                file_offset: *file_offset,
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let previous = SINK.with(|s| s.replace(Some(sink)));
+    let result = parse();
+    let sink = SINK.with(|s| s.replace(previous));
+    (result, sink.map(|s| s.diagnostics).unwrap_or_default())
+}
+
+/// Records which block is being parsed, clearing the layer and packet.
+pub(crate) fn set_block(block: Block) {
+    with_sink(|s| {
+        s.block = block;
+        s.layer = None;
+        s.packet_code = None;
+    });
+}
+
+/// Records which symbology layer is being parsed, clearing the packet.
+pub(crate) fn set_layer(layer: Option<usize>) {
+    with_sink(|s| {
+        s.layer = layer;
+        s.packet_code = None;
+    });
+}
+
+/// Records which packet is being parsed.
+pub(crate) fn set_packet(packet_code: Option<PacketCode>) {
+    with_sink(|s| s.packet_code = packet_code);
+}
+
+/// The file offset of the start of `input`, if it lies in a registered buffer.
+pub(crate) fn offset_of(input: &[u8]) -> Option<usize> {
+    SINK.with(|s| {
+        let sink = s.borrow();
+        let at = input.as_ptr() as usize;
+        sink.as_ref()?
+            .regions
+            .iter()
+            .find(|r| {
+                let end = r.start + r.len;
+                // An empty slice may sit one past the end of its buffer.
+                at >= r.start && (at < end || (input.is_empty() && at == end))
+            })
+            .map(|r| r.file_offset + (at - r.start))
+    })
+}
+
+/// Logs an anomaly found at the start of `input` and, during a parse, records
+/// it with the current block, layer and packet.
+pub(crate) fn report(severity: Severity, input: &[u8], message: impl Into<String>) {
+    let message = message.into();
+    match severity {
+        Severity::Warning => warn!("{message}"),
+        Severity::Error => error!("{message}"),
+    }
+    let offset = offset_of(input);
+    with_sink(|s| {
+        s.diagnostics.push(Diagnostic {
+            severity,
+            block: s.block,
+            layer: s.layer,
+            packet_code: s.packet_code,
+            offset,
+            message,
+        })
+    });
+}
+
+/// Logs why a parser is giving up at the start of `input` and returns the
+/// `nom` failure to propagate, remembering `detail` for the [`ParseError`]
+/// that failure becomes.
+pub(crate) fn failure(input: &[u8], detail: impl Into<String>) -> nom::Err<nom::error::Error<&[u8]>> {
+    let detail = detail.into();
+    error!("{detail}");
+    FAILURE.with(|f| f.replace(Some((input.as_ptr() as usize, detail))));
+    nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Fail))
+}
+
+/// Describes a `nom` error with the current block, layer and packet.
+///
+/// The detail is the one given to [`failure`] when the error came from there,
+/// and otherwise is worked out from the `nom` error kind.
+pub(crate) fn parse_error(err: &nom::Err<nom::error::Error<&[u8]>>) -> ParseError {
+    let (input, detail) = match err {
+        nom::Err::Incomplete(_) => (None, "expected more input than was given".to_string()),
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            let at = e.input.as_ptr() as usize;
+            let recorded = FAILURE.with(|f| {
+                f.borrow_mut()
+                    .take_if(|(ptr, _)| *ptr == at)
+                    .map(|(_, detail)| detail)
+            });
+            let detail = recorded.unwrap_or_else(|| match e.code {
+                nom::error::ErrorKind::Eof => {
+                    format!("expected more data but found only {} byte(s)", e.input.len())
+                }
+                code => format!("{code:?} check failed with {} byte(s) left", e.input.len()),
+            });
+            (Some(e.input), detail)
+        }
+    };
+    let offset = input.and_then(offset_of);
+    SINK.with(|s| {
+        let sink = s.borrow();
+        ParseError {
+            block: sink.as_ref().map(|s| s.block),
+            layer: sink.as_ref().and_then(|s| s.layer),
+            packet_code: sink.as_ref().and_then(|s| s.packet_code),
+            offset,
+            detail,
+        }
+    })
+}
+
+fn with_sink(f: impl FnOnce(&mut Sink)) {
+    SINK.with(|s| {
+        if let Some(sink) = s.borrow_mut().as_mut() {
+            f(sink);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_outside_a_parse_are_only_logged() {
+        report(Severity::Error, &[], "nobody is listening");
+        let (_, diagnostics) = collect(&[], || ());
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn records_context_and_file_offset() {
+        let header = [0u8; 8];
+        let body = [0u8; 16];
+        let ((), diagnostics) = collect(&[(&header, 0), (&body, 8)], || {
+            report(Severity::Warning, &header[2..], "in the header");
+            set_block(Block::Symbology);
+            set_layer(Some(1));
+            set_packet(Some(PacketCode::WindBarbData));
+            report(Severity::Error, &body[4..], "in a packet");
+        });
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].block, Block::Header);
+        assert_eq!(diagnostics[0].offset, Some(2));
+        assert_eq!(diagnostics[1].block, Block::Symbology);
+        assert_eq!(diagnostics[1].layer, Some(1));
+        assert_eq!(diagnostics[1].packet_code, Some(PacketCode::WindBarbData));
+        assert_eq!(diagnostics[1].offset, Some(12));
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "Error in the symbology block, layer 1, packet WindBarbData, byte 12: in a packet"
+        );
+    }
+
+    #[test]
+    fn a_slice_from_an_unregistered_buffer_has_no_offset() {
+        let registered = [0u8; 4];
+        let other = [0u8; 4];
+        let ((), diagnostics) = collect(&[(&registered, 0)], || {
+            report(Severity::Warning, &other, "elsewhere");
+        });
+        assert_eq!(diagnostics[0].offset, None);
+    }
+
+    #[test]
+    fn a_failure_keeps_its_detail_and_context() {
+        let body = [0u8; 16];
+        let (error, _) = collect(&[(&body, 150)], || {
+            set_block(Block::Symbology);
+            set_layer(Some(0));
+            set_packet(Some(PacketCode::RasterDataBA0F));
+            parse_error(&failure(&body[6..], "expected 4 rows but found -1"))
+        });
+
+        assert_eq!(error.block, Some(Block::Symbology));
+        assert_eq!(error.layer, Some(0));
+        assert_eq!(error.packet_code, Some(PacketCode::RasterDataBA0F));
+        assert_eq!(error.offset, Some(156));
+        assert_eq!(error.detail, "expected 4 rows but found -1");
+    }
+
+    #[test]
+    fn a_plain_nom_error_describes_its_kind() {
+        let body = [0u8; 3];
+        let (error, _) = collect(&[(&body, 0)], || {
+            // A stale failure at a different position is not picked up.
+            let _ = failure(&body[..], "somewhere else");
+            let err = nom::Err::Error(nom::error::Error::new(&body[1..], nom::error::ErrorKind::Eof));
+            parse_error(&err)
+        });
+        assert_eq!(error.offset, Some(1));
+        assert_eq!(error.detail, "expected more data but found only 2 byte(s)");
+    }
+
+    #[test]
+    fn outside_a_parse_only_the_detail_is_known() {
+        let body = [0u8; 2];
+        let error = parse_error(&failure(&body, "expected -1"));
+        assert_eq!(error.block, None);
+        assert_eq!(error.offset, None);
+        assert_eq!(error.detail, "expected -1");
+    }
+
+    #[test]
+    fn severities_order_warning_below_error() {
+        assert!(Severity::Warning < Severity::Error);
+    }
+}
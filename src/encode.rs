@@ -0,0 +1,257 @@
+//! The inverse of the parsers: serializes a [`Radar`] back into a Level 3
+//! byte stream.
+//!
+//! Each block and packet type implements [`Encode`] next to its parser, so the
+//! layout is described once per file in both directions. What is derived
+//! rather than stored is recomputed as the bytes are written: the message
+//! length, the block offsets in the product description, the symbology and
+//! alphanumeric block lengths, every layer length, and each packet's own
+//! length fields. Everything else is written back as decoded, so a parsed
+//! file encodes to the same bytes it was read from.
+
+use std::io::Write;
+
+use bzip2::write::BzEncoder;
+
+use crate::dependent_params::CompressionMethod;
+use crate::error_r::Error;
+use crate::{Radar, MESSAGE_HEADERS_LEN};
+
+/// Serializes a block or packet in its on-the-wire layout.
+pub(crate) trait Encode {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error>;
+}
+
+/// How [`Radar::to_vec_with`] should write a product.
+///
+/// `Default` gives the behaviour of [`Radar::to_vec`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Whether to bzip2-compress everything after the product description.
+    /// When `None`, products that carry the compression halfwords (Table V
+    /// halfword 51) are compressed if it says so, and others are not.
+    pub compress: Option<bool>,
+    /// bzip2 block size in units of 100 kB, 1 to 9. When `None`, the smallest
+    /// size that holds the whole payload in one block is used, which is what
+    /// the RPG does.
+    pub block_size: Option<u32>,
+}
+
+impl EncodeOptions {
+    /// Options matching [`Radar::to_vec`].
+    pub fn new() -> Self {
+        EncodeOptions {
+            compress: None,
+            block_size: None,
+        }
+    }
+
+    /// Compresses, or does not compress, regardless of halfword 51.
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = Some(compress);
+        self
+    }
+
+    /// Sets the bzip2 block size, clamped to 1 to 9.
+    pub fn with_block_size(mut self, block_size: u32) -> Self {
+        self.block_size = Some(block_size.clamp(1, 9));
+        self
+    }
+}
+
+impl Radar {
+    /// Serializes this product as a complete Level 3 file.
+    ///
+    /// Block offsets and lengths are recomputed from what is being written,
+    /// so a product whose blocks or packets have been edited still encodes to
+    /// a consistent file. Compression follows halfword 51 of the product
+    /// description; see [`Radar::to_vec_with`] to choose.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::FieldWidth`] if a fixed-width field holds the wrong
+    /// number of bytes, and [`Error::TooLong`] if a block or packet has grown
+    /// past what its length field can count.
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        self.to_vec_with(&EncodeOptions::default())
+    }
+
+    /// Serializes this product as [`Radar::to_vec`] does, with `options`
+    /// deciding how the part after the product description is compressed.
+    ///
+    /// When compressing a product that carries the compression halfwords, they
+    /// are updated to say so and to give the uncompressed size; when not, the
+    /// method halfword is set to 0.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::to_vec`].
+    pub fn to_vec_with(&self, options: &EncodeOptions) -> Result<Vec<u8>, Error> {
+        // The blocks after the product description, laid out uncompressed,
+        // since that is what the offsets count.
+        let mut description = self.product_description.clone();
+        let mut payload = Vec::new();
+        let header_halfwords = (MESSAGE_HEADERS_LEN / 2) as i32;
+        let offset_of = |payload: &Vec<u8>| header_halfwords + (payload.len() / 2) as i32;
+
+        description.offset_symbology = 0;
+        if let Some(symbology) = &self.symbology {
+            description.offset_symbology = offset_of(&payload);
+            symbology.encode(&mut payload)?;
+        }
+        description.offset_graphic = 0;
+        if let Some(graphic) = &self.graphic {
+            description.offset_graphic = offset_of(&payload);
+            graphic.encode(&mut payload)?;
+        }
+        description.offset_tabular = 0;
+        if let Some(tabular) = &self.tabular {
+            description.offset_tabular = offset_of(&payload);
+            tabular.encode(&mut payload)?;
+        }
+
+        let compression = description.dependent_params().compression();
+        let compress = options
+            .compress
+            .unwrap_or_else(|| compression.is_some_and(|c| c.method == CompressionMethod::Bzip2));
+        if compression.is_some() {
+            let size = len_i32("Uncompressed product", payload.len())?;
+            description.set_dependent_halfword(51, compress as i16);
+            description.set_dependent_halfword(52, (size >> 16) as i16);
+            description.set_dependent_halfword(53, size as i16);
+        }
+
+        let payload = if compress {
+            let block_size = options
+                .block_size
+                .unwrap_or_else(|| payload.len().div_ceil(100_000).clamp(1, 9) as u32);
+            let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::new(block_size));
+            encoder.write_all(&payload)?;
+            encoder.finish()?
+        } else {
+            payload
+        };
+
+        let mut out = Vec::with_capacity(MESSAGE_HEADERS_LEN + payload.len());
+        if let Some(text_header) = &self.text_header {
+            text_header.encode(&mut out)?;
+        }
+        let mut message_header = self.message_header.clone();
+        message_header.length = len_i32(
+            "Message",
+            MESSAGE_HEADERS_LEN + payload.len(),
+        )?;
+        message_header.encode(&mut out)?;
+        description.encode(&mut out)?;
+        out.extend_from_slice(&payload);
+        Ok(out)
+    }
+}
+
+pub(crate) fn put_i16(out: &mut Vec<u8>, value: i16) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+pub(crate) fn put_i32(out: &mut Vec<u8>, value: i32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Writes every value as a halfword.
+pub(crate) fn put_i16s(out: &mut Vec<u8>, values: &[i16]) {
+    for value in values {
+        put_i16(out, *value);
+    }
+}
+
+/// Checks that a count fits the halfword that holds it.
+pub(crate) fn len_i16(what: &'static str, len: usize) -> Result<i16, Error> {
+    i16::try_from(len).map_err(|_| Error::TooLong { what, len })
+}
+
+/// Checks that a count fits the fullword that holds it.
+pub(crate) fn len_i32(what: &'static str, len: usize) -> Result<i32, Error> {
+    i32::try_from(len).map_err(|_| Error::TooLong { what, len })
+}
+
+/// Writes `bytes`, which must be exactly `width` long.
+pub(crate) fn put_fixed(out: &mut Vec<u8>, field: &'static str, bytes: &[u8], width: usize) -> Result<(), Error> {
+    if bytes.len() != width {
+        return Err(Error::FieldWidth {
+            field,
+            expected: width,
+            actual: bytes.len(),
+        });
+    }
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Writes what `body` writes, preceded by its length in bytes as a halfword:
+/// the "length of block" convention most packets use.
+pub(crate) fn with_i16_length(
+    out: &mut Vec<u8>,
+    what: &'static str,
+    body: impl FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let at = out.len();
+    put_i16(out, 0);
+    body(out)?;
+    let len = len_i16(what, out.len() - at - 2)?;
+    out[at..at + 2].copy_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+/// As [`with_i16_length`], for blocks whose length is a fullword.
+pub(crate) fn with_i32_length(
+    out: &mut Vec<u8>,
+    what: &'static str,
+    body: impl FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let at = out.len();
+    put_i32(out, 0);
+    body(out)?;
+    let len = len_i32(what, out.len() - at - 4)?;
+    out[at..at + 4].copy_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+/// The bytes of `text` as 8-bit characters, the inverse of
+/// [`crate::product_symbology::latin1_string`]. Characters past U+00FF have
+/// no single-byte form and are written as `?`.
+pub(crate) fn latin1_bytes(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn length_prefix_counts_only_the_body() {
+        let mut out = vec![0xAA];
+        with_i16_length(&mut out, "test", |out| {
+            put_i16s(out, &[1, 2, 3]);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(out, [0xAA, 0, 6, 0, 1, 0, 2, 0, 3]);
+    }
+
+    #[test]
+    fn an_overlong_body_is_an_error() {
+        let err = with_i16_length(&mut Vec::new(), "Text packet", |out| {
+            out.extend(std::iter::repeat_n(0, 40_000));
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(matches!(err, Error::TooLong { what: "Text packet", len: 40_000 }));
+    }
+
+    #[test]
+    fn fixed_width_fields_must_match() {
+        let err = put_fixed(&mut Vec::new(), "threshold data", &[0; 30], 32).unwrap_err();
+        assert!(matches!(err, Error::FieldWidth { expected: 32, actual: 30, .. }));
+    }
+}
@@ -1,6 +1,9 @@
+use std::fmt;
+
 use thiserror::Error;
 
-use crate::codes::MessageCode;
+use crate::codes::{MessageCode, PacketCode};
+use crate::diagnostics::{self, Block, Diagnostic};
 
 /// Everything that can go wrong parsing or plotting a NEXRAD Level 3 file.
 ///
@@ -9,9 +12,15 @@ use crate::codes::MessageCode;
 /// instead, propagated via `?` through the `nom`-based block parsers.
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("Product type `{0}` is not supported")]
+    #[error("Message code `{0}` is not a product")]
     ProductType(MessageCode),
 
+    #[error("Product `{0}` has not been validated and the parse options reject unvalidated products")]
+    UnvalidatedProduct(MessageCode),
+
+    #[error("Strict parse rejected the file: {0}")]
+    Strict(Diagnostic),
+
     #[error("Product version is {0:?} but currently only version <= {1:?} are supported")]
     SupportedVersion(u8, Option<u8>),
 
@@ -30,43 +39,111 @@ pub enum Error {
     #[error("Failed to convert JSON")]
     Json(#[from] serde_json::Error),
 
-    #[error("byte error")]
-    Byte,
-
-    #[error("string error")]
-    Utf8,
-
     #[error("Product has no symbology block to plot")]
     NoSymbologyData,
 
     #[error("Symbology block has no data layers to plot")]
     NoSymbologyLayers,
 
-    #[error("Error - `{0}`")]
-    Other(String),
+    #[error("The first symbology layer holds no radial data packet")]
+    NoRadialData,
+
+    #[error("No symbology layer holds a data packet")]
+    NoDataPacket,
+
+    #[error("Could not draw the plot: {0}")]
+    Plot(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Grid resolution must be finite and above zero but is {0}")]
+    GridResolution(f64),
+
+    #[error("A grid of {nx} by {ny} cells is too large")]
+    GridSize { nx: usize, ny: usize },
+
+    #[error("The 1/4 LFM grid of packets 17 and 18 is polar stereographic about an origin the product does not give, so it cannot be georeferenced")]
+    LfmGrid,
+
+    #[cfg(feature = "arrow")]
+    #[error("Could not write the Arrow stream: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    #[error("{what} holds {len} bytes, more than its length field can count")]
+    TooLong { what: &'static str, len: usize },
+
+    #[error("Product `{0}` does not encode its data levels with a linear scale and offset")]
+    NoLinearScaling(MessageCode),
+
+    #[error("Halfword 51 says the product is bzip2 compressed, but the data after the product description is not a bzip2 stream")]
+    NotBzip2,
 
-    #[error("Error - `{0}`")]
-    Nom(String),
+    #[error("Compression method {0} in halfword 51 is not one Table V defines")]
+    CompressionMethod(i16),
+
+    #[error("Could not decompress the product, which may be truncated or corrupt: {0}")]
+    Decompress(#[source] std::io::Error),
+
+    #[error("{field} must be {expected} bytes but is {actual}")]
+    FieldWidth {
+        field: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+/// Where and why a block or packet parser gave up.
+///
+/// The context is whatever [`crate::Radar::from_vec_with`] was parsing when
+/// the failure happened, so it is all `None` when a block parser is called
+/// directly; the detail is always there.
+#[derive(Error, Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// The block being parsed.
+    pub block: Option<Block>,
+    /// Index of the symbology layer, when in the symbology block.
+    pub layer: Option<usize>,
+    /// The packet being decoded, when inside one.
+    pub packet_code: Option<PacketCode>,
+    /// Byte offset from the start of the file, counting the part after the
+    /// product description as it is once decompressed.
+    pub offset: Option<usize>,
+    /// What was expected and what was found instead.
+    pub detail: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Could not parse")?;
+        if let Some(block) = self.block {
+            write!(f, " the {block}")?;
+        }
+        if let Some(layer) = self.layer {
+            write!(f, ", layer {layer}")?;
+        }
+        if let Some(code) = self.packet_code {
+            write!(f, ", packet {code:?}")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, ", byte {offset}")?;
+        }
+        write!(f, ": {}", self.detail)
+    }
 }
 
 impl<E> From<plotters::drawing::DrawingAreaErrorKind<E>> for Error
 where
-    E: std::error::Error + Send + Sync,
+    E: std::error::Error + Send + Sync + 'static,
 {
     fn from(value: plotters::drawing::DrawingAreaErrorKind<E>) -> Self {
-        Error::Other(value.to_string())
+        Error::Plot(Box::new(value))
     }
 }
 
 impl From<nom::Err<nom::error::Error<&[u8]>>> for Error {
     fn from(value: nom::Err<nom::error::Error<&[u8]>>) -> Self {
-        Error::Nom(format!("{value:?}"))
-    }
-}
-
-impl From<Box<dyn std::error::Error>> for Error {
-    fn from(value: Box<dyn std::error::Error>) -> Self {
-        Error::Other(value.to_string())
+        Error::Parse(diagnostics::parse_error(&value))
     }
 }
 
@@ -84,13 +161,30 @@ mod tests {
         let nom_error =
             nom::Err::Error(nom::error::Error::new(&b"abc"[..], nom::error::ErrorKind::Tag));
         let error: Error = nom_error.into();
-        assert!(matches!(error, Error::Nom(_)));
+        assert!(matches!(error, Error::Parse(_)));
+    }
+
+    #[test]
+    fn parse_error_names_its_context() {
+        let error = ParseError {
+            block: Some(Block::Symbology),
+            layer: Some(0),
+            packet_code: Some(PacketCode::DigitalRadialDataArray),
+            offset: Some(1234),
+            detail: "expected 460 bins but found -3".to_string(),
+        };
+        assert_eq!(
+            Error::Parse(error).to_string(),
+            "Could not parse the symbology block, layer 0, packet DigitalRadialDataArray, byte 1234: \
+             expected 460 bins but found -3"
+        );
     }
 
     #[test]
-    fn boxed_error_conversion_preserves_the_message() {
-        let boxed: Box<dyn std::error::Error> = "something specific went wrong".into();
-        let error: Error = boxed.into();
-        assert_eq!(error.to_string(), "Error - `something specific went wrong`");
+    fn plot_errors_keep_their_source() {
+        let drawing: plotters::drawing::DrawingAreaErrorKind<std::io::Error> =
+            plotters::drawing::DrawingAreaErrorKind::LayoutError;
+        let error: Error = drawing.into();
+        assert!(matches!(error, Error::Plot(_)));
     }
 }
\ No newline at end of file
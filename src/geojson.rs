@@ -0,0 +1,528 @@
+//! GeoJSON export of the symbology block's symbols, tracks, contours and
+//! generic areas.
+//!
+//! Every feature is placed in WGS84 longitude and latitude. I/J coordinates
+//! are 1/4 km east and south of the radar on the display, which is taken as
+//! the azimuthal equidistant projection about the site
+//! ([`crate::Site::offset`]); Appendix E areas are placed from whichever of
+//! lat/lon, X/Y or azimuth/range their area type selects.
+//!
+//! Each feature's properties name what it is (`kind`), the layer it came
+//! from and its packet code, plus whatever the packet carries:
+//!
+//! | Packet | Geometry | `kind` | Other properties |
+//! | -- | -- | -- | -- |
+//! | 3, 11 mesocyclone | Point | `mesocyclone` | `radius_km` |
+//! | 12 TVS, 26 ETVS | Point | `tvs`, `etvs` | |
+//! | 13, 14 hail | Point | `hail_positive`, `hail_probable` | |
+//! | 15 storm ID | Point | `storm_id` | `storm_id` |
+//! | 19 HDA hail | Point | `hail` | `probability_of_hail`, `probability_of_severe_hail`, `max_hail_size_in` |
+//! | 20 point feature | Point | `point_feature` | `feature_type`, `feature_attribute` |
+//! | 25 STI circle | Point | `sti_circle` | `radius_km` |
+//! | 23, 24 SCIT tracks | LineString | `sti_past_track`, `sti_forecast_track` | |
+//! | 1, 8 text | Point | `text` | `text`, `color_level` |
+//! | 2 special symbols | Point | `special_symbol` | `text`, `color_level` |
+//! | 6, 9 linked vectors | LineString | `vector` | `color_level` |
+//! | 7, 10 unlinked vectors | MultiLineString | `vector` | `color_level` |
+//! | `0E03`, `3501` contours | LineString, MultiLineString | `contour` | `color_level`, from the preceding `0802` |
+//! | 4 wind barb | Point | `wind_barb` | `direction_deg`, `speed_kt`, `color_level` |
+//! | 28, 29 area components | Point, Polygon, LineString | `area` | the component's parameters |
+//! | `0E23`, `3521` map vectors | none | `map_vector` | `points_eighth_km` or `segments_eighth_km` |
+//! | `4E00`, `4E01` map text | none | `map_text`, `map_special_symbol` | `position_eighth_km`, `text` |
+//!
+//! Map message features have a `null` geometry: their 1/8 km coordinates
+//! start at the corner of an area of coverage the packets do not describe,
+//! so they are given as they are. Radial, raster and other data packets are
+//! left out.
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    AreaComponent, AreaShape, ContourVectorPacket, GenericComponent, GenericPoint, LatLon, MapMessagePacket,
+    PacketCode, Point, Radar, SpecialSymbolPacket, Site, SymPacketData, Vector,
+};
+
+/// Metres per I/J unit.
+const SCREEN_UNIT: f64 = 250.0;
+
+impl Radar {
+    /// The symbology block's symbols, tracks, contours and generic areas as a
+    /// GeoJSON `FeatureCollection` in WGS84. A product without a symbology
+    /// block gives an empty collection.
+    pub fn to_geojson(&self) -> Value {
+        let site = Site::from_description(&self.product_description);
+        let mut features = Vec::new();
+        for (index, layer) in self.symbology.iter().flat_map(|s| s.layers.iter()).enumerate() {
+            let mut exporter = Exporter {
+                site,
+                layer: index,
+                color_level: None,
+                features: &mut features,
+            };
+            for packet in layer {
+                exporter.packet(packet);
+            }
+        }
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+}
+
+/// Collects the features of one symbology layer.
+struct Exporter<'a> {
+    site: Site,
+    layer: usize,
+    /// The colour level the last `0802` packet set, for the contours after it.
+    color_level: Option<i16>,
+    features: &'a mut Vec<Value>,
+}
+
+impl Exporter<'_> {
+    fn packet(&mut self, packet: &SymPacketData) {
+        match packet {
+            SymPacketData::SpecialGraphicSymbol(symbols) => self.special_symbols(symbols),
+            SymPacketData::TextAndSpecialSymbol1(text) | SymPacketData::TextAndSpecialSymbol8(text) => {
+                // Packet 2 shares code 1's variant, but its characters select
+                // special symbols rather than spelling text.
+                let kind = match text.packet_code {
+                    PacketCode::TextAndSpecialSymbol2 => "special_symbol",
+                    _ => "text",
+                };
+                self.push(
+                    kind,
+                    text.packet_code as i16,
+                    self.point(text.i_coord, text.j_coord),
+                    json!({ "text": text.text, "color_level": text.color_level }),
+                );
+            }
+            SymPacketData::LinkedVector(vector) => {
+                let code = if vector.value.is_some() { 9 } else { 6 };
+                self.push(
+                    "vector",
+                    code,
+                    self.line_string(vector.start, &vector.ends),
+                    json!({ "color_level": vector.value }),
+                );
+            }
+            SymPacketData::UnlinkedVector(vector) => {
+                let code = if vector.value.is_some() { 10 } else { 7 };
+                self.push(
+                    "vector",
+                    code,
+                    self.multi_line_string(&vector.vectors),
+                    json!({ "color_level": vector.value }),
+                );
+            }
+            SymPacketData::ContourVector(contour) => match contour {
+                ContourVectorPacket::SetColorLevel { value, .. } => self.color_level = Some(*value),
+                ContourVectorPacket::Linked { start, ends, .. } => self.push(
+                    "contour",
+                    0x0E03,
+                    self.line_string(*start, ends),
+                    json!({ "color_level": self.color_level }),
+                ),
+                ContourVectorPacket::Unlinked { vectors } => self.push(
+                    "contour",
+                    0x3501,
+                    self.multi_line_string(vectors),
+                    json!({ "color_level": self.color_level }),
+                ),
+            },
+            SymPacketData::WindBarbData(barbs) => {
+                for barb in &barbs.barbs {
+                    self.push(
+                        "wind_barb",
+                        4,
+                        self.point(barb.x_coord, barb.y_coord),
+                        json!({
+                            "direction_deg": barb.direction,
+                            "speed_kt": barb.speed,
+                            "color_level": barb.value,
+                        }),
+                    );
+                }
+            }
+            SymPacketData::GenericData(generic) => {
+                let components = match (&generic.product_description, &generic.external_description) {
+                    (Some(description), _) => &description.components,
+                    (None, Some(description)) => &description.components,
+                    (None, None) => return,
+                };
+                self.components(generic.header.packet_code, components);
+            }
+            SymPacketData::MapMessage(message) => self.map_message(message),
+            _ => {}
+        }
+    }
+
+    /// Map messages are measured in 1/8 km from the upper left corner of an
+    /// area of coverage the product does not describe, so they cannot be
+    /// placed. They are kept as features without a geometry, carrying their
+    /// screen coordinates.
+    fn map_message(&mut self, message: &MapMessagePacket) {
+        let eighths = |p: &Point| json!([p.i, p.j]);
+        let (kind, packet_code, properties) = match message {
+            MapMessagePacket::LinkedVector { start, ends, .. } => {
+                let points: Vec<Value> = std::iter::once(start).chain(ends).map(eighths).collect();
+                ("map_vector", 0x0E23, json!({ "points_eighth_km": points }))
+            }
+            MapMessagePacket::UnlinkedVector { vectors } => {
+                let segments: Vec<Value> = vectors.iter().map(|v| json!([eighths(&v.begin), eighths(&v.end)])).collect();
+                ("map_vector", 0x3521, json!({ "segments_eighth_km": segments }))
+            }
+            MapMessagePacket::Text {
+                packet_code,
+                position,
+                text,
+                ..
+            } => {
+                let kind = if message.is_special_symbols() { "map_special_symbol" } else { "map_text" };
+                (kind, *packet_code, json!({ "position_eighth_km": eighths(position), "text": text }))
+            }
+        };
+        self.push(kind, packet_code, Value::Null, properties);
+    }
+
+    fn special_symbols(&mut self, symbols: &SpecialSymbolPacket) {
+        match symbols {
+            SpecialSymbolPacket::Mesocyclone { packet_code, symbols } => {
+                // A radius of 0 means no mesocyclone (Figure 3-14 sheet 3).
+                for symbol in symbols.iter().filter(|s| s.radius != 0) {
+                    self.push(
+                        "mesocyclone",
+                        *packet_code,
+                        self.point(symbol.i_position, symbol.j_position),
+                        json!({ "radius_km": symbol.radius as f64 / 4.0 }),
+                    );
+                }
+            }
+            SpecialSymbolPacket::Position { packet_code, symbols } => {
+                let kind = match packet_code {
+                    12 => "tvs",
+                    26 => "etvs",
+                    13 => "hail_positive",
+                    _ => "hail_probable",
+                };
+                for symbol in symbols {
+                    self.push(
+                        kind,
+                        *packet_code,
+                        self.point(symbol.i_position, symbol.j_position),
+                        json!({}),
+                    );
+                }
+            }
+            SpecialSymbolPacket::StormId { symbols } => {
+                for symbol in symbols {
+                    self.push(
+                        "storm_id",
+                        15,
+                        self.point(symbol.i_position, symbol.j_position),
+                        json!({ "storm_id": symbol.storm_id }),
+                    );
+                }
+            }
+            SpecialSymbolPacket::Hail { symbols } => {
+                for symbol in symbols {
+                    self.push(
+                        "hail",
+                        19,
+                        self.point(symbol.i_position, symbol.j_position),
+                        json!({
+                            "probability_of_hail": symbol.probability_of_hail,
+                            "probability_of_severe_hail": symbol.probability_of_severe_hail,
+                            "max_hail_size_in": symbol.max_hail_size,
+                        }),
+                    );
+                }
+            }
+            SpecialSymbolPacket::PointFeature { symbols } => {
+                for symbol in symbols {
+                    self.push(
+                        "point_feature",
+                        20,
+                        self.point(symbol.i_position, symbol.j_position),
+                        json!({
+                            "feature_type": symbol.feature_type,
+                            "feature_attribute": symbol.feature_attribute,
+                        }),
+                    );
+                }
+            }
+            SpecialSymbolPacket::StiCircle { symbols } => {
+                for symbol in symbols {
+                    self.push(
+                        "sti_circle",
+                        25,
+                        self.point(symbol.i_position, symbol.j_position),
+                        json!({ "radius_km": symbol.radius as f64 / 4.0 }),
+                    );
+                }
+            }
+            SpecialSymbolPacket::ScitData { packet_code, .. } => {
+                let kind = if *packet_code == 23 { "sti_past_track" } else { "sti_forecast_track" };
+                for nested in symbols.nested_packets() {
+                    match nested {
+                        SymPacketData::LinkedVector(track) => {
+                            self.push(kind, *packet_code, self.line_string(track.start, &track.ends), json!({}))
+                        }
+                        other => self.packet(&other),
+                    }
+                }
+            }
+        }
+    }
+
+    fn components(&mut self, packet_code: i16, components: &[GenericComponent]) {
+        for component in components {
+            match component {
+                GenericComponent::Area(area) => self.area(packet_code, area),
+                GenericComponent::Event(event) => self.components(packet_code, &event.components),
+                _ => {}
+            }
+        }
+    }
+
+    fn area(&mut self, packet_code: i16, area: &AreaComponent) {
+        let positions: Vec<Value> = area.points.iter().map(|p| position(self.generic_point(p))).collect();
+        let geometry = match area.shape {
+            AreaShape::Point if positions.len() == 1 => json!({ "type": "Point", "coordinates": positions[0] }),
+            AreaShape::Point => json!({ "type": "MultiPoint", "coordinates": positions }),
+            AreaShape::Area => {
+                // GeoJSON rings are closed: the last position repeats the first.
+                let mut ring = positions;
+                if let Some(first) = ring.first().cloned() {
+                    if ring.last() != Some(&first) {
+                        ring.push(first);
+                    }
+                }
+                json!({ "type": "Polygon", "coordinates": [ring] })
+            }
+            AreaShape::Polyline | AreaShape::Unknown(_) => json!({ "type": "LineString", "coordinates": positions }),
+        };
+        let parameters: Map<String, Value> = area
+            .parameters
+            .iter()
+            .map(|p| (p.name.clone(), Value::String(p.value.clone())))
+            .collect();
+        self.push("area", packet_code, geometry, Value::Object(parameters));
+    }
+
+    /// Adds a feature, with `kind`, `layer` and `packet_code` alongside the
+    /// packet's own properties.
+    fn push(&mut self, kind: &str, packet_code: i16, geometry: Value, properties: Value) {
+        let mut all = Map::new();
+        all.insert("kind".into(), kind.into());
+        all.insert("layer".into(), self.layer.into());
+        all.insert("packet_code".into(), packet_code.into());
+        if let Value::Object(properties) = properties {
+            all.extend(properties);
+        }
+        self.features
+            .push(json!({ "type": "Feature", "geometry": geometry, "properties": all }));
+    }
+
+    /// Where an I/J point is on the ground.
+    fn screen(&self, i: i16, j: i16) -> LatLon {
+        self.site.offset(i as f64 * SCREEN_UNIT, -(j as f64) * SCREEN_UNIT)
+    }
+
+    fn point(&self, i: i16, j: i16) -> Value {
+        json!({ "type": "Point", "coordinates": position(self.screen(i, j)) })
+    }
+
+    fn line_string(&self, start: Point, ends: &[Point]) -> Value {
+        let coordinates: Vec<Value> = std::iter::once(&start)
+            .chain(ends)
+            .map(|p| position(self.screen(p.i, p.j)))
+            .collect();
+        json!({ "type": "LineString", "coordinates": coordinates })
+    }
+
+    fn multi_line_string(&self, vectors: &[Vector]) -> Value {
+        let coordinates: Vec<Value> = vectors
+            .iter()
+            .map(|v| json!([position(self.screen(v.begin.i, v.begin.j)), position(self.screen(v.end.i, v.end.j))]))
+            .collect();
+        json!({ "type": "MultiLineString", "coordinates": coordinates })
+    }
+
+    /// Where an Appendix E area point is: X/Y are kilometres east and north
+    /// of the radar, azimuth/range degrees and kilometres.
+    fn generic_point(&self, point: &GenericPoint) -> LatLon {
+        match *point {
+            GenericPoint::LatLon { latitude, longitude } => LatLon {
+                latitude: latitude as f64,
+                longitude: longitude as f64,
+            },
+            GenericPoint::XY { x, y } => self.site.offset(x as f64 * 1000.0, y as f64 * 1000.0),
+            GenericPoint::AzRan { azimuth, range } => self.site.project(azimuth as f64, range as f64 * 1000.0),
+        }
+    }
+}
+
+/// A GeoJSON position: longitude first.
+pub(crate) fn position(point: LatLon) -> Value {
+    json!([point.longitude, point.latitude])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CircleSymbol, ContourVectorPacket, GenericDataHeader, GenericDataPacket, GenericProductDescription,
+        MessageCode, Parameter, PointSymbol, ProductBuilder, StormIdSymbol, TextPacket,
+    };
+
+    fn radar(layer: Vec<SymPacketData>) -> Radar {
+        let bytes = ProductBuilder::new(MessageCode::StormRelativeMeanRadialVelocity, "KMKX")
+            .with_layer(layer)
+            .to_vec()
+            .unwrap();
+        Radar::from_vec(bytes).unwrap().1
+    }
+
+    fn kinds(collection: &Value) -> Vec<&str> {
+        collection["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["properties"]["kind"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn symbols_become_points_with_their_attributes() {
+        let radar = radar(vec![
+            SymPacketData::SpecialGraphicSymbol(SpecialSymbolPacket::Mesocyclone {
+                packet_code: 3,
+                symbols: vec![
+                    CircleSymbol { i_position: 40, j_position: 0, radius: 8 },
+                    CircleSymbol { i_position: 0, j_position: 0, radius: 0 },
+                ],
+            }),
+            SymPacketData::SpecialGraphicSymbol(SpecialSymbolPacket::Position {
+                packet_code: 12,
+                symbols: vec![PointSymbol { i_position: 0, j_position: -40 }],
+            }),
+            SymPacketData::SpecialGraphicSymbol(SpecialSymbolPacket::StormId {
+                symbols: vec![StormIdSymbol { i_position: 0, j_position: 40, storm_id: "A1".into() }],
+            }),
+        ]);
+        let collection = radar.to_geojson();
+        assert_eq!(collection["type"], "FeatureCollection");
+        assert_eq!(kinds(&collection), ["mesocyclone", "tvs", "storm_id"]);
+
+        let site = Site::from_description(&radar.product_description);
+        let [meso, tvs, storm] = [0, 1, 2].map(|n| collection["features"][n].clone());
+        assert_eq!(meso["properties"]["radius_km"], 2.0);
+        assert_eq!(meso["properties"]["layer"], 0);
+        assert_eq!(meso["properties"]["packet_code"], 3);
+        // 10 km east, north and south: longitude first.
+        assert!(meso["geometry"]["coordinates"][0].as_f64().unwrap() > site.longitude);
+        assert!(tvs["geometry"]["coordinates"][1].as_f64().unwrap() > site.latitude);
+        assert!(storm["geometry"]["coordinates"][1].as_f64().unwrap() < site.latitude);
+        assert_eq!(storm["properties"]["storm_id"], "A1");
+    }
+
+    #[test]
+    fn contours_carry_the_colour_level_set_before_them() {
+        let radar = radar(vec![
+            SymPacketData::ContourVector(ContourVectorPacket::SetColorLevel { indicator: 2, value: 7 }),
+            SymPacketData::ContourVector(ContourVectorPacket::Linked {
+                indicator: -0x8000,
+                start: Point { i: 0, j: 0 },
+                ends: vec![Point { i: 10, j: 0 }, Point { i: 10, j: 10 }],
+            }),
+        ]);
+        let collection = radar.to_geojson();
+        let contour = &collection["features"][0];
+        assert_eq!(contour["properties"]["kind"], "contour");
+        assert_eq!(contour["properties"]["color_level"], 7);
+        assert_eq!(contour["geometry"]["type"], "LineString");
+        assert_eq!(contour["geometry"]["coordinates"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn area_components_become_closed_polygons() {
+        let area = AreaComponent {
+            parameters: vec![Parameter { name: "id".into(), value: "7".into() }],
+            area_type: 2,
+            shape: AreaShape::Area,
+            points: vec![
+                GenericPoint::LatLon { latitude: 43.0, longitude: -88.0 },
+                GenericPoint::LatLon { latitude: 43.0, longitude: -87.0 },
+                GenericPoint::LatLon { latitude: 44.0, longitude: -87.0 },
+            ],
+        };
+        let packet = GenericDataPacket {
+            header: GenericDataHeader { packet_code: 28, reserved: 0, length: 0 },
+            product_description: Some(GenericProductDescription {
+                components: vec![GenericComponent::Area(area)],
+                ..Default::default()
+            }),
+            external_description: None,
+            raw: Vec::new(),
+        };
+        let radar = radar(vec![SymPacketData::GenericData(Box::new(packet))]);
+        let collection = radar.to_geojson();
+        let feature = &collection["features"][0];
+        assert_eq!(feature["geometry"]["type"], "Polygon");
+        let ring = feature["geometry"]["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.len(), 4);
+        assert_eq!(ring[0], ring[3]);
+        assert_eq!(ring[1], json!([-87.0, 43.0]));
+        assert_eq!(feature["properties"]["id"], "7");
+    }
+
+    #[test]
+    fn text_and_special_symbols_keep_their_packet_code() {
+        let text = TextPacket::new(0, 0, "A", None);
+        let symbols = TextPacket {
+            packet_code: PacketCode::TextAndSpecialSymbol2,
+            ..TextPacket::new(4, 4, "!", None)
+        };
+        let coloured = TextPacket::new(8, 8, "B", Some(3));
+        let collection = radar(vec![
+            SymPacketData::TextAndSpecialSymbol1(text),
+            SymPacketData::TextAndSpecialSymbol1(symbols),
+            SymPacketData::TextAndSpecialSymbol8(coloured),
+        ])
+        .to_geojson();
+        assert_eq!(kinds(&collection), ["text", "special_symbol", "text"]);
+        let codes: Vec<&Value> = (0..3).map(|n| &collection["features"][n]["properties"]["packet_code"]).collect();
+        assert_eq!(codes, [1, 2, 8]);
+    }
+
+    #[test]
+    fn map_messages_are_kept_without_a_geometry() {
+        let collection = radar(vec![
+            SymPacketData::MapMessage(MapMessagePacket::LinkedVector {
+                indicator: 0x8000u16 as i16,
+                start: Point { i: 8, j: 16 },
+                ends: vec![Point { i: 24, j: 16 }],
+            }),
+            SymPacketData::MapMessage(MapMessagePacket::Text {
+                packet_code: 0x4E00,
+                indicator: 0x0C23,
+                initial_point_indicator: 0x8000u16 as i16,
+                position: Point { i: 40, j: 80 },
+                text: "MKE".into(),
+            }),
+        ])
+        .to_geojson();
+        assert_eq!(kinds(&collection), ["map_vector", "map_text"]);
+        let [vector, text] = [0, 1].map(|n| collection["features"][n].clone());
+        assert_eq!(vector["geometry"], Value::Null);
+        assert_eq!(vector["properties"]["packet_code"], 0x0E23);
+        assert_eq!(vector["properties"]["points_eighth_km"], json!([[8, 16], [24, 16]]));
+        assert_eq!(text["properties"]["position_eighth_km"], json!([40, 80]));
+        assert_eq!(text["properties"]["text"], "MKE");
+    }
+
+    #[test]
+    fn products_without_symbology_give_an_empty_collection() {
+        let mut radar = radar(Vec::new());
+        radar.symbology = None;
+        assert_eq!(radar.to_geojson()["features"], json!([]));
+    }
+}
@@ -0,0 +1,563 @@
+//! Latitude, longitude and height of the range bins of radial products.
+//!
+//! A bin is located by following the beam out from the site under the 4/3
+//! effective earth radius model, which accounts for standard atmospheric
+//! refraction, to find how far along the ground it is; that ground range and
+//! the bin's azimuth are then projected with an azimuthal equidistant
+//! projection centred on the site. The earth is taken to be a sphere of
+//! [`EARTH_RADIUS`], which puts a bin at 230 km within a few tens of metres of
+//! where an ellipsoidal solution would.
+//!
+//! The same model gives the height of the beam at each bin, and with the
+//! WSR-88D's [`BEAMWIDTH`], the heights of the bottom and top of the beam.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DigitalRadialPacket, MessageCode, ProductDescription, RadialComponent, RadialPacket};
+
+/// Mean radius of the earth, metres.
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Ratio of the effective earth radius to [`EARTH_RADIUS`] under standard
+/// refraction.
+pub const EFFECTIVE_RADIUS_FACTOR: f64 = 4.0 / 3.0;
+
+/// Half-power beamwidth of the WSR-88D antenna, degrees.
+pub const BEAMWIDTH: f64 = 0.95;
+
+const FEET_TO_METRES: f64 = 0.3048;
+
+/// A point on the earth, degrees, + for north and east.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub struct LatLon {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// The radar antenna's position.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub struct Site {
+    /// Degrees, + for north.
+    pub latitude: f64,
+    /// Degrees, + for east.
+    pub longitude: f64,
+    /// Metres above mean sea level.
+    pub height: f64,
+}
+
+impl Site {
+    /// The site a product was generated for, from the product description's
+    /// thousandths of a degree and feet.
+    pub fn from_description(pd: &ProductDescription) -> Site {
+        Site {
+            latitude: pd.latitude as f64 / 1000.0,
+            longitude: pd.longitude as f64 / 1000.0,
+            height: pd.height as f64 * FEET_TO_METRES,
+        }
+    }
+
+    /// Distance along the earth's surface, metres, from the site to below a
+    /// point `slant_range` metres along a beam raised `elevation` degrees.
+    pub fn ground_range(&self, slant_range: f64, elevation: f64) -> f64 {
+        let effective_radius = EFFECTIVE_RADIUS_FACTOR * EARTH_RADIUS;
+        let height = self.beam_height(slant_range, elevation);
+        let elevation = elevation.to_radians();
+        effective_radius * (slant_range * elevation.cos() / (effective_radius + height)).asin()
+    }
+
+    /// The point `ground_range` metres from the site along the great circle
+    /// leaving it at `azimuth` degrees clockwise from north: the inverse of
+    /// the azimuthal equidistant projection about the site.
+    pub fn project(&self, azimuth: f64, ground_range: f64) -> LatLon {
+        let (latitude, longitude) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let azimuth = azimuth.to_radians();
+        let distance = ground_range / EARTH_RADIUS;
+
+        let to_latitude = (latitude.sin() * distance.cos()
+            + latitude.cos() * distance.sin() * azimuth.cos())
+        .asin();
+        let to_longitude = longitude
+            + (azimuth.sin() * distance.sin() * latitude.cos())
+                .atan2(distance.cos() - latitude.sin() * to_latitude.sin());
+        LatLon {
+            latitude: to_latitude.to_degrees(),
+            longitude: normalize_longitude(to_longitude.to_degrees()),
+        }
+    }
+
+    /// The point `east` and `north` metres from the site on the azimuthal
+    /// equidistant projection about it, which is how the symbology block's
+    /// I/J coordinates and Appendix E's X/Y points lay out the ground.
+    pub fn offset(&self, east: f64, north: f64) -> LatLon {
+        self.project(east.atan2(north).to_degrees(), east.hypot(north))
+    }
+
+    /// Height of the beam centre above the antenna, metres, `slant_range`
+    /// metres out along a beam raised `elevation` degrees (Doviak and Zrnić,
+    /// eq. 2.28b). Add [`Site::height`] for the height above sea level.
+    pub fn beam_height(&self, slant_range: f64, elevation: f64) -> f64 {
+        let effective_radius = EFFECTIVE_RADIUS_FACTOR * EARTH_RADIUS;
+        (slant_range.powi(2)
+            + effective_radius.powi(2)
+            + 2.0 * slant_range * effective_radius * elevation.to_radians().sin())
+        .sqrt()
+            - effective_radius
+    }
+
+    /// Heights above sea level of the bottom, centre and top of a beam
+    /// raised `elevation` degrees, `slant_range` metres out, taking the
+    /// bottom and top half a [`BEAMWIDTH`] either side of the centre.
+    pub fn beam(&self, slant_range: f64, elevation: f64) -> BeamHeight {
+        let above_sea_level = |elevation| self.height + self.beam_height(slant_range, elevation);
+        BeamHeight {
+            bottom: above_sea_level(elevation - BEAMWIDTH / 2.0),
+            centre: above_sea_level(elevation),
+            top: above_sea_level(elevation + BEAMWIDTH / 2.0),
+        }
+    }
+
+    /// The point below where a beam raised `elevation` degrees and pointing
+    /// `azimuth` degrees clockwise from north is `slant_range` metres from the
+    /// antenna.
+    pub fn locate(&self, azimuth: f64, slant_range: f64, elevation: f64) -> LatLon {
+        self.project(azimuth, self.ground_range(slant_range, elevation))
+    }
+
+    /// Slant range, metres, along a beam raised `elevation` degrees to above
+    /// the point `ground_range` metres away: the inverse of
+    /// [`Site::ground_range`].
+    pub fn slant_range(&self, ground_range: f64, elevation: f64) -> f64 {
+        let effective_radius = EFFECTIVE_RADIUS_FACTOR * EARTH_RADIUS;
+        let angle = ground_range / effective_radius;
+        effective_radius * angle.sin() / (angle + elevation.to_radians()).cos()
+    }
+
+    /// Azimuth, degrees clockwise from north in `0..360`, and distance along
+    /// the earth's surface, metres, from the site to `point`: the azimuthal
+    /// equidistant projection about the site, in polar form.
+    pub fn azimuth_range(&self, point: LatLon) -> (f64, f64) {
+        let (from_latitude, to_latitude) = (self.latitude.to_radians(), point.latitude.to_radians());
+        let delta_latitude = to_latitude - from_latitude;
+        let delta_longitude = (point.longitude - self.longitude).to_radians();
+
+        let azimuth = (delta_longitude.sin() * to_latitude.cos()).atan2(
+            from_latitude.cos() * to_latitude.sin()
+                - from_latitude.sin() * to_latitude.cos() * delta_longitude.cos(),
+        );
+        let haversine = (delta_latitude / 2.0).sin().powi(2)
+            + from_latitude.cos() * to_latitude.cos() * (delta_longitude / 2.0).sin().powi(2);
+        let distance = 2.0 * haversine.sqrt().atan2((1.0 - haversine).sqrt());
+        (azimuth.to_degrees().rem_euclid(360.0), distance * EARTH_RADIUS)
+    }
+}
+
+/// How high a beam reaches at one range, metres above mean sea level.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub struct BeamHeight {
+    /// The lower half-power edge of the beam.
+    pub bottom: f64,
+    pub centre: f64,
+    /// The upper half-power edge of the beam.
+    pub top: f64,
+}
+
+impl BeamHeight {
+    /// Whether the whole beam, bottom edge included, is above `height`
+    /// metres above sea level, such as the top of the melting layer.
+    pub fn is_above(&self, height: f64) -> bool {
+        self.bottom > height
+    }
+}
+
+fn normalize_longitude(longitude: f64) -> f64 {
+    (longitude + 540.0).rem_euclid(360.0) - 180.0
+}
+
+/// One radial's angular extent.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub struct RadialSpan {
+    /// Azimuth of the radial's leading edge, degrees clockwise from north.
+    pub azimuth: f64,
+    /// Angular width of the radial, degrees.
+    pub width: f64,
+    /// Elevation angle of the beam, degrees.
+    pub elevation: f64,
+}
+
+/// Where the bins of one radial packet or component are: the site, the range
+/// to the first bin and the bin size, and each radial's azimuths.
+///
+/// Radials and bins are indexed as in the packet they came from, so
+/// `bin_centre(r, b)` is the location of `radials[r].data[b]`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RadialGeometry {
+    pub site: Site,
+    /// Slant range to the centre of the first bin, metres.
+    pub first_bin_range: f64,
+    /// Range extent of each bin, metres.
+    pub bin_size: f64,
+    /// Number of bins along each radial.
+    pub num_bins: usize,
+    pub radials: Vec<RadialSpan>,
+}
+
+impl RadialGeometry {
+    /// The geometry of a Radial Data packet (`AF1F`) from the product
+    /// described by `pd`.
+    ///
+    /// The packet's bins are the size Table III gives for the product
+    /// ([`MessageCode::bin_size`]), or its range scale factor in thousandths
+    /// of a kilometre where the product is not listed. The beam is at the
+    /// elevation the product description gives, or 0° for products that
+    /// combine several elevations, which places their bins at the range they
+    /// are labelled with.
+    pub fn from_radial_packet(packet: &RadialPacket, pd: &ProductDescription) -> RadialGeometry {
+        let header = &packet.header;
+        let radials = packet.radials.iter().map(|radial| (radial.header.angle_start, radial.header.angle_delta));
+        RadialGeometry::from_packet_header(
+            pd,
+            header.first_bin,
+            header.num_bins,
+            header.range_scale,
+            radials,
+        )
+    }
+
+    /// The geometry of a Digital Radial Data Array packet (code 16), as for
+    /// [`RadialGeometry::from_radial_packet`].
+    pub fn from_digital_radial_packet(packet: &DigitalRadialPacket, pd: &ProductDescription) -> RadialGeometry {
+        let header = &packet.header;
+        let radials = packet.radials.iter().map(|radial| (radial.header.angle_start, radial.header.angle_delta));
+        RadialGeometry::from_packet_header(
+            pd,
+            header.first_bin,
+            header.num_bins,
+            header.range_scale,
+            radials,
+        )
+    }
+
+    fn from_packet_header(
+        pd: &ProductDescription,
+        first_bin: i16,
+        num_bins: i16,
+        range_scale: i16,
+        radials: impl Iterator<Item = (i16, i16)>,
+    ) -> RadialGeometry {
+        let code = <MessageCode as num::FromPrimitive>::from_i16(pd.product_code).unwrap_or_default();
+        let bin_size = code.bin_size().unwrap_or(range_scale as f64);
+        let elevation = pd.dependent_params().elevation_angle().unwrap_or(0.0) as f64;
+        RadialGeometry {
+            site: Site::from_description(pd),
+            first_bin_range: (first_bin as f64 + 0.5) * bin_size,
+            bin_size,
+            num_bins: num_bins.max(0) as usize,
+            radials: radials
+                .map(|(start, delta)| RadialSpan {
+                    azimuth: start as f64 / 10.0,
+                    width: delta as f64 / 10.0,
+                    elevation,
+                })
+                .collect(),
+        }
+    }
+
+    /// The geometry of a generic Radial Component, which carries its own bin
+    /// size, first bin range, and per-radial elevation.
+    pub fn from_radial_component(component: &RadialComponent, pd: &ProductDescription) -> RadialGeometry {
+        RadialGeometry {
+            site: Site::from_description(pd),
+            first_bin_range: component.first_bin_range as f64,
+            bin_size: component.bin_size as f64,
+            num_bins: component.radials.iter().map(|r| r.num_bins.max(0) as usize).max().unwrap_or(0),
+            radials: component
+                .radials
+                .iter()
+                .map(|radial| RadialSpan {
+                    azimuth: radial.azimuth as f64,
+                    width: radial.width as f64,
+                    elevation: radial.elevation as f64,
+                })
+                .collect(),
+        }
+    }
+
+    /// Slant range to the centre of `bin`, metres.
+    pub fn bin_range(&self, bin: usize) -> f64 {
+        self.first_bin_range + bin as f64 * self.bin_size
+    }
+
+    /// The radial and bin that `point` falls in, or `None` outside every
+    /// radial or past the first or last bin.
+    pub fn bin_at(&self, point: LatLon) -> Option<(usize, usize)> {
+        let (azimuth, ground_range) = self.site.azimuth_range(point);
+        let radial = self
+            .radials
+            .iter()
+            .position(|span| (azimuth - span.azimuth).rem_euclid(360.0) < span.width)?;
+        let slant_range = self.site.slant_range(ground_range, self.radials[radial].elevation);
+        let bin = ((slant_range - self.first_bin_range) / self.bin_size).round();
+        if bin < 0.0 || bin >= self.num_bins as f64 {
+            return None;
+        }
+        Some((radial, bin as usize))
+    }
+
+    /// Centre of bin `bin` of radial `radial`: half way along the bin and
+    /// across the radial. `None` if either is out of range.
+    pub fn bin_centre(&self, radial: usize, bin: usize) -> Option<LatLon> {
+        let span = self.radials.get(radial)?;
+        if bin >= self.num_bins {
+            return None;
+        }
+        let azimuth = span.azimuth + span.width / 2.0;
+        Some(self.site.locate(azimuth, self.bin_range(bin), span.elevation))
+    }
+
+    /// Corners of bin `bin` of radial `radial`, in the order near edge at the
+    /// leading azimuth, near edge at the trailing azimuth, then the far edge
+    /// trailing and leading, so they trace the bin's outline. `None` if
+    /// either is out of range.
+    pub fn bin_corners(&self, radial: usize, bin: usize) -> Option<[LatLon; 4]> {
+        let span = self.radials.get(radial)?;
+        if bin >= self.num_bins {
+            return None;
+        }
+        let centre = self.bin_range(bin);
+        let (near, far) = ((centre - self.bin_size / 2.0).max(0.0), centre + self.bin_size / 2.0);
+        let (leading, trailing) = (span.azimuth, span.azimuth + span.width);
+        let at = |azimuth, range| self.site.locate(azimuth, range, span.elevation);
+        Some([at(leading, near), at(trailing, near), at(trailing, far), at(leading, far)])
+    }
+
+    /// Heights above sea level of the beam at bin `bin` of radial `radial`.
+    /// `None` if either is out of range.
+    ///
+    /// Only meaningful for products generated from one elevation; for those
+    /// that combine several the beam is taken to be at 0°, as for
+    /// [`RadialGeometry::from_radial_packet`].
+    pub fn bin_height(&self, radial: usize, bin: usize) -> Option<BeamHeight> {
+        let span = self.radials.get(radial)?;
+        if bin >= self.num_bins {
+            return None;
+        }
+        Some(self.site.beam(self.bin_range(bin), span.elevation))
+    }
+
+    /// Heights of the beam at every bin, one row per radial.
+    pub fn bin_heights(&self) -> Vec<Vec<BeamHeight>> {
+        (0..self.radials.len())
+            .map(|radial| (0..self.num_bins).filter_map(|bin| self.bin_height(radial, bin)).collect())
+            .collect()
+    }
+
+    /// Centre of every bin, one row per radial.
+    pub fn bin_centres(&self) -> Vec<Vec<LatLon>> {
+        (0..self.radials.len())
+            .map(|radial| (0..self.num_bins).filter_map(|bin| self.bin_centre(radial, bin)).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Radar, SymPacketData};
+
+    const KMKX: Site = Site {
+        latitude: 42.968,
+        longitude: -88.551,
+        height: 311.5,
+    };
+
+    fn first_packet(bytes: &[u8]) -> (SymPacketData, ProductDescription) {
+        let (_, radar) = Radar::from_vec(bytes.to_vec()).unwrap();
+        let packet = radar.symbology.unwrap().layers[0][0].clone();
+        (packet, radar.product_description)
+    }
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance
+    }
+
+    #[test]
+    fn the_site_comes_from_the_product_description() {
+        let (_, pd) = first_packet(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let site = Site::from_description(&pd);
+        assert_eq!((site.latitude, site.longitude), (42.968, -88.551));
+        assert!(close(site.height, 1022.0 * 0.3048, 1e-9));
+    }
+
+    #[test]
+    fn a_degree_of_arc_north_is_a_degree_of_latitude() {
+        let arc = EARTH_RADIUS * 1f64.to_radians();
+        let north = KMKX.project(0.0, arc);
+        assert!(close(north.latitude, KMKX.latitude + 1.0, 1e-9));
+        assert!(close(north.longitude, KMKX.longitude, 1e-9));
+
+        let south = KMKX.project(180.0, arc);
+        assert!(close(south.latitude, KMKX.latitude - 1.0, 1e-9));
+    }
+
+    #[test]
+    fn eastward_points_spread_by_the_cosine_of_latitude() {
+        let east = KMKX.project(90.0, 10_000.0);
+        let expected = (10_000.0 / EARTH_RADIUS).to_degrees() / KMKX.latitude.to_radians().cos();
+        assert!(close(east.longitude - KMKX.longitude, expected, 1e-5));
+        assert!(east.latitude < KMKX.latitude + 1e-3);
+    }
+
+    #[test]
+    fn offsets_are_projected_about_the_site() {
+        let offset = KMKX.offset(30_000.0, -40_000.0);
+        let (azimuth, range) = KMKX.azimuth_range(offset);
+        assert!(close(azimuth, 30f64.atan2(-40.0).to_degrees().rem_euclid(360.0), 1e-6));
+        assert!(close(range, 50_000.0, 1e-3));
+        let site = KMKX.offset(0.0, 0.0);
+        assert!(close(site.latitude, KMKX.latitude, 1e-9) && close(site.longitude, KMKX.longitude, 1e-9));
+    }
+
+    #[test]
+    fn longitudes_wrap_at_the_antimeridian() {
+        let site = Site {
+            latitude: 0.0,
+            longitude: 179.9,
+            height: 0.0,
+        };
+        let east = site.project(90.0, 50_000.0);
+        assert!(east.longitude < -179.0, "{east:?}");
+    }
+
+    #[test]
+    fn slant_range_inverts_ground_range() {
+        for (slant, elevation) in [(0.0, 0.5), (50_000.0, 0.5), (230_000.0, 0.0), (120_000.0, 19.5)] {
+            let ground = KMKX.ground_range(slant, elevation);
+            assert!(close(KMKX.slant_range(ground, elevation), slant, 1e-6), "{slant} at {elevation}");
+        }
+    }
+
+    #[test]
+    fn azimuth_range_inverts_project() {
+        for (azimuth, distance) in [(0.0, 1000.0), (45.0, 150_000.0), (200.0, 230_000.0), (359.5, 5_000.0)] {
+            let (back_azimuth, back_distance) = KMKX.azimuth_range(KMKX.project(azimuth, distance));
+            assert!(close(back_azimuth, azimuth, 1e-9), "{azimuth}: {back_azimuth}");
+            assert!(close(back_distance, distance, 1e-6), "{distance}: {back_distance}");
+        }
+    }
+
+    #[test]
+    fn ground_range_is_shorter_than_slant_range_up_the_beam() {
+        assert!(close(KMKX.ground_range(100_000.0, 0.0), 100_000.0, 5.0));
+        let raised = KMKX.ground_range(100_000.0, 19.5);
+        assert!(close(raised, 100_000.0 * 19.5f64.to_radians().cos(), 500.0));
+        assert!(raised < 100_000.0 * 19.5f64.to_radians().cos());
+    }
+
+    #[test]
+    fn beam_height_follows_the_four_thirds_earth_model() {
+        // Level with the antenna, the beam rises only through the earth's
+        // curvature: r^2 / (2 * ke * a) to first order.
+        let curvature = 100_000f64.powi(2) / (2.0 * EFFECTIVE_RADIUS_FACTOR * EARTH_RADIUS);
+        assert!(close(KMKX.beam_height(100_000.0, 0.0), curvature, 1.0));
+        // At 0.5 deg and 100 km, about 1.46 km above the antenna.
+        assert!(close(KMKX.beam_height(100_000.0, 0.5), 1461.0, 5.0));
+        assert_eq!(KMKX.beam_height(0.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn the_beam_spans_a_beamwidth_about_its_centre() {
+        let beam = KMKX.beam(100_000.0, 0.5);
+        assert!(close(beam.centre, KMKX.height + KMKX.beam_height(100_000.0, 0.5), 1e-9));
+        assert!(beam.bottom < beam.centre && beam.centre < beam.top);
+        // 0.95 deg at 100 km is about 1.66 km from edge to edge.
+        assert!(close(beam.top - beam.bottom, 1658.0, 5.0));
+        assert!(beam.is_above(beam.bottom - 1.0));
+        assert!(!beam.is_above(beam.centre));
+    }
+
+    #[test]
+    fn bin_heights_cover_the_sweep() {
+        let (packet, pd) = first_packet(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let SymPacketData::RadialDataAF1F(packet) = packet else {
+            panic!("expected a radial packet");
+        };
+        let geometry = RadialGeometry::from_radial_packet(&packet, &pd);
+        let heights = geometry.bin_heights();
+        assert_eq!((heights.len(), heights[0].len()), (360, 230));
+        assert!(heights[0].windows(2).all(|pair| pair[0].centre < pair[1].centre));
+        assert_eq!(geometry.bin_height(0, 229), Some(heights[0][229]));
+        assert_eq!(geometry.bin_height(0, 230), None);
+    }
+
+    #[test]
+    fn reflectivity_bins_are_two_kilometres() {
+        let (packet, pd) = first_packet(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let SymPacketData::RadialDataAF1F(packet) = packet else {
+            panic!("expected a radial packet");
+        };
+        let geometry = RadialGeometry::from_radial_packet(&packet, &pd);
+        assert_eq!(geometry.bin_size, 2000.0);
+        assert_eq!(geometry.first_bin_range, 1000.0);
+        assert_eq!((geometry.radials.len(), geometry.num_bins), (360, 230));
+        assert!(geometry.radials[0].elevation > 0.0);
+    }
+
+    #[test]
+    fn a_bin_centre_lies_inside_its_corners() {
+        let (packet, pd) = first_packet(include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"));
+        let SymPacketData::DigitalRadialDataArray(packet) = packet else {
+            panic!("expected a digital radial packet");
+        };
+        let geometry = RadialGeometry::from_digital_radial_packet(&packet, &pd);
+        assert_eq!(geometry.bin_size, 250.0);
+
+        let centre = geometry.bin_centre(90, 400).unwrap();
+        let corners = geometry.bin_corners(90, 400).unwrap();
+        let (lats, lons): (Vec<f64>, Vec<f64>) = corners.iter().map(|c| (c.latitude, c.longitude)).unzip();
+        let within = |v: f64, vs: &[f64]| vs.iter().cloned().fold(f64::MAX, f64::min) < v && v < vs.iter().cloned().fold(f64::MIN, f64::max);
+        assert!(within(centre.latitude, &lats) && within(centre.longitude, &lons), "{centre:?} {corners:?}");
+
+        assert_eq!(geometry.bin_centre(90, geometry.num_bins), None);
+        assert_eq!(geometry.bin_corners(geometry.radials.len(), 0), None);
+        assert_eq!(geometry.bin_centres().len(), geometry.radials.len());
+    }
+
+    #[test]
+    fn a_bin_centre_is_found_in_its_own_bin() {
+        let (packet, pd) = first_packet(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let SymPacketData::RadialDataAF1F(packet) = packet else {
+            panic!("expected a radial packet");
+        };
+        let geometry = RadialGeometry::from_radial_packet(&packet, &pd);
+        for (radial, bin) in [(0, 0), (17, 100), (359, 229)] {
+            assert_eq!(geometry.bin_at(geometry.bin_centre(radial, bin).unwrap()), Some((radial, bin)));
+        }
+        assert_eq!(geometry.bin_at(KMKX.project(0.0, 500_000.0)), None);
+    }
+
+    #[test]
+    fn a_radial_component_brings_its_own_geometry() {
+        let component = RadialComponent {
+            bin_size: 250.0,
+            first_bin_range: 125.0,
+            radials: vec![crate::GenericRadial {
+                azimuth: 0.0,
+                elevation: 0.5,
+                width: 1.0,
+                num_bins: 4,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let pd = ProductDescription {
+            latitude: 42_968,
+            longitude: -88_551,
+            ..Default::default()
+        };
+        let geometry = RadialGeometry::from_radial_component(&component, &pd);
+        assert_eq!(geometry.num_bins, 4);
+        assert_eq!(geometry.bin_range(3), 875.0);
+        let centre = geometry.bin_centre(0, 3).unwrap();
+        assert!(centre.latitude > KMKX.latitude && centre.longitude > KMKX.longitude);
+    }
+}
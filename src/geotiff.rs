@@ -0,0 +1,435 @@
+//! Single-band GeoTIFF output of gridded and native raster products.
+//!
+//! The file is a baseline TIFF (uncompressed, one strip, little-endian) with
+//! the GeoTIFF 1.0 tags GDAL reads: a tie point and pixel scale placing the
+//! north-west corner of the top left pixel, and a GeoKey directory naming the
+//! CRS. Two CRSs are written:
+//!
+//! - for grids in kilometres about the radar and rasters, a user-defined azimuthal equidistant projection centred on the site, in
+//!   metres, on a sphere of [`crate::EARTH_RADIUS`] — the same plane
+//!   [`crate::Site::project`] inverts;
+//! - for latitude/longitude grids, EPSG:4326.
+//!
+//! Float32 files carry physical values with `NaN`s replaced by a nodata value,
+//! recorded in GDAL's `GDAL_NODATA` tag; uint8 files carry raw data levels.
+//!
+//! The 1/4 LFM arrays of packets 17 and 18 are not written: their grid is
+//! polar stereographic, true at 60°N and oriented on 105°W, and the product
+//! does not say where its corner lies on it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, FieldCoordinates, Grid, GridCoordinates, GridMap, GriddedField, Radar, Resampling, Site, EARTH_RADIUS,
+};
+
+/// What each pixel of a GeoTIFF holds.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GeoTiffSamples {
+    /// Float32 physical values, with cells that hold no measurement set to
+    /// [`GeoTiffOptions::nodata`].
+    #[default]
+    Values,
+    /// Uint8 raw data levels. Gridded levels always take the nearest bin's,
+    /// since averaging levels means nothing, and cells outside the radar's
+    /// coverage are level 0.
+    Levels,
+}
+
+/// Options for [`Radar::to_geotiff`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GeoTiffOptions {
+    pub samples: GeoTiffSamples,
+    /// The value written for cells without a measurement in a float32 file.
+    pub nodata: f32,
+    /// The grid to resample a radial product onto. Without one, radial
+    /// products are gridded at their bin size out to their last bin. Rasters
+    /// are always written on their own cells, and ignore the grid.
+    pub grid: Option<Grid>,
+    /// How radial values are resampled onto the grid.
+    pub resampling: Resampling,
+}
+
+impl Default for GeoTiffOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GeoTiffOptions {
+    /// Float32 values with a nodata value of -9999, radial products gridded
+    /// at their bin size by nearest neighbour.
+    pub fn new() -> Self {
+        GeoTiffOptions {
+            samples: GeoTiffSamples::Values,
+            nodata: -9999.0,
+            grid: None,
+            resampling: Resampling::Nearest,
+        }
+    }
+
+    /// Writes raw data levels as uint8 rather than values.
+    pub fn with_levels(mut self) -> Self {
+        self.samples = GeoTiffSamples::Levels;
+        self
+    }
+
+    /// Sets the nodata value of float32 files.
+    pub fn with_nodata(mut self, nodata: f32) -> Self {
+        self.nodata = nodata;
+        self
+    }
+
+    /// Resamples radial products onto `grid`, in kilometres about the radar
+    /// or in degrees. Rasters ignore it.
+    pub fn with_grid(mut self, grid: Grid, resampling: Resampling) -> Self {
+        self.grid = Some(grid);
+        self.resampling = resampling;
+        self
+    }
+}
+
+impl Radar {
+    /// The product as a single-band GeoTIFF: the first radial layer gridded
+    /// as [`Radar::grid`] does, or the first raster packet on its own cells.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::data_field`] for a product without a data packet, as
+    /// [`Radar::sweep`] for a radial product whose radials are not in the
+    /// first layer, as [`GridMap::new`] for an invalid grid, and [`Error::LfmGrid`] for a precipitation array.
+    pub fn to_geotiff(&self, options: &GeoTiffOptions) -> Result<Vec<u8>, Error> {
+        let site = Site::from_description(&self.product_description);
+        let field = self.data_field()?;
+        let image = match &field.coordinates {
+            FieldCoordinates::Polar(_) => {
+                let sweep = self.sweep()?;
+                let grid = match options.grid {
+                    Some(grid) => grid,
+                    None => {
+                        let geometry = &sweep.geometry;
+                        let reach = geometry.bin_range(geometry.num_bins) - geometry.bin_size / 2.0;
+                        Grid::cartesian(reach / 1000.0, geometry.bin_size / 1000.0)?
+                    }
+                };
+                let samples = match options.samples {
+                    GeoTiffSamples::Values => sweep.grid(&grid, options.resampling)?.values.into(),
+                    GeoTiffSamples::Levels => {
+                        let levels: Vec<Vec<f32>> =
+                            sweep.levels.iter().map(|radial| radial.iter().map(|&l| l as f32).collect()).collect();
+                        let gridded = GridMap::new(&sweep.geometry, &grid, Resampling::Nearest)?.apply(&levels);
+                        Samples::levels(gridded.values.iter().map(|&level| level as i32))
+                    }
+                };
+                GeoImage::on_grid(&grid, site, samples)
+            }
+            FieldCoordinates::LfmGrid => return Err(Error::LfmGrid),
+            &FieldCoordinates::Raster {
+                i_start,
+                j_start,
+                x_scale,
+                y_scale,
+            } => {
+                let samples = match options.samples {
+                    GeoTiffSamples::Values => field.values.clone().into(),
+                    GeoTiffSamples::Levels => Samples::levels(field.levels.iter().copied()),
+                };
+                GeoImage {
+                    width: field.cols,
+                    height: field.rows,
+                    samples,
+                    crs: Crs::AzimuthalEquidistant(site),
+                    west: i_start * 250.0,
+                    north: -j_start * 250.0,
+                    pixel_width: x_scale.max(1.0) * 250.0,
+                    pixel_height: y_scale.max(1.0) * 250.0,
+                }
+            }
+        };
+        Ok(image.encode(options.nodata))
+    }
+
+    /// Writes the product to `path` as [`Radar::to_geotiff`] encodes it.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::to_geotiff`], or [`Error::Io`] if the file cannot be
+    /// written.
+    pub fn write_geotiff<P: AsRef<std::path::Path>>(&self, path: P, options: &GeoTiffOptions) -> Result<(), Error> {
+        std::fs::write(path, self.to_geotiff(options)?)?;
+        Ok(())
+    }
+}
+
+impl GriddedField {
+    /// The field as a float32 GeoTIFF, `NaN`s written as `nodata`. A grid in
+    /// kilometres is placed about `site`.
+    pub fn to_geotiff(&self, site: Site, nodata: f32) -> Vec<u8> {
+        GeoImage::on_grid(&self.grid, site, self.values.clone().into()).encode(nodata)
+    }
+}
+
+/// The pixels of a GeoTIFF, row-major from the north-west corner.
+enum Samples {
+    Float32(Vec<f32>),
+    Uint8(Vec<u8>),
+}
+
+impl From<Vec<f32>> for Samples {
+    fn from(values: Vec<f32>) -> Self {
+        Samples::Float32(values)
+    }
+}
+
+impl Samples {
+    fn levels(levels: impl Iterator<Item = i32>) -> Samples {
+        Samples::Uint8(levels.map(|level| level.clamp(0, 255) as u8).collect())
+    }
+}
+
+/// The coordinate reference system of a GeoTIFF.
+enum Crs {
+    /// Metres east and north of the site.
+    AzimuthalEquidistant(Site),
+    /// EPSG:4326 longitude and latitude.
+    Geographic,
+}
+
+/// A georeferenced single-band image.
+struct GeoImage {
+    width: usize,
+    height: usize,
+    samples: Samples,
+    crs: Crs,
+    /// Where the outer edges of the top left pixel are, in CRS units.
+    west: f64,
+    north: f64,
+    pixel_width: f64,
+    pixel_height: f64,
+}
+
+/// The pixels follow the 8 byte header directly.
+const STRIP_OFFSET: usize = 8;
+
+// TIFF field types (TIFF 6.0, section 2).
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const DOUBLE: u16 = 12;
+
+// GeoKey IDs and codes (GeoTIFF 1.0, section 6).
+const GT_MODEL_TYPE: u16 = 1024;
+const GT_RASTER_TYPE: u16 = 1025;
+const GT_CITATION: u16 = 1026;
+const GEOGRAPHIC_TYPE: u16 = 2048;
+const GEOG_CITATION: u16 = 2049;
+const GEOG_GEODETIC_DATUM: u16 = 2050;
+const GEOG_PRIME_MERIDIAN: u16 = 2051;
+const GEOG_ANGULAR_UNITS: u16 = 2054;
+const GEOG_ELLIPSOID: u16 = 2056;
+const GEOG_SEMI_MAJOR_AXIS: u16 = 2057;
+const GEOG_SEMI_MINOR_AXIS: u16 = 2058;
+const PROJECTED_CS_TYPE: u16 = 3072;
+const PROJECTION: u16 = 3074;
+const PROJ_COORD_TRANS: u16 = 3075;
+const PROJ_LINEAR_UNITS: u16 = 3076;
+const PROJ_FALSE_EASTING: u16 = 3082;
+const PROJ_FALSE_NORTHING: u16 = 3083;
+const PROJ_CENTER_LONG: u16 = 3088;
+const PROJ_CENTER_LAT: u16 = 3089;
+const USER_DEFINED: u16 = 32767;
+
+// TIFF tags holding the GeoTIFF and GDAL metadata.
+const MODEL_PIXEL_SCALE: u16 = 33550;
+const MODEL_TIEPOINT: u16 = 33922;
+const GEO_KEY_DIRECTORY: u16 = 34735;
+const GEO_DOUBLE_PARAMS: u16 = 34736;
+const GEO_ASCII_PARAMS: u16 = 34737;
+const GDAL_NODATA: u16 = 42113;
+
+/// A GeoKey's value: inline, or an entry in the double or ASCII parameters.
+enum GeoKeyValue {
+    Short(u16),
+    Double(f64),
+    Ascii(String),
+}
+
+/// A TIFF directory entry's values.
+enum FieldValue {
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Double(Vec<f64>),
+    Ascii(String),
+}
+
+impl GeoImage {
+    /// An image covering `grid`, its kilometres converted to metres.
+    fn on_grid(grid: &Grid, site: Site, samples: Samples) -> GeoImage {
+        let (crs, unit) = match grid.coordinates {
+            GridCoordinates::Radar => (Crs::AzimuthalEquidistant(site), 1000.0),
+            GridCoordinates::LatLon => (Crs::Geographic, 1.0),
+        };
+        GeoImage {
+            width: grid.nx,
+            height: grid.ny,
+            samples,
+            crs,
+            west: grid.west * unit,
+            north: grid.north * unit,
+            pixel_width: grid.resolution * unit,
+            pixel_height: grid.resolution * unit,
+        }
+    }
+
+    fn geo_keys(&self) -> Vec<(u16, GeoKeyValue)> {
+        use GeoKeyValue::*;
+        match self.crs {
+            Crs::Geographic => vec![
+                (GT_MODEL_TYPE, Short(2)),
+                (GT_RASTER_TYPE, Short(1)),
+                (GEOGRAPHIC_TYPE, Short(4326)),
+                (GEOG_ANGULAR_UNITS, Short(9102)),
+            ],
+            Crs::AzimuthalEquidistant(site) => vec![
+                (GT_MODEL_TYPE, Short(1)),
+                (GT_RASTER_TYPE, Short(1)),
+                (GT_CITATION, Ascii("Azimuthal equidistant about the radar".into())),
+                (GEOGRAPHIC_TYPE, Short(USER_DEFINED)),
+                (GEOG_CITATION, Ascii("Sphere".into())),
+                (GEOG_GEODETIC_DATUM, Short(USER_DEFINED)),
+                (GEOG_PRIME_MERIDIAN, Short(8901)),
+                (GEOG_ANGULAR_UNITS, Short(9102)),
+                (GEOG_ELLIPSOID, Short(USER_DEFINED)),
+                (GEOG_SEMI_MAJOR_AXIS, Double(EARTH_RADIUS)),
+                (GEOG_SEMI_MINOR_AXIS, Double(EARTH_RADIUS)),
+                (PROJECTED_CS_TYPE, Short(USER_DEFINED)),
+                (PROJECTION, Short(USER_DEFINED)),
+                (PROJ_COORD_TRANS, Short(12)),
+                (PROJ_LINEAR_UNITS, Short(9001)),
+                (PROJ_FALSE_EASTING, Double(0.0)),
+                (PROJ_FALSE_NORTHING, Double(0.0)),
+                (PROJ_CENTER_LONG, Double(site.longitude)),
+                (PROJ_CENTER_LAT, Double(site.latitude)),
+            ],
+        }
+    }
+
+    /// The directory entries, in tag order as TIFF requires.
+    fn fields(&self, nodata: f32) -> Vec<(u16, FieldValue)> {
+        let (bits, format, strip_bytes) = match &self.samples {
+            Samples::Float32(values) => (32, 3, values.len() * 4),
+            Samples::Uint8(levels) => (8, 1, levels.len()),
+        };
+
+        // The key directory: a header, then one entry per key, with doubles
+        // and strings gathered into their own tags.
+        let keys = self.geo_keys();
+        let mut directory = vec![1, 1, 0, keys.len() as u16];
+        let mut doubles = Vec::new();
+        let mut ascii = String::new();
+        for (id, value) in keys {
+            let entry = match value {
+                GeoKeyValue::Short(value) => [id, 0, 1, value],
+                GeoKeyValue::Double(value) => {
+                    doubles.push(value);
+                    [id, GEO_DOUBLE_PARAMS, 1, doubles.len() as u16 - 1]
+                }
+                GeoKeyValue::Ascii(text) => {
+                    let offset = ascii.len() as u16;
+                    ascii.push_str(&text);
+                    ascii.push('|');
+                    [id, GEO_ASCII_PARAMS, text.len() as u16 + 1, offset]
+                }
+            };
+            directory.extend(entry);
+        }
+
+        let mut fields = vec![
+            (256, FieldValue::Long(vec![self.width as u32])),
+            (257, FieldValue::Long(vec![self.height as u32])),
+            (258, FieldValue::Short(vec![bits])),
+            (259, FieldValue::Short(vec![1])),
+            (262, FieldValue::Short(vec![1])),
+            (273, FieldValue::Long(vec![STRIP_OFFSET as u32])),
+            (277, FieldValue::Short(vec![1])),
+            (278, FieldValue::Long(vec![self.height as u32])),
+            (279, FieldValue::Long(vec![strip_bytes as u32])),
+            (284, FieldValue::Short(vec![1])),
+            (339, FieldValue::Short(vec![format])),
+            (MODEL_PIXEL_SCALE, FieldValue::Double(vec![self.pixel_width, self.pixel_height, 0.0])),
+            (MODEL_TIEPOINT, FieldValue::Double(vec![0.0, 0.0, 0.0, self.west, self.north, 0.0])),
+            (GEO_KEY_DIRECTORY, FieldValue::Short(directory)),
+        ];
+        if !doubles.is_empty() {
+            fields.push((GEO_DOUBLE_PARAMS, FieldValue::Double(doubles)));
+        }
+        if !ascii.is_empty() {
+            fields.push((GEO_ASCII_PARAMS, FieldValue::Ascii(ascii)));
+        }
+        if let Samples::Float32(_) = self.samples {
+            fields.push((GDAL_NODATA, FieldValue::Ascii(nodata.to_string())));
+        }
+        fields
+    }
+
+    /// The whole file: header, pixels, then the directory and the values too
+    /// long to fit in its entries.
+    fn encode(&self, nodata: f32) -> Vec<u8> {
+        let pixels: Vec<u8> = match &self.samples {
+            Samples::Float32(values) => values
+                .iter()
+                .flat_map(|&value| if value.is_nan() { nodata } else { value }.to_le_bytes())
+                .collect(),
+            Samples::Uint8(levels) => levels.clone(),
+        };
+
+        let mut file = b"II\x2a\x00".to_vec();
+        let directory_offset = word_aligned(STRIP_OFFSET + pixels.len());
+        file.extend((directory_offset as u32).to_le_bytes());
+        file.extend(&pixels);
+        file.resize(directory_offset, 0);
+
+        let fields = self.fields(nodata);
+        let mut overflow_offset = directory_offset + 2 + fields.len() * 12 + 4;
+        let mut directory = (fields.len() as u16).to_le_bytes().to_vec();
+        let mut overflow = Vec::new();
+        for (tag, value) in fields {
+            let (field_type, count, mut bytes) = match value {
+                FieldValue::Short(values) => (SHORT, values.len(), le_bytes(&values, u16::to_le_bytes)),
+                FieldValue::Long(values) => (LONG, values.len(), le_bytes(&values, u32::to_le_bytes)),
+                FieldValue::Double(values) => (DOUBLE, values.len(), le_bytes(&values, f64::to_le_bytes)),
+                FieldValue::Ascii(text) => {
+                    let mut bytes = text.into_bytes();
+                    bytes.push(0);
+                    (ASCII, bytes.len(), bytes)
+                }
+            };
+            directory.extend(tag.to_le_bytes());
+            directory.extend(field_type.to_le_bytes());
+            directory.extend((count as u32).to_le_bytes());
+            if bytes.len() <= 4 {
+                bytes.resize(4, 0);
+                directory.extend(bytes);
+            } else {
+                directory.extend((overflow_offset as u32).to_le_bytes());
+                overflow_offset += word_aligned(bytes.len());
+                bytes.resize(word_aligned(bytes.len()), 0);
+                overflow.extend(bytes);
+            }
+        }
+        directory.extend(0u32.to_le_bytes());
+
+        file.extend(directory);
+        file.extend(overflow);
+        file
+    }
+}
+
+fn le_bytes<T: Copy, const N: usize>(values: &[T], to_bytes: fn(T) -> [u8; N]) -> Vec<u8> {
+    values.iter().flat_map(|&value| to_bytes(value)).collect()
+}
+
+/// `len` rounded up to a whole number of TIFF words (2 bytes).
+fn word_aligned(len: usize) -> usize {
+    len + len % 2
+}
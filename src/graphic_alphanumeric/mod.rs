@@ -14,7 +14,11 @@ use nom::{
     },
     IResult,
 };
-use tracing::{debug, error, warn};
+use tracing::{debug, warn};
+
+use crate::diagnostics::{failure, report, Severity};
+use crate::encode::{len_i16, len_i32, put_i16, with_i16_length, Encode};
+use crate::error_r::Error;
 
 use crate::product_symbology::{symbology_layer_packet, SymPacketData};
 
@@ -53,17 +57,18 @@ pub struct GraphicBlock {
 /// Fails if the block ID is not 2, if a declared page length runs past the end
 /// of the input, or if the page count is negative.
 pub fn graphic_alphanumeric(input: &[u8]) -> IResult<&[u8], GraphicBlock> {
+    let start = input;
     let (input, divider) = nom_i16(Big)(input)?;
     if divider != -1 {
-        error!("Graphic alphanumeric block divider should be -1 but found {divider}");
+        report(
+            Severity::Error,
+            start,
+            format!("Graphic alphanumeric block divider should be -1 but found {divider}"),
+        );
     }
     let (input, id) = nom_i16(Big)(input)?;
     if id != 2 {
-        error!("Graphic alphanumeric block should have ID=2 but found {id}");
-        return Err(nom::Err::Failure(nom::error::Error::new(
-            input,
-            nom::error::ErrorKind::Fail,
-        )));
+        return Err(failure(input, format!("Graphic alphanumeric block should have ID=2 but found {id}")));
     }
     let (input, block_length) = nom_i32(Big)(input)?;
     let (mut input, num_pages) = nom_i16(Big)(input)?;
@@ -73,11 +78,7 @@ pub fn graphic_alphanumeric(input: &[u8]) -> IResult<&[u8], GraphicBlock> {
     let page_count = match usize::try_from(num_pages) {
         Ok(n) => n,
         Err(_) => {
-            error!("Graphic alphanumeric block declares {num_pages} pages");
-            return Err(nom::Err::Failure(nom::error::Error::new(
-                input,
-                nom::error::ErrorKind::Fail,
-            )));
+            return Err(failure(input, format!("Graphic alphanumeric block declares {num_pages} pages")));
         }
     };
 
@@ -88,11 +89,7 @@ pub fn graphic_alphanumeric(input: &[u8]) -> IResult<&[u8], GraphicBlock> {
         let len = match usize::try_from(length) {
             Ok(n) => n,
             Err(_) => {
-                error!("Graphic alphanumeric page declares {length} bytes");
-                return Err(nom::Err::Failure(nom::error::Error::new(
-                    rest,
-                    nom::error::ErrorKind::Fail,
-                )));
+                return Err(failure(rest, format!("Graphic alphanumeric page declares {length} bytes")));
             }
         };
         let (rest, body) = nom::bytes::complete::take(len)(rest)?;
@@ -139,9 +136,40 @@ fn decode_page_packets(mut body: &[u8]) -> (Vec<SymPacketData>, Vec<u8>) {
             Err(_) => break,
         }
     }
+    if !body.is_empty() {
+        report(
+            Severity::Error,
+            body,
+            format!("Graphic alphanumeric page left {} byte(s) undecoded", body.len()),
+        );
+    }
     (packets, body.to_vec())
 }
 
+/// Writes the block with `block_length`, `num_pages` and each page's
+/// `length` recomputed. A page's `undecoded` bytes follow its packets.
+impl Encode for GraphicBlock {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let start = out.len();
+        put_i16(out, self.divider);
+        put_i16(out, self.id);
+        let length_at = out.len();
+        out.extend_from_slice(&[0; 4]);
+        put_i16(out, len_i16("Graphic alphanumeric page count", self.pages.len())?);
+        for page in &self.pages {
+            put_i16(out, page.page_number);
+            with_i16_length(out, "Graphic alphanumeric page", |out| {
+                page.packets.iter().try_for_each(|packet| packet.encode(out))?;
+                out.extend_from_slice(&page.undecoded);
+                Ok(())
+            })?;
+        }
+        let block_length = len_i32("Graphic alphanumeric block", out.len() - start)?;
+        out[length_at..length_at + 4].copy_from_slice(&block_length.to_be_bytes());
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,675 @@
+//! Resampling a radial sweep onto a regular grid.
+//!
+//! A [`Sweep`] is the first radial layer of a product decoded to physical
+//! values, `NaN` wherever a bin holds no measurement. A [`Grid`] is either
+//! kilometres east and north of the radar, in the azimuthal equidistant plane
+//! about the site that [`crate::Site::project`] inverts, or a latitude and
+//! longitude box. [`GridMap`] works out once which bins feed each cell for a
+//! given radar geometry, so gridding every volume scan from one radar costs
+//! only a pass over the cells.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, LatLon, LevelScaling, RadialGeometry, Radar, SymPacketData};
+
+/// A radial layer decoded to physical values: `values[r][b]` is bin `b` of
+/// radial `r`, located by `geometry`, decoded from `levels[r][b]`.
+///
+/// Bins below threshold, range folded, or otherwise holding a flag rather
+/// than a measurement are `NaN`. For products without a [`LevelScaling`],
+/// the values are the levels themselves, with level 0 as `NaN`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sweep {
+    pub geometry: RadialGeometry,
+    pub levels: Vec<Vec<u8>>,
+    pub values: Vec<Vec<f32>>,
+    /// The product's level scaling, if it has one.
+    pub scaling: Option<LevelScaling>,
+}
+
+impl Sweep {
+    /// Resamples the sweep onto `grid`. Builds a [`GridMap`] each time; keep
+    /// one to grid many sweeps with the same geometry.
+    ///
+    /// # Errors
+    ///
+    /// As [`GridMap::new`].
+    pub fn grid(&self, grid: &Grid, resampling: Resampling) -> Result<GriddedField, Error> {
+        Ok(GridMap::new(&self.geometry, grid, resampling)?.apply(&self.values))
+    }
+}
+
+/// The physical value a level decodes to, `NaN` for no measurement.
+fn decode(level: u8, scaling: Option<&LevelScaling>) -> f32 {
+    match scaling {
+        Some(scaling) if scaling.is_range_folded(level) => f32::NAN,
+        Some(scaling) => scaling.value(level).unwrap_or(f32::NAN),
+        None if level == 0 => f32::NAN,
+        None => level as f32,
+    }
+}
+
+impl Radar {
+    /// Decodes the first radial packet (`AF1F` or code 16) of the first
+    /// symbology layer.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::NoSymbologyData`] or [`Error::NoSymbologyLayers`] if there is
+    /// no first layer, and [`Error::NoRadialData`] if it holds no radial
+    /// packet.
+    pub fn sweep(&self) -> Result<Sweep, Error> {
+        let symbology = self.symbology.as_ref().ok_or(Error::NoSymbologyData)?;
+        let layer = symbology.layers.first().ok_or(Error::NoSymbologyLayers)?;
+        let pd = &self.product_description;
+
+        let (geometry, levels) = layer
+            .iter()
+            .find_map(|packet| match packet {
+                SymPacketData::RadialDataAF1F(packet) => {
+                    let geometry = RadialGeometry::from_radial_packet(packet, pd);
+                    let levels: Vec<Vec<u8>> = packet
+                        .radials
+                        .iter()
+                        .map(|radial| {
+                            let mut levels: Vec<u8> = radial
+                                .data
+                                .iter()
+                                .flat_map(|run| std::iter::repeat_n(run.color, run.run as usize))
+                                .collect();
+                            levels.resize(geometry.num_bins, 0);
+                            levels
+                        })
+                        .collect();
+                    Some((geometry, levels))
+                }
+                SymPacketData::DigitalRadialDataArray(packet) => {
+                    let geometry = RadialGeometry::from_digital_radial_packet(packet, pd);
+                    let levels = packet.radials.iter().map(|radial| radial.data.clone()).collect();
+                    Some((geometry, levels))
+                }
+                _ => None,
+            })
+            .ok_or(Error::NoRadialData)?;
+
+        let scaling = pd.level_scaling();
+        let values = levels
+            .iter()
+            .map(|radial| radial.iter().map(|level| decode(*level, scaling.as_ref())).collect())
+            .collect();
+        Ok(Sweep {
+            geometry,
+            levels,
+            values,
+            scaling,
+        })
+    }
+
+    /// Decodes the first radial layer as [`Radar::sweep`] does and resamples
+    /// it onto `grid`.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::sweep`] and [`GridMap::new`].
+    pub fn grid(&self, grid: &Grid, resampling: Resampling) -> Result<GriddedField, Error> {
+        self.sweep()?.grid(grid, resampling)
+    }
+}
+
+/// What a [`Grid`]'s coordinates are.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GridCoordinates {
+    /// Kilometres east (x) and north (y) of the radar, in the azimuthal
+    /// equidistant projection about the site.
+    #[default]
+    Radar,
+    /// Degrees of longitude (x) and latitude (y).
+    LatLon,
+}
+
+/// A regular grid of `ny` rows by `nx` columns, row 0 at the north edge and
+/// column 0 at the west edge, each cell `resolution` on a side.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub struct Grid {
+    pub coordinates: GridCoordinates,
+    /// x of the west edge of column 0.
+    pub west: f64,
+    /// y of the north edge of row 0.
+    pub north: f64,
+    /// Cell size, in kilometres or degrees as `coordinates` says.
+    pub resolution: f64,
+    pub nx: usize,
+    pub ny: usize,
+}
+
+impl Grid {
+    /// A square grid centred on the radar reaching `half_width` kilometres
+    /// east, west, north and south of it, with cells `resolution` kilometres
+    /// on a side.
+    ///
+    /// # Errors
+    ///
+    /// As [`Grid::cells`].
+    pub fn cartesian(half_width: f64, resolution: f64) -> Result<Grid, Error> {
+        let cells = (2.0 * half_width / resolution).ceil().max(0.0) as usize;
+        let grid = Grid {
+            coordinates: GridCoordinates::Radar,
+            west: -half_width,
+            north: half_width,
+            resolution,
+            nx: cells,
+            ny: cells,
+        };
+        grid.cells()?;
+        Ok(grid)
+    }
+
+    /// A grid covering the box from `south`/`west` to `north`/`east`, with
+    /// cells `resolution` degrees on a side. The last row and column are
+    /// whole cells, so may reach past `south` and `east`.
+    ///
+    /// # Errors
+    ///
+    /// As [`Grid::cells`].
+    pub fn lat_lon(south: f64, west: f64, north: f64, east: f64, resolution: f64) -> Result<Grid, Error> {
+        let grid = Grid {
+            coordinates: GridCoordinates::LatLon,
+            west,
+            north,
+            resolution,
+            nx: ((east - west) / resolution).ceil().max(0.0) as usize,
+            ny: ((north - south) / resolution).ceil().max(0.0) as usize,
+        };
+        grid.cells()?;
+        Ok(grid)
+    }
+
+    /// The number of cells, `nx` by `ny`.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::GridResolution`] unless the resolution is finite and above
+    /// zero, and [`Error::GridSize`] if the cells are too many to count or
+    /// number `u32::MAX` or more.
+    pub fn cells(&self) -> Result<usize, Error> {
+        if !self.resolution.is_finite() || self.resolution <= 0.0 {
+            return Err(Error::GridResolution(self.resolution));
+        }
+        self.nx
+            .checked_mul(self.ny)
+            .filter(|cells| *cells < u32::MAX as usize)
+            .ok_or(Error::GridSize { nx: self.nx, ny: self.ny })
+    }
+
+    /// The x and y of the centre of the cell at `row`, `col`.
+    pub fn cell_centre(&self, row: usize, col: usize) -> (f64, f64) {
+        (
+            self.west + (col as f64 + 0.5) * self.resolution,
+            self.north - (row as f64 + 0.5) * self.resolution,
+        )
+    }
+
+    /// The row and column of the cell holding `x`, `y`, if any.
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let col = ((x - self.west) / self.resolution).floor();
+        let row = ((self.north - y) / self.resolution).floor();
+        if col < 0.0 || row < 0.0 || col >= self.nx as f64 || row >= self.ny as f64 {
+            return None;
+        }
+        Some((row as usize, col as usize))
+    }
+}
+
+/// How the bins around a cell become its value.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Resampling {
+    /// The bin the cell centre falls in.
+    #[default]
+    Nearest,
+    /// Interpolated linearly in azimuth between the two nearest radials and
+    /// in range between the two nearest bins. A cell whose own bin has no
+    /// measurement has none either, so echo edges are not smeared outwards;
+    /// otherwise neighbours without one are left out of the average.
+    BilinearPolar,
+    /// The largest value among the bins whose centres fall in the cell, or
+    /// the nearest bin's value for a cell smaller than the bins around it.
+    MaxInCell,
+}
+
+/// Values on a [`Grid`], row-major from the north-west corner, `NaN` where
+/// there is no measurement or the cell is outside the radar's coverage.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GriddedField {
+    pub grid: Grid,
+    pub values: Vec<f32>,
+}
+
+impl GriddedField {
+    /// The value of the cell at `row`, `col`, or `None` outside the grid.
+    pub fn get(&self, row: usize, col: usize) -> Option<f32> {
+        if row >= self.grid.ny || col >= self.grid.nx {
+            return None;
+        }
+        self.values.get(row * self.grid.nx + col).copied()
+    }
+}
+
+/// Which bins feed each cell of a [`Grid`], and with what weights, for one
+/// radar geometry.
+///
+/// Building the map does all the geometry; [`GridMap::apply`] is then a
+/// weighted lookup per cell, so one map serves every sweep with the same
+/// radials, bins and site, such as successive scans of one product.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GridMap {
+    grid: Grid,
+    resampling: Resampling,
+    num_bins: usize,
+    /// Cell `i` draws on `sources[starts[i]..starts[i + 1]]`. For
+    /// [`Resampling::BilinearPolar`] the first source is the cell's own bin.
+    starts: Vec<u32>,
+    sources: Vec<Source>,
+}
+
+/// A bin feeding a cell: its index as `radial * num_bins + bin`, and weight.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Source {
+    bin: u32,
+    weight: f32,
+}
+
+impl GridMap {
+    /// Works out which bins of `geometry` feed each cell of `grid`.
+    ///
+    /// # Errors
+    ///
+    /// As [`Grid::cells`], which a grid built field by field may fail, and
+    /// [`Error::GridSize`] if the cells draw on `u32::MAX` bins or more in
+    /// all.
+    pub fn new(geometry: &RadialGeometry, grid: &Grid, resampling: Resampling) -> Result<GridMap, Error> {
+        let cells = grid.cells()?;
+        let azimuths = AzimuthIndex::new(geometry);
+        let too_large = || Error::GridSize { nx: grid.nx, ny: grid.ny };
+
+        // The bins whose centres fall in each cell, for MaxInCell only.
+        let mut cell_sources: Vec<Vec<Source>> = Vec::new();
+        if resampling == Resampling::MaxInCell {
+            cell_sources = vec![Vec::new(); cells];
+            for (radial, span) in geometry.radials.iter().enumerate() {
+                let azimuth = span.azimuth + span.width / 2.0;
+                for bin in 0..geometry.num_bins {
+                    let (x, y) = match grid.coordinates {
+                        GridCoordinates::Radar => {
+                            let ground = geometry.site.ground_range(geometry.bin_range(bin), span.elevation) / 1000.0;
+                            let azimuth = azimuth.to_radians();
+                            (ground * azimuth.sin(), ground * azimuth.cos())
+                        }
+                        GridCoordinates::LatLon => {
+                            let centre = geometry.site.locate(azimuth, geometry.bin_range(bin), span.elevation);
+                            (centre.longitude, centre.latitude)
+                        }
+                    };
+                    if let Some((row, col)) = grid.cell_at(x, y) {
+                        cell_sources[row * grid.nx + col].push(Source {
+                            bin: (radial * geometry.num_bins + bin) as u32,
+                            weight: 1.0,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut starts = Vec::with_capacity(cells + 1);
+        let mut sources = Vec::new();
+        for row in 0..grid.ny {
+            for col in 0..grid.nx {
+                starts.push(u32::try_from(sources.len()).map_err(|_| too_large())?);
+                let binned = cell_sources.get_mut(row * grid.nx + col).map(std::mem::take).unwrap_or_default();
+                if !binned.is_empty() {
+                    sources.extend(binned);
+                    continue;
+                }
+                let (azimuth, ground_range) = polar(geometry, grid, row, col);
+                if resampling == Resampling::BilinearPolar {
+                    azimuths.bilinear(geometry, azimuth, ground_range, &mut sources);
+                } else if let Some(bin) = azimuths.nearest(geometry, azimuth, ground_range) {
+                    sources.push(Source { bin, weight: 1.0 });
+                }
+            }
+        }
+        starts.push(u32::try_from(sources.len()).map_err(|_| too_large())?);
+
+        Ok(GridMap {
+            grid: *grid,
+            resampling,
+            num_bins: geometry.num_bins,
+            starts,
+            sources,
+        })
+    }
+
+    /// The grid this map fills.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Fills the grid from `values`, indexed as [`Sweep::values`] for the
+    /// geometry the map was built for.
+    pub fn apply(&self, values: &[Vec<f32>]) -> GriddedField {
+        let value = |source: &Source| {
+            let (radial, bin) = (source.bin as usize / self.num_bins, source.bin as usize % self.num_bins);
+            values.get(radial).and_then(|row| row.get(bin)).copied().unwrap_or(f32::NAN)
+        };
+
+        let values = self
+            .starts
+            .windows(2)
+            .map(|range| {
+                let sources = &self.sources[range[0] as usize..range[1] as usize];
+                match self.resampling {
+                    Resampling::Nearest => sources.first().map_or(f32::NAN, value),
+                    Resampling::MaxInCell => sources
+                        .iter()
+                        .map(value)
+                        .filter(|v| !v.is_nan())
+                        .reduce(f32::max)
+                        .unwrap_or(f32::NAN),
+                    Resampling::BilinearPolar => {
+                        if sources.first().is_none_or(|own| value(own).is_nan()) {
+                            return f32::NAN;
+                        }
+                        let (sum, weights) = sources
+                            .iter()
+                            .map(|source| (value(source), source.weight))
+                            .filter(|(v, _)| !v.is_nan())
+                            .fold((0.0, 0.0), |(sum, weights), (v, w)| (sum + v * w, weights + w));
+                        sum / weights
+                    }
+                }
+            })
+            .collect();
+
+        GriddedField { grid: self.grid, values }
+    }
+}
+
+/// Azimuth and ground range from the radar to the centre of a cell.
+fn polar(geometry: &RadialGeometry, grid: &Grid, row: usize, col: usize) -> (f64, f64) {
+    let (x, y) = grid.cell_centre(row, col);
+    match grid.coordinates {
+        GridCoordinates::Radar => (x.atan2(y).to_degrees().rem_euclid(360.0), x.hypot(y) * 1000.0),
+        GridCoordinates::LatLon => geometry.site.azimuth_range(LatLon {
+            latitude: y,
+            longitude: x,
+        }),
+    }
+}
+
+/// Difference `a - b` between two azimuths, degrees, in `-180..180`.
+fn azimuth_difference(a: f64, b: f64) -> f64 {
+    (a - b + 540.0).rem_euclid(360.0) - 180.0
+}
+
+/// A geometry's radials in order of azimuth, to find the one holding a given
+/// azimuth by binary search.
+struct AzimuthIndex {
+    /// Leading edge in `0..360`, width, and index into the geometry's radials.
+    radials: Vec<(f64, f64, usize)>,
+}
+
+impl AzimuthIndex {
+    fn new(geometry: &RadialGeometry) -> AzimuthIndex {
+        let mut radials: Vec<_> = geometry
+            .radials
+            .iter()
+            .enumerate()
+            .map(|(index, span)| (span.azimuth.rem_euclid(360.0), span.width, index))
+            .collect();
+        radials.sort_by(|a, b| a.0.total_cmp(&b.0));
+        AzimuthIndex { radials }
+    }
+
+    /// Position in `radials` of the radial holding `azimuth`.
+    fn position(&self, azimuth: f64) -> Option<usize> {
+        let holds = |&(leading, width, _): &(f64, f64, usize)| {
+            let into = (azimuth - leading).rem_euclid(360.0);
+            into < width
+        };
+        let after = self.radials.partition_point(|radial| radial.0 <= azimuth);
+        // The radial starting at or before the azimuth, or, wrapping past
+        // north, the last one.
+        let candidate = after.checked_sub(1).unwrap_or(self.radials.len().checked_sub(1)?);
+        if holds(&self.radials[candidate]) {
+            return Some(candidate);
+        }
+        let last = self.radials.len() - 1;
+        holds(&self.radials[last]).then_some(last)
+    }
+
+    /// Fractional bin, centre-based, of a ground range along a radial.
+    fn bin_position(geometry: &RadialGeometry, radial: usize, ground_range: f64) -> f64 {
+        let elevation = geometry.radials[radial].elevation;
+        let slant_range = geometry.site.slant_range(ground_range, elevation);
+        (slant_range - geometry.first_bin_range) / geometry.bin_size
+    }
+
+    fn nearest(&self, geometry: &RadialGeometry, azimuth: f64, ground_range: f64) -> Option<u32> {
+        let (_, _, radial) = self.radials[self.position(azimuth)?];
+        let bin = Self::bin_position(geometry, radial, ground_range).round();
+        if bin < 0.0 || bin >= geometry.num_bins as f64 {
+            return None;
+        }
+        Some((radial * geometry.num_bins + bin as usize) as u32)
+    }
+
+    fn bilinear(&self, geometry: &RadialGeometry, azimuth: f64, ground_range: f64, sources: &mut Vec<Source>) {
+        let Some(position) = self.position(azimuth) else {
+            return;
+        };
+        let Some(own) = self.nearest(geometry, azimuth, ground_range) else {
+            return;
+        };
+        let (leading, width, radial) = self.radials[position];
+
+        // The neighbouring radial on the side of the centre the azimuth is.
+        let centre = leading + width / 2.0;
+        let offset = azimuth_difference(azimuth, centre);
+        let count = self.radials.len();
+        let next = if offset >= 0.0 { (position + 1) % count } else { (position + count - 1) % count };
+        let (next_leading, next_width, next_radial) = self.radials[next];
+        let separation = azimuth_difference(next_leading + next_width / 2.0, centre).abs();
+        // Radials more than a radial apart are across a gap, not neighbours.
+        let t = if next == position || separation == 0.0 || separation > width.max(next_width) * 1.5 {
+            0.0
+        } else {
+            (offset.abs() / separation).min(1.0)
+        };
+
+        let last_bin = geometry.num_bins.saturating_sub(1) as f64;
+        let mut own_weight = 0.0;
+        let mut others = Vec::with_capacity(4);
+        for (radial, radial_weight) in [(radial, 1.0 - t), (next_radial, t)] {
+            let fraction = Self::bin_position(geometry, radial, ground_range).clamp(0.0, last_bin);
+            let first = fraction.floor();
+            let along = fraction - first;
+            for (bin, bin_weight) in [(first, 1.0 - along), (first + 1.0, along)] {
+                let weight = (radial_weight * bin_weight) as f32;
+                if weight <= 0.0 || bin > last_bin {
+                    continue;
+                }
+                let index = (radial * geometry.num_bins + bin as usize) as u32;
+                if index == own {
+                    own_weight += weight;
+                } else {
+                    others.push(Source { bin: index, weight });
+                }
+            }
+        }
+        sources.push(Source { bin: own, weight: own_weight });
+        sources.extend(others);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RadialSpan, Site};
+
+    /// 360 one-degree radials of 100 one-kilometre bins, level with the
+    /// antenna, at the equator.
+    fn geometry() -> RadialGeometry {
+        RadialGeometry {
+            site: Site::default(),
+            first_bin_range: 500.0,
+            bin_size: 1000.0,
+            num_bins: 100,
+            radials: (0..360)
+                .map(|azimuth| RadialSpan {
+                    azimuth: azimuth as f64,
+                    width: 1.0,
+                    elevation: 0.0,
+                })
+                .collect(),
+        }
+    }
+
+    /// Each bin's value is its bin number.
+    fn by_range() -> Vec<Vec<f32>> {
+        vec![(0..100).map(|bin| bin as f32).collect(); 360]
+    }
+
+    fn load(bytes: &[u8]) -> Radar {
+        Radar::from_vec(bytes.to_vec()).unwrap().1
+    }
+
+    #[test]
+    fn grids_are_laid_out_from_the_north_west_corner() {
+        let grid = Grid::cartesian(50.0, 1.0).unwrap();
+        assert_eq!((grid.nx, grid.ny), (100, 100));
+        assert_eq!(grid.cell_centre(0, 0), (-49.5, 49.5));
+        assert_eq!(grid.cell_at(-49.5, 49.5), Some((0, 0)));
+        assert_eq!(grid.cell_at(0.2, -0.2), Some((50, 50)));
+        assert_eq!(grid.cell_at(50.0, 0.0), None);
+
+        let grid = Grid::lat_lon(42.0, -89.5, 44.0, -87.5, 0.25).unwrap();
+        assert_eq!((grid.nx, grid.ny), (8, 8));
+        assert_eq!(grid.cell_centre(7, 0), (-89.375, 42.125));
+    }
+
+    #[test]
+    fn nearest_takes_the_bin_under_the_cell_centre() {
+        let field = Sweep { geometry: geometry(), values: by_range(), ..Default::default() }.grid(&Grid::cartesian(50.0, 1.0).unwrap(), Resampling::Nearest).unwrap();
+        // The cell centred 10.5 km east and 0.5 km north: bin 10.
+        assert_eq!(field.get(49, 60), Some(10.0));
+        // The corners are 70 km out, still covered; past 100 km is not.
+        assert_eq!(field.get(0, 0), Some(70.0));
+        let wide = Sweep { geometry: geometry(), values: by_range(), ..Default::default() }.grid(&Grid::cartesian(100.0, 10.0).unwrap(), Resampling::Nearest).unwrap();
+        assert!(wide.get(0, 0).unwrap().is_nan());
+    }
+
+    #[test]
+    fn bilinear_interpolates_in_range() {
+        let sweep = Sweep { geometry: geometry(), values: by_range(), ..Default::default() };
+        let field = sweep.grid(&Grid::cartesian(50.0, 1.0).unwrap(), Resampling::BilinearPolar).unwrap();
+        for (row, col) in [(49, 60), (10, 10), (80, 45)] {
+            let (x, y) = field.grid.cell_centre(row, col);
+            let expected = (x.hypot(y) * 1000.0 - 500.0) / 1000.0;
+            let value = field.get(row, col).unwrap() as f64;
+            assert!((value - expected).abs() < 0.01, "({row}, {col}): {value} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn bilinear_leaves_no_data_bins_empty() {
+        let mut values = by_range();
+        values[87][10] = f32::NAN;
+        let field = Sweep { geometry: geometry(), values, ..Default::default() }.grid(&Grid::cartesian(50.0, 1.0).unwrap(), Resampling::BilinearPolar).unwrap();
+        assert!(field.get(49, 60).unwrap().is_nan());
+        // A cell next to it still has a value, averaged without the gap.
+        assert!(!field.get(49, 61).unwrap().is_nan());
+    }
+
+    #[test]
+    fn max_in_cell_keeps_the_peak() {
+        let mut values = vec![vec![1.0; 100]; 360];
+        values[45][30] = 60.0;
+        let sweep = Sweep { geometry: geometry(), values, ..Default::default() };
+        let grid = Grid::cartesian(100.0, 10.0).unwrap();
+
+        let peak = sweep.grid(&grid, Resampling::MaxInCell).unwrap();
+        let centre = (30.5_f64 * 45.5f64.to_radians().sin(), 30.5 * 45.5f64.to_radians().cos());
+        let (row, col) = grid.cell_at(centre.0, centre.1).unwrap();
+        assert_eq!(peak.get(row, col), Some(60.0));
+        assert_eq!(peak.values.iter().filter(|v| **v == 60.0).count(), 1);
+        // Outside coverage is still empty.
+        assert!(peak.get(0, 0).unwrap().is_nan());
+    }
+
+    #[test]
+    fn a_map_is_reusable_across_sweeps() {
+        let grid = Grid::cartesian(50.0, 2.0).unwrap();
+        let map = GridMap::new(&geometry(), &grid, Resampling::BilinearPolar).unwrap();
+        let first = by_range();
+        let second: Vec<Vec<f32>> = first.iter().map(|radial| radial.iter().map(|v| v * 2.0).collect()).collect();
+
+        assert_eq!(map.apply(&first), Sweep { geometry: geometry(), values: first, ..Default::default() }.grid(&grid, Resampling::BilinearPolar).unwrap());
+        let doubled = map.apply(&second);
+        assert_eq!(doubled.get(10, 10), Some(map.apply(&by_range()).get(10, 10).unwrap() * 2.0));
+    }
+
+    #[test]
+    fn a_sweep_decodes_the_first_radial_layer() {
+        let radar = load(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let sweep = radar.sweep().unwrap();
+        assert_eq!((sweep.values.len(), sweep.values[0].len()), (360, 230));
+        let scaling = radar.product_description.level_scaling().unwrap();
+        let SymPacketData::RadialDataAF1F(packet) = &radar.symbology.as_ref().unwrap().layers[0][0] else {
+            panic!("expected a radial packet");
+        };
+        let first_run = packet.radials[0].data[0];
+        let expected = scaling.value(first_run.color).unwrap_or(f32::NAN);
+        assert_eq!(sweep.values[0][0].to_bits(), expected.to_bits());
+        assert!(sweep.values.iter().flatten().any(|v| v.is_nan()));
+        assert!(sweep.values.iter().flatten().any(|v| !v.is_nan()));
+    }
+
+    #[test]
+    fn a_product_grids_onto_latitude_and_longitude() {
+        let radar = load(include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last"));
+        let grid = Grid::lat_lon(41.0, -90.5, 45.0, -86.5, 0.05).unwrap();
+        let field = radar.grid(&grid, Resampling::Nearest).unwrap();
+        assert_eq!(field.values.len(), 80 * 80);
+        assert!(field.values.iter().any(|v| !v.is_nan()));
+
+        // The site's own cell takes the first bin of the radial it falls in.
+        let (row, col) = grid.cell_at(-88.551, 42.968).unwrap();
+        let (x, y) = grid.cell_centre(row, col);
+        let sweep = radar.sweep().unwrap();
+        let (azimuth, ground) = sweep.geometry.site.azimuth_range(LatLon { latitude: y, longitude: x });
+        let radial = sweep.geometry.radials.iter().position(|r| (azimuth - r.azimuth).rem_euclid(360.0) < r.width).unwrap();
+        let bin = ((ground - sweep.geometry.first_bin_range) / sweep.geometry.bin_size).round() as usize;
+        assert_eq!(field.get(row, col).unwrap().to_bits(), sweep.values[radial][bin].to_bits());
+    }
+
+    #[test]
+    fn a_product_without_radial_data_cannot_be_gridded() {
+        let mut radar = load(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        radar.symbology.as_mut().unwrap().layers[0].clear();
+        assert!(matches!(radar.sweep(), Err(Error::NoRadialData)));
+        radar.symbology = None;
+        assert!(matches!(radar.grid(&Grid::cartesian(10.0, 1.0).unwrap(), Resampling::Nearest), Err(Error::NoSymbologyData)));
+    }
+
+    #[test]
+    fn grids_need_a_positive_finite_resolution() {
+        for resolution in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(Grid::cartesian(50.0, resolution), Err(Error::GridResolution(_))), "{resolution}");
+            assert!(matches!(Grid::lat_lon(42.0, -90.0, 44.0, -87.0, resolution), Err(Error::GridResolution(_))));
+        }
+        assert!(matches!(Grid::cartesian(1e300, 1.0), Err(Error::GridSize { .. })));
+
+        // A grid built field by field is checked when it is used.
+        let grid = Grid { nx: usize::MAX, ny: 2, resolution: 1.0, ..Default::default() };
+        assert!(matches!(GridMap::new(&geometry(), &grid, Resampling::Nearest), Err(Error::GridSize { .. })));
+    }
+}
@@ -247,6 +247,65 @@ impl LevelScaling {
         }
     }
 
+    /// Encodes a physical value as the raw level that decodes nearest to it:
+    /// the inverse of [`Self::value`].
+    ///
+    /// Values that are not finite encode as level 0, which every product uses
+    /// for "below threshold" or blank. Otherwise the level is clamped to the
+    /// data levels, so a value out of range never lands on a flag such as
+    /// range folded. A threshold table has no arithmetic to invert, so the
+    /// level chosen is the highest whose threshold does not exceed the value,
+    /// and a value below every threshold is level 0 too.
+    pub fn level(&self, value: f32) -> u8 {
+        if !value.is_finite() {
+            return 0;
+        }
+        let first = self.first_data_level as f32;
+        let clamp = |raw: f32| raw.round().clamp(first, 255.0) as u8;
+        match &self.decoding {
+            LevelDecoding::Linear { scale, offset } => {
+                if *scale == 0.0 {
+                    return self.first_data_level;
+                }
+                clamp((value - offset) / scale)
+            }
+
+            LevelDecoding::Thresholds(thresholds) => thresholds
+                .iter()
+                .enumerate()
+                .filter_map(|(i, t)| match t {
+                    LevelThreshold::Value { value: v, .. } if *v <= value => Some(i as u8),
+                    _ => None,
+                })
+                .next_back()
+                .unwrap_or(0),
+
+            LevelDecoding::LinearThenLog {
+                linear_scale,
+                linear_offset,
+                log_start,
+                log_scale,
+                log_offset,
+            } => {
+                let linear = value * linear_scale + linear_offset;
+                if linear < *log_start as f32 || value <= 0.0 {
+                    clamp(linear)
+                } else {
+                    clamp(value.ln() * log_scale + log_offset)
+                }
+            }
+
+            LevelDecoding::EnhancedEchoTops {
+                data_mask,
+                data_scale,
+                data_offset,
+                ..
+            } => ((value + data_offset) * data_scale)
+                .round()
+                .clamp(first, *data_mask as f32) as u8,
+        }
+    }
+
     /// The decoded threshold for a level, for products that carry a packed
     /// threshold table. This exposes the categorical codes that [`Self::value`]
     /// reports as `None`.
@@ -567,10 +626,7 @@ impl ProductDescription {
     ///
     /// This is what the reference plots print as `MAX: 56 DBZ`.
     pub fn max_value_annotation(&self) -> Option<(i16, &'static str)> {
-        let hw47 = self
-            .halfwords_47_53
-            .get(..2)
-            .map(|d| i16::from_be_bytes([d[0], d[1]]))?;
+        let hw47 = self.dependent_halfword(47)?;
 
         // Table V, "MSG CODE / HWORD# 47" rows.
         let units = match self.product_code {
@@ -954,6 +1010,55 @@ mod tests {
         assert!(reference_dhr().level_scaling().unwrap().is_topped(50).is_none());
     }
 
+    #[test]
+    fn level_inverts_a_linear_value_and_clamps_to_the_data_levels() {
+        let s = reference_dhr().level_scaling().unwrap();
+        for raw in [2u8, 100, 255] {
+            assert_eq!(s.level(s.value(raw).unwrap()), raw);
+        }
+        assert_eq!(s.level(20.2), 104);
+        // Too low lands on the first data level, not range folded.
+        assert_eq!(s.level(-100.0), 2);
+        assert_eq!(s.level(1000.0), 255);
+        assert_eq!(s.level(f32::NAN), 0);
+    }
+
+    #[test]
+    fn level_picks_the_highest_threshold_not_above_the_value() {
+        let mut th = vec![0x80, 2, 0x80, 3];
+        for level in 0..14u8 {
+            th.push(0x00);
+            th.push(level * 5);
+        }
+        let s = description(19, &th).level_scaling().unwrap();
+        assert_eq!(s.level(0.0), 2);
+        assert_eq!(s.level(14.9), 4);
+        assert_eq!(s.level(15.0), 5);
+        assert_eq!(s.level(-3.0), 0);
+        assert_eq!(s.level(500.0), 15);
+    }
+
+    #[test]
+    fn level_inverts_the_vil_and_echo_tops_encodings() {
+        const F_ONE: i16 = 0x4000u16 as i16;
+        let mut th = Vec::new();
+        for hw in [F_ONE, 0, 100, 0x54DCu16 as i16, 0] {
+            th.extend_from_slice(&hw.to_be_bytes());
+        }
+        let vil = description(134, &th).level_scaling().unwrap();
+        for raw in [50u8, 99, 200, 250] {
+            assert_eq!(vil.level(vil.value(raw).unwrap()), raw);
+        }
+
+        let mut th = Vec::new();
+        for hw in [127i16, 1, 2, 128] {
+            th.extend_from_slice(&hw.to_be_bytes());
+        }
+        let eet = description(135, &th).level_scaling().unwrap();
+        assert_eq!(eet.level(30.0), 32);
+        assert_eq!(eet.level(500.0), 127);
+    }
+
     #[test]
     fn products_with_no_documented_scaling_return_none() {
         // 177 is in Note 1's exception list and its levels are class indices.
@@ -5,8 +5,8 @@
 //!
 //! # Format
 //!
-//! A Level 3 file is a WMO/AWIPS text header ([`text_header`]) followed by
-//! a binary Graphic Product Message made of, in order: a message header
+//! A Level 3 file is a WMO/AWIPS text header ([`text_header`]), when it has
+//! not been stripped, followed by a binary Graphic Product Message made of, in order: a message header
 //! ([`message_header`]), a product description ([`product_description`]),
 //! and then zero or one each of a symbology block, a graphic alphanumeric
 //! block, and a tabular alphanumeric block. See the "Format" section of
@@ -19,7 +19,7 @@
 //! let bytes = std::fs::read("data/sn_DS.p20-r_kmkx.last")?;
 //! let (leftover, radar) = radar::Radar::from_vec(bytes)?;
 //!
-//! println!("{} at {}", radar.text_header.location, radar.message_header.datetime);
+//! println!("{} at {}", radar.product_description.product_code, radar.message_header.datetime);
 //! # Ok::<(), radar::Error>(())
 //! ```
 //!
@@ -32,15 +32,38 @@
 //! through every encoding Note 1 of Figure 3-6 defines.
 //!
 //! Parsing a file that needs something unimplemented returns an [`Error`]
-//! rather than panicking. See `README.md` for the current list of what is
+//! rather than panicking. [`Radar::from_vec_with`] also returns the
+//! [`Diagnostic`]s for anything it tolerated, and [`ParseOptions`] can make
+//! those fail the parse instead. [`Radar::validate`] checks a parsed product's
+//! length and offset fields against what was found, for rejecting files that
+//! parse but are inconsistent. See `README.md` for the current list of what is
 //! and isn't supported, and for the field-by-field spec conformance table.
 //!
+//! # Writing
+//!
+//! [`Radar::to_vec`] serializes a product back into a Level 3 file, with
+//! offsets and lengths recomputed; [`Radar::to_vec_with`] takes
+//! [`EncodeOptions`] to choose whether and how it is compressed. A parsed file
+//! encodes to the same bytes it was read from.
+//!
+//! [`ProductBuilder`] assembles a new product from a product code, the site's
+//! position, its times and layers of packets, which constructors such as
+//! [`DigitalRadialPacket::from_values`] build from a field of values.
+//!
+//! # Geolocation
+//!
+//! [`RadialGeometry`] locates the range bins of radial packets on the earth,
+//! following the beam under the 4/3 earth radius model and projecting about
+//! the [`Site`] with an azimuthal equidistant projection. [`Radar::grid`]
+//! resamples the first radial layer onto a regular [`Grid`].
+//!
 //! # Plotting
 //!
-//! [`Radar::plot`] renders the first symbology layer to a PNG with an
-//! annotation panel and a colour bar; [`Radar::plot_with`] takes
-//! [`PlotOptions`] to choose the [`ColorRamp`], the site label, and the image
-//! size. Digital data arrays are coloured by decoding their levels to physical
+//! [`Radar::plot`] renders every symbology layer — radials, rasters and
+//! overlays — to a PNG with an annotation panel and a colour bar;
+//! [`Radar::plot_with`] takes [`PlotOptions`] to choose the [`ColorRamp`], the
+//! site label, the image size, and a true-scale range with [`RangeRings`].
+//! Digital data arrays are coloured by decoding their levels to physical
 //! values where [`ProductDescription::level_scaling`] knows how, so
 //! reflectivity products plot against a real dBZ scale.
 
@@ -50,8 +73,8 @@ extern crate num_derive;
 use serde::{Deserialize, Serialize};
 use std::io::Read;
 
-use bzip2::bufread::BzDecoder;
-use tracing::{info, warn};
+use bzip2::bufread::MultiBzDecoder;
+use tracing::info;
 
 mod codes;
 pub use codes::{ColorTable, MessageCode, PacketCode, FALLBACK_GRAY};
@@ -70,6 +93,12 @@ pub use message_header::{message_header, MessageHeader};
 mod product_description;
 pub use product_description::{product_description, ProductDescription};
 
+mod dependent_params;
+pub use dependent_params::{
+    Compression, CompressionMethod, DeltaTime, JulianTime, ProductDependentParams,
+    SupplementalScan,
+};
+
 mod product_symbology;
 pub use product_symbology::*;
 
@@ -80,152 +109,340 @@ mod tabular_alphanumeric;
 pub use tabular_alphanumeric::{tabular_alphanumeric, TabularBlock, TabularPage};
 
 mod text_header;
-pub use text_header::{text_header, TextHeader};
+pub use text_header::{text_header, AwipsId, TextHeader};
 
 mod plot;
-pub use plot::PlotOptions;
+pub use plot::{DistanceUnits, PlotOptions, RangeRings, MIN_SPOKE_SPACING};
 
 mod error_r;
-pub use error_r::Error;
+pub use error_r::{Error, ParseError};
+
+mod encode;
+pub use encode::EncodeOptions;
+
+mod builder;
+pub use builder::ProductBuilder;
+
+mod diagnostics;
+pub use diagnostics::{Block, Diagnostic, ParseOptions, Severity};
+
+mod validate;
+pub use validate::Layout;
+
+mod geolocation;
+pub use geolocation::{
+    BeamHeight, LatLon, RadialGeometry, RadialSpan, Site, BEAMWIDTH, EARTH_RADIUS, EFFECTIVE_RADIUS_FACTOR,
+};
+
+mod grid;
+pub use grid::{Grid, GridCoordinates, GridMap, GriddedField, Resampling, Sweep};
+
+mod query;
+pub use query::{BinSample, PointValue, TransectPoint, ANTIPODAL_TOLERANCE};
+
+mod data_field;
+pub use data_field::{CellMask, DataField, FieldCoordinates};
+
+mod geojson;
+
+mod polygonize;
+pub use polygonize::PolygonMode;
+
+mod geotiff;
+pub use geotiff::{GeoTiffOptions, GeoTiffSamples};
+
+mod netcdf;
+mod cfradial;
+
+mod tabular;
+#[cfg(feature = "arrow")]
+pub use tabular::ARROW_BATCH_ROWS;
+pub use tabular::{BinFlag, BinRow, BinRows, BIN_COLUMNS};
+
+use diagnostics::report;
 
 /// A fully parsed NEXRAD Level 3 product: the text header, binary message
 /// header, product description, and (if present) the symbology, graphic,
 /// and tabular alphanumeric blocks that follow.
 ///
-/// Build one with [`Radar::from_vec`].
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+/// Build one with [`Radar::from_vec`], or with [`Radar::from_vec_with`] to
+/// choose how tolerant the parse is and to get its [`Diagnostic`]s back.
+///
+/// Two products are equal when their contents are, wherever they came from:
+/// [`Radar::layout`] is left out of the comparison.
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Radar {
-    pub text_header: TextHeader,
+    /// The WMO/AWIPS header, or `None` for a file that starts at the message
+    /// header.
+    pub text_header: Option<TextHeader>,
     pub message_header: MessageHeader,
     pub product_description: ProductDescription,
     pub symbology: Option<SymbologyBlock>,
     pub graphic: Option<GraphicBlock>,
     pub tabular: Option<TabularBlock>,
+    /// Whether this product type is one this crate's decoding has been
+    /// validated against ([`MessageCode::is_validated_product`]). Other
+    /// products are parsed all the same, with a warning, since every packet
+    /// they use has a parser; treat their decoded values with more caution.
+    pub validated: bool,
+    /// Where each block was found in the file, for [`Radar::validate`];
+    /// `None` for a product that was built rather than parsed, or read back
+    /// through serde, which does not carry it.
+    #[serde(skip)]
+    pub layout: Option<Layout>,
 }
 
-/// Number of bytes from the start of the file to the start of the message
-/// header: the WMO/AWIPS text header is always exactly this long.
-const TEXT_HEADER_LEN: usize = 30;
+impl PartialEq for Radar {
+    fn eq(&self, other: &Self) -> bool {
+        let Radar {
+            text_header,
+            message_header,
+            product_description,
+            symbology,
+            graphic,
+            tabular,
+            validated,
+            layout: _,
+        } = self;
+        *text_header == other.text_header
+            && *message_header == other.message_header
+            && *product_description == other.product_description
+            && *symbology == other.symbology
+            && *graphic == other.graphic
+            && *tabular == other.tabular
+            && *validated == other.validated
+    }
+}
 
-/// Number of bytes from the start of the file to the data that follows the
-/// fixed headers — the text header plus the 18 byte message header plus the
-/// 102 byte product description block.
-const HEADER_SECTION_LEN: usize = TEXT_HEADER_LEN + 18 + 102;
+/// The result of [`Radar::from_vec_with`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parsed {
+    /// Trailing bytes after the last block parsed, as from [`Radar::from_vec`].
+    pub leftover: Vec<u8>,
+    pub radar: Radar,
+    /// Every anomaly the parse tolerated, in the order found.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Number of bytes from the start of the message header to the data that
+/// follows the fixed headers — the 18 byte message header plus the 102 byte
+/// product description block. The text header before them varies in length,
+/// so where the message header starts is found by parsing it.
+const MESSAGE_HEADERS_LEN: usize = 18 + 102;
 
 /// Locates a block by its product-description halfword offset and parses it.
 ///
 /// Per Figure 3-6 sheet 2, halfwords 55-60 hold offsets "from the beginning of
 /// the message header (halfword 1) to the (-1) divider of each block". The
-/// message header starts [`TEXT_HEADER_LEN`] bytes into the file and
-/// `remaining_file` starts at [`HEADER_SECTION_LEN`], so a halfword offset
-/// maps to `offset * 2 - (HEADER_SECTION_LEN - TEXT_HEADER_LEN)` bytes into
-/// `remaining_file`.
+/// `remaining_file` starts [`MESSAGE_HEADERS_LEN`] bytes after the message
+/// header, so a halfword offset maps to `offset * 2 - MESSAGE_HEADERS_LEN`
+/// bytes into `remaining_file`.
 ///
-/// Parsing is best effort: a block that cannot be located or decoded is logged
-/// and reported as `None` rather than failing the whole product, since these
-/// blocks are supplementary to the symbology data.
+/// Parsing is best effort: a block that cannot be located or decoded is
+/// reported as a [`Severity::Error`] diagnostic and returned as `None` rather
+/// than failing the whole product, since these blocks are supplementary to
+/// the symbology data. A parsed block comes back with the number of bytes it
+/// took.
 fn parse_offset_block<T, F>(
     remaining_file: &[u8],
     halfword_offset: i32,
     name: &str,
     parser: F,
-) -> Option<T>
+) -> Option<(T, usize)>
 where
     F: for<'a> Fn(&'a [u8]) -> nom::IResult<&'a [u8], T>,
 {
-    let header_bytes = (HEADER_SECTION_LEN - TEXT_HEADER_LEN) as i64;
+    let header_bytes = MESSAGE_HEADERS_LEN as i64;
     let byte_offset = (halfword_offset as i64) * 2 - header_bytes;
     if byte_offset < 0 {
-        warn!("{name} block offset {halfword_offset} falls inside the fixed header section");
+        report(
+            Severity::Error,
+            remaining_file,
+            format!("{name} block offset {halfword_offset} falls inside the fixed header section"),
+        );
         return None;
     }
 
     let Some(slice) = remaining_file.get(byte_offset as usize..) else {
-        warn!(
-            "{name} block offset {halfword_offset} is past the end of the {} byte payload",
-            remaining_file.len()
+        report(
+            Severity::Error,
+            remaining_file,
+            format!(
+                "{name} block offset {halfword_offset} is past the end of the {} byte payload",
+                remaining_file.len()
+            ),
         );
         return None;
     };
 
     match parser(slice) {
-        Ok((_, block)) => Some(block),
+        Ok((rest, block)) => Some((block, slice.len() - rest.len())),
         Err(e) => {
-            warn!("Could not decode the {name} block: {e:?}");
+            let at = match &e {
+                nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+                nom::Err::Incomplete(_) => slice,
+            };
+            let detail = diagnostics::parse_error(&e).detail;
+            report(Severity::Error, at, format!("Could not decode the {name} block: {detail}"));
             None
         }
     }
 }
 
+/// Decompresses everything after the product description.
+///
+/// The RPG writes one bzip2 stream, but a payload that has been through other
+/// tools may hold several back to back, so every stream is read.
+fn decompress(payload: &[u8]) -> Result<Vec<u8>, Error> {
+    if !payload.starts_with(b"BZh") {
+        return Err(Error::NotBzip2);
+    }
+    let mut decompressed = Vec::new();
+    MultiBzDecoder::new(payload)
+        .read_to_end(&mut decompressed)
+        .map_err(Error::Decompress)?;
+    Ok(decompressed)
+}
+
 impl Radar {
-    /// Parses the fixed-size header section (text header + message header +
-    /// product description, always 150 bytes) and, if the product
+    /// Parses the header section (text header, if any, + message header +
+    /// product description) and, if the product
     /// description declares one, the symbology block from `remaining_file`
     /// (already BZ-decompressed by the caller if necessary).
-    fn parse<'a>(header_section: &'a [u8], remaining_file: &'a [u8]) -> Result<(&'a [u8], Radar), Error> {
+    fn parse<'a>(
+        header_section: &'a [u8],
+        remaining_file: &'a [u8],
+        options: &ParseOptions,
+    ) -> Result<(&'a [u8], Radar), Error> {
         info!("File is {:?} bytes.", header_section.len());
         info!("Decode is {:?} bytes.", remaining_file.len());
         
-        // Text header
-        let (input_header, text_header) = text_header(header_section)?;
+        // Text header, unless the file starts at the message header
+        let (input_header, text_header) = match text_header(header_section) {
+            Ok((rest, header)) => (rest, Some(header)),
+            Err(_) => {
+                report(
+                    Severity::Warning,
+                    header_section,
+                    "No WMO/AWIPS text header; reading the file from the message header".to_string(),
+                );
+                (header_section, None)
+            }
+        };
+        let message_start = input_header;
 
         // Read and decode 18 byte Message Header Block
         let (input_header, message_header) = message_header(input_header)?;
 
-        // fail if code is not in supported products list
-        if !message_header.code.is_supported_product() {
+        // fail if code is not a product at all, and warn if it is one whose
+        // decoding has not been validated
+        if !message_header.code.is_product() {
             return Err(error_r::Error::ProductType(message_header.code))
         };
+        let validated = message_header.code.is_validated_product();
+        if !validated {
+            if !options.allow_unvalidated {
+                return Err(Error::UnvalidatedProduct(message_header.code));
+            }
+            report(
+                Severity::Warning,
+                message_start,
+                format!(
+                    "Product {:?} has not been validated against this crate; parsing it generically",
+                    message_header.code
+                ),
+            );
+        }
 
         // Read and decode 102 byte Product Description Block
         let (input_header, product_description) = product_description(input_header)?;
 
         if !input_header.is_empty() {
-            warn!("Header leftovers: {:?}", input_header);
+            report(Severity::Warning, input_header, format!("Header leftovers: {:?}", input_header));
+        }
+
+        // A decompressed product should be the size halfwords 52-53 promise;
+        // anything else means the download was cut short or corrupted.
+        if let Some(compression) = product_description
+            .dependent_params()
+            .compression()
+            .filter(|c| c.method == CompressionMethod::Bzip2)
+        {
+            if remaining_file.len() != compression.uncompressed_size as usize {
+                report(
+                    Severity::Error,
+                    remaining_file,
+                    format!(
+                        "Product decompressed to {} bytes but halfwords 52-53 give {}",
+                        remaining_file.len(),
+                        compression.uncompressed_size
+                    ),
+                );
+            }
         }
         
         info!("{:?}", product_description);
 
         // Check product version number
         // if there is a supported version of this product type BUT (and) the product version is greater than the supported version
-        if message_header.code.supported_version().is_some_and(|supported_version| product_description.version > supported_version) {
-            return Err(Error::SupportedVersion(product_description.version, message_header.code.supported_version()))
+        if let Some(supported_version) = message_header
+            .code
+            .supported_version()
+            .filter(|supported_version| product_description.version > *supported_version)
+        {
+            if !options.allow_newer_versions {
+                return Err(Error::SupportedVersion(product_description.version, Some(supported_version)));
+            }
+            report(
+                Severity::Warning,
+                message_start,
+                format!(
+                    "Product version {} is newer than the supported version {}; parsing it anyway",
+                    product_description.version, supported_version
+                ),
+            );
         }
 
         // ---------------------------
         // File contents after header
         // ---------------------------
 
+        diagnostics::set_block(Block::Symbology);
         let (input_data, symbology) = if product_description.offset_symbology > 0 {
             let (input_data, symbology) = symbology(remaining_file)?;
             (input_data, Some(symbology))
         } else {
             (remaining_file, None)
         };
+        let symbology_len = symbology.as_ref().map(|_| remaining_file.len() - input_data.len());
 
         // The graphic and tabular blocks are located by their own halfword
         // offsets rather than by following on from the symbology block, so
         // they are seeked to directly.
-        let graphic = if product_description.offset_graphic > 0 {
+        diagnostics::set_block(Block::Graphic);
+        let (graphic, graphic_len) = if product_description.offset_graphic > 0 {
             parse_offset_block(
                 remaining_file,
                 product_description.offset_graphic,
                 "graphic alphanumeric",
                 graphic_alphanumeric,
             )
+            .unzip()
         } else {
-            None
+            (None, None)
         };
 
-        let tabular = if product_description.offset_tabular > 0 {
+        diagnostics::set_block(Block::Tabular);
+        let (tabular, tabular_len) = if product_description.offset_tabular > 0 {
             parse_offset_block(
                 remaining_file,
                 product_description.offset_tabular,
                 "tabular alphanumeric",
                 tabular_alphanumeric,
             )
+            .unzip()
         } else {
-            None
+            (None, None)
         };
 
         Ok((
@@ -237,6 +454,13 @@ impl Radar {
                 symbology,
                 graphic,
                 tabular,
+                validated,
+                layout: Some(Layout {
+                    symbology_len,
+                    graphic_len,
+                    tabular_len,
+                    ..Layout::default()
+                }),
             },
         ))
     }
@@ -252,38 +476,86 @@ impl Radar {
     ///
     /// Returns an [`Error`] — never panics — if `file` is too short to
     /// contain the fixed-size text/message/product-description headers, if
-    /// those headers are malformed, if the product type or version isn't
-    /// supported, or if the symbology block uses a packet code this crate
+    /// those headers are malformed, if the message code is not a product or
+    /// the version of a validated product is newer than supported, or if the symbology block uses a packet code this crate
     /// doesn't implement yet.
-    pub fn from_vec(mut file: Vec<u8>) -> Result<(Vec<u8>, Radar), Error> {
-        if file.len() < HEADER_SECTION_LEN {
+    ///
+    /// Compression follows halfword 51 of the product description where Table
+    /// V defines it: [`Error::NotBzip2`] if it claims bzip2 but the data is not,
+    /// [`Error::Decompress`] if the stream is truncated or corrupt, and
+    /// [`Error::CompressionMethod`] for a method Table V does not define. A
+    /// product that decompresses to a size other than halfwords 52-53 give is
+    /// reported as a [`Severity::Error`] diagnostic.
+    ///
+    /// Anything tolerated along the way is only logged; use
+    /// [`Radar::from_vec_with`] to get it back as [`Diagnostic`]s.
+    pub fn from_vec(file: Vec<u8>) -> Result<(Vec<u8>, Radar), Error> {
+        let parsed = Radar::from_vec_with(file, &ParseOptions::default())?;
+        Ok((parsed.leftover, parsed.radar))
+    }
+
+    /// Parses a complete NEXRAD Level 3 file as [`Radar::from_vec`] does,
+    /// tolerating what `options` allows and collecting a [`Diagnostic`] for
+    /// each anomaly instead of only logging it.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::from_vec`], plus [`Error::UnvalidatedProduct`] when
+    /// `options` rejects unvalidated products, and [`Error::Strict`] with
+    /// the first [`Severity::Error`] diagnostic when `options` is strict.
+    /// Newer product versions are only an error unless
+    /// [`ParseOptions::allow_newer_versions`] is set.
+    pub fn from_vec_with(mut file: Vec<u8>, options: &ParseOptions) -> Result<Parsed, Error> {
+        // Whatever form the text header takes, it ends where the message
+        // header starts.
+        let text_header_len = text_header(&file).map_or(0, |(rest, _)| file.len() - rest.len());
+        let header_section_len = text_header_len + MESSAGE_HEADERS_LEN;
+        if file.len() < header_section_len {
             return Err(Error::TooShort {
-                expected: HEADER_SECTION_LEN,
+                expected: header_section_len,
                 actual: file.len(),
             });
         }
-        let file_after_headers = file.split_off(HEADER_SECTION_LEN);
-
-        // Uncompress symbology block if necessary
-        let decomp_vec = if file_after_headers.starts_with(b"BZ") {
-            let mut decoder = BzDecoder::new(file_after_headers.as_slice());
-            let mut decomp_vec = Vec::new();
-            decoder.read_to_end(&mut decomp_vec)?;
-            decomp_vec
-        } else {
-            // combine file after header back on to file???
-            file_after_headers
+        let file_after_headers = file.split_off(header_section_len);
+        let compressed_len = file_after_headers.len();
+
+        // Halfword 51 says whether the rest is compressed. The description is
+        // parsed again, with diagnostics, once the rest is ready.
+        let compression = product_description(&file[text_header_len + 18..])
+            .ok()
+            .and_then(|(_, description)| description.dependent_params().compression());
+        let decomp_vec = match compression.map(|c| c.method) {
+            Some(CompressionMethod::Bzip2) => decompress(&file_after_headers)?,
+            Some(CompressionMethod::None) => file_after_headers,
+            Some(CompressionMethod::Unknown(method)) => return Err(Error::CompressionMethod(method)),
+            // Table V gives this product no compression halfwords, so only
+            // the data itself can say.
+            None if file_after_headers.starts_with(b"BZh") => decompress(&file_after_headers)?,
+            None => file_after_headers,
         };
 
         info!("File is {:?} bytes.", file.len());
-        match Radar::parse(&file, &decomp_vec) {
-            Ok((leftover, radar)) => {
-                Ok((leftover.to_vec(), radar))
+        let (result, diagnostics) = diagnostics::collect(
+            &[(&file, 0), (&decomp_vec, header_section_len)],
+            || Radar::parse(&file, &decomp_vec, options),
+        );
+        let (leftover, mut radar) = result?;
+        if let Some(layout) = &mut radar.layout {
+            layout.text_header_len = text_header_len;
+            layout.message_len = file.len() - text_header_len + compressed_len;
+            layout.payload_len = decomp_vec.len();
+        }
+
+        if options.strict {
+            if let Some(first) = diagnostics.iter().find(|d| d.severity == Severity::Error) {
+                return Err(Error::Strict(first.clone()));
             }
-            Err(e) => {
-                
-                Err(e)
-            },
         }
+
+        Ok(Parsed {
+            leftover: leftover.to_vec(),
+            radar,
+            diagnostics,
+        })
     }
 }
\ No newline at end of file
@@ -8,6 +8,8 @@ use nom::{
 };
 
 use super::MessageCode;
+use crate::encode::{put_i16, put_i32, Encode};
+use crate::error_r::Error;
 
 
 /// Graphic Product Message: Message Header Block
@@ -68,6 +70,21 @@ pub fn message_header(input: &[u8]) -> IResult<&[u8], MessageHeader> {
     ))
 }
 
+impl Encode for MessageHeader {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let timestamp = self.datetime.timestamp();
+        put_i16(out, self.code as i16);
+        // Back to the 1-based day count and seconds after midnight.
+        put_i16(out, (timestamp.div_euclid(86_400) + 1) as i16);
+        put_i32(out, timestamp.rem_euclid(86_400) as i32);
+        put_i32(out, self.length);
+        put_i16(out, self.source);
+        put_i16(out, self.dest);
+        put_i16(out, self.nblocks);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,6 +131,15 @@ mod tests {
         assert_eq!(header.datetime.to_string(), "2022-09-09 12:55:14 UTC");
     }
 
+    #[test]
+    fn encodes_the_sample_header_back_to_its_bytes() {
+        let file = include_bytes!("../data/sn_DS.p20-r_kmkx.last");
+        let (_, header) = message_header(&file[30..]).unwrap();
+        let mut out = Vec::new();
+        header.encode(&mut out).unwrap();
+        assert_eq!(out, file[30..48]);
+    }
+
     #[test]
     fn unrecognized_code_falls_back_to_spare_instead_of_failing() {
         let mut bytes = Vec::new();
@@ -0,0 +1,334 @@
+//! A minimal writer for the NetCDF classic format, 64-bit offset variant
+//! (CDF-2), as the NetCDF Users Guide's "File Format Specification" gives it.
+//!
+//! Only what [`crate::cfradial`] needs is implemented: fixed-size dimensions
+//! (no record dimension), global and variable attributes, and `char`, `int`,
+//! `float` and `double` variables. Like XDR, which the format is built on,
+//! everything is big-endian and padded to a 4-byte boundary.
+
+/// The `nc_type` codes.
+const NC_CHAR: u32 = 2;
+const NC_INT: u32 = 4;
+const NC_FLOAT: u32 = 5;
+const NC_DOUBLE: u32 = 6;
+
+/// Tags that open the dimension, variable and attribute lists.
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+
+/// Values of one attribute or variable.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Values {
+    Char(String),
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+}
+
+impl From<&str> for Values {
+    fn from(text: &str) -> Self {
+        Values::Char(text.to_string())
+    }
+}
+
+impl From<String> for Values {
+    fn from(text: String) -> Self {
+        Values::Char(text)
+    }
+}
+
+impl From<f32> for Values {
+    fn from(value: f32) -> Self {
+        Values::Float(vec![value])
+    }
+}
+
+impl From<f64> for Values {
+    fn from(value: f64) -> Self {
+        Values::Double(vec![value])
+    }
+}
+
+impl From<i32> for Values {
+    fn from(value: i32) -> Self {
+        Values::Int(vec![value])
+    }
+}
+
+impl Values {
+    fn nc_type(&self) -> u32 {
+        match self {
+            Values::Char(_) => NC_CHAR,
+            Values::Int(_) => NC_INT,
+            Values::Float(_) => NC_FLOAT,
+            Values::Double(_) => NC_DOUBLE,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Values::Char(text) => text.len(),
+            Values::Int(values) => values.len(),
+            Values::Float(values) => values.len(),
+            Values::Double(values) => values.len(),
+        }
+    }
+
+    /// The values, big-endian, padded to 4 bytes.
+    fn bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = match self {
+            Values::Char(text) => text.as_bytes().to_vec(),
+            Values::Int(values) => values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+            Values::Float(values) => values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+            Values::Double(values) => values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+        };
+        bytes.resize(padded(bytes.len()), 0);
+        bytes
+    }
+}
+
+/// A variable: its dimensions, by index, its attributes and its values,
+/// which must number the product of its dimensions' lengths.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Variable {
+    pub name: String,
+    pub dimensions: Vec<usize>,
+    pub attributes: Vec<(String, Values)>,
+    pub values: Values,
+}
+
+impl Variable {
+    pub fn new(name: &str, dimensions: &[usize], values: impl Into<Values>) -> Variable {
+        Variable {
+            name: name.to_string(),
+            dimensions: dimensions.to_vec(),
+            attributes: Vec::new(),
+            values: values.into(),
+        }
+    }
+
+    pub fn with(mut self, name: &str, value: impl Into<Values>) -> Variable {
+        self.attributes.push((name.to_string(), value.into()));
+        self
+    }
+}
+
+/// A whole NetCDF dataset, held in memory until encoded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Dataset {
+    pub dimensions: Vec<(String, usize)>,
+    pub attributes: Vec<(String, Values)>,
+    pub variables: Vec<Variable>,
+}
+
+impl Dataset {
+    /// Adds a dimension, returning its index.
+    pub fn dimension(&mut self, name: &str, len: usize) -> usize {
+        self.dimensions.push((name.to_string(), len));
+        self.dimensions.len() - 1
+    }
+
+    pub fn attribute(&mut self, name: &str, value: impl Into<Values>) {
+        self.attributes.push((name.to_string(), value.into()));
+    }
+
+    pub fn variable(&mut self, variable: Variable) {
+        self.variables.push(variable);
+    }
+
+    /// The dataset as a CDF-2 file: the header, then each variable's values
+    /// in order.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Offsets are 8 bytes whatever their value, so the header's length
+        // can be found by encoding it once with placeholder offsets.
+        let header_len = self.header(&vec![0; self.variables.len()]).len();
+        let mut begins = Vec::new();
+        let mut offset = header_len as u64;
+        for variable in &self.variables {
+            begins.push(offset);
+            offset += padded(variable.values.bytes().len()) as u64;
+        }
+
+        let mut file = self.header(&begins);
+        for variable in &self.variables {
+            file.extend(variable.values.bytes());
+        }
+        file
+    }
+
+    fn header(&self, begins: &[u64]) -> Vec<u8> {
+        let mut out = b"CDF\x02".to_vec();
+        put_u32(&mut out, 0); // numrecs: no record dimension
+
+        put_list(&mut out, NC_DIMENSION, self.dimensions.len());
+        for (name, len) in &self.dimensions {
+            put_name(&mut out, name);
+            put_u32(&mut out, *len as u32);
+        }
+
+        put_attributes(&mut out, &self.attributes);
+
+        put_list(&mut out, NC_VARIABLE, self.variables.len());
+        for (variable, begin) in self.variables.iter().zip(begins) {
+            put_name(&mut out, &variable.name);
+            put_u32(&mut out, variable.dimensions.len() as u32);
+            for dimension in &variable.dimensions {
+                put_u32(&mut out, *dimension as u32);
+            }
+            put_attributes(&mut out, &variable.attributes);
+            put_u32(&mut out, variable.values.nc_type());
+            put_u32(&mut out, padded(variable.values.bytes().len()) as u32);
+            out.extend(begin.to_be_bytes());
+        }
+        out
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_be_bytes());
+}
+
+/// A list's tag and count, or ABSENT (two zero words) for an empty list.
+fn put_list(out: &mut Vec<u8>, tag: u32, len: usize) {
+    put_u32(out, if len == 0 { 0 } else { tag });
+    put_u32(out, len as u32);
+}
+
+fn put_name(out: &mut Vec<u8>, name: &str) {
+    put_u32(out, name.len() as u32);
+    out.extend(name.as_bytes());
+    out.resize(padded(out.len()), 0);
+}
+
+fn put_attributes(out: &mut Vec<u8>, attributes: &[(String, Values)]) {
+    put_list(out, NC_ATTRIBUTE, attributes.len());
+    for (name, values) in attributes {
+        put_name(out, name);
+        put_u32(out, values.nc_type());
+        put_u32(out, values.len() as u32);
+        out.extend(values.bytes());
+    }
+}
+
+/// `len` rounded up to a multiple of 4.
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+/// Reads back what [`Dataset::to_bytes`] writes, for tests.
+#[cfg(test)]
+pub(crate) fn read(bytes: &[u8]) -> Dataset {
+    struct Reader<'a>(&'a [u8], usize);
+    impl Reader<'_> {
+        fn u32(&mut self) -> u32 {
+            let value = u32::from_be_bytes(self.0[self.1..self.1 + 4].try_into().unwrap());
+            self.1 += 4;
+            value
+        }
+        fn bytes(&mut self, len: usize) -> &[u8] {
+            let start = self.1;
+            self.1 += padded(len);
+            &self.0[start..start + len]
+        }
+        fn name(&mut self) -> String {
+            let len = self.u32() as usize;
+            String::from_utf8(self.bytes(len).to_vec()).unwrap()
+        }
+        fn values(&mut self, nc_type: u32, len: usize) -> Values {
+            let size = match nc_type {
+                NC_CHAR => 1,
+                NC_DOUBLE => 8,
+                _ => 4,
+            };
+            let bytes = self.bytes(len * size).to_vec();
+            match nc_type {
+                NC_CHAR => Values::Char(String::from_utf8(bytes).unwrap()),
+                NC_INT => Values::Int(bytes.chunks(4).map(|c| i32::from_be_bytes(c.try_into().unwrap())).collect()),
+                NC_FLOAT => Values::Float(bytes.chunks(4).map(|c| f32::from_be_bytes(c.try_into().unwrap())).collect()),
+                _ => Values::Double(bytes.chunks(8).map(|c| f64::from_be_bytes(c.try_into().unwrap())).collect()),
+            }
+        }
+        fn attributes(&mut self) -> Vec<(String, Values)> {
+            let (_, count) = (self.u32(), self.u32());
+            (0..count)
+                .map(|_| {
+                    let name = self.name();
+                    let (nc_type, len) = (self.u32(), self.u32() as usize);
+                    (name, self.values(nc_type, len))
+                })
+                .collect()
+        }
+    }
+
+    assert_eq!(&bytes[..4], b"CDF\x02");
+    let mut r = Reader(bytes, 8);
+    let (_, count) = (r.u32(), r.u32());
+    let dimensions: Vec<(String, usize)> = (0..count).map(|_| (r.name(), r.u32() as usize)).collect();
+    let attributes = r.attributes();
+    let (_, count) = (r.u32(), r.u32());
+    let mut variables = Vec::new();
+    for _ in 0..count {
+        let name = r.name();
+        let rank = r.u32();
+        let var_dimensions: Vec<usize> = (0..rank).map(|_| r.u32() as usize).collect();
+        let var_attributes = r.attributes();
+        let (nc_type, _vsize) = (r.u32(), r.u32());
+        let begin = u64::from_be_bytes(r.bytes(8).try_into().unwrap()) as usize;
+        let len = var_dimensions.iter().map(|d| dimensions[*d].1).product();
+        let values = Reader(bytes, begin).values(nc_type, len);
+        variables.push(Variable {
+            name,
+            dimensions: var_dimensions,
+            attributes: var_attributes,
+            values,
+        });
+    }
+    Dataset {
+        dimensions,
+        attributes,
+        variables,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_small_dataset_is_laid_out_as_the_specification_gives() {
+        let mut dataset = Dataset::default();
+        let x = dataset.dimension("x", 3);
+        dataset.attribute("title", "t");
+        dataset.variable(Variable::new("v", &[x], Values::Int(vec![1, 2, 3])).with("units", "m"));
+        let bytes = dataset.to_bytes();
+
+        let mut expected = b"CDF\x02".to_vec();
+        let words = |out: &mut Vec<u8>, words: &[u32]| out.extend(words.iter().flat_map(|w| w.to_be_bytes()));
+        words(&mut expected, &[0, NC_DIMENSION, 1, 1]);
+        expected.extend(b"x\0\0\0");
+        words(&mut expected, &[3, NC_ATTRIBUTE, 1, 5]);
+        expected.extend(b"title\0\0\0");
+        words(&mut expected, &[NC_CHAR, 1]);
+        expected.extend(b"t\0\0\0");
+        words(&mut expected, &[NC_VARIABLE, 1, 1]);
+        expected.extend(b"v\0\0\0");
+        words(&mut expected, &[1, 0, NC_ATTRIBUTE, 1, 5]);
+        expected.extend(b"units\0\0\0");
+        words(&mut expected, &[NC_CHAR, 1]);
+        expected.extend(b"m\0\0\0");
+        words(&mut expected, &[NC_INT, 12]);
+        let begin = expected.len() as u64 + 8;
+        expected.extend(begin.to_be_bytes());
+        words(&mut expected, &[1, 2, 3]);
+        assert_eq!(bytes, expected);
+
+        assert_eq!(read(&bytes), dataset);
+    }
+
+    #[test]
+    fn empty_lists_are_absent() {
+        let bytes = Dataset::default().to_bytes();
+        assert_eq!(bytes, [b"CDF\x02".as_slice(), &[0; 28]].concat());
+    }
+}
@@ -3,22 +3,26 @@
 //!
 //! The layout follows the reference NWS plots — a square radar image on black,
 //! with a light panel to its right carrying the product annotations and a
-//! colour bar legend. Only the first symbology layer is drawn, and the graphic
-//! and tabular alphanumeric blocks are not rendered.
+//! colour bar legend. Every symbology layer is drawn, unless hidden with
+//! [`PlotOptions::with_hidden_layer`]: the data packets of all layers first,
+//! in layer order, then the text, symbols and vectors of all layers on top.
+//! The first radial packet sets the scale and the legend. The graphic and
+//! tabular alphanumeric blocks are not rendered.
 //!
 //! # Colouring
 //!
-//! The two radial packet types are coloured by different rules, because their
-//! data levels mean different things:
+//! Data packets are coloured by one of two rules, because their data levels
+//! mean different things:
 //!
-//! - **Radial Data (`AF1F`, Figure 3-10)** carries 4-bit levels, which are
-//!   exactly the display categories the Product Specification's colour tables
-//!   are written against. These go through
-//!   [`crate::MessageCode::color_code`], so a product with no table renders
-//!   entirely in [`crate::FALLBACK_GRAY`].
+//! - **Radial Data (`AF1F`, Figure 3-10)** and **Raster Data (`BA0F`/`BA07`,
+//!   Figure 3-11)** carry 4-bit levels, which are exactly the display
+//!   categories the Product Specification's colour tables are written against.
+//!   These go through [`crate::MessageCode::color_code`], so a product with no
+//!   table renders entirely in [`crate::FALLBACK_GRAY`].
 //!
-//! - **Digital Radial Data Array (packet code 16, Figure 3-11c)** carries 8-bit
-//!   levels that are *not* colour table indices. Where the product's threshold
+//! - **Digital Radial Data Array (packet code 16, Figure 3-11c)**, and the
+//!   gridded arrays of packets 17, 18 and 33, carry levels that are *not*
+//!   colour table indices. Where the product's threshold
 //!   scaling is known ([`crate::LevelScaling`]) they are decoded to a physical
 //!   value and passed through a [`ColorRamp`]; otherwise the raw level is fed
 //!   to a ramp that needs no units. Either way these products render in colour
@@ -27,13 +31,18 @@
 //! Pick the ramp with [`PlotOptions::ramp`], or leave it unset to let
 //! [`ColorRamp::default_for_units`] choose from the product's units.
 
+mod overlay;
+
+use image::RgbaImage;
 use plotters::coord::types::RangedCoordf32;
 use plotters::prelude::*;
 use tracing::{debug, warn};
 
 use crate::color_ramp::RANGE_FOLDED;
+use crate::data_field::lfm_box_size;
 use crate::{
-    error_r::Error, product_symbology::SymPacketData, ColorRamp, LevelThreshold, Qualifier, Radar,
+    error_r::Error, product_symbology::{SymPacketData, SymbologyBlock}, ColorRamp, LevelThreshold, Qualifier, Radar,
+    RadialGeometry, Run, Site,
 };
 
 /// Width in pixels of the annotation and legend panel.
@@ -44,10 +53,68 @@ const PLOT_BACKGROUND: RGBColor = RGBColor(0, 0, 0);
 /// Background of the annotation panel, matching the reference plots.
 const PANEL_BACKGROUND: RGBColor = RGBColor(220, 220, 220);
 
+/// Units for range ring labels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DistanceUnits {
+    #[default]
+    Kilometres,
+    NauticalMiles,
+}
+
+impl DistanceUnits {
+    /// Metres in one unit.
+    pub fn metres(self) -> f64 {
+        match self {
+            DistanceUnits::Kilometres => 1000.0,
+            DistanceUnits::NauticalMiles => 1852.0,
+        }
+    }
+
+    /// The abbreviation ring labels carry.
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            DistanceUnits::Kilometres => "km",
+            DistanceUnits::NauticalMiles => "nmi",
+        }
+    }
+}
+
+/// Range rings and azimuth spokes drawn over a true-scale plot.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RangeRings {
+    /// Distance between rings, in `units`.
+    pub spacing: f64,
+    pub units: DistanceUnits,
+    /// Degrees between azimuth spokes, starting from north; 0 draws none.
+    /// Spacings below [`MIN_SPOKE_SPACING`] are drawn at it.
+    pub spoke_spacing: f64,
+}
+
+/// The closest azimuth spokes are drawn, in degrees.
+pub const MIN_SPOKE_SPACING: f64 = 1.0;
+
+impl RangeRings {
+    /// Rings every `spacing` `units`, with spokes every 30 degrees.
+    pub fn new(spacing: f64, units: DistanceUnits) -> Self {
+        RangeRings {
+            spacing,
+            units,
+            spoke_spacing: 30.0,
+        }
+    }
+
+    /// Sets the degrees between azimuth spokes; 0 draws none, and anything
+    /// else is raised to at least [`MIN_SPOKE_SPACING`].
+    pub fn with_spoke_spacing(mut self, degrees: f64) -> Self {
+        self.spoke_spacing = if degrees > 0.0 { degrees.max(MIN_SPOKE_SPACING) } else { 0.0 };
+        self
+    }
+}
+
 /// How to render a product.
 ///
 /// `Default` gives the settings [`Radar::plot`] uses.
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct PlotOptions {
     /// Colour ramp for digital data arrays. When `None`, the ramp is chosen
     /// from the product's units by [`ColorRamp::default_for_units`].
@@ -61,7 +128,7 @@ pub struct PlotOptions {
     ///
     /// The file only carries the station identifier, not a place name, so when
     /// this is `None` the panel shows the identifier from the text header on its
-    /// own.
+    /// own, or leaves the line blank for a file without one.
     pub site_label: Option<String>,
 
     /// Side length in pixels of the square radar image. The panel is added to
@@ -70,6 +137,26 @@ pub struct PlotOptions {
 
     /// Whether to draw the annotation and legend panel at all.
     pub panel: bool,
+
+    /// Range in kilometres from the radar to the edge of the image circle.
+    ///
+    /// When set, bins are drawn at their true range — the packet's index of
+    /// first range bin and the product's bin size are applied, and the sweep
+    /// is centred on the packet's I/J centre of sweep — so plots of different
+    /// products from one site at the same size and range line up pixel for
+    /// pixel. When `None`, the last bin simply reaches the edge of the circle.
+    pub max_range_km: Option<f64>,
+
+    /// Range rings and azimuth spokes to draw over a true-scale plot. Ignored
+    /// without [`PlotOptions::max_range_km`], since the rings need a scale.
+    pub range_rings: Option<RangeRings>,
+
+    /// Indices of symbology layers to leave out. Every other layer is drawn.
+    pub hidden_layers: Vec<usize>,
+
+    /// Whether the radar image's background is transparent rather than black,
+    /// for compositing onto a map. The panel, if shown, stays opaque.
+    pub transparent: bool,
 }
 
 impl PlotOptions {
@@ -80,6 +167,10 @@ impl PlotOptions {
             site_label: None,
             image_size: 1200,
             panel: true,
+            max_range_km: None,
+            range_rings: None,
+            hidden_layers: Vec::new(),
+            transparent: false,
         }
     }
 
@@ -107,6 +198,36 @@ impl PlotOptions {
         self
     }
 
+    /// Draws bins at their true range, out to `km` from the radar at the edge
+    /// of the image circle.
+    pub fn with_max_range_km(mut self, km: f64) -> Self {
+        self.max_range_km = Some(km);
+        self
+    }
+
+    /// Draws range rings and azimuth spokes over a true-scale plot.
+    pub fn with_range_rings(mut self, rings: RangeRings) -> Self {
+        self.range_rings = Some(rings);
+        self
+    }
+
+    /// Leaves symbology layer `index` (counting from 0) out of the plot.
+    pub fn with_hidden_layer(mut self, index: usize) -> Self {
+        self.hidden_layers.push(index);
+        self
+    }
+
+    /// Leaves the radar image's background transparent.
+    pub fn with_transparent_background(mut self) -> Self {
+        self.transparent = true;
+        self
+    }
+
+    /// Whether symbology layer `index` is drawn.
+    pub fn shows_layer(&self, index: usize) -> bool {
+        !self.hidden_layers.contains(&index)
+    }
+
     fn resolved_image_size(&self) -> u32 {
         self.image_size.max(200)
     }
@@ -122,6 +243,40 @@ struct Legend {
     range_folded: bool,
 }
 
+/// Whether the plotter draws `packet` as data: radials, rasters and gridded
+/// arrays.
+fn is_data_packet(packet: &SymPacketData) -> bool {
+    packet.num_bins() > 0
+        || matches!(
+            packet,
+            SymPacketData::RasterData(_)
+                | SymPacketData::DigitalPrecipitationDataArray(_)
+                | SymPacketData::PrecipitationRateDataArray(_)
+                | SymPacketData::DigitalRasterDataArray(_)
+        )
+}
+
+/// The packet the legend is worked out for: the first data packet in a shown
+/// layer, since data may share its layer with other packets, or otherwise
+/// simply the first packet of a shown layer.
+fn reference_packet<'a>(symbology: &'a SymbologyBlock, options: &PlotOptions) -> Option<&'a SymPacketData> {
+    let mut shown = symbology
+        .layers
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| options.shows_layer(*index))
+        .flat_map(|(_, layer)| layer);
+    shown.clone().find(|packet| is_data_packet(packet)).or_else(|| shown.next())
+}
+
+/// The packet the plot is scaled by: the first data packet, or otherwise the
+/// first packet, whether or not its layer is shown, so hiding a layer never
+/// moves the others.
+fn scale_packet(symbology: &SymbologyBlock) -> Option<&SymPacketData> {
+    let mut packets = symbology.layers.iter().flatten();
+    packets.clone().find(|packet| is_data_packet(packet)).or_else(|| packets.next())
+}
+
 impl Radar {
     /// Renders this product to `image.png` in the current directory using
     /// [`PlotOptions::new`].
@@ -130,7 +285,7 @@ impl Radar {
     ///
     /// Returns [`Error::NoSymbologyData`] if this product has no symbology
     /// block at all, or [`Error::NoSymbologyLayers`] if it has one but with
-    /// zero layers.
+    /// no packets in any shown layer.
     pub fn plot(&self) -> Result<(), Error> {
         self.plot_to("image.png")
     }
@@ -147,36 +302,148 @@ impl Radar {
     /// Azimuths follow the ICD convention (Figure 3-10: "Scan is always in
     /// Clockwise direction") mapped to a north-up display: azimuth 0° points to
     /// the top of the image and angles increase clockwise, so 90° points right
-    /// (east), 180° down (south), and 270° left (west). Range is drawn as a
-    /// fraction of the image radius, not to a geographic scale — the packet's
-    /// range scale factor and index of first range bin are not applied.
+    /// (east), 180° down (south), and 270° left (west). Rasters and the other
+    /// I/J coordinates are 1/4 km east and south of the display centre.
+    ///
+    /// With [`PlotOptions::max_range_km`] set, range is drawn to scale: each
+    /// bin sits at its true range from the index of first range bin and the
+    /// product's bin size, about the packet's I/J centre of sweep. Without it,
+    /// range is drawn as a fraction of the image radius, the last bin reaching
+    /// the edge of the circle.
+    ///
+    /// With [`PlotOptions::transparent`] set, the PNG carries an alpha channel
+    /// and the radar image's background is transparent.
     pub fn plot_with<P: AsRef<std::path::Path>>(
         &self,
         path: P,
         options: &PlotOptions,
     ) -> Result<(), Error> {
         let path = path.as_ref();
-        let symbology = self.symbology.as_ref().ok_or(Error::NoSymbologyData)?;
-        let first_layer = symbology.layers.first().ok_or(Error::NoSymbologyLayers)?;
+        if options.transparent {
+            return self.render_rgba(options)?.save(path).map_err(|e| Error::Plot(Box::new(e)));
+        }
+
+        let root = BitMapBackend::new(path, self.canvas_size(options)).into_drawing_area();
+        self.draw(&root, options, Some(PLOT_BACKGROUND))?;
+        root.present()?;
+        Ok(())
+    }
+
+    /// Renders this product with the given options to an image in memory, as
+    /// [`Radar::plot_with`] would write it.
+    ///
+    /// With [`PlotOptions::transparent`] set, the radar image's background has
+    /// an alpha of 0. The plot is drawn twice, over black and over white, and
+    /// each pixel's alpha is recovered from the difference, so anti-aliased
+    /// edges blend smoothly onto whatever the image is composited over.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::plot`].
+    pub fn render_rgba(&self, options: &PlotOptions) -> Result<RgbaImage, Error> {
+        let over_black = self.render_rgb(options, PLOT_BACKGROUND)?;
+        let (width, height) = self.canvas_size(options);
+        if !options.transparent {
+            let pixels = over_black.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], u8::MAX]).collect();
+            return Ok(RgbaImage::from_raw(width, height, pixels).expect("buffer matches the canvas"));
+        }
+
+        let over_white = self.render_rgb(options, WHITE)?;
+        let pixels = over_black
+            .chunks_exact(3)
+            .zip(over_white.chunks_exact(3))
+            .flat_map(|(black, white)| unblend(black, white))
+            .collect();
+        Ok(RgbaImage::from_raw(width, height, pixels).expect("buffer matches the canvas"))
+    }
+
+    /// Renders this product with the given options to PNG bytes in memory.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::plot`], or [`Error::Plot`] if the PNG cannot be encoded.
+    pub fn render_png_bytes(&self, options: &PlotOptions) -> Result<Vec<u8>, Error> {
+        let image = self.render_rgba(options)?;
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        if options.transparent {
+            image.write_to(&mut bytes, image::ImageFormat::Png)
+        } else {
+            image::DynamicImage::ImageRgba8(image).into_rgb8().write_to(&mut bytes, image::ImageFormat::Png)
+        }
+        .map_err(|e| Error::Plot(Box::new(e)))?;
+        Ok(bytes.into_inner())
+    }
 
+    /// Renders this product with the given options as an SVG document.
+    ///
+    /// Every bin, cell and symbol becomes its own element, so a large product
+    /// makes a large document. With [`PlotOptions::transparent`] set, the
+    /// radar image has no background at all.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::plot`].
+    pub fn render_svg(&self, options: &PlotOptions) -> Result<String, Error> {
+        let mut svg = String::new();
+        {
+            let root = SVGBackend::with_string(&mut svg, self.canvas_size(options)).into_drawing_area();
+            let background = (!options.transparent).then_some(PLOT_BACKGROUND);
+            self.draw(&root, options, background)?;
+            root.present()?;
+        }
+        Ok(svg)
+    }
+
+    /// Width and height of the whole image, panel included.
+    fn canvas_size(&self, options: &PlotOptions) -> (u32, u32) {
         let plot_size = options.resolved_image_size();
         let panel_width = if options.panel { PANEL_WIDTH } else { 0 };
-        let legend = self.legend_for(first_layer, options);
+        (plot_size + panel_width, plot_size)
+    }
+
+    /// Renders into a fresh RGB buffer with the radar image on `background`.
+    fn render_rgb(&self, options: &PlotOptions, background: RGBColor) -> Result<Vec<u8>, Error> {
+        let (width, height) = self.canvas_size(options);
+        let mut buffer = vec![0; width as usize * height as usize * 3];
+        {
+            let root = BitMapBackend::with_buffer(&mut buffer, (width, height)).into_drawing_area();
+            self.draw(&root, options, Some(background))?;
+            root.present()?;
+        }
+        Ok(buffer)
+    }
+
+    /// Draws the whole plot onto `root`: the radar image, on `background`
+    /// unless that is `None`, and the panel.
+    fn draw<DB: DrawingBackend>(
+        &self,
+        root: &DrawingArea<DB, plotters::coord::Shift>,
+        options: &PlotOptions,
+        background: Option<RGBColor>,
+    ) -> Result<(), Error>
+    where
+        Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+    {
+        let symbology = self.symbology.as_ref().ok_or(Error::NoSymbologyData)?;
+        let reference = reference_packet(symbology, options).ok_or(Error::NoSymbologyLayers)?;
+        let scale_by = scale_packet(symbology).ok_or(Error::NoSymbologyLayers)?;
 
-        let root = BitMapBackend::new(path, (plot_size + panel_width, plot_size))
-            .into_drawing_area();
-        root.fill(&PANEL_BACKGROUND)?;
+        let plot_size = options.resolved_image_size();
+        let legend = self.legend_for(reference, options);
 
         let (plot_area, panel_area) = root.split_horizontally(plot_size);
-        plot_area.fill(&PLOT_BACKGROUND)?;
+        if options.panel {
+            panel_area.fill(&PANEL_BACKGROUND)?;
+        }
+        if let Some(background) = background {
+            plot_area.fill(&background)?;
+        }
 
-        self.draw_sweep(&plot_area, first_layer, plot_size, &legend)?;
+        self.draw_layers(&plot_area, symbology, scale_by, plot_size, &legend, options)?;
 
         if options.panel {
             self.draw_panel(&panel_area, &legend, options)?;
         }
-
-        root.present()?;
         Ok(())
     }
 
@@ -185,7 +452,10 @@ impl Radar {
     fn legend_for(&self, layer: &SymPacketData, options: &PlotOptions) -> Legend {
         let message_code = self.message_header.code;
         match layer {
-            SymPacketData::DigitalRadialDataArray(_) => {
+            SymPacketData::DigitalRadialDataArray(_)
+            | SymPacketData::DigitalPrecipitationDataArray(_)
+            | SymPacketData::PrecipitationRateDataArray(_)
+            | SymPacketData::DigitalRasterDataArray(_) => {
                 let scaling = self.product_description.level_scaling();
                 let units = scaling.as_ref().map(|s| s.units).unwrap_or("");
 
@@ -224,7 +494,7 @@ impl Radar {
                         .is_some(),
                 }
             }
-            SymPacketData::RadialDataAF1F(_) => {
+            SymPacketData::RadialDataAF1F(_) | SymPacketData::RasterData(_) => {
                 if !message_code.has_color_table() {
                     warn!(
                         "No color table defined for {message_code:?}; every data level renders in \
@@ -253,13 +523,17 @@ impl Radar {
         }
     }
 
-    /// Draws the radial sweep into `area`.
-    fn draw_sweep<DB: DrawingBackend>(
+    /// Draws every shown layer into `area`: the data packets of all of them
+    /// first, in layer order, then their annotation packets on top, so text,
+    /// symbols and vectors are never hidden under data.
+    fn draw_layers<DB: DrawingBackend>(
         &self,
         area: &DrawingArea<DB, plotters::coord::Shift>,
-        layer: &SymPacketData,
+        symbology: &SymbologyBlock,
+        scale_by: &SymPacketData,
         plot_size: u32,
         legend: &Legend,
+        options: &PlotOptions,
     ) -> Result<(), Error>
     where
         Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
@@ -267,8 +541,6 @@ impl Radar {
         let logical = plot_size as f32;
         // Leave a small margin so the outermost bins are not clipped.
         let r_max = logical * 0.458;
-        let xc = logical / 2.0;
-        let yc = logical / 2.0;
 
         let area = area.apply_coord_spec(Cartesian2d::<RangedCoordf32, RangedCoordf32>::new(
             0f32..logical,
@@ -276,11 +548,142 @@ impl Radar {
             (0..plot_size as i32, 0..plot_size as i32),
         ));
 
-        let n_bins = layer.num_bins() as f32;
-        if n_bins <= 0.0 {
-            warn!("First symbology layer reports no range bins; nothing to draw");
+        let scale = PlotScale::new(scale_by, &self.product_description, options, logical, r_max);
+        let shown = || {
+            symbology
+                .layers
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| options.shows_layer(*index))
+                .flat_map(|(_, layer)| layer)
+        };
+
+        for packet in shown().filter(|packet| is_data_packet(packet)) {
+            if packet.num_bins() > 0 {
+                self.draw_radial_packet(&area, packet, &scale, legend);
+            } else {
+                self.draw_raster_packet(&area, packet, &scale, legend)?;
+            }
+        }
+        for packet in shown() {
+            overlay::draw_overlay(&area, packet, &scale, self.message_header.code)?;
+        }
+
+        if let (Some(rings), true) = (options.range_rings, scale.true_scale) {
+            draw_range_rings(&area, rings, scale.centre, r_max, scale.pixels_per_metre)?;
+        }
+
+        Ok(())
+    }
+
+    /// How the digital data arrays colour an 8-bit level, or `None` to leave
+    /// it undrawn.
+    fn digital_color(&self, legend: &Legend) -> impl Fn(u8) -> Option<RGBColor> {
+        let ramp = legend.ramp.unwrap_or_default();
+        let scaling = self.product_description.level_scaling();
+
+        // 8-bit levels are not colour table indices. A ramp with units is fed
+        // the decoded physical value; a ramp without units is defined over the
+        // raw level itself, so it must be fed the raw level rather than a
+        // measurement in some other quantity. `None` means "don't draw",
+        // leaving the background showing.
+        move |level: u8| -> Option<RGBColor> {
+            if let Some(scaling) = scaling.as_ref() {
+                if scaling.is_range_folded(level) {
+                    return Some(RANGE_FOLDED);
+                }
+                if level < scaling.first_data_level {
+                    return None; // below threshold
+                }
+                if ramp.units().is_some() {
+                    return Some(ramp.color_at(scaling.value(level)?));
+                }
+            }
+            Some(ramp.color_at(level as f32))
+        }
+    }
+
+    /// Draws one raster or gridded array packet as filled cells.
+    ///
+    /// `BA0F`/`BA07` levels are 4-bit colour table categories, like `AF1F`;
+    /// the levels of packets 17, 18 and 33 are coloured as the digital radial
+    /// arrays are.
+    fn draw_raster_packet<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        packet: &SymPacketData,
+        scale: &PlotScale,
+        legend: &Legend,
+    ) -> Result<(), Error>
+    where
+        Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+    {
+        let Some(layout) = CellLayout::of(packet, &self.product_description) else {
             return Ok(());
+        };
+        let message_code = self.message_header.code;
+        let digital = self.digital_color(legend);
+        let color_for = |level: u16| match packet {
+            SymPacketData::RasterData(_) => Some(message_code.color_code(level as u8)),
+            _ => digital(level.min(u8::MAX as u16) as u8),
+        };
+
+        // Runs of equal levels are drawn as one rectangle.
+        let runs: Vec<Vec<(u16, usize)>> = match packet {
+            SymPacketData::RasterData(packet) => packet
+                .rows
+                .iter()
+                .map(|row| row.iter().map(|run| (run.level, run.run as usize)).collect())
+                .collect(),
+            SymPacketData::DigitalPrecipitationDataArray(packet)
+            | SymPacketData::PrecipitationRateDataArray(packet) => packet
+                .rows
+                .iter()
+                .map(|row| row.iter().map(|run| (run.level, run.run as usize)).collect())
+                .collect(),
+            SymPacketData::DigitalRasterDataArray(packet) => packet
+                .rows
+                .iter()
+                .map(|row| {
+                    row.chunk_by(|a, b| a == b)
+                        .map(|cells| (cells[0] as u16, cells.len()))
+                        .collect()
+                })
+                .collect(),
+            _ => return Ok(()),
+        };
+
+        for (row, row_runs) in runs.iter().enumerate() {
+            let top = layout.top + row as f64 * layout.cell_height;
+            let mut col = 0;
+            for &(level, count) in row_runs {
+                let left = layout.left + col as f64 * layout.cell_width;
+                col += count;
+                let Some(color) = color_for(level) else {
+                    continue;
+                };
+                let right = layout.left + col as f64 * layout.cell_width;
+                area.draw(&Rectangle::new(
+                    [scale.at(left, top), scale.at(right, top + layout.cell_height)],
+                    color.filled(),
+                ))?;
+            }
         }
+        Ok(())
+    }
+
+    /// Draws one radial packet as filled wedges.
+    fn draw_radial_packet<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        packet: &SymPacketData,
+        scale: &PlotScale,
+        legend: &Legend,
+    ) {
+        let Some(geometry) = radial_geometry(packet, &self.product_description) else {
+            return;
+        };
+        let (xc, yc) = scale.centre;
 
         // Azimuth 0 points up and angles increase clockwise (Figure 3-10), which
         // in this y-down coordinate space means starting a quarter turn back.
@@ -289,8 +692,8 @@ impl Radar {
         };
 
         let wedge = |angle: f32, delta: f32, inner: f32, outer: f32, color: RGBColor| {
-            let radius_inner = inner / n_bins * r_max;
-            let radius_outer = outer / n_bins * r_max;
+            let radius_inner = scale.bin_radius(&geometry, inner);
+            let radius_outer = scale.bin_radius(&geometry, outer);
             let points = vec![
                 (radius_inner * angle.cos() + xc, radius_inner * angle.sin() + yc),
                 (
@@ -306,7 +709,7 @@ impl Radar {
             let _ = area.draw(&Polygon::new(points, color.filled()));
         };
 
-        match layer {
+        match packet {
             SymPacketData::RadialDataAF1F(packet) => {
                 let message_code = self.message_header.code;
                 for radial in &packet.radials {
@@ -335,28 +738,7 @@ impl Radar {
             }
 
             SymPacketData::DigitalRadialDataArray(packet) => {
-                let ramp = legend.ramp.unwrap_or_default();
-                let scaling = self.product_description.level_scaling();
-
-                // 8-bit levels are not colour table indices. A ramp with units
-                // is fed the decoded physical value; a ramp without units is
-                // defined over the raw level itself, so it must be fed the raw
-                // level rather than a measurement in some other quantity.
-                // `None` means "don't draw", leaving the background showing.
-                let color_for = |level: u8| -> Option<RGBColor> {
-                    if let Some(scaling) = scaling.as_ref() {
-                        if scaling.is_range_folded(level) {
-                            return Some(RANGE_FOLDED);
-                        }
-                        if level < scaling.first_data_level {
-                            return None; // below threshold
-                        }
-                        if ramp.units().is_some() {
-                            return Some(ramp.color_at(scaling.value(level)?));
-                        }
-                    }
-                    Some(ramp.color_at(level as f32))
-                };
+                let color_for = self.digital_color(legend);
 
                 for radial in &packet.radials {
                     let angle = to_radians(radial.header.angle_start);
@@ -373,12 +755,8 @@ impl Radar {
                 }
             }
 
-            other => {
-                warn!("First symbology layer is {other:?}, which the plotter cannot draw");
-            }
+            _ => {}
         }
-
-        Ok(())
     }
 
     /// Draws the annotation text and colour bar into `area`.
@@ -624,7 +1002,8 @@ impl Radar {
             options
                 .site_label
                 .clone()
-                .unwrap_or_else(|| self.text_header.location.clone()),
+                .or_else(|| self.text_header.as_ref().map(|h| h.location.clone()))
+                .unwrap_or_default(),
         ];
 
         if let Some(product) = format_modified_julian(pd.product_date, pd.product_time) {
@@ -664,6 +1043,258 @@ impl Radar {
     }
 }
 
+/// Range the plot spans when nothing in it says otherwise: no true-scale range
+/// was asked for and no radial packet gives one.
+const DEFAULT_RANGE_KM: f64 = 230.0;
+
+/// The geometry of a radial packet, or `None` for any other packet.
+fn radial_geometry(packet: &SymPacketData, pd: &crate::ProductDescription) -> Option<RadialGeometry> {
+    match packet {
+        SymPacketData::RadialDataAF1F(packet) => Some(RadialGeometry::from_radial_packet(packet, pd)),
+        SymPacketData::DigitalRadialDataArray(packet) => Some(RadialGeometry::from_digital_radial_packet(packet, pd)),
+        _ => None,
+    }
+}
+
+/// How the product's ranges and I/J screen coordinates map to pixels.
+struct PlotScale {
+    /// Pixel position of I/J 0,0.
+    origin: (f32, f32),
+    /// Pixel position of the radar: the origin moved by the reference
+    /// packet's centre of sweep on a true-scale plot.
+    centre: (f32, f32),
+    pixels_per_metre: f64,
+    /// Whether bins are drawn at their true range, rather than fitted so the
+    /// last one reaches `r_max`.
+    true_scale: bool,
+    r_max: f32,
+}
+
+impl PlotScale {
+    fn new(
+        reference: &SymPacketData,
+        pd: &crate::ProductDescription,
+        options: &PlotOptions,
+        logical: f32,
+        r_max: f32,
+    ) -> Self {
+        let origin = (logical / 2.0, logical / 2.0);
+        let geometry = radial_geometry(reference, pd);
+        if let Some(km) = options.max_range_km.filter(|km| *km > 0.0) {
+            let pixels_per_metre = r_max as f64 / (km * 1000.0);
+            // The centre of sweep is in 1/4 km screen units, I east and J south.
+            let (i, j) = match reference {
+                SymPacketData::RadialDataAF1F(packet) => (packet.header.i_sweep_center, packet.header.j_sweep_center),
+                SymPacketData::DigitalRadialDataArray(packet) => {
+                    (packet.header.i_sweep_center, packet.header.j_sweep_center)
+                }
+                _ => (0, 0),
+            };
+            return PlotScale {
+                origin,
+                centre: (
+                    origin.0 + (i as f64 * 250.0 * pixels_per_metre) as f32,
+                    origin.1 + (j as f64 * 250.0 * pixels_per_metre) as f32,
+                ),
+                pixels_per_metre,
+                true_scale: true,
+                r_max,
+            };
+        }
+        let extent = match (geometry, CellLayout::of(reference, pd)) {
+            (Some(geometry), _) if geometry.num_bins > 0 && geometry.bin_size > 0.0 => {
+                geometry.num_bins as f64 * geometry.bin_size
+            }
+            (_, Some(layout)) if layout.extent() > 0.0 => layout.extent(),
+            _ => DEFAULT_RANGE_KM * 1000.0,
+        };
+        PlotScale {
+            origin,
+            centre: origin,
+            pixels_per_metre: r_max as f64 / extent,
+            true_scale: false,
+            r_max,
+        }
+    }
+
+    /// Pixel radius of the near edge of `bin` of a packet with `geometry`.
+    fn bin_radius(&self, geometry: &RadialGeometry, bin: f32) -> f32 {
+        if self.true_scale {
+            let range = geometry.first_bin_range - geometry.bin_size / 2.0 + bin as f64 * geometry.bin_size;
+            (range * self.pixels_per_metre) as f32
+        } else {
+            bin / geometry.num_bins.max(1) as f32 * self.r_max
+        }
+    }
+
+    /// Pixel length of `km4` 1/4 km.
+    fn length(&self, km4: i16) -> f32 {
+        (km4 as f64 * 250.0 * self.pixels_per_metre) as f32
+    }
+
+    /// Pixel position of an I/J screen coordinate in 1/4 km.
+    fn point(&self, i: i16, j: i16) -> (f32, f32) {
+        (self.origin.0 + self.length(i), self.origin.1 + self.length(j))
+    }
+
+    /// Pixel position of a point `x` metres east and `y` metres south of I/J
+    /// 0,0.
+    fn at(&self, x: f64, y: f64) -> (f32, f32) {
+        (
+            self.origin.0 + (x * self.pixels_per_metre) as f32,
+            self.origin.1 + (y * self.pixels_per_metre) as f32,
+        )
+    }
+}
+
+/// Where the cells of a raster or gridded array packet fall, in metres east
+/// and south of I/J 0,0.
+struct CellLayout {
+    left: f64,
+    top: f64,
+    cell_width: f64,
+    cell_height: f64,
+    rows: usize,
+    cols: usize,
+}
+
+impl CellLayout {
+    /// The layout of `packet`, or `None` for a packet that is not a raster or
+    /// gridded array.
+    ///
+    /// Rasters carry their start and scale in 1/4 km. The 1/4 LFM arrays of
+    /// packets 17 and 18 are drawn centred on the radar, with boxes scaled to
+    /// the site's latitude and the grid's slight rotation away from north
+    /// ignored.
+    fn of(packet: &SymPacketData, pd: &crate::ProductDescription) -> Option<CellLayout> {
+        let widest = |rows: &[Vec<Run>]| {
+            rows.iter()
+                .map(|row| row.iter().map(|run| run.run as usize).sum::<usize>())
+                .max()
+                .unwrap_or(0)
+        };
+        match packet {
+            SymPacketData::RasterData(packet) => {
+                let header = &packet.header;
+                Some(CellLayout {
+                    left: header.i_start as f64 * 250.0,
+                    top: header.j_start as f64 * 250.0,
+                    cell_width: header.x_scale_int.max(1) as f64 * 250.0,
+                    cell_height: header.y_scale_int.max(1) as f64 * 250.0,
+                    rows: packet.rows.len(),
+                    cols: widest(&packet.rows),
+                })
+            }
+            SymPacketData::DigitalRasterDataArray(packet) => {
+                let header = &packet.header;
+                Some(CellLayout {
+                    left: header.i_start as f64 * 250.0,
+                    top: header.j_start as f64 * 250.0,
+                    cell_width: header.j_scale.max(1) as f64 * 250.0,
+                    cell_height: header.i_scale.max(1) as f64 * 250.0,
+                    rows: packet.rows.len(),
+                    cols: packet.rows.iter().map(Vec::len).max().unwrap_or(0),
+                })
+            }
+            SymPacketData::DigitalPrecipitationDataArray(packet)
+            | SymPacketData::PrecipitationRateDataArray(packet) => {
+                let box_size = lfm_box_size(&Site::from_description(pd));
+                let (rows, cols) = (packet.rows.len(), widest(&packet.rows));
+                Some(CellLayout {
+                    left: -(cols as f64) / 2.0 * box_size,
+                    top: -(rows as f64) / 2.0 * box_size,
+                    cell_width: box_size,
+                    cell_height: box_size,
+                    rows,
+                    cols,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Metres from I/J 0,0 to the furthest edge of the cells.
+    fn extent(&self) -> f64 {
+        let right = self.left + self.cols as f64 * self.cell_width;
+        let bottom = self.top + self.rows as f64 * self.cell_height;
+        [self.left, right, self.top, bottom]
+            .into_iter()
+            .map(f64::abs)
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Grey of the range rings, spokes and their labels.
+const RING_COLOR: RGBColor = RGBColor(150, 150, 150);
+
+/// Draws range rings out to the image circle, labelled at north, and azimuth
+/// spokes labelled in degrees.
+fn draw_range_rings<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    rings: RangeRings,
+    (xc, yc): (f32, f32),
+    r_max: f32,
+    pixels_per_metre: f64,
+) -> Result<(), Error>
+where
+    Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+{
+    let label = ("sans-serif", 14).into_font().color(&RING_COLOR);
+    let spacing = rings.spacing * rings.units.metres() * pixels_per_metre;
+    if spacing >= 1.0 {
+        let mut n = 1;
+        while n as f64 * spacing <= r_max as f64 + 0.5 {
+            let radius = (n as f64 * spacing) as f32;
+            let points: Vec<(f32, f32)> = (0..=180)
+                .map(|step| {
+                    let angle = step as f32 * std::f32::consts::PI / 90.0;
+                    (xc + radius * angle.cos(), yc + radius * angle.sin())
+                })
+                .collect();
+            area.draw(&PathElement::new(points, RING_COLOR.stroke_width(1)))?;
+            area.draw(&Text::new(
+                format!("{} {}", format_tick((n as f64 * rings.spacing) as f32), rings.units.abbreviation()),
+                (xc + 3.0, yc - radius + 2.0),
+                label.clone(),
+            ))?;
+            n += 1;
+        }
+    }
+
+    if rings.spoke_spacing > 0.0 {
+        // The field is public, so bound the loop here as well.
+        let spacing = rings.spoke_spacing.max(MIN_SPOKE_SPACING);
+        let mut azimuth = 0.0;
+        while azimuth < 360.0 {
+            let angle = (azimuth as f32 - 90.0).to_radians();
+            let end = (xc + r_max * angle.cos(), yc + r_max * angle.sin());
+            area.draw(&PathElement::new(vec![(xc, yc), end], RING_COLOR.stroke_width(1)))?;
+            area.draw(&Text::new(
+                format!("{}°", format_tick(azimuth as f32)),
+                (end.0 + 3.0, end.1 + 2.0),
+                label.clone(),
+            ))?;
+            azimuth += spacing;
+        }
+    }
+    Ok(())
+}
+
+/// Recovers the RGBA pixel that, composited over black and over white, gave
+/// `black` and `white`: the alpha is how little the background showed through.
+fn unblend(black: &[u8], white: &[u8]) -> [u8; 4] {
+    let shown = (0..3)
+        .map(|c| white[c].saturating_sub(black[c]) as u32)
+        .max()
+        .unwrap_or(0);
+    let alpha = 255 - shown;
+    if alpha == 0 {
+        return [0, 0, 0, 0];
+    }
+    let channel = |c: usize| ((black[c] as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+    [channel(0), channel(1), channel(2), alpha as u8]
+}
+
 /// Formats a tick value, dropping the decimal point when it is a whole number.
 fn format_tick(value: f32) -> String {
     if (value - value.round()).abs() < 0.05 {
@@ -721,6 +1352,16 @@ fn format_modified_julian(date: i16, seconds: i32) -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn unblending_recovers_colour_and_alpha() {
+        // Nothing drawn: black over black, white over white.
+        assert_eq!(unblend(&[0, 0, 0], &[255, 255, 255]), [0, 0, 0, 0]);
+        // Opaque red shows no background at all.
+        assert_eq!(unblend(&[255, 0, 0], &[255, 0, 0]), [255, 0, 0, 255]);
+        // Red at half alpha: 128 over black, 255/127/127 over white.
+        assert_eq!(unblend(&[128, 0, 0], &[255, 127, 127]), [255, 0, 0, 128]);
+    }
+
     #[test]
     fn formats_degrees_as_the_reference_plots_do() {
         // The bundled product 32 fixture: 42.968 N, 88.551 W, which the
@@ -771,7 +1412,7 @@ mod tests {
     /// reference plot prints, line for line.
     #[test]
     fn annotations_match_the_reference_plot() {
-        let file = include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last").to_vec();
+        let file = include_bytes!("../../data/sn_DC.radar_DS.32dhr_KMKX.last").to_vec();
         let (_, radar) = Radar::from_vec(file).expect("fixture should parse");
 
         let options = PlotOptions::new().with_site_label("KMKX - MILWAUKEE, WI");
@@ -794,7 +1435,7 @@ mod tests {
     /// identifier, since the file carries no place name.
     #[test]
     fn site_line_defaults_to_the_station_identifier() {
-        let file = include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last").to_vec();
+        let file = include_bytes!("../../data/sn_DC.radar_DS.32dhr_KMKX.last").to_vec();
         let (_, radar) = Radar::from_vec(file).unwrap();
         let lines = radar.annotations(&PlotOptions::new());
         assert_eq!(lines[2], "KMKX");
@@ -804,13 +1445,27 @@ mod tests {
         Radar::from_vec(bytes.to_vec()).expect("fixture should parse").1
     }
 
+    /// A hidden layer's packets neither colour the legend nor stand in for
+    /// it, but the hidden data still sets the scale.
+    #[test]
+    fn only_shown_layers_give_the_legend() {
+        let text = |t: &str| SymPacketData::TextAndSpecialSymbol1(crate::TextPacket::new(0, 0, t, None));
+        let radar = crate::ProductBuilder::new(crate::MessageCode::StormTrackingInformation, "KMKX")
+            .with_layer([text("hidden")])
+            .with_layer([text("shown")])
+            .build()
+            .unwrap();
+        let symbology = radar.symbology.as_ref().unwrap();
+
+        let reference = reference_packet(symbology, &PlotOptions::new().with_hidden_layer(0));
+        assert!(matches!(reference, Some(SymPacketData::TextAndSpecialSymbol1(t)) if t.text == "shown"));
+        assert!(matches!(scale_packet(symbology), Some(SymPacketData::TextAndSpecialSymbol1(t)) if t.text == "hidden"));
+        let options = PlotOptions::new().with_hidden_layer(0).with_hidden_layer(1);
+        assert_eq!(reference_packet(symbology, &options), None);
+    }
+
     fn legend_of(radar: &Radar, options: &PlotOptions) -> Legend {
-        let layer = radar
-            .symbology
-            .as_ref()
-            .unwrap()
-            .layers
-            .first()
+        let layer = reference_packet(radar.symbology.as_ref().unwrap(), options)
             .expect("fixture should have a layer");
         radar.legend_for(layer, options)
     }
@@ -819,7 +1474,7 @@ mod tests {
     /// legend is labelled in dBZ.
     #[test]
     fn dbz_product_defaults_to_the_reflectivity_ramp() {
-        let radar = load(include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last"));
+        let radar = load(include_bytes!("../../data/sn_DC.radar_DS.32dhr_KMKX.last"));
         let legend = legend_of(&radar, &PlotOptions::new());
 
         assert_eq!(legend.ramp, Some(ColorRamp::NwsReflectivity));
@@ -832,7 +1487,7 @@ mod tests {
     /// labelled as showing raw levels rather than a measurement.
     #[test]
     fn an_explicit_raw_ramp_overrides_the_default() {
-        let radar = load(include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last"));
+        let radar = load(include_bytes!("../../data/sn_DC.radar_DS.32dhr_KMKX.last"));
         let options = PlotOptions::new().with_ramp(ColorRamp::Grayscale);
         let legend = legend_of(&radar, &options);
 
@@ -844,7 +1499,7 @@ mod tests {
     /// rather than plot knots against a dBZ scale. Product 99 decodes to knots.
     #[test]
     fn a_ramp_with_mismatched_units_falls_back() {
-        let radar = load(include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"));
+        let radar = load(include_bytes!("../../data/sn_DC.radar_DS.p99v0_KMKX.last"));
         assert_eq!(
             radar.product_description.level_scaling().unwrap().units,
             "kt"
@@ -861,7 +1516,7 @@ mod tests {
     /// to the unitless default rather than mislabelling the bar.
     #[test]
     fn a_product_without_a_matching_ramp_uses_the_unitless_default() {
-        let radar = load(include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"));
+        let radar = load(include_bytes!("../../data/sn_DC.radar_DS.p99v0_KMKX.last"));
         let legend = legend_of(&radar, &PlotOptions::new());
 
         assert_eq!(legend.ramp, Some(ColorRamp::Hue));
@@ -872,7 +1527,7 @@ mod tests {
     /// ramp is involved and the chosen ramp is ignored.
     #[test]
     fn table_coloured_products_use_no_ramp() {
-        let radar = load(include_bytes!("../data/sn_DC.radar_DS.56rm1_KMKX.last"));
+        let radar = load(include_bytes!("../../data/sn_DC.radar_DS.56rm1_KMKX.last"));
         let options = PlotOptions::new().with_ramp(ColorRamp::Grayscale);
         let legend = legend_of(&radar, &options);
 
@@ -886,12 +1541,27 @@ mod tests {
             .with_ramp(ColorRamp::Grayscale)
             .with_site_label("KMKX - MILWAUKEE, WI")
             .with_image_size(600)
-            .without_panel();
+            .without_panel()
+            .with_max_range_km(230.0)
+            .with_range_rings(RangeRings::new(25.0, DistanceUnits::NauticalMiles))
+            .with_hidden_layer(2);
 
         assert_eq!(options.ramp, Some(ColorRamp::Grayscale));
         assert_eq!(options.site_label.as_deref(), Some("KMKX - MILWAUKEE, WI"));
         assert_eq!(options.image_size, 600);
         assert!(!options.panel);
+        assert_eq!(options.max_range_km, Some(230.0));
+        assert_eq!(options.range_rings.map(|r| r.spoke_spacing), Some(30.0));
+        assert!(options.shows_layer(0) && !options.shows_layer(2));
+    }
+
+    #[test]
+    fn spokes_are_no_closer_than_the_minimum() {
+        let rings = |degrees| RangeRings::new(50.0, DistanceUnits::Kilometres).with_spoke_spacing(degrees);
+        assert_eq!(rings(1e-9).spoke_spacing, MIN_SPOKE_SPACING);
+        assert_eq!(rings(45.0).spoke_spacing, 45.0);
+        assert_eq!(rings(0.0).spoke_spacing, 0.0);
+        assert_eq!(rings(f64::NAN).spoke_spacing, 0.0);
     }
 
     /// A tiny image size must not produce a degenerate canvas.
@@ -904,7 +1574,7 @@ mod tests {
     /// halfwords, so it reads in real units instead of by level number.
     #[test]
     fn table_legend_labels_come_from_the_decoded_thresholds() {
-        let radar = load(include_bytes!("../data/sn_DC.radar_DS.56rm1_KMKX.last"));
+        let radar = load(include_bytes!("../../data/sn_DC.radar_DS.56rm1_KMKX.last"));
 
         // Product 56's table: ND at level 0, -80 to +80 kt, RF at level 15.
         assert_eq!(radar.level_label(0), "ND");
@@ -922,7 +1592,7 @@ mod tests {
 
     #[test]
     fn base_reflectivity_legend_is_labelled_in_dbz() {
-        let radar = load(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let radar = load(include_bytes!("../../data/sn_DS.p20-r_kmkx.last"));
 
         assert_eq!(radar.level_label(0), "ND");
         assert_eq!(radar.level_label(1), "-28");
@@ -937,7 +1607,7 @@ mod tests {
     /// number rather than inventing a label.
     #[test]
     fn level_labels_fall_back_to_the_level_number() {
-        let mut radar = load(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let mut radar = load(include_bytes!("../../data/sn_DS.p20-r_kmkx.last"));
         // Product 153 is in Note 1's exception list, so nothing decodes.
         radar.product_description.product_code = 153;
         assert_eq!(radar.level_label(7), "7");
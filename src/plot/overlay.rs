@@ -0,0 +1,414 @@
+//! Symbology overlays: the text, vector, wind and special symbol packets drawn
+//! over a product's data.
+//!
+//! Every coordinate is an I/J screen position in 1/4 km east and south of the
+//! display centre, placed through the plot's [`PlotScale`]. Symbols follow the
+//! shapes of the PUP and AWIPS displays:
+//!
+//! | Packet | Drawn as |
+//! | -- | -- |
+//! | 3 mesocyclone, 11 3-D correlated shear | yellow circle of the given radius, thick for 3 |
+//! | 12 TVS, 26 ETVS | red inverted triangle, filled for TVS |
+//! | 13 hail positive, 14 hail probable | green triangle, filled for positive |
+//! | 15 storm ID | white label |
+//! | 19 HDA hail | green triangle, filled at POSH of 50% or more, labelled `POSH/POH` |
+//! | 20 point feature | yellow circle of the feature's radius, or a cross |
+//! | 23 SCIT past, 24 SCIT forecast | their nested tracks and positions: solid past, dashed forecast |
+//! | 25 STI circle | white circle |
+//! | 4 wind barb | staff into the wind with 50 kt pennants, 10 kt barbs and 5 kt half barbs |
+//! | 5 vector arrow | arrow with the wind |
+//!
+//! Map message packets are not drawn: their coordinates start at the corner of
+//! an area of coverage the packets do not describe.
+
+use plotters::coord::types::RangedCoordf32;
+use plotters::element::DashedPathElement;
+use plotters::prelude::*;
+use tracing::debug;
+
+use super::PlotScale;
+use crate::{
+    error_r::Error, product_symbology::SymPacketData, ContourVectorPacket, MessageCode, PacketCode, Point,
+    PointFeatureSymbol, SpecialSymbolPacket, UnlinkedVectorPacket, Vector, WindBarb,
+};
+
+/// Colour of text, vectors, storm IDs and STI tracks.
+const OVERLAY_WHITE: RGBColor = RGBColor(255, 255, 255);
+/// Colour of mesocyclones and point features.
+const MESOCYCLONE: RGBColor = RGBColor(255, 255, 0);
+/// Colour of TVS and ETVS triangles.
+const TVS: RGBColor = RGBColor(255, 0, 0);
+/// Colour of hail markers and their labels.
+const HAIL: RGBColor = RGBColor(0, 255, 0);
+
+type Area<DB> = DrawingArea<DB, Cartesian2d<RangedCoordf32, RangedCoordf32>>;
+
+/// Whether a SCIT track is of past or forecast positions.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Track {
+    Past,
+    Forecast,
+}
+
+/// Draws one overlay packet. Data packets, and packets with no place on the
+/// image, draw nothing.
+pub(super) fn draw_overlay<DB: DrawingBackend>(
+    area: &Area<DB>,
+    packet: &SymPacketData,
+    scale: &PlotScale,
+    message_code: MessageCode,
+) -> Result<(), Error>
+where
+    Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+{
+    let style = OVERLAY_WHITE.stroke_width(1);
+    match packet {
+        SymPacketData::TextAndSpecialSymbol1(text) | SymPacketData::TextAndSpecialSymbol8(text) => {
+            let at = scale.point(text.i_coord, text.j_coord);
+            // Packet 2's characters select symbols from the display's own
+            // font, which has no equivalent here, so each becomes a marker.
+            if text.packet_code == PacketCode::TextAndSpecialSymbol2 {
+                area.draw(&Circle::new(at, 3, OVERLAY_WHITE.filled()))?;
+            } else {
+                area.draw(&Text::new(text.text.clone(), at, label(OVERLAY_WHITE)))?;
+            }
+        }
+        SymPacketData::LinkedVector(packet) => {
+            area.draw(&PathElement::new(polyline(scale, &packet.start, &packet.ends), style))?;
+        }
+        SymPacketData::ContourVector(ContourVectorPacket::Linked { start, ends, .. }) => {
+            area.draw(&PathElement::new(polyline(scale, start, ends), style))?;
+        }
+        SymPacketData::UnlinkedVector(UnlinkedVectorPacket { vectors, .. })
+        | SymPacketData::ContourVector(ContourVectorPacket::Unlinked { vectors }) => {
+            for vector in vectors {
+                area.draw(&PathElement::new(segment(scale, vector), style))?;
+            }
+        }
+        SymPacketData::VectorArrowData(packet) => {
+            for arrow in &packet.arrows {
+                // The arrow is centred on its point and points with the wind.
+                let (x, y) = scale.point(arrow.i_coord, arrow.j_coord);
+                let angle = screen_angle(arrow.direction as f32);
+                let half = arrow.arrow_length as f32 / 2.0;
+                let tail = (x - half * angle.cos(), y - half * angle.sin());
+                let tip = (x + half * angle.cos(), y + half * angle.sin());
+                area.draw(&PathElement::new(vec![tail, tip], style))?;
+                for side in [angle + 2.6, angle - 2.6] {
+                    let head = arrow.arrow_head_length as f32;
+                    area.draw(&PathElement::new(
+                        vec![tip, (tip.0 + head * side.cos(), tip.1 + head * side.sin())],
+                        style,
+                    ))?;
+                }
+            }
+        }
+        SymPacketData::WindBarbData(packet) => {
+            let table = message_code.color_table();
+            for barb in &packet.barbs {
+                // The value is a colour level of the product's barb table.
+                let color = table
+                    .as_ref()
+                    .and_then(|table| table.color(barb.value.clamp(0, u8::MAX as i16) as u8))
+                    .unwrap_or(OVERLAY_WHITE);
+                draw_wind_barb(area, scale.point(barb.x_coord, barb.y_coord), barb, color)?;
+            }
+        }
+        SymPacketData::SpecialGraphicSymbol(symbols) => draw_symbols(area, symbols, scale)?,
+        SymPacketData::MapMessage(_) => {
+            debug!("Map message packets are not drawn: their origin is the corner of an unknown area");
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Draws a special graphic symbol packet.
+fn draw_symbols<DB: DrawingBackend>(
+    area: &Area<DB>,
+    symbols: &SpecialSymbolPacket,
+    scale: &PlotScale,
+) -> Result<(), Error>
+where
+    Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+{
+    match symbols {
+        SpecialSymbolPacket::Mesocyclone { packet_code, symbols } => {
+            let width = if *packet_code == 3 { 2 } else { 1 };
+            // A radius of 0 means no mesocyclone (Figure 3-14 sheet 3).
+            for symbol in symbols.iter().filter(|symbol| symbol.radius > 0) {
+                area.draw(&Circle::new(
+                    scale.point(symbol.i_position, symbol.j_position),
+                    scale.length(symbol.radius).max(3.0).round() as i32,
+                    MESOCYCLONE.stroke_width(width),
+                ))?;
+            }
+        }
+        SpecialSymbolPacket::StiCircle { symbols } => {
+            for symbol in symbols.iter().filter(|symbol| symbol.radius > 0) {
+                area.draw(&Circle::new(
+                    scale.point(symbol.i_position, symbol.j_position),
+                    scale.length(symbol.radius).max(3.0).round() as i32,
+                    OVERLAY_WHITE.stroke_width(1),
+                ))?;
+            }
+        }
+        SpecialSymbolPacket::Position { packet_code, symbols } => {
+            // TVS and ETVS point down, hail markers up; the definite symbol of
+            // each pair is filled.
+            let (color, inverted, filled) = match packet_code {
+                12 => (TVS, true, true),
+                26 => (TVS, true, false),
+                13 => (HAIL, false, true),
+                _ => (HAIL, false, false),
+            };
+            for symbol in symbols {
+                let at = scale.point(symbol.i_position, symbol.j_position);
+                draw_triangle(area, at, 7.0, inverted, color, filled)?;
+            }
+        }
+        SpecialSymbolPacket::StormId { symbols } => {
+            for symbol in symbols {
+                let (x, y) = scale.point(symbol.i_position, symbol.j_position);
+                area.draw(&Text::new(symbol.storm_id.clone(), (x + 6.0, y - 6.0), label(OVERLAY_WHITE)))?;
+            }
+        }
+        SpecialSymbolPacket::Hail { symbols } => {
+            // Negative probabilities are the "not computed" flags.
+            for symbol in symbols
+                .iter()
+                .filter(|symbol| symbol.probability_of_hail > 0 || symbol.probability_of_severe_hail > 0)
+            {
+                let (x, y) = scale.point(symbol.i_position, symbol.j_position);
+                let filled = symbol.probability_of_severe_hail >= 50;
+                draw_triangle(area, (x, y), 7.0, false, HAIL, filled)?;
+                area.draw(&Text::new(
+                    format!(
+                        "{}/{}",
+                        symbol.probability_of_severe_hail.max(0),
+                        symbol.probability_of_hail.max(0)
+                    ),
+                    (x + 9.0, y - 7.0),
+                    label(HAIL),
+                ))?;
+            }
+        }
+        SpecialSymbolPacket::PointFeature { symbols } => {
+            for symbol in symbols {
+                let at = scale.point(symbol.i_position, symbol.j_position);
+                if let Some(radius) = feature_radius(symbol) {
+                    area.draw(&Circle::new(
+                        at,
+                        scale.length(radius).max(3.0).round() as i32,
+                        MESOCYCLONE.stroke_width(1),
+                    ))?;
+                } else {
+                    draw_cross(area, at, MESOCYCLONE)?;
+                }
+            }
+        }
+        SpecialSymbolPacket::ScitData { packet_code, .. } => {
+            let track = if *packet_code == 24 { Track::Forecast } else { Track::Past };
+            for nested in symbols.nested_packets() {
+                draw_track_packet(area, &nested, scale, track)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Draws one packet nested in SCIT past or forecast data: a track as linked
+/// vectors, positions as text and special symbol packets, or STI circles.
+fn draw_track_packet<DB: DrawingBackend>(
+    area: &Area<DB>,
+    packet: &SymPacketData,
+    scale: &PlotScale,
+    track: Track,
+) -> Result<(), Error>
+where
+    Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+{
+    match packet {
+        SymPacketData::LinkedVector(packet) => {
+            let points = polyline(scale, &packet.start, &packet.ends);
+            match track {
+                Track::Past => area.draw(&PathElement::new(points, OVERLAY_WHITE.stroke_width(1)))?,
+                Track::Forecast => {
+                    area.draw(&DashedPathElement::new(points, 6.0, 4.0, OVERLAY_WHITE.stroke_width(1)))?
+                }
+            }
+        }
+        // Past positions are filled dots and forecast positions crosses,
+        // whatever symbol character the packet selects.
+        SymPacketData::TextAndSpecialSymbol1(text) | SymPacketData::TextAndSpecialSymbol8(text) => {
+            let at = scale.point(text.i_coord, text.j_coord);
+            match track {
+                Track::Past => area.draw(&Circle::new(at, 3, OVERLAY_WHITE.filled()))?,
+                Track::Forecast => draw_cross(area, at, OVERLAY_WHITE)?,
+            }
+        }
+        SymPacketData::SpecialGraphicSymbol(symbols) => draw_symbols(area, symbols, scale)?,
+        other => draw_overlay(area, other, scale, MessageCode::default())?,
+    }
+    Ok(())
+}
+
+/// Draws a wind barb at `at`: the staff points into the wind, with the
+/// speed, rounded to 5 kt, spelled out from the far end in 50 kt pennants,
+/// 10 kt barbs and a 5 kt half barb, all on the clockwise side of the staff.
+fn draw_wind_barb<DB: DrawingBackend>(
+    area: &Area<DB>,
+    (x, y): (f32, f32),
+    barb: &WindBarb,
+    color: RGBColor,
+) -> Result<(), Error>
+where
+    Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+{
+    const STAFF: f32 = 28.0;
+    const FEATHER: f32 = 10.0;
+    const SPACING: f32 = 4.0;
+
+    let speed = barb.speed.max(0);
+    if feathers(speed) == (0, 0, false) {
+        // Calm: a circle around the station.
+        area.draw(&Circle::new((x, y), 5, color.stroke_width(1)))?;
+        return Ok(());
+    }
+
+    let angle = screen_angle(barb.direction as f32);
+    let along = |distance: f32| (x + distance * angle.cos(), y + distance * angle.sin());
+    // Feathers lean back toward the tip of the staff, on its clockwise side.
+    let side = angle + std::f32::consts::FRAC_PI_2 - 0.35;
+    let feather = |(fx, fy): (f32, f32), length: f32| (fx + length * side.cos(), fy + length * side.sin());
+
+    area.draw(&PathElement::new(vec![(x, y), along(STAFF)], color.stroke_width(1)))?;
+    let (pennants, barbs, half) = feathers(speed);
+
+    let mut position = STAFF;
+    for _ in 0..pennants {
+        let base = along(position);
+        let inner = along(position - SPACING * 1.5);
+        area.draw(&Polygon::new(vec![base, feather(base, FEATHER), inner], color.filled()))?;
+        position -= SPACING * 2.0;
+    }
+    for _ in 0..barbs {
+        let base = along(position);
+        area.draw(&PathElement::new(vec![base, feather(base, FEATHER)], color.stroke_width(1)))?;
+        position -= SPACING;
+    }
+    if half {
+        // A lone half barb is set in from the tip so it is not mistaken for
+        // a full one.
+        if pennants == 0 && barbs == 0 {
+            position -= SPACING;
+        }
+        let base = along(position);
+        area.draw(&PathElement::new(vec![base, feather(base, FEATHER / 2.0)], color.stroke_width(1)))?;
+    }
+    Ok(())
+}
+
+/// The pennants, full barbs and half barb that spell out `speed` knots,
+/// rounded to the nearest 5 after clamping to the ICD's 0 to 195.
+fn feathers(speed: i16) -> (i16, i16, bool) {
+    let speed = (speed.clamp(0, 195) + 2) / 5 * 5;
+    (speed / 50, speed % 50 / 10, speed % 10 == 5)
+}
+
+/// The radius of a point feature, in 1/4 km, for the types whose attribute
+/// is one: mesocyclones (1-4) and MDA circulations (9-11).
+fn feature_radius(symbol: &PointFeatureSymbol) -> Option<i16> {
+    let has_radius = matches!(symbol.feature_type, 1..=4 | 9..=11);
+    (has_radius && symbol.feature_attribute > 0).then_some(symbol.feature_attribute)
+}
+
+/// Converts degrees clockwise from north to an angle in the y-down image.
+fn screen_angle(degrees: f32) -> f32 {
+    (degrees - 90.0).to_radians()
+}
+
+fn label(color: RGBColor) -> TextStyle<'static> {
+    ("sans-serif", 14).into_font().color(&color)
+}
+
+fn polyline(scale: &PlotScale, start: &Point, ends: &[Point]) -> Vec<(f32, f32)> {
+    std::iter::once(start)
+        .chain(ends)
+        .map(|point| scale.point(point.i, point.j))
+        .collect()
+}
+
+fn segment(scale: &PlotScale, vector: &Vector) -> Vec<(f32, f32)> {
+    vec![
+        scale.point(vector.begin.i, vector.begin.j),
+        scale.point(vector.end.i, vector.end.j),
+    ]
+}
+
+fn draw_cross<DB: DrawingBackend>(area: &Area<DB>, (x, y): (f32, f32), color: RGBColor) -> Result<(), Error>
+where
+    Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+{
+    area.draw(&PathElement::new(vec![(x - 4.0, y), (x + 4.0, y)], color.stroke_width(1)))?;
+    area.draw(&PathElement::new(vec![(x, y - 4.0), (x, y + 4.0)], color.stroke_width(1)))?;
+    Ok(())
+}
+
+/// Draws an equilateral triangle of side `size` centred on `(x, y)`, pointing
+/// down if `inverted`.
+fn draw_triangle<DB: DrawingBackend>(
+    area: &Area<DB>,
+    (x, y): (f32, f32),
+    size: f32,
+    inverted: bool,
+    color: RGBColor,
+    filled: bool,
+) -> Result<(), Error>
+where
+    Error: From<plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>>,
+{
+    let height = size * 0.866;
+    let (apex, base) = if inverted { (height / 2.0, -height / 2.0) } else { (-height / 2.0, height / 2.0) };
+    let points = vec![(x, y + apex), (x + size / 2.0, y + base), (x - size / 2.0, y + base)];
+    if filled {
+        area.draw(&Polygon::new(points, color.filled()))?;
+    } else {
+        let mut outline = points.clone();
+        outline.push(points[0]);
+        area.draw(&PathElement::new(outline, color.stroke_width(1)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn barbs_spell_out_the_speed_in_pennants_barbs_and_half_barbs() {
+        assert_eq!(feathers(0), (0, 0, false));
+        assert_eq!(feathers(2), (0, 0, false));
+        assert_eq!(feathers(3), (0, 0, true));
+        assert_eq!(feathers(15), (0, 1, true));
+        assert_eq!(feathers(48), (1, 0, false));
+        assert_eq!(feathers(65), (1, 1, true));
+        assert_eq!(feathers(110), (2, 1, false));
+        assert_eq!(feathers(i16::MAX), feathers(195));
+        assert_eq!(feathers(-40), (0, 0, false));
+    }
+
+    #[test]
+    fn only_circulation_features_have_a_radius() {
+        let feature = |feature_type| PointFeatureSymbol {
+            feature_type,
+            feature_attribute: 40,
+            ..Default::default()
+        };
+        assert_eq!(feature_radius(&feature(1)), Some(40));
+        assert_eq!(feature_radius(&feature(10)), Some(40));
+        assert_eq!(feature_radius(&feature(5)), None);
+        assert_eq!(feature_radius(&feature(8)), None);
+        assert_eq!(feature_radius(&PointFeatureSymbol { feature_attribute: 0, ..feature(3) }), None);
+    }
+}
@@ -0,0 +1,486 @@
+//! Radial bins as GeoJSON polygons, one per bin or dissolved into contours.
+//!
+//! Both modes start from a [`Sweep`], so values are decoded by the product's
+//! [`crate::LevelScaling`] exactly as for gridding and point queries, and
+//! bins are placed by [`RadialGeometry`] under the 4/3 earth model.
+//!
+//! Contours are traced on the radial/bin index grid: every edge between a bin
+//! inside the contour and one outside (or off the sweep) is a boundary edge,
+//! and following them with the inside on the left closes each outline into a
+//! ring. In the plane about the site that makes outer rings counter-clockwise
+//! and holes clockwise, as RFC 7946 asks, and each hole is given to the
+//! smallest outer ring around it. Where two bins touch only at a corner the
+//! trace turns tight around each, so they become separate polygons rather
+//! than one that touches itself. Adjacent radials share an edge when one's
+//! trailing azimuth is within a hundredth of a degree of the next one's
+//! leading azimuth, so a full sweep closes around north and gaps between
+//! radials are left out.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::geojson::position;
+use crate::{Error, LatLon, Radar, RadialGeometry, Sweep};
+
+/// How [`Sweep::polygons_geojson`] turns bins into polygons.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PolygonMode {
+    /// One quadrilateral per bin that holds a measurement, with its `value`,
+    /// `level`, `radial` and `bin` as properties.
+    Bins,
+    /// For each threshold, one MultiPolygon covering every bin whose value is
+    /// at or above it, with the `threshold` as a property. Contours at
+    /// increasing thresholds nest inside one another.
+    Contours(Vec<f32>),
+}
+
+/// Azimuths closer than this, degrees, are the same radial edge.
+const SHARED_EDGE_TOLERANCE: f64 = 0.01;
+
+impl Sweep {
+    /// The sweep's bins as a GeoJSON `FeatureCollection` of polygons in
+    /// WGS84, as `mode` says.
+    pub fn polygons_geojson(&self, mode: &PolygonMode) -> Value {
+        let units = self.scaling.as_ref().map_or("", |s| s.units);
+        let features: Vec<Value> = match mode {
+            PolygonMode::Bins => self.bin_features(units),
+            PolygonMode::Contours(thresholds) => {
+                let edges = EdgeLines::of(&self.geometry);
+                thresholds
+                    .iter()
+                    .map(|&threshold| {
+                        let polygons = edges.trace(&self.geometry, |radial, bin| {
+                            self.value(radial, bin).is_some_and(|value| value >= threshold)
+                        });
+                        json!({
+                            "type": "Feature",
+                            "geometry": { "type": "MultiPolygon", "coordinates": polygons },
+                            "properties": { "threshold": threshold, "units": units },
+                        })
+                    })
+                    .collect()
+            }
+        };
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+
+    /// The value of a bin, `None` where it holds no measurement or the radial
+    /// is short.
+    fn value(&self, radial: usize, bin: usize) -> Option<f32> {
+        self.values.get(radial)?.get(bin).copied().filter(|value| !value.is_nan())
+    }
+
+    fn bin_features(&self, units: &str) -> Vec<Value> {
+        let mut features = Vec::new();
+        for radial in 0..self.geometry.radials.len() {
+            for bin in 0..self.geometry.num_bins {
+                let (Some(value), Some(corners)) = (self.value(radial, bin), self.geometry.bin_corners(radial, bin))
+                else {
+                    continue;
+                };
+                let mut ring: Vec<Value> = corners.iter().map(|&c| position(c)).collect();
+                ring.push(ring[0].clone());
+                features.push(json!({
+                    "type": "Feature",
+                    "geometry": { "type": "Polygon", "coordinates": [ring] },
+                    "properties": {
+                        "value": value,
+                        "level": self.levels[radial][bin],
+                        "units": units,
+                        "radial": radial,
+                        "bin": bin,
+                    },
+                }));
+            }
+        }
+        features
+    }
+}
+
+impl Radar {
+    /// Decodes the first radial layer as [`Radar::sweep`] does and exports
+    /// its bins as GeoJSON polygons, as [`Sweep::polygons_geojson`].
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::sweep`].
+    pub fn polygons_geojson(&self, mode: &PolygonMode) -> Result<Value, Error> {
+        Ok(self.sweep()?.polygons_geojson(mode))
+    }
+}
+
+/// The azimuth lines between radials: each radial's leading and trailing
+/// edge, with a trailing edge and the next radial's leading edge being one
+/// line where they meet.
+struct EdgeLines {
+    /// Radial indices in order of azimuth.
+    order: Vec<usize>,
+    /// The line of each radial's leading and trailing edge, by radial index.
+    leading: Vec<usize>,
+    trailing: Vec<usize>,
+    /// Azimuth and elevation of each line, degrees.
+    lines: Vec<(f64, f64)>,
+}
+
+/// A corner of the bin grid: an azimuth line and a range edge, edge `b`
+/// being the near edge of bin `b`.
+type Vertex = (usize, usize);
+
+/// A step along a boundary, in index space: +1 or -1 along the radials, or
+/// along the bins.
+type Direction = (i8, i8);
+
+impl EdgeLines {
+    fn of(geometry: &RadialGeometry) -> EdgeLines {
+        let spans = &geometry.radials;
+        let mut order: Vec<usize> = (0..spans.len()).collect();
+        order.sort_by(|&a, &b| spans[a].azimuth.total_cmp(&spans[b].azimuth));
+
+        let mut lines = Vec::new();
+        let mut leading = vec![0; spans.len()];
+        let mut trailing = vec![0; spans.len()];
+        for (position, &radial) in order.iter().enumerate() {
+            let span = &spans[radial];
+            let shared = position
+                .checked_sub(1)
+                .map(|previous| order[previous])
+                .filter(|&previous| meets(spans[previous].azimuth + spans[previous].width, span.azimuth));
+            leading[radial] = match shared {
+                Some(previous) => trailing[previous],
+                None => {
+                    lines.push((span.azimuth, span.elevation));
+                    lines.len() - 1
+                }
+            };
+            lines.push((span.azimuth + span.width, span.elevation));
+            trailing[radial] = lines.len() - 1;
+        }
+
+        // Close the sweep around north if the last radial meets the first.
+        if let (Some(&first), Some(&last)) = (order.first(), order.last()) {
+            if order.len() > 1 && meets(spans[last].azimuth + spans[last].width, spans[first].azimuth) {
+                let closing = trailing[last];
+                let merged = leading[first];
+                for line in trailing.iter_mut().chain(leading.iter_mut()) {
+                    if *line == closing {
+                        *line = merged;
+                    }
+                }
+            }
+        }
+
+        EdgeLines {
+            order,
+            leading,
+            trailing,
+            lines,
+        }
+    }
+
+    /// Outlines of the bins `inside` selects, as GeoJSON polygon coordinate
+    /// arrays: each an outer ring followed by its holes.
+    fn trace(&self, geometry: &RadialGeometry, inside: impl Fn(usize, usize) -> bool) -> Vec<Value> {
+        // Which radial is across each line from each side.
+        let mut after: HashMap<usize, usize> = HashMap::new();
+        let mut before: HashMap<usize, usize> = HashMap::new();
+        for &radial in &self.order {
+            after.insert(self.leading[radial], radial);
+            before.insert(self.trailing[radial], radial);
+        }
+
+        // Boundary edges, each with the inside on its left, keyed by where
+        // they start.
+        let mut edges: BTreeMap<Vertex, Vec<(Vertex, Direction)>> = BTreeMap::new();
+        let mut add = |from: Vertex, to: Vertex, direction: Direction| {
+            edges.entry(from).or_default().push((to, direction));
+        };
+        for &radial in &self.order {
+            let (lead, trail) = (self.leading[radial], self.trailing[radial]);
+            for bin in (0..geometry.num_bins).filter(|&bin| inside(radial, bin)) {
+                if bin == 0 || !inside(radial, bin - 1) {
+                    add((lead, bin), (trail, bin), (1, 0));
+                }
+                if !after.get(&trail).is_some_and(|&next| inside(next, bin)) {
+                    add((trail, bin), (trail, bin + 1), (0, 1));
+                }
+                if !inside(radial, bin + 1) {
+                    add((trail, bin + 1), (lead, bin + 1), (-1, 0));
+                }
+                if !before.get(&lead).is_some_and(|&previous| inside(previous, bin)) {
+                    add((lead, bin + 1), (lead, bin), (0, -1));
+                }
+            }
+        }
+
+        let mut outers: Vec<Ring> = Vec::new();
+        let mut holes: Vec<Ring> = Vec::new();
+        while let Some(&start) = edges.keys().next() {
+            let ring = self.follow(geometry, &mut edges, start);
+            if ring.area > 0.0 {
+                outers.push(ring);
+            } else if ring.area < 0.0 {
+                holes.push(ring);
+            }
+        }
+
+        let mut polygons: Vec<Vec<Vec<Value>>> = outers.iter().map(|ring| vec![ring.positions()]).collect();
+        for hole in &holes {
+            let Some(probe) = hole.beside() else {
+                continue;
+            };
+            let owner = outers
+                .iter()
+                .enumerate()
+                .filter(|(_, outer)| outer.contains(probe))
+                .min_by(|(_, a), (_, b)| a.area.total_cmp(&b.area));
+            if let Some((index, _)) = owner {
+                polygons[index].push(hole.positions());
+            }
+        }
+        polygons.into_iter().map(|rings| json!(rings)).collect()
+    }
+
+    /// Follows boundary edges from `start` until the ring closes, removing
+    /// them from `edges`. Where two edges leave one vertex, the one turning
+    /// left is taken.
+    fn follow(
+        &self,
+        geometry: &RadialGeometry,
+        edges: &mut BTreeMap<Vertex, Vec<(Vertex, Direction)>>,
+        start: Vertex,
+    ) -> Ring {
+        let mut vertices = vec![start];
+        let mut at = start;
+        let mut heading: Option<Direction> = None;
+        while let Some(outgoing) = edges.get_mut(&at) {
+            let pick = heading
+                .and_then(|(dr, db)| outgoing.iter().position(|&(_, d)| d == (-db, dr)))
+                .unwrap_or(0);
+            let (to, direction) = outgoing.swap_remove(pick);
+            if outgoing.is_empty() {
+                edges.remove(&at);
+            }
+            heading = Some(direction);
+            at = to;
+            if at == start {
+                break;
+            }
+            vertices.push(at);
+        }
+        Ring::new(self, geometry, &vertices)
+    }
+}
+
+/// A traced ring: its vertices on the ground and in the plane about the
+/// site, and its signed area in that plane, positive counter-clockwise.
+struct Ring {
+    points: Vec<(f64, f64)>,
+    locations: Vec<LatLon>,
+    area: f64,
+}
+
+impl Ring {
+    fn new(lines: &EdgeLines, geometry: &RadialGeometry, vertices: &[Vertex]) -> Ring {
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        let mut locations: Vec<LatLon> = Vec::new();
+        for &(line, edge) in vertices {
+            let (azimuth, elevation) = lines.lines[line];
+            let range = (geometry.first_bin_range + (edge as f64 - 0.5) * geometry.bin_size).max(0.0);
+            let point = (range * azimuth.to_radians().sin(), range * azimuth.to_radians().cos());
+            // Bins that reach the site share their near corner there.
+            if points.last() == Some(&point) {
+                continue;
+            }
+            points.push(point);
+            locations.push(geometry.site.locate(azimuth, range, elevation));
+        }
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+            locations.pop();
+        }
+        let area = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+            .sum::<f64>()
+            / 2.0;
+        Ring { points, locations, area }
+    }
+
+    /// The ring as GeoJSON positions, closed.
+    fn positions(&self) -> Vec<Value> {
+        let mut positions: Vec<Value> = self.locations.iter().map(|&l| position(l)).collect();
+        if let Some(first) = positions.first().cloned() {
+            positions.push(first);
+        }
+        positions
+    }
+
+    /// A point just to the left of the middle of the ring's first edge: for
+    /// a hole, inside the bins around it.
+    fn beside(&self) -> Option<(f64, f64)> {
+        self.points
+            .iter()
+            .zip(self.points.iter().cycle().skip(1))
+            .find(|(a, b)| a != b)
+            .map(|(a, b)| {
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                ((a.0 + b.0) / 2.0 - dy * 1e-3, (a.1 + b.1) / 2.0 + dx * 1e-3)
+            })
+    }
+
+    /// Whether `point` is inside the ring, by the even-odd rule.
+    fn contains(&self, (x, y): (f64, f64)) -> bool {
+        let mut inside = false;
+        for (a, b) in self.points.iter().zip(self.points.iter().cycle().skip(1)) {
+            if (a.1 > y) != (b.1 > y) && x < a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+}
+
+fn meets(trailing: f64, leading: f64) -> bool {
+    let gap = (leading - trailing).rem_euclid(360.0);
+    gap.min(360.0 - gap) < SHARED_EDGE_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RadialSpan, Site};
+
+    /// A sweep of `radials` radials each `360 / radials` degrees wide and
+    /// 1 km bins from the site out, holding `value(radial, bin)`.
+    fn sweep(radials: usize, num_bins: usize, value: impl Fn(usize, usize) -> f32) -> Sweep {
+        let width = 360.0 / radials as f64;
+        let geometry = RadialGeometry {
+            site: Site {
+                latitude: 42.968,
+                longitude: -88.551,
+                height: 0.0,
+            },
+            first_bin_range: 500.0,
+            bin_size: 1000.0,
+            num_bins,
+            radials: (0..radials)
+                .map(|r| RadialSpan {
+                    azimuth: r as f64 * width,
+                    width,
+                    elevation: 0.0,
+                })
+                .collect(),
+        };
+        let values: Vec<Vec<f32>> = (0..radials).map(|r| (0..num_bins).map(|b| value(r, b)).collect()).collect();
+        let levels = values
+            .iter()
+            .map(|radial| radial.iter().map(|v| if v.is_nan() { 0 } else { *v as u8 }).collect())
+            .collect();
+        Sweep {
+            geometry,
+            levels,
+            values,
+            scaling: None,
+        }
+    }
+
+    fn contours(sweep: &Sweep, threshold: f32) -> Vec<Value> {
+        let collection = sweep.polygons_geojson(&PolygonMode::Contours(vec![threshold]));
+        collection["features"][0]["geometry"]["coordinates"].as_array().unwrap().clone()
+    }
+
+    /// Signed area of a ring of GeoJSON positions, positive counter-clockwise.
+    fn signed_area(ring: &Value) -> f64 {
+        let points: Vec<(f64, f64)> = ring
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| (p[0].as_f64().unwrap(), p[1].as_f64().unwrap()))
+            .collect();
+        points.windows(2).map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1).sum::<f64>() / 2.0
+    }
+
+    #[test]
+    fn bin_mode_writes_a_closed_quad_per_measured_bin() {
+        let sweep = sweep(4, 3, |r, b| if r == 1 && b == 2 { f32::NAN } else { (r * 10 + b) as f32 });
+        let collection = sweep.polygons_geojson(&PolygonMode::Bins);
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(features.len(), 11);
+
+        let bin = features.iter().find(|f| f["properties"]["radial"] == 2 && f["properties"]["bin"] == 1).unwrap();
+        assert_eq!(bin["properties"]["value"], 21.0);
+        assert_eq!(bin["properties"]["level"], 21);
+        let ring = bin["geometry"]["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring[0], ring[4]);
+    }
+
+    #[test]
+    fn an_annulus_is_one_polygon_with_a_hole() {
+        let sweep = sweep(36, 6, |_, b| if (2..4).contains(&b) { 40.0 } else { 10.0 });
+        let polygons = contours(&sweep, 35.0);
+        assert_eq!(polygons.len(), 1);
+        let rings = polygons[0].as_array().unwrap();
+        assert_eq!(rings.len(), 2, "outer ring and the hole around the site");
+        assert!(signed_area(&rings[0]) > 0.0, "outer rings run counter-clockwise");
+        assert!(signed_area(&rings[1]) < 0.0, "holes run clockwise");
+        assert_eq!(rings[0].as_array().unwrap().len(), 37, "36 radial edges, closed");
+    }
+
+    #[test]
+    fn a_hole_away_from_the_site_goes_to_the_polygon_around_it() {
+        let sweep = sweep(36, 6, |r, b| if r < 5 && !(r == 2 && b == 3) { 40.0 } else { f32::NAN });
+        let polygons = contours(&sweep, 35.0);
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn bins_touching_at_a_corner_are_separate_polygons() {
+        let sweep = sweep(36, 6, |r, b| if (r, b) == (3, 2) || (r, b) == (4, 3) { 40.0 } else { 0.0 });
+        let polygons = contours(&sweep, 35.0);
+        assert_eq!(polygons.len(), 2);
+        for polygon in &polygons {
+            let outer = &polygon[0];
+            assert_eq!(outer.as_array().unwrap().len(), 5, "each is a single bin");
+        }
+    }
+
+    #[test]
+    fn a_disc_around_the_site_has_no_hole() {
+        let sweep = sweep(36, 6, |_, b| if b < 3 { 40.0 } else { 0.0 });
+        let polygons = contours(&sweep, 35.0);
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].as_array().unwrap().len(), 1);
+        assert!(signed_area(&polygons[0][0]) > 0.0);
+    }
+
+    #[test]
+    fn contours_of_a_real_product_nest() {
+        let (_, radar) = Radar::from_vec(include_bytes!("../data/sn_DS.p20-r_kmkx.last").to_vec()).unwrap();
+        let sweep = radar.sweep().unwrap();
+        let collection = sweep.polygons_geojson(&PolygonMode::Contours(vec![10.0, 20.0]));
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(features[0]["properties"]["threshold"], 10.0);
+        assert_eq!(features[0]["properties"]["units"], "dBZ");
+
+        let area = |feature: &Value| -> f64 {
+            feature["geometry"]["coordinates"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .flat_map(|polygon| polygon.as_array().unwrap().iter().map(signed_area))
+                .sum()
+        };
+        let (low, high) = (area(&features[0]), area(&features[1]));
+        assert!(low > 0.0 && high > 0.0);
+        assert!(high < low, "{high} should be inside {low}");
+
+        let measured = sweep.values.iter().flatten().filter(|v| !v.is_nan()).count();
+        let bins = radar.polygons_geojson(&PolygonMode::Bins).unwrap();
+        assert_eq!(bins["features"].as_array().unwrap().len(), measured);
+    }
+}
@@ -5,7 +5,11 @@ use nom::{
     number::complete::{i16 as nom_i16, i32 as nom_i32},
     number::Endianness::Big,
 };
-use tracing::{error, info};
+use tracing::info;
+
+use crate::diagnostics::{report, Severity};
+use crate::encode::{put_fixed, put_i16, put_i32, Encode};
+use crate::error_r::Error;
 
 /// Graphic Product Message: Product Description Block
 /// Description: section 3.3.1.1, page 3-3
@@ -39,7 +43,8 @@ pub struct ProductDescription {
     pub product_date: i16,
     /// Product Generation Time, sec since midnight
     pub product_time: i32,
-    ///  Product dependent parameters 1 and 2 TABLE V (length 4s)
+    ///  Product dependent parameters 1 and 2 TABLE V (length 4s). See
+    ///  [`ProductDescription::dependent_params`] for the decoded form.
     pub halfwords_27_28: Vec<u8>,
     /// Elevation number within volume scan
     pub elevation_num: i16,
@@ -66,9 +71,14 @@ pub struct ProductDescription {
 /// 51 halfwords
 /// Figure 3-6 Sheet 2, 6, 7, page 3-24 to 3-26.
 pub fn product_description(input: &[u8]) -> IResult<&[u8], ProductDescription> {
+    let start = input;
     let (input, divider) = nom_i16(Big)(input)?; //: i16,
     if divider != -1 {
-        error!("Block divider error");
+        report(
+            Severity::Error,
+            start,
+            format!("Product description divider should be -1 but found {divider}"),
+        );
     }
     let (input, latitude) = nom_i32(Big)(input)?; //: i32,
     let (input, longitude) = nom_i32(Big)(input)?; //: i32,
@@ -127,6 +137,35 @@ pub fn product_description(input: &[u8]) -> IResult<&[u8], ProductDescription> {
     ))
 }
 
+impl Encode for ProductDescription {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, self.divider);
+        put_i32(out, self.latitude);
+        put_i32(out, self.longitude);
+        put_i16(out, self.height);
+        put_i16(out, self.product_code);
+        put_i16(out, self.operational_mode);
+        put_i16(out, self.vcp);
+        put_i16(out, self.sequence_num);
+        put_i16(out, self.vol_scan_num);
+        put_i16(out, self.vol_scan_date);
+        put_i32(out, self.vol_scan_time);
+        put_i16(out, self.product_date);
+        put_i32(out, self.product_time);
+        put_fixed(out, "Halfwords 27-28", &self.halfwords_27_28, 4)?;
+        put_i16(out, self.elevation_num);
+        put_fixed(out, "Halfword 30", &self.halfwords_30, 2)?;
+        put_fixed(out, "Threshold data", &self.threshold_data, 32)?;
+        put_fixed(out, "Halfwords 47-53", &self.halfwords_47_53, 14)?;
+        out.push(self.version);
+        out.push(self.spot_blank);
+        put_i32(out, self.offset_symbology);
+        put_i32(out, self.offset_graphic);
+        put_i32(out, self.offset_tabular);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,6 +192,15 @@ mod tests {
         assert_eq!(rest.len(), file.len() - 48 - 102);
     }
 
+    #[test]
+    fn encodes_the_sample_description_back_to_its_bytes() {
+        let file = include_bytes!("../data/sn_DS.p20-r_kmkx.last");
+        let (_, description) = product_description(&file[48..]).unwrap();
+        let mut out = Vec::new();
+        description.encode(&mut out).unwrap();
+        assert_eq!(out, file[48..150]);
+    }
+
     #[test]
     fn rejects_truncated_input() {
         assert!(product_description(&[0xFF, 0xFF, 0, 1]).is_err());
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 
-use nom::{IResult, Parser};
+use nom::IResult;
+
+use crate::diagnostics;
+use crate::encode::{len_i16, len_i32, put_i16, with_i32_length, Encode};
+use crate::error_r::Error;
 
 mod packet;
 pub use packet::*;
@@ -19,14 +23,15 @@ use tracing::{debug, info};
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct SymbologyBlock {
     pub header: SymbologyHeader,
-    /// One entry per layer declared in `header.layers`. Only the packet
-    /// codes with a real variant below (as opposed to falling into
-    /// [`symbology_layer`] failing outright) are represented here.
-    pub layers: Vec<SymPacketData>,
+    /// One entry per layer declared in `header.layers`, each holding every
+    /// packet in that layer in file order. Only the packet codes with a real
+    /// variant below (as opposed to falling into [`symbology_layer`] failing
+    /// outright) are represented here.
+    pub layers: Vec<Vec<SymPacketData>>,
 }
 
-/// A single symbology-block data layer, tagged by which [`crate::PacketCode`]
-/// it was decoded from.
+/// A single display data packet from a symbology-block data layer, tagged by
+/// which [`crate::PacketCode`] it was decoded from.
 ///
 /// Every packet code defined in Figures 3-7 through 3-15c has a variant here.
 /// Codes that share an on-the-wire layout share a variant and record which
@@ -88,6 +93,91 @@ impl SymPacketData {
     }
 }
 
+/// Wraps a packet in the variant it is written as, so a layer can be built
+/// with `.into()`.
+macro_rules! into_sym_packet_data {
+    ($($packet:ty => $variant:ident),* $(,)?) => {
+        $(impl From<$packet> for SymPacketData {
+            fn from(packet: $packet) -> Self {
+                SymPacketData::$variant(packet)
+            }
+        })*
+    };
+}
+
+into_sym_packet_data! {
+    RadialPacket => RadialDataAF1F,
+    DigitalRadialPacket => DigitalRadialDataArray,
+    LinkedVectorPacket => LinkedVector,
+    UnlinkedVectorPacket => UnlinkedVector,
+    ContourVectorPacket => ContourVector,
+    RasterPacket => RasterData,
+    DigitalRasterPacket => DigitalRasterDataArray,
+    VectorArrowPacket => VectorArrowData,
+    WindBarbPacket => WindBarbData,
+    SpecialSymbolPacket => SpecialGraphicSymbol,
+    MapMessagePacket => MapMessage,
+}
+
+/// Codes 1 and 2 share a variant; code 8 has its own.
+impl From<TextPacket> for SymPacketData {
+    fn from(packet: TextPacket) -> Self {
+        if packet.packet_code == crate::PacketCode::TextAndSpecialSymbol8 {
+            SymPacketData::TextAndSpecialSymbol8(packet)
+        } else {
+            SymPacketData::TextAndSpecialSymbol1(packet)
+        }
+    }
+}
+
+impl Encode for SymPacketData {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            SymPacketData::GenericData(x) => x.encode(out),
+            SymPacketData::RadialDataAF1F(x) => x.encode(out),
+            SymPacketData::DigitalRadialDataArray(x) => x.encode(out),
+            SymPacketData::TextAndSpecialSymbol1(x) => x.encode(out),
+            SymPacketData::TextAndSpecialSymbol8(x) => x.encode(out),
+            SymPacketData::LinkedVector(x) => x.encode(out),
+            SymPacketData::UnlinkedVector(x) => x.encode(out),
+            SymPacketData::ContourVector(x) => x.encode(out),
+            SymPacketData::RasterData(x) => x.encode(out),
+            SymPacketData::DigitalPrecipitationDataArray(x) => x.encode_byte_runs(out),
+            SymPacketData::PrecipitationRateDataArray(x) => x.encode_nibble_runs(out),
+            SymPacketData::DigitalRasterDataArray(x) => x.encode(out),
+            SymPacketData::VectorArrowData(x) => x.encode(out),
+            SymPacketData::WindBarbData(x) => x.encode(out),
+            SymPacketData::SpecialGraphicSymbol(x) => x.encode(out),
+            SymPacketData::CellTrendData(x) => x.encode(out),
+            SymPacketData::CellTrendVolumeScanTimes(x) => x.encode(out),
+            SymPacketData::MapMessage(x) => x.encode(out),
+        }
+    }
+}
+
+/// Writes the block header with `block_length` and `layers` recomputed, then
+/// each layer behind a -1 divider and its length.
+impl Encode for SymbologyBlock {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let start = out.len();
+        put_i16(out, self.header.divider);
+        put_i16(out, self.header.id);
+        let length_at = out.len();
+        out.extend_from_slice(&[0; 4]);
+        put_i16(out, len_i16("Symbology block layer count", self.layers.len())?);
+        for layer in &self.layers {
+            put_i16(out, -1);
+            with_i32_length(out, "Symbology layer", |out| {
+                layer.iter().try_for_each(|packet| packet.encode(out))
+            })?;
+        }
+        // Unlike a layer's, the block's length counts its own header.
+        let block_length = len_i32("Symbology block", out.len() - start)?;
+        out[length_at..length_at + 4].copy_from_slice(&block_length.to_be_bytes());
+        Ok(())
+    }
+}
+
 /// Parses the Product Symbology Block: its 16 byte header followed by
 /// exactly `header.layers` data layers.
 pub fn symbology(input: &[u8]) -> IResult<&[u8], SymbologyBlock> {
@@ -96,7 +186,15 @@ pub fn symbology(input: &[u8]) -> IResult<&[u8], SymbologyBlock> {
 
     debug!("symbology header {:?}", symbology_header);
 
-    let (input, symbology_layers) = nom::multi::count(symbology_layer, symbology_header.layers as usize).parse(input)?;
+    let mut input = input;
+    let mut symbology_layers = Vec::with_capacity(symbology_header.layers.max(0) as usize);
+    for layer in 0..symbology_header.layers.max(0) as usize {
+        diagnostics::set_layer(Some(layer));
+        let (rest, packets) = symbology_layer(input)?;
+        symbology_layers.push(packets);
+        input = rest;
+    }
+    diagnostics::set_layer(None);
     Ok((
         input,
         SymbologyBlock{
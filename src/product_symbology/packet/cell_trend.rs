@@ -9,7 +9,9 @@ use nom::{
     IResult,
 };
 
-use super::util::{block_length, payload};
+use super::util::{block_length, latin1_string, payload};
+use crate::encode::{latin1_bytes, put_fixed, put_i16, put_i16s, with_i16_length, Encode};
+use crate::error_r::Error;
 use crate::product_symbology::SymPacketData;
 
 /// Which quantity a cell trend series holds — Figure 3-15 sheet 1.
@@ -96,7 +98,7 @@ pub fn cell_trend_data(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     if body.len() < 6 {
         return super::util::fail(input, "Cell trend packet is too short to hold its cell header");
     }
-    let cell_id = String::from_utf8_lossy(&body[0..2]).into_owned();
+    let cell_id = latin1_string(&body[0..2]);
     let i_position = i16::from_be_bytes([body[2], body[3]]);
     let j_position = i16::from_be_bytes([body[4], body[5]]);
 
@@ -178,6 +180,38 @@ pub fn cell_trend_volume_times(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     ))
 }
 
+/// Each series' `num_volumes` is written as the number of values it holds.
+impl Encode for CellTrendPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, 21);
+        with_i16_length(out, "Cell trend packet", |out| {
+            put_fixed(out, "cell ID", &latin1_bytes(&self.cell_id), 2)?;
+            put_i16s(out, &[self.i_position, self.j_position]);
+            for trend in &self.trends {
+                let num_volumes = u8::try_from(trend.values.len()).map_err(|_| Error::TooLong {
+                    what: "Cell trend series",
+                    len: trend.values.len(),
+                })?;
+                put_i16(out, trend.trend_code as i16);
+                out.extend_from_slice(&[num_volumes, trend.latest_volume_pointer]);
+                put_i16s(out, &trend.values);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Encode for CellTrendVolumeTimesPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, 22);
+        with_i16_length(out, "Cell trend volume times packet", |out| {
+            put_i16s(out, &[self.num_volumes, self.latest_volume_pointer]);
+            put_i16s(out, &self.times);
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -269,6 +303,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn encodes_back_to_the_same_bytes() {
+        let mut trend = b"B7".to_vec();
+        trend.extend_from_slice(&hw(&[120, -240, 4]));
+        trend.extend_from_slice(&[2, 1]);
+        trend.extend_from_slice(&hw(&[80, -999]));
+        let mut trend_bytes = hw(&[21, trend.len() as i16]);
+        trend_bytes.extend_from_slice(&trend);
+        let times_bytes = hw(&[22, 8, 3, 2, 1000, 2000]);
+
+        for bytes in [trend_bytes, times_bytes] {
+            let (_, parsed) = crate::product_symbology::symbology_layer_packet(&bytes).unwrap();
+            let mut out = Vec::new();
+            parsed.encode(&mut out).unwrap();
+            assert_eq!(out, bytes, "{parsed:?}");
+        }
+    }
+
     #[test]
     fn rejects_a_cell_trend_packet_that_cannot_hold_its_header() {
         let body = hw(&[1]);
@@ -2,8 +2,13 @@ use serde::{Deserialize, Serialize};
 use nom::{
     bytes::complete::take, multi::count, number::{complete::i16 as nom_i16, Endianness::Big}, IResult, Parser,
 };
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info};
 
+use crate::codes::PacketCode;
+use crate::diagnostics::{failure, report, Severity};
+use crate::encode::{len_i16, put_i16s, Encode};
+use crate::error_r::Error;
+use crate::level_scaling::LevelScaling;
 use crate::product_symbology::SymPacketData;
 
 
@@ -60,8 +65,7 @@ fn packet_header(input: &[u8]) -> IResult<&[u8], DigitalRadialPacketHeader> {
     
     let (input, packet_code) = nom_i16(Big)(input)?;
     if packet_code != 16 {
-        error!("Digital Radial Data Array Packet header should have packet code 16 but found {}", packet_code);
-        return Err(nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Fail)));
+        return Err(failure(input, format!("Digital Radial Data Array Packet header should have packet code 16 but found {}", packet_code)));
     }
     let (input, first_bin) = nom_i16(Big)(input)?;
     let (input, num_bins) = nom_i16(Big)(input)?;
@@ -103,13 +107,16 @@ fn data_block(input: &[u8], num_bins: usize) -> IResult<&[u8], DigitalRadial> {
     // halfword pad byte in the stream and desynchronize every radial after
     // this one.
     let num_bytes = usize::try_from(temp_header.num_bytes).map_err(|_| {
-        error!("Radial declares a negative byte count ({})", temp_header.num_bytes);
-        nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Fail))
+        failure(input, format!("Radial declares a negative byte count ({})", temp_header.num_bytes))
     })?;
     if num_bytes < num_bins {
-        warn!(
-            "Radial declares {} bytes but the packet header declares {} bins; keeping the {} bytes present",
-            num_bytes, num_bins, num_bytes
+        report(
+            Severity::Warning,
+            input,
+            format!(
+                "Radial declares {} bytes but the packet header declares {} bins; keeping the {} bytes present",
+                num_bytes, num_bins, num_bytes
+            ),
         );
     }
     let (input, payload) = take(num_bytes)(input)?;
@@ -156,6 +163,65 @@ pub struct DigitalRadial {
     pub data: Vec<u8>,
 }
 
+impl DigitalRadialPacket {
+    /// Builds a packet from a field of physical values, one row per radial
+    /// and one value per range bin, encoding each through `scaling`.
+    ///
+    /// The radials are taken to be evenly spaced clockwise from north and the
+    /// bins to start at the radar, with the sweep centred on the origin; edit
+    /// the headers afterwards for anything else. Missing values (NaN) encode
+    /// as level 0.
+    pub fn from_values(values: &[Vec<f32>], scaling: &LevelScaling) -> Self {
+        let num_bins = values.iter().map(Vec::len).max().unwrap_or(0);
+        let delta = 3600.0 / values.len().max(1) as f32;
+        DigitalRadialPacket {
+            header: DigitalRadialPacketHeader {
+                packet_code: PacketCode::DigitalRadialDataArray as i16,
+                first_bin: 0,
+                num_bins: num_bins as i16,
+                i_sweep_center: 0,
+                j_sweep_center: 0,
+                range_scale: 1000,
+                num_radials: values.len() as i16,
+            },
+            radials: values
+                .iter()
+                .enumerate()
+                .map(|(i, row)| DigitalRadial {
+                    header: DigitalRadialHeader {
+                        num_bytes: row.len().next_multiple_of(2) as i16,
+                        angle_start: (i as f32 * delta).round() as i16,
+                        angle_delta: delta.round() as i16,
+                    },
+                    data: row.iter().map(|v| scaling.level(*v)).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Encode for DigitalRadialPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let h = &self.header;
+        let num_radials = len_i16("Digital radial packet radial count", self.radials.len())?;
+        put_i16s(
+            out,
+            &[h.packet_code, h.first_bin, h.num_bins, h.i_sweep_center, h.j_sweep_center, h.range_scale, num_radials],
+        );
+        for radial in &self.radials {
+            // Note 1 of Figure 3-11c: an odd bin count is padded to a
+            // halfword boundary, and the pad is counted in `num_bytes`.
+            let num_bytes = len_i16("Digital radial", radial.data.len().next_multiple_of(2))?;
+            put_i16s(out, &[num_bytes, radial.header.angle_start, radial.header.angle_delta]);
+            out.extend_from_slice(&radial.data);
+            if radial.data.len() % 2 == 1 {
+                out.push(0);
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,6 +264,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn encodes_back_to_the_same_bytes() {
+        let bytes = sample_bytes(4, 2);
+        let (_, parsed) = digital_radial_data_array(&bytes).unwrap();
+        let SymPacketData::DigitalRadialDataArray(packet) = parsed else { unreachable!() };
+        let mut out = Vec::new();
+        packet.encode(&mut out).unwrap();
+        assert_eq!(out, bytes);
+    }
+
     #[test]
     fn rejects_the_wrong_packet_code() {
         let mut bytes = sample_bytes(3, 1);
@@ -256,4 +332,22 @@ mod tests {
         bytes.truncate(bytes.len() - 1); // one byte short of the last radial's data
         assert!(digital_radial_data_array(&bytes).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn from_values_spaces_the_radials_evenly_and_encodes_each_bin() {
+        let description = crate::ProductBuilder::new(crate::MessageCode::DigitalHybridScanReflectivity, "KMKX")
+            .with_linear_scaling(0.5, -32.0)
+            .level_scaling()
+            .unwrap();
+        let field: Vec<Vec<f32>> = (0..720).map(|_| vec![-32.0, 0.0, f32::NAN]).collect();
+        let packet = DigitalRadialPacket::from_values(&field, &description);
+
+        assert_eq!(packet.header.num_radials, 720);
+        assert_eq!(packet.header.num_bins, 3);
+        assert_eq!(packet.radials[3].header.angle_start, 15);
+        assert_eq!(packet.radials[3].header.angle_delta, 5);
+        // -32 dBZ is level 0, which is below the first data level.
+        assert_eq!(packet.radials[0].data, vec![2, 64, 0]);
+        assert_eq!(packet.radials[0].header.num_bytes, 4);
+    }
+}
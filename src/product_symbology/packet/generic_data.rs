@@ -41,10 +41,13 @@ use nom::{
     number::{complete::i16 as nom_i16, Endianness::Big},
     IResult,
 };
-use tracing::{debug, warn};
+use tracing::debug;
 
 use super::util::{fail, payload};
-use super::xdr::{XdrError, XdrReader};
+use super::xdr::{XdrError, XdrReader, XdrWriter};
+use crate::diagnostics::{report, Severity};
+use crate::encode::{len_i32, put_i16, put_i16s, Encode};
+use crate::error_r::Error;
 use crate::product_symbology::SymPacketData;
 
 /// The binary header of a Generic Data Packet (Figure 3-15c).
@@ -313,13 +316,25 @@ pub fn generic_data(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     match packet_code {
         28 => match decode_product_description(body) {
             Ok(pd) => product_description = Some(pd),
-            Err(e) => warn!("Could not decode generic product description: {e}"),
+            Err(e) => report(
+                Severity::Error,
+                body,
+                format!("Could not decode generic product description: {e}"),
+            ),
         },
         29 => match decode_external_data_description(body) {
             Ok(ed) => external_description = Some(ed),
-            Err(e) => warn!("Could not decode external data description: {e}"),
+            Err(e) => report(
+                Severity::Error,
+                body,
+                format!("Could not decode external data description: {e}"),
+            ),
         },
-        other => warn!("Generic data packet has unexpected code {other}; payload left undecoded"),
+        other => report(
+            Severity::Error,
+            body,
+            format!("Generic data packet has unexpected code {other}; payload left undecoded"),
+        ),
     }
 
     Ok((
@@ -365,7 +380,7 @@ pub fn decode_external_data_description(
     })
 }
 
-/// Warns when a payload was not fully consumed.
+/// Reports a payload that was not fully consumed.
 ///
 /// Appendix E structures should account for every serialized byte, so leftovers
 /// mean this crate's reading of some field's layout is off. Surfacing that is
@@ -373,11 +388,15 @@ pub fn decode_external_data_description(
 /// payload is retained either way.
 fn warn_if_payload_remains(r: &XdrReader<'_>, what: &str) {
     if !r.is_done() {
-        warn!(
-            "{} left {} of {} XDR byte(s) unread; some field layout may be wrong",
-            what,
-            r.remaining(),
-            r.position() + r.remaining()
+        report(
+            Severity::Error,
+            r.rest(),
+            format!(
+                "{} left {} of {} XDR byte(s) unread; some field layout may be wrong",
+                what,
+                r.remaining(),
+                r.position() + r.remaining()
+            ),
         );
     }
 }
@@ -479,7 +498,11 @@ fn read_components_at_depth(
         return Ok(Vec::new());
     }
     if depth >= MAX_COMPONENT_DEPTH {
-        warn!("Generic product components nested more than {MAX_COMPONENT_DEPTH} deep; stopping");
+        report(
+            Severity::Error,
+            r.rest(),
+            format!("Generic product components nested more than {MAX_COMPONENT_DEPTH} deep; stopping"),
+        );
         return Ok(Vec::new());
     }
 
@@ -502,7 +525,11 @@ fn read_components_at_depth(
             other => {
                 // Without knowing the structure's length there is no way to
                 // skip it, so stop here and keep what was decoded.
-                warn!("Unknown generic product component type {other}; stopping");
+                report(
+                    Severity::Error,
+                    r.rest(),
+                    format!("Unknown generic product component type {other}; stopping"),
+                );
                 out.push(GenericComponent::Unsupported {
                     component_type: other,
                 });
@@ -571,7 +598,11 @@ fn read_area_component(r: &mut XdrReader<'_>) -> Result<AreaComponent, XdrError>
                 range: b,
             },
             other => {
-                warn!("Unknown area coordinate system {other}; reading points as lat/lon");
+                report(
+                    Severity::Warning,
+                    r.rest(),
+                    format!("Unknown area coordinate system {other}; reading points as lat/lon"),
+                );
                 GenericPoint::LatLon {
                     latitude: a,
                     longitude: b,
@@ -640,6 +671,188 @@ fn read_radial_component(r: &mut XdrReader<'_>) -> Result<RadialComponent, XdrEr
     })
 }
 
+/// Serializes a Product Description (Figure E-1), the inverse of
+/// [`decode_product_description`].
+///
+/// Pointer placeholders are written as 0, and strings without the NUL
+/// terminators some producers add, so a description read from a file need not
+/// serialize to that file's exact bytes.
+pub fn encode_product_description(pd: &GenericProductDescription) -> Vec<u8> {
+    let mut w = XdrWriter::new();
+    w.string(&pd.name);
+    w.string(&pd.description);
+    w.int(pd.code);
+    w.int(pd.product_type);
+    w.uint(pd.generation_time);
+    w.string(&pd.radar_name);
+    w.float(pd.radar_latitude);
+    w.float(pd.radar_longitude);
+    w.float(pd.radar_height);
+    w.uint(pd.volume_scan_start_time);
+    w.uint(pd.elevation_scan_start_time);
+    w.float(pd.elevation_angle);
+    w.ints(&[
+        pd.volume_scan_number,
+        pd.operational_mode,
+        pd.vcp_number,
+        pd.elevation_number,
+        pd.compression,
+        pd.uncompressed_size,
+    ]);
+    write_parameters(&mut w, &pd.parameters);
+    write_components(&mut w, &pd.components);
+    w.into_bytes()
+}
+
+/// Serializes an External Data Description (Figure E-1b), the inverse of
+/// [`decode_external_data_description`], on the same terms as
+/// [`encode_product_description`].
+pub fn encode_external_data_description(ed: &ExternalDataDescription) -> Vec<u8> {
+    let mut w = XdrWriter::new();
+    w.string(&ed.name);
+    w.string(&ed.description);
+    w.int(ed.code);
+    w.int(ed.product_type);
+    w.uint(ed.generation_time);
+    w.ints(&ed.spares);
+    write_parameters(&mut w, &ed.parameters);
+    write_components(&mut w, &ed.components);
+    w.into_bytes()
+}
+
+/// Writes the count, pointer placeholder, entries and inter-entry
+/// placeholders that [`read_parameters`] expects.
+fn write_parameters(w: &mut XdrWriter, parameters: &[Parameter]) {
+    w.int(parameters.len() as i32);
+    w.int(0);
+    for (i, p) in parameters.iter().enumerate() {
+        if i > 0 {
+            w.int(0);
+        }
+        w.string(&p.name);
+        w.string(&p.value);
+    }
+}
+
+fn write_components(w: &mut XdrWriter, components: &[GenericComponent]) {
+    w.int(components.len() as i32);
+    w.int(0);
+    for (i, component) in components.iter().enumerate() {
+        if i > 0 {
+            w.int(0);
+        }
+        match component {
+            GenericComponent::Radial(c) => {
+                w.int(1);
+                w.string(&c.description);
+                w.float(c.bin_size);
+                w.float(c.first_bin_range);
+                write_parameters(w, &c.parameters);
+                w.int(c.radials.len() as i32);
+                for radial in &c.radials {
+                    w.float(radial.azimuth);
+                    w.float(radial.elevation);
+                    w.float(radial.width);
+                    w.int(radial.num_bins);
+                    w.string(&radial.attributes);
+                    w.int_array(&radial.data);
+                }
+            }
+            GenericComponent::Grid(c) => {
+                w.int(2);
+                w.int(c.dimensions.len() as i32);
+                w.int(0);
+                w.ints(&c.dimensions);
+                w.int(c.grid_type);
+                write_parameters(w, &c.parameters);
+                w.string(&c.attributes);
+                w.int_array(&c.data);
+            }
+            GenericComponent::Area(c) => {
+                w.int(3);
+                write_parameters(w, &c.parameters);
+                w.int(c.area_type);
+                w.int(c.points.len() as i32);
+                w.int(0);
+                for point in &c.points {
+                    let (a, b) = match *point {
+                        GenericPoint::LatLon { latitude, longitude } => (latitude, longitude),
+                        GenericPoint::XY { x, y } => (x, y),
+                        GenericPoint::AzRan { azimuth, range } => (azimuth, range),
+                    };
+                    w.float(a);
+                    w.float(b);
+                }
+            }
+            GenericComponent::Text(c) => {
+                w.int(4);
+                write_parameters(w, &c.parameters);
+                w.string(&c.text);
+            }
+            GenericComponent::Table(c) => {
+                w.int(5);
+                write_parameters(w, &c.parameters);
+                w.string(&c.title);
+                w.int(c.columns);
+                w.int(c.rows);
+                w.string_array(&c.column_labels);
+                w.string_array(&c.row_labels);
+                w.string_array(&c.entries);
+            }
+            GenericComponent::Event(c) => {
+                w.int(6);
+                write_parameters(w, &c.parameters);
+                write_components(w, &c.components);
+            }
+            GenericComponent::Unsupported { component_type } => w.int(*component_type),
+        }
+    }
+}
+
+/// Whether `components` holds a component whose layout is unknown, and so
+/// cannot be serialized.
+fn has_unsupported(components: &[GenericComponent]) -> bool {
+    components.iter().any(|c| match c {
+        GenericComponent::Unsupported { .. } => true,
+        GenericComponent::Event(e) => has_unsupported(&e.components),
+        _ => false,
+    })
+}
+
+impl GenericDataPacket {
+    /// The XDR payload to write: the decoded description serialized afresh if
+    /// it no longer matches `raw`, and `raw` as read otherwise. A description
+    /// holding an [`GenericComponent::Unsupported`] component cannot be
+    /// serialized, so `raw` is written for it too.
+    fn payload(&self) -> Vec<u8> {
+        if let Some(pd) = &self.product_description {
+            if !has_unsupported(&pd.components) && decode_product_description(&self.raw).as_ref() != Ok(pd) {
+                return encode_product_description(pd);
+            }
+        }
+        if let Some(ed) = &self.external_description {
+            if !has_unsupported(&ed.components)
+                && decode_external_data_description(&self.raw).as_ref() != Ok(ed)
+            {
+                return encode_external_data_description(ed);
+            }
+        }
+        self.raw.clone()
+    }
+}
+
+/// Writes the header with `length` recomputed from the payload.
+impl Encode for GenericDataPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let payload = self.payload();
+        let length = len_i32("Generic data packet", payload.len())?;
+        put_i16(out, self.header.packet_code);
+        put_i16s(out, &[self.header.reserved, (length >> 16) as i16, length as i16]);
+        out.extend_from_slice(&payload);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1116,6 +1329,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn an_unedited_packet_encodes_its_raw_payload() {
+        // Trailing NULs on a string are dropped when decoding, so only the
+        // raw payload reproduces these bytes.
+        let mut payload = sample_payload();
+        payload[3] += 4;
+        payload.splice(4 + 24..4 + 24, [0, 0, 0, 0]);
+        let bytes = wrap_packet(28, &payload);
+        let (_, parsed) = generic_data(&bytes).unwrap();
+        let mut out = Vec::new();
+        parsed.encode(&mut out).unwrap();
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn an_edited_description_is_serialized_afresh() {
+        let (_, parsed) = generic_data(&wrap_packet(28, &sample_payload())).unwrap();
+        let SymPacketData::GenericData(mut packet) = parsed else { unreachable!() };
+        let pd = packet.product_description.as_mut().unwrap();
+        pd.radar_name = "KGRB".into();
+        pd.components.push(GenericComponent::Text(TextComponent {
+            parameters: vec![Parameter { name: "a".into(), value: "b".into() }],
+            text: "TORNADO".into(),
+        }));
+        let edited = pd.clone();
+
+        let mut out = Vec::new();
+        packet.encode(&mut out).unwrap();
+        let (rest, reparsed) = generic_data(&out).unwrap();
+        assert!(rest.is_empty());
+        let SymPacketData::GenericData(reparsed) = reparsed else { unreachable!() };
+        assert_eq!(reparsed.product_description, Some(edited));
+    }
+
     #[test]
     fn rejects_a_truncated_payload() {
         let mut bytes = wrap_packet(28, &[1, 2, 3, 4]);
@@ -21,8 +21,10 @@ use nom::{
     IResult,
 };
 
-use super::util::{block_length, fail, i16_array, payload};
+use super::util::{block_length, fail, i16_array, latin1_string, payload};
 use super::vector::{Point, Vector};
+use crate::encode::{latin1_bytes, put_i16, put_i16s, with_i16_length, Encode};
+use crate::error_r::Error;
 use crate::product_symbology::SymPacketData;
 
 /// The decoded payload of a Map Message packet.
@@ -54,7 +56,8 @@ pub enum MapMessagePacket {
         /// Where the string starts, in 1/8 km from the upper left corner.
         position: Point,
         /// The characters. For `0x4E01` these select special symbols rather
-        /// than reading as text, so they are decoded lossily.
+        /// than reading as text, so each byte is kept as one character
+        /// (ISO 8859-1) rather than being decoded as UTF-8.
         text: String,
     },
 }
@@ -139,7 +142,7 @@ pub fn map_message(input: &[u8]) -> IResult<&[u8], SymPacketData> {
                     position: Point { i, j },
                     // Special symbol selectors set the high bit, so this cannot
                     // be required to be valid UTF-8.
-                    text: String::from_utf8_lossy(body).into_owned(),
+                    text: latin1_string(body),
                 }),
             ))
         }
@@ -151,6 +154,42 @@ pub fn map_message(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     }
 }
 
+impl Encode for MapMessagePacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            MapMessagePacket::LinkedVector { indicator, start, ends } => {
+                put_i16s(out, &[0x0E23, *indicator, start.i, start.j]);
+                with_i16_length(out, "Map message linked vector packet", |out| {
+                    ends.iter().for_each(|p| put_i16s(out, &[p.i, p.j]));
+                    Ok(())
+                })
+            }
+            MapMessagePacket::UnlinkedVector { vectors } => {
+                put_i16(out, 0x3521);
+                with_i16_length(out, "Map message unlinked vector packet", |out| {
+                    for v in vectors {
+                        put_i16s(out, &[v.begin.i, v.begin.j, v.end.i, v.end.j]);
+                    }
+                    Ok(())
+                })
+            }
+            MapMessagePacket::Text {
+                packet_code,
+                indicator,
+                initial_point_indicator,
+                position,
+                text,
+            } => {
+                put_i16s(out, &[*packet_code, *indicator, *initial_point_indicator, position.i, position.j]);
+                with_i16_length(out, "Map message text packet", |out| {
+                    out.extend_from_slice(&latin1_bytes(text));
+                    Ok(())
+                })
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -242,7 +281,7 @@ mod tests {
     }
 
     /// Special symbol selectors set the high bit, so the payload is not valid
-    /// UTF-8 and must decode lossily rather than failing.
+    /// UTF-8 and must decode byte for byte rather than failing.
     #[test]
     fn parses_map_special_symbols_with_the_high_bit_set() {
         let body = [0x81u8, 0x82, 0x83, 0x84];
@@ -263,8 +302,13 @@ mod tests {
                 assert_eq!(packet_code as u16, 0x4E01);
                 assert!(p.is_special_symbols());
             }
-            other => panic!("expected Text, got {other:?}"),
+            ref other => panic!("expected Text, got {other:?}"),
         }
+
+        // Every selector survives, so the packet encodes to what was read.
+        let mut out = Vec::new();
+        parsed.encode(&mut out).unwrap();
+        assert_eq!(out, bytes);
     }
 
     #[test]
@@ -3,6 +3,7 @@
 
 mod util;
 pub use util::decode_nibble_rle;
+pub(crate) use util::latin1_string;
 
 pub mod xdr;
 
@@ -51,7 +52,8 @@ pub use map_message::{map_message, MapMessagePacket};
 
 mod generic_data;
 pub use generic_data::{
-    decode_external_data_description, decode_product_description, generic_data, AreaComponent,
+    decode_external_data_description, decode_product_description, encode_external_data_description,
+    encode_product_description, generic_data, AreaComponent,
     AreaShape, EventComponent, ExternalDataDescription, GenericComponent, GenericDataHeader,
     GenericDataPacket, GenericPoint, GenericProductDescription, GenericRadial, GridComponent,
     Parameter, RadialComponent, TableComponent, TextComponent,
@@ -2,8 +2,13 @@ use serde::{Deserialize, Serialize};
 use nom::{
     bytes::complete::take, multi::count, number::{complete::i16 as nom_i16, Endianness::Big}, IResult, Parser,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, info};
 
+use crate::diagnostics::failure;
+use crate::encode::{len_i16, put_i16s, Encode};
+use crate::codes::PacketCode;
+use crate::error_r::Error;
+use crate::level_scaling::LevelScaling;
 use crate::product_symbology::SymPacketData;
 
 
@@ -62,8 +67,7 @@ fn packet_header(input: &[u8]) -> IResult<&[u8], RadialPacketHeader> {
     
     let (input, packet_code) = nom_i16(Big)(input)?;
     if packet_code != -20705 {
-        error!("Radial Data Packet should have packet code AF1F (-20705) but found {}", packet_code);
-        return Err(nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Fail)));
+        return Err(failure(input, format!("Radial Data Packet should have packet code AF1F (-20705) but found {}", packet_code)));
     }
     let (input, first_bin) = nom_i16(Big)(input)?;
     let (input, num_bins) = nom_i16(Big)(input)?;
@@ -103,11 +107,10 @@ fn data_block(input: &[u8]) -> IResult<&[u8], Radial> {
         .ok()
         .and_then(|hw| hw.checked_mul(2))
         .ok_or_else(|| {
-            error!(
-                "Radial declares an invalid RLE halfword count ({})",
-                temp_header.num_halfwords
-            );
-            nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Fail))
+            failure(
+                input,
+                format!("Radial declares an invalid RLE halfword count ({})", temp_header.num_halfwords),
+            )
         })?;
     let (input, rle) = take(rle_size)(input)?;
 
@@ -161,6 +164,80 @@ pub struct RunLevelEncoding {
     pub color: u8,
 }
 
+impl RadialPacket {
+    /// Builds a run-length encoded packet from a field of physical values,
+    /// one row per radial and one value per range bin, encoding each through
+    /// `scaling`.
+    ///
+    /// The `AF1F` packet only has room for 16 colour levels, so levels above
+    /// 15 are clipped. Radial geometry is as for
+    /// [`DigitalRadialPacket::from_values`](super::DigitalRadialPacket::from_values).
+    pub fn from_values(values: &[Vec<f32>], scaling: &LevelScaling) -> Self {
+        let num_bins = values.iter().map(Vec::len).max().unwrap_or(0);
+        let delta = 3600.0 / values.len().max(1) as f32;
+        RadialPacket {
+            header: RadialPacketHeader {
+                packet_code: PacketCode::RadialDataAF1F as i16,
+                first_bin: 0,
+                num_bins: num_bins as i16,
+                i_sweep_center: 0,
+                j_sweep_center: 0,
+                range_scale: 1000,
+                num_radials: values.len() as i16,
+            },
+            radials: values
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let data = runs(row.iter().map(|v| scaling.level(*v).min(15)));
+                    Radial {
+                        header: RadialHeader {
+                            num_halfwords: data.len().div_ceil(2) as i16,
+                            angle_start: (i as f32 * delta).round() as i16,
+                            angle_delta: delta.round() as i16,
+                        },
+                        data,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Collapses levels into runs of at most 15, the most a nibble can count.
+fn runs(levels: impl Iterator<Item = u8>) -> Vec<RunLevelEncoding> {
+    let mut runs: Vec<RunLevelEncoding> = Vec::new();
+    for level in levels {
+        match runs.last_mut() {
+            Some(last) if last.color == level && last.run < 15 => last.run += 1,
+            _ => runs.push(RunLevelEncoding { run: 1, color: level }),
+        }
+    }
+    runs
+}
+
+impl Encode for RadialPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let h = &self.header;
+        let num_radials = len_i16("Radial packet radial count", self.radials.len())?;
+        put_i16s(
+            out,
+            &[h.packet_code, h.first_bin, h.num_bins, h.i_sweep_center, h.j_sweep_center, h.range_scale, num_radials],
+        );
+        for radial in &self.radials {
+            // Runs are packed two to a halfword, so an odd count is padded
+            // with an empty run.
+            let num_halfwords = len_i16("Radial", radial.data.len().div_ceil(2))?;
+            put_i16s(out, &[num_halfwords, radial.header.angle_start, radial.header.angle_delta]);
+            out.extend(radial.data.iter().map(|r| (r.run << 4) | (r.color & 0x0F)));
+            if radial.data.len() % 2 == 1 {
+                out.push(0);
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,6 +282,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn encodes_back_to_the_same_bytes() {
+        let bytes = sample_bytes(&[0x53, 0xA1]);
+        let (_, parsed) = radial_data_af1f(&bytes).unwrap();
+        let SymPacketData::RadialDataAF1F(packet) = parsed else { unreachable!() };
+        let mut out = Vec::new();
+        packet.encode(&mut out).unwrap();
+        assert_eq!(out, bytes);
+    }
+
     #[test]
     fn rejects_the_wrong_packet_code() {
         let mut bytes = sample_bytes(&[0x53, 0xA1]);
@@ -218,4 +305,29 @@ mod tests {
         bytes.pop();
         assert!(radial_data_af1f(&bytes).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn from_values_run_length_encodes_the_levels() {
+        let file = include_bytes!("../../../data/sn_DS.p20-r_kmkx.last");
+        let (rest, _) = crate::text_header(file).unwrap();
+        let (rest, _) = crate::message_header(rest).unwrap();
+        let scaling = crate::product_description(rest).unwrap().1.level_scaling().unwrap();
+
+        let mut row = vec![f32::NAN; 3];
+        row.extend(std::iter::repeat_n(60.0, 20));
+        let packet = RadialPacket::from_values(&[row.clone(), row], &scaling);
+
+        assert_eq!(packet.header.num_bins, 23);
+        assert_eq!(packet.radials[1].header.angle_start, 1800);
+        let top = scaling.level(60.0);
+        assert_eq!(
+            packet.radials[0].data,
+            vec![
+                RunLevelEncoding { run: 3, color: 0 },
+                RunLevelEncoding { run: 15, color: top },
+                RunLevelEncoding { run: 5, color: top },
+            ]
+        );
+        assert_eq!(packet.radials[0].header.num_halfwords, 2);
+    }
+}
@@ -10,9 +10,12 @@ use nom::{
     number::{complete::i16 as nom_i16, Endianness::Big},
     IResult,
 };
-use tracing::{debug, warn};
+use tracing::debug;
 
 use super::util::{decode_nibble_rle, fail, payload};
+use crate::diagnostics::{report, Severity};
+use crate::encode::{len_i16, put_i16, put_i16s, with_i16_length, Encode};
+use crate::error_r::Error;
 use crate::product_symbology::SymPacketData;
 
 /// One run of a run-length-encoded raster row.
@@ -64,13 +67,18 @@ pub struct RasterPacket {
 /// Rows are run-length encoded with a 4-bit run count in the high nibble of
 /// each byte and a 4-bit colour code in the low nibble.
 pub fn raster_data(input: &[u8]) -> IResult<&[u8], SymPacketData> {
+    let start = input;
     let (input, packet_code) = nom_i16(Big)(input)?;
     let (input, packet_code_2) = nom_i16(Big)(input)?;
     let (input, packet_code_3) = nom_i16(Big)(input)?;
     if packet_code_2 as u16 != 0x8000 || packet_code_3 as u16 != 0x00C0 {
-        warn!(
-            "Raster packet expects secondary codes 0x8000/0x00C0 but found {:#06x}/{:#06x}",
-            packet_code_2 as u16, packet_code_3 as u16
+        report(
+            Severity::Warning,
+            &start[2..],
+            format!(
+                "Raster packet expects secondary codes 0x8000/0x00C0 but found {:#06x}/{:#06x}",
+                packet_code_2 as u16, packet_code_3 as u16
+            ),
         );
     }
     let (input, i_start) = nom_i16(Big)(input)?;
@@ -299,9 +307,99 @@ pub fn digital_raster_array(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     ))
 }
 
+/// Writes each row as a halfword byte count and one byte per run, packed by
+/// `pack`.
+fn put_rows(out: &mut Vec<u8>, rows: &[Vec<Run>], pack: impl Fn(&Run, &mut Vec<u8>)) -> Result<(), Error> {
+    for row in rows {
+        with_i16_length(out, "Raster row", |out| {
+            row.iter().for_each(|run| pack(run, out));
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// A run and level in the two nibbles of one byte; wider values are masked.
+fn pack_nibbles(run: &Run, out: &mut Vec<u8>) {
+    out.push((((run.run & 0x0F) << 4) | (run.level & 0x0F)) as u8);
+}
+
+/// A run and level in a byte each; wider values are masked.
+fn pack_bytes(run: &Run, out: &mut Vec<u8>) {
+    out.extend_from_slice(&[run.run as u8, run.level as u8]);
+}
+
+impl Encode for RasterPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let h = &self.header;
+        put_i16s(
+            out,
+            &[
+                h.packet_code,
+                h.packet_code_2,
+                h.packet_code_3,
+                h.i_start,
+                h.j_start,
+                h.x_scale_int,
+                h.x_scale_fractional,
+                h.y_scale_int,
+                h.y_scale_fractional,
+                len_i16("Raster packet row count", self.rows.len())?,
+                h.packing_descriptor,
+            ],
+        );
+        put_rows(out, &self.rows, pack_nibbles)
+    }
+}
+
+impl PrecipArrayPacket {
+    fn put_header(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let h = &self.header;
+        let num_rows = len_i16("Precipitation array row count", self.rows.len())?;
+        put_i16s(out, &[h.packet_code, h.spares[0], h.spares[1], h.num_boxes, num_rows]);
+        Ok(())
+    }
+
+    /// Writes the packet in the byte-wide run layout of packet code 17.
+    pub(crate) fn encode_byte_runs(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        self.put_header(out)?;
+        put_rows(out, &self.rows, pack_bytes)
+    }
+
+    /// Writes the packet in the nibble run layout of packet code 18.
+    pub(crate) fn encode_nibble_runs(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        self.put_header(out)?;
+        put_rows(out, &self.rows, pack_nibbles)
+    }
+}
+
+impl Encode for DigitalRasterPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let h = &self.header;
+        put_i16s(
+            out,
+            &[
+                h.packet_code,
+                h.i_start,
+                h.j_start,
+                h.i_scale,
+                h.j_scale,
+                h.num_cells,
+                len_i16("Digital raster row count", self.rows.len())?,
+            ],
+        );
+        for row in &self.rows {
+            put_i16(out, len_i16("Digital raster row", row.len())?);
+            out.extend_from_slice(row);
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::product_symbology::symbology_layer_packet;
 
     fn hw(values: &[i16]) -> Vec<u8> {
         values.iter().flat_map(|v| v.to_be_bytes()).collect()
@@ -395,6 +493,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn encodes_back_to_the_same_bytes() {
+        let mut raster = hw(&[0xBA07u16 as i16, 0x8000u16 as i16, 0x00C0, 1, 2, 3, 0, 4, 0, 1, 2]);
+        raster.extend_from_slice(&hw(&[3]));
+        raster.extend_from_slice(&[0x53, 0x21, 0xF0]);
+        let mut precip = hw(&[17, 0, 0, 131, 1, 4]);
+        precip.extend_from_slice(&[200, 250, 1, 2]);
+        let mut rate = hw(&[18, 0, 0, 131, 1, 1]);
+        rate.push(0x7A);
+        let mut digital = hw(&[33, 0, 0, 1, 1, 3, 1, 3]);
+        digital.extend_from_slice(&[10, 20, 30]);
+
+        for bytes in [raster, precip, rate, digital] {
+            let (_, packet) = symbology_layer_packet(&bytes).unwrap();
+            let mut out = Vec::new();
+            packet.encode(&mut out).unwrap();
+            assert_eq!(out, bytes, "{packet:?}");
+        }
+    }
+
     #[test]
     fn rejects_a_negative_row_count() {
         let bytes = hw(&[33, 0, 0, 1, 1, 3, -5]);
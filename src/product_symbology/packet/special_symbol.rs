@@ -21,7 +21,9 @@ use nom::{
     IResult,
 };
 
-use super::util::{block_length, i16_array, payload};
+use super::util::{block_length, i16_array, latin1_string, payload};
+use crate::encode::{latin1_bytes, put_fixed, put_i16, put_i16s, with_i16_length, Encode};
+use crate::error_r::Error;
 use crate::product_symbology::SymPacketData;
 
 /// A symbol with a position and an associated radius (Km/4).
@@ -101,6 +103,29 @@ pub enum SpecialSymbolPacket {
     ScitData { packet_code: i16, nested: Vec<u8> },
 }
 
+impl SpecialSymbolPacket {
+    /// The display data packets nested in SCIT past or forecast data (codes 23
+    /// and 24), parsed up to the first that fails; empty for every other
+    /// symbol packet.
+    pub fn nested_packets(&self) -> Vec<SymPacketData> {
+        let SpecialSymbolPacket::ScitData { nested, .. } = self else {
+            return Vec::new();
+        };
+        let mut input = &nested[..];
+        let mut packets = Vec::new();
+        while input.len() >= 2 {
+            match crate::symbology_layer_packet(input) {
+                Ok((rest, packet)) if rest.len() < input.len() => {
+                    packets.push(packet);
+                    input = rest;
+                }
+                _ => break,
+            }
+        }
+        packets
+    }
+}
+
 /// Special Graphic Symbol Packet — every code listed in Figure 3-14.
 pub fn special_graphic_symbol(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     let (input, packet_code) = nom_i16(Big)(input)?;
@@ -149,7 +174,7 @@ pub fn special_graphic_symbol(input: &[u8]) -> IResult<&[u8], SymPacketData> {
                 .map(|c| StormIdSymbol {
                     i_position: i16::from_be_bytes([c[0], c[1]]),
                     j_position: i16::from_be_bytes([c[2], c[3]]),
-                    storm_id: String::from_utf8_lossy(&c[4..6]).into_owned(),
+                    storm_id: latin1_string(&c[4..6]),
                 })
                 .collect(),
         },
@@ -188,6 +213,69 @@ pub fn special_graphic_symbol(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     Ok((input, SymPacketData::SpecialGraphicSymbol(packet)))
 }
 
+impl SpecialSymbolPacket {
+    /// The packet code this symbol set is written under.
+    pub fn packet_code(&self) -> i16 {
+        match self {
+            SpecialSymbolPacket::Mesocyclone { packet_code, .. }
+            | SpecialSymbolPacket::Position { packet_code, .. }
+            | SpecialSymbolPacket::ScitData { packet_code, .. } => *packet_code,
+            SpecialSymbolPacket::StormId { .. } => 15,
+            SpecialSymbolPacket::Hail { .. } => 19,
+            SpecialSymbolPacket::PointFeature { .. } => 20,
+            SpecialSymbolPacket::StiCircle { .. } => 25,
+        }
+    }
+}
+
+impl Encode for SpecialSymbolPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, self.packet_code());
+        with_i16_length(out, "Special graphic symbol packet", |out| {
+            match self {
+                SpecialSymbolPacket::Mesocyclone { symbols, .. }
+                | SpecialSymbolPacket::StiCircle { symbols } => {
+                    for s in symbols {
+                        put_i16s(out, &[s.i_position, s.j_position, s.radius]);
+                    }
+                }
+                SpecialSymbolPacket::Position { symbols, .. } => {
+                    for s in symbols {
+                        put_i16s(out, &[s.i_position, s.j_position]);
+                    }
+                }
+                SpecialSymbolPacket::StormId { symbols } => {
+                    for s in symbols {
+                        put_i16s(out, &[s.i_position, s.j_position]);
+                        put_fixed(out, "storm ID", &latin1_bytes(&s.storm_id), 2)?;
+                    }
+                }
+                SpecialSymbolPacket::Hail { symbols } => {
+                    for s in symbols {
+                        put_i16s(
+                            out,
+                            &[
+                                s.i_position,
+                                s.j_position,
+                                s.probability_of_hail,
+                                s.probability_of_severe_hail,
+                                s.max_hail_size,
+                            ],
+                        );
+                    }
+                }
+                SpecialSymbolPacket::PointFeature { symbols } => {
+                    for s in symbols {
+                        put_i16s(out, &[s.i_position, s.j_position, s.feature_type, s.feature_attribute]);
+                    }
+                }
+                SpecialSymbolPacket::ScitData { nested, .. } => out.extend_from_slice(nested),
+            }
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,6 +384,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parses_scit_nested_packets_on_request() {
+        let mut nested = packet(25, &hw(&[1, 2, 3]));
+        nested.extend(packet(12, &hw(&[4, 5])));
+        let (_, parsed) = special_graphic_symbol(&packet(24, &nested)).unwrap();
+        let SymPacketData::SpecialGraphicSymbol(scit) = parsed else {
+            panic!("expected a special symbol packet");
+        };
+        match &scit.nested_packets()[..] {
+            [SymPacketData::SpecialGraphicSymbol(SpecialSymbolPacket::StiCircle { symbols }), SymPacketData::SpecialGraphicSymbol(SpecialSymbolPacket::Position { .. })] => {
+                assert_eq!(symbols[0].radius, 3);
+            }
+            other => panic!("expected an STI circle and a position, got {other:?}"),
+        }
+        assert!(SpecialSymbolPacket::StormId { symbols: vec![] }.nested_packets().is_empty());
+    }
+
+    #[test]
+    fn encodes_back_to_the_same_bytes() {
+        let mut storm_id = hw(&[10, 20]);
+        storm_id.extend_from_slice(b"A1");
+        let cases = [
+            packet(3, &hw(&[100, 200, 12])),
+            packet(26, &hw(&[5, 6])),
+            packet(15, &storm_id),
+            packet(19, &hw(&[1, 2, 70, 30, 2])),
+            packet(20, &hw(&[1, 2, 3, 40])),
+            packet(25, &hw(&[1, 2, 3])),
+            packet(24, &hw(&[25, 6, 1, 2, 3])),
+        ];
+        for bytes in cases {
+            let (_, parsed) = special_graphic_symbol(&bytes).unwrap();
+            let mut out = Vec::new();
+            parsed.encode(&mut out).unwrap();
+            assert_eq!(out, bytes, "{parsed:?}");
+        }
+    }
+
+    #[test]
+    fn a_storm_id_must_be_two_characters() {
+        let packet = SpecialSymbolPacket::StormId {
+            symbols: vec![StormIdSymbol { storm_id: "A12".into(), ..Default::default() }],
+        };
+        assert!(matches!(packet.encode(&mut Vec::new()), Err(Error::FieldWidth { .. })));
+    }
+
     #[test]
     fn rejects_a_truncated_symbol_block() {
         let bytes = hw(&[3, 12, 1, 2]);
@@ -4,7 +4,9 @@ use nom::{
 };
 
 
-use crate::{codes::PacketCode, product_symbology::SymPacketData};
+use crate::{codes::PacketCode, diagnostics::failure, product_symbology::SymPacketData};
+use crate::encode::{put_i16, put_i16s, with_i16_length, Encode};
+use crate::error_r::Error;
 
 /// Text and Special Symbol Packets - Packet Codes 1, 2, and 8
 /// Figure 3-8b, page 3-88
@@ -28,11 +30,11 @@ pub fn text_and_symbol(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     // j coordinate, & maybe color_level which adds up to 4 (or 6) bytes so the
     // text string must be length minus that offset of 4 (or 6).
     let text_len = (length as usize).checked_sub(offset).ok_or_else(|| {
-        nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Fail))
+        failure(input, format!("Text packet declares {length} bytes but needs at least {offset}"))
     })?;
     let (input, part) = take(text_len)(input)?;
     let text = std::str::from_utf8(part)
-        .map_err(|_| nom::Err::Failure(nom::error::Error::new(part, nom::error::ErrorKind::Fail)))?
+        .map_err(|e| failure(part, format!("Text packet expected UTF-8 text but found {e}")))?
         .to_string();
 
     let packet = TextPacket {
@@ -72,6 +74,42 @@ pub struct TextPacket {
     pub text: String,
 }
 
+impl TextPacket {
+    /// A text packet at `(i, j)`: code 8 when it has a colour level, and code 1
+    /// when it does not.
+    pub fn new(i_coord: i16, j_coord: i16, text: impl Into<String>, color_level: Option<i16>) -> Self {
+        TextPacket {
+            packet_code: if color_level.is_some() {
+                PacketCode::TextAndSpecialSymbol8
+            } else {
+                PacketCode::TextAndSpecialSymbol1
+            },
+            length: 0,
+            color_level,
+            i_coord,
+            j_coord,
+            text: text.into(),
+        }
+    }
+}
+
+/// Writes the packet under its own `packet_code`, with `length` recomputed.
+/// Only code 8 carries a colour level, so `color_level` is written for that
+/// code alone (as 0 if unset).
+impl Encode for TextPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, self.packet_code as i16);
+        with_i16_length(out, "Text packet", |out| {
+            if self.packet_code == PacketCode::TextAndSpecialSymbol8 {
+                put_i16(out, self.color_level.unwrap_or_default());
+            }
+            put_i16s(out, &[self.i_coord, self.j_coord]);
+            out.extend_from_slice(self.text.as_bytes());
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,6 +141,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn encodes_back_to_the_same_bytes() {
+        let bytes = sample_packet1("HELLO");
+        let (_, parsed) = text_and_symbol(&bytes).unwrap();
+        let SymPacketData::TextAndSpecialSymbol1(packet) = parsed else { unreachable!() };
+        let mut out = Vec::new();
+        packet.encode(&mut out).unwrap();
+        assert_eq!(out, bytes);
+    }
+
     #[test]
     fn rejects_a_length_too_short_to_hold_the_fixed_fields_instead_of_panicking() {
         // length=2 is smaller than the 4-byte fixed-field offset for packet
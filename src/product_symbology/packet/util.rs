@@ -4,17 +4,13 @@ use nom::{
     number::{complete::i16 as nom_i16, Endianness::Big},
     IResult,
 };
-use tracing::error;
+use crate::diagnostics::failure;
 
-/// Builds a `nom` failure anchored at `input`, for the cases where a packet's
+/// Builds a `nom` failure anchored at `input` (see [`failure`]), for the cases where a packet's
 /// own declared sizes are internally inconsistent (as opposed to the input
 /// simply being too short, which `take` reports on its own).
 pub fn fail<'a, T>(input: &'a [u8], message: &str) -> IResult<&'a [u8], T> {
-    error!("{message}");
-    Err(nom::Err::Failure(nom::error::Error::new(
-        input,
-        nom::error::ErrorKind::Fail,
-    )))
+    Err(failure(input, message))
 }
 
 /// Reads a packet's "length of block" halfword and converts it to a usize.
@@ -44,6 +40,15 @@ pub fn i16_array(bytes: &[u8]) -> Vec<i16> {
         .collect()
 }
 
+/// Reads `bytes` as 8-bit characters, one `char` per byte (ISO 8859-1).
+///
+/// The ICD only promises ASCII, but unlike a lossy UTF-8 decode this keeps
+/// every byte, so [`crate::encode::latin1_bytes`] writes back exactly what
+/// was read.
+pub fn latin1_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
 /// Decodes a run-length-encoded byte stream where each byte packs a 4-bit run
 /// count in the high nibble and a 4-bit level in the low nibble.
 ///
@@ -10,6 +10,8 @@ use nom::{
 };
 
 use super::util::{block_length, fail, i16_array, payload};
+use crate::encode::{put_i16, put_i16s, with_i16_length, Encode};
+use crate::error_r::Error;
 use crate::product_symbology::SymPacketData;
 
 /// A point in the product's I/J coordinate space (Km/4 or screen pixels).
@@ -208,9 +210,76 @@ pub fn contour_vector(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     }
 }
 
+fn put_points(out: &mut Vec<u8>, points: &[Point]) {
+    for p in points {
+        put_i16s(out, &[p.i, p.j]);
+    }
+}
+
+fn put_vectors(out: &mut Vec<u8>, vectors: &[Vector]) {
+    for v in vectors {
+        put_i16s(out, &[v.begin.i, v.begin.j, v.end.i, v.end.j]);
+    }
+}
+
+/// Written as code 9 when `value` is set and code 6 otherwise.
+impl Encode for LinkedVectorPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, if self.value.is_some() { 9 } else { 6 });
+        with_i16_length(out, "Linked vector packet", |out| {
+            if let Some(value) = self.value {
+                put_i16(out, value);
+            }
+            put_points(out, &[self.start]);
+            put_points(out, &self.ends);
+            Ok(())
+        })
+    }
+}
+
+/// Written as code 10 when `value` is set and code 7 otherwise.
+impl Encode for UnlinkedVectorPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, if self.value.is_some() { 10 } else { 7 });
+        with_i16_length(out, "Unlinked vector packet", |out| {
+            if let Some(value) = self.value {
+                put_i16(out, value);
+            }
+            put_vectors(out, &self.vectors);
+            Ok(())
+        })
+    }
+}
+
+impl Encode for ContourVectorPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        match self {
+            ContourVectorPacket::SetColorLevel { indicator, value } => {
+                put_i16s(out, &[0x0802, *indicator, *value]);
+                Ok(())
+            }
+            ContourVectorPacket::Linked { indicator, start, ends } => {
+                put_i16s(out, &[0x0E03, *indicator, start.i, start.j]);
+                with_i16_length(out, "Linked contour vector packet", |out| {
+                    put_points(out, ends);
+                    Ok(())
+                })
+            }
+            ContourVectorPacket::Unlinked { vectors } => {
+                put_i16(out, 0x3501);
+                with_i16_length(out, "Unlinked contour vector packet", |out| {
+                    put_vectors(out, vectors);
+                    Ok(())
+                })
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::product_symbology::symbology_layer_packet;
 
     fn hw(values: &[i16]) -> Vec<u8> {
         values.iter().flat_map(|v| v.to_be_bytes()).collect()
@@ -342,6 +411,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn vectors_encode_back_to_the_same_bytes() {
+        let cases = [
+            hw(&[9, 14, 7, 1, 2, 3, 4, 5, 6]),
+            hw(&[7, 8, 1, 2, 3, 4]),
+            hw(&[10, 10, 3, 1, 2, 3, 4]),
+            hw(&[0x0802u16 as i16, 0x0002, 12]),
+            hw(&[0x0E03u16 as i16, 0x8000u16 as i16, 10, 20, 4, 30, 40]),
+            hw(&[0x3501u16 as i16, 8, 1, 2, 3, 4]),
+        ];
+        for bytes in cases {
+            let (_, packet) = symbology_layer_packet(&bytes).unwrap();
+            let mut out = Vec::new();
+            packet.encode(&mut out).unwrap();
+            assert_eq!(out, bytes, "{packet:?}");
+        }
+    }
+
     #[test]
     fn rejects_a_negative_block_length() {
         let bytes = hw(&[6, -8, 0, 0]);
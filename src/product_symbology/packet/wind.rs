@@ -10,6 +10,8 @@ use nom::{
 };
 
 use super::util::{block_length, i16_array, payload};
+use crate::encode::{put_i16, put_i16s, with_i16_length, Encode};
+use crate::error_r::Error;
 use crate::product_symbology::SymPacketData;
 
 /// One arrow from a Vector Arrow Data Packet (Figure 3-12).
@@ -99,9 +101,34 @@ pub fn wind_barb(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     Ok((input, SymPacketData::WindBarbData(WindBarbPacket { barbs })))
 }
 
+impl Encode for VectorArrowPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, 5);
+        with_i16_length(out, "Vector arrow packet", |out| {
+            for a in &self.arrows {
+                put_i16s(out, &[a.i_coord, a.j_coord, a.direction, a.arrow_length, a.arrow_head_length]);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Encode for WindBarbPacket {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_i16(out, 4);
+        with_i16_length(out, "Wind barb packet", |out| {
+            for b in &self.barbs {
+                put_i16s(out, &[b.value, b.x_coord, b.y_coord, b.direction, b.speed]);
+            }
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::product_symbology::symbology_layer_packet;
 
     fn hw(values: &[i16]) -> Vec<u8> {
         values.iter().flat_map(|v| v.to_be_bytes()).collect()
@@ -147,6 +174,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn encodes_back_to_the_same_bytes() {
+        let arrows = hw(&[5, 10, 100, 200, 270, 40, 10]);
+        let barbs = hw(&[4, 10, 3, 10, 20, 180, 45]);
+        for bytes in [arrows, barbs] {
+            let (_, packet) = symbology_layer_packet(&bytes).unwrap();
+            let mut out = Vec::new();
+            packet.encode(&mut out).unwrap();
+            assert_eq!(out, bytes);
+        }
+    }
+
     #[test]
     fn rejects_a_truncated_barb_block() {
         let bytes = hw(&[4, 10, 1, 2]);
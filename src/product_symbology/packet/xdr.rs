@@ -1,5 +1,5 @@
-//! A minimal reader for External Data Representation (XDR) encoded data,
-//! per RFC 1832 / RFC 4506.
+//! A minimal reader and writer for External Data Representation (XDR)
+//! encoded data, per RFC 1832 / RFC 4506.
 //!
 //! The Generic Data Packet (codes 28 and 29, Figure 3-15c) carries its
 //! payload as XDR, so only the subset the Generic Product Format of Appendix
@@ -60,6 +60,11 @@ impl<'a> XdrReader<'a> {
         self.data.len().saturating_sub(self.pos)
     }
 
+    /// The bytes not yet consumed.
+    pub fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos.min(self.data.len())..]
+    }
+
     /// Whether the whole stream has been consumed.
     pub fn is_done(&self) -> bool {
         self.remaining() == 0
@@ -162,6 +167,69 @@ impl<'a> XdrReader<'a> {
     }
 }
 
+/// Builds an XDR byte stream, the inverse of [`XdrReader`].
+#[derive(Debug, Default)]
+pub struct XdrWriter {
+    out: Vec<u8>,
+}
+
+impl XdrWriter {
+    pub fn new() -> Self {
+        XdrWriter::default()
+    }
+
+    /// The bytes written so far.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.out
+    }
+
+    /// Writes a 4-byte signed integer.
+    pub fn int(&mut self, value: i32) {
+        self.out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Writes a 4-byte unsigned integer.
+    pub fn uint(&mut self, value: u32) {
+        self.out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Writes a 4-byte IEEE-754 float.
+    pub fn float(&mut self, value: f32) {
+        self.out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Writes a counted string, zero-padded to a 4-byte boundary. No NUL
+    /// terminator is added.
+    pub fn string(&mut self, value: &str) {
+        self.int(value.len() as i32);
+        self.out.extend_from_slice(value.as_bytes());
+        self.out.resize(self.out.len().next_multiple_of(4), 0);
+    }
+
+    /// Writes a counted array of 4-byte signed integers.
+    pub fn int_array(&mut self, values: &[i32]) {
+        self.int(values.len() as i32);
+        self.ints(values);
+    }
+
+    /// Writes a counted array of 4-byte floats.
+    pub fn float_array(&mut self, values: &[f32]) {
+        self.int(values.len() as i32);
+        values.iter().for_each(|v| self.float(*v));
+    }
+
+    /// Writes a counted array of strings.
+    pub fn string_array(&mut self, values: &[String]) {
+        self.int(values.len() as i32);
+        values.iter().for_each(|v| self.string(v));
+    }
+
+    /// Writes signed integers without a leading count.
+    pub fn ints(&mut self, values: &[i32]) {
+        values.iter().for_each(|v| self.int(*v));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -230,6 +298,24 @@ mod tests {
         assert_eq!(r.int_array().unwrap(), vec![10, 20, 30]);
     }
 
+    #[test]
+    fn the_writer_produces_what_the_reader_reads() {
+        let mut w = XdrWriter::new();
+        w.int(-7);
+        w.string("abc");
+        w.float_array(&[1.5, 2.5]);
+        w.string_array(&["abcd".to_string(), String::new()]);
+        let bytes = w.into_bytes();
+        assert_eq!(bytes.len() % 4, 0);
+
+        let mut r = XdrReader::new(&bytes);
+        assert_eq!(r.int().unwrap(), -7);
+        assert_eq!(r.string().unwrap(), "abc");
+        assert_eq!(r.float_array().unwrap(), vec![1.5, 2.5]);
+        assert_eq!(r.string_array().unwrap(), vec!["abcd".to_string(), String::new()]);
+        assert!(r.is_done());
+    }
+
     #[test]
     fn reports_the_end_of_the_stream_rather_than_panicking() {
         let bytes = [0u8, 0, 0];
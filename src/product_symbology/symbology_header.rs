@@ -4,7 +4,9 @@ use nom::{
     number::Endianness::Big,
     IResult,
 };
-use tracing::{error, info};
+use tracing::info;
+
+use crate::diagnostics::{failure, report, Severity};
 
 /// Graphic Product Message: Product Symbology Block
 /// Description
@@ -29,15 +31,18 @@ pub struct SymbologyHeader {
 pub fn symbology_header(input: &[u8]) -> IResult<&[u8], SymbologyHeader> {
     // warn!("sym header {:?}", input);
     
+    let start = input;
     let (input, divider) = nom_i16(Big)(input)?;
     if divider != -1 {
-        error!("Block divider error");
+        report(
+            Severity::Error,
+            start,
+            format!("Symbology block divider should be -1 but found {divider}"),
+        );
     }
     let (input, id) = nom_i16(Big)(input)?;
     if id != 1 {
-        let e = nom::error::Error::new(input, nom::error::ErrorKind::Fail);
-        error!("Product symbology header should have ID=1 but found {}", id);
-        return Err(nom::Err::Failure(e));
+        return Err(failure(input, format!("Product symbology header should have ID=1 but found {}", id)));
     }
     let (input, block_length) = nom_i32(Big)(input)?;
     let (input, layers) = nom_i16(Big)(input)?;
@@ -1,12 +1,13 @@
 use nom::{
-    combinator::peek, IResult, Parser,
+    bytes::complete::take, combinator::peek, IResult, Parser,
     number::complete::{i16 as nom_i16, i32 as nom_i32},
     number::Endianness::Big,
 };
 
-use tracing::{debug, error, info,};
+use tracing::{debug, info};
 
 use crate::codes::PacketCode;
+use crate::diagnostics::{self, failure, report, Severity};
 
 use super::{SymPacketData, packet::*};
 
@@ -14,25 +15,75 @@ use super::{SymPacketData, packet::*};
 /// layout has not been implemented yet. See the module-level docs for the
 /// list of packet codes that are currently supported.
 fn unsupported_packet(input: &[u8], packet_code: PacketCode) -> IResult<&[u8], SymPacketData> {
-    error!("Packet code {:?} is not yet implemented in this crate", packet_code);
-    Err(nom::Err::Failure(nom::error::Error::new(
-        input,
-        nom::error::ErrorKind::Fail,
-    )))
+    Err(failure(input, format!("Packet code {:?} is not yet implemented in this crate", packet_code)))
 }
 
-/// Parses one symbology data layer: the layer divider and length, then the
-/// single display data packet the layer holds.
-pub fn symbology_layer(input: &[u8]) -> IResult<&[u8], SymPacketData> {
+/// Parses one symbology data layer: the layer divider and length, then every
+/// display data packet the layer holds.
+///
+/// Packets are dispatched one after another until `layer_length` bytes have
+/// been used. A layer whose last packet runs past the declared length, or
+/// which ends in padding that holds no packet, is logged and accepted, with
+/// parsing resuming after whichever of the two ends is further on.
+pub fn symbology_layer(input: &[u8]) -> IResult<&[u8], Vec<SymPacketData>> {
 
+    let start = input;
     let (input, layer_divider) = nom_i16(Big)(input)?;
     if layer_divider != -1 {
-        error!("Symbology layer divider error. Found {} but expected -1", layer_divider);
+        report(
+            Severity::Error,
+            start,
+            format!("Symbology layer divider should be -1 but found {layer_divider}"),
+        );
     }
-    let (input, layer_length) = nom_i32(Big)(input)?;
+    let (mut input, layer_length) = nom_i32(Big)(input)?;
+    let Ok(mut remaining) = usize::try_from(layer_length) else {
+        return Err(failure(input, format!("Symbology layer declares a negative length ({})", layer_length)));
+    };
     info!("Layer data section is {} bytes.", layer_length);
 
-    symbology_layer_packet(input)
+    let mut packets = Vec::new();
+    while remaining > 0 {
+        // Too little left for a packet code, or nothing but zero padding.
+        let tail = &input[..remaining.min(input.len())];
+        if remaining < 2 || tail.iter().all(|&b| b == 0) {
+            report(
+                Severity::Warning,
+                input,
+                format!(
+                    "Symbology layer under-ran its declared length: {} byte(s) left after {} packet(s)",
+                    remaining,
+                    packets.len()
+                ),
+            );
+            let (rest, _) = take(remaining).parse(input)?;
+            input = rest;
+            break;
+        }
+
+        let (rest, packet) = symbology_layer_packet(input)?;
+        let used = input.len() - rest.len();
+        packets.push(packet);
+
+        if used > remaining {
+            report(
+                Severity::Error,
+                &input[remaining..],
+                format!(
+                    "Symbology layer over-ran its declared length of {} bytes by {} byte(s)",
+                    layer_length,
+                    used - remaining
+                ),
+            );
+            input = rest;
+            break;
+        }
+        input = rest;
+        remaining -= used;
+    }
+    debug!("Layer held {} packet(s)", packets.len());
+
+    Ok((input, packets))
 }
 
 /// Parses a single display data packet, dispatching on its leading packet
@@ -47,6 +98,7 @@ pub fn symbology_layer_packet(input: &[u8]) -> IResult<&[u8], SymPacketData> {
     let packet_code = <PacketCode as num::FromPrimitive>::from_i16(packet_code_int).unwrap_or_default();
 
     debug!("Packet Code {:?}", packet_code);
+    diagnostics::set_packet(Some(packet_code));
 
     let (input, symbology) = match packet_code {
         // Text and special symbols (Figure 3-8b)
@@ -167,21 +219,21 @@ mod tests {
         bytes.extend_from_slice(&packet);
 
         let (_, parsed) = symbology_layer(&bytes).unwrap();
-        assert!(matches!(parsed, SymPacketData::MapMessage(_)));
+        assert!(matches!(parsed[..], [SymPacketData::MapMessage(_)]));
     }
 
     #[test]
     fn dispatches_a_wind_barb_packet() {
         let bytes = layer_with_block(4, &hw(&[3, 10, 20, 180, 45]));
         let (_, parsed) = symbology_layer(&bytes).unwrap();
-        assert!(matches!(parsed, SymPacketData::WindBarbData(_)));
+        assert!(matches!(parsed[..], [SymPacketData::WindBarbData(_)]));
     }
 
     #[test]
     fn dispatches_a_linked_vector_packet() {
         let bytes = layer_with_block(6, &hw(&[10, 20, 30, 40]));
         let (_, parsed) = symbology_layer(&bytes).unwrap();
-        assert!(matches!(parsed, SymPacketData::LinkedVector(_)));
+        assert!(matches!(parsed[..], [SymPacketData::LinkedVector(_)]));
     }
 
     #[test]
@@ -192,7 +244,7 @@ mod tests {
             &b.clone()
         });
         let (_, parsed) = symbology_layer(&bytes).unwrap();
-        assert!(matches!(parsed, SymPacketData::SpecialGraphicSymbol(_)));
+        assert!(matches!(parsed[..], [SymPacketData::SpecialGraphicSymbol(_)]));
     }
 
     #[test]
@@ -202,7 +254,7 @@ mod tests {
         body.extend_from_slice(&hw(&[0, 0]));
         let bytes = layer_with_block(21, &body);
         let (_, parsed) = symbology_layer(&bytes).unwrap();
-        assert!(matches!(parsed, SymPacketData::CellTrendData(_)));
+        assert!(matches!(parsed[..], [SymPacketData::CellTrendData(_)]));
     }
 
     /// Packet 28 is XDR-encoded; its header length field is what frames it.
@@ -220,6 +272,102 @@ mod tests {
         bytes.extend_from_slice(&packet);
 
         let (_, parsed) = symbology_layer(&bytes).unwrap();
-        assert!(matches!(parsed, SymPacketData::GenericData(_)));
+        assert!(matches!(parsed[..], [SymPacketData::GenericData(_)]));
+    }
+
+    /// Builds a layer around the given already-encoded packets.
+    fn layer_of(packets: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = packets.concat();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(-1i16).to_be_bytes());
+        bytes.extend_from_slice(&(body.len() as i32).to_be_bytes());
+        bytes.extend_from_slice(&body);
+        bytes
     }
-}
\ No newline at end of file
+
+    fn packet_with_block(packet_code: i16, body: &[u8]) -> Vec<u8> {
+        let mut packet = packet_code.to_be_bytes().to_vec();
+        packet.extend_from_slice(&(body.len() as i16).to_be_bytes());
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    /// STI, hail index and the VWP put many packets in a single layer; every
+    /// one of them must come back, in order.
+    #[test]
+    fn parses_every_packet_in_a_layer() {
+        let mut storm_id = hw(&[10, 20]);
+        storm_id.extend_from_slice(b"A1");
+        let bytes = layer_of(&[
+            packet_with_block(15, &storm_id),
+            packet_with_block(4, &hw(&[3, 10, 20, 180, 45])),
+            packet_with_block(6, &hw(&[10, 20, 30, 40])),
+        ]);
+
+        let (rest, parsed) = symbology_layer(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert!(matches!(
+            parsed[..],
+            [
+                SymPacketData::SpecialGraphicSymbol(_),
+                SymPacketData::WindBarbData(_),
+                SymPacketData::LinkedVector(_),
+            ]
+        ));
+    }
+
+    /// The layer length, not the packets, decides where the next layer
+    /// starts, so bytes after the layer are left for the caller.
+    #[test]
+    fn stops_at_the_declared_layer_length() {
+        let mut bytes = layer_of(&[packet_with_block(6, &hw(&[10, 20, 30, 40]))]);
+        bytes.extend_from_slice(&hw(&[-1, 0]));
+
+        let (rest, parsed) = symbology_layer(&bytes).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(rest, &hw(&[-1, 0])[..]);
+    }
+
+    #[test]
+    fn an_empty_layer_holds_no_packets() {
+        let bytes = layer_of(&[]);
+        let (rest, parsed) = symbology_layer(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert!(parsed.is_empty());
+    }
+
+    /// Zero padding after the last packet is skipped rather than being
+    /// dispatched as packet code 0.
+    #[test]
+    fn trailing_padding_is_skipped() {
+        let mut packet = packet_with_block(6, &hw(&[10, 20, 30, 40]));
+        packet.extend_from_slice(&[0, 0, 0, 0]);
+        let mut bytes = layer_of(&[packet]);
+        bytes.extend_from_slice(&hw(&[7]));
+
+        let (rest, parsed) = symbology_layer(&bytes).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(rest, &hw(&[7])[..]);
+    }
+
+    /// A layer length too short for its packet still yields the packet, and
+    /// parsing resumes after the packet rather than inside it.
+    #[test]
+    fn a_packet_overrunning_the_layer_is_kept() {
+        let packet = packet_with_block(6, &hw(&[10, 20, 30, 40]));
+        let mut bytes = hw(&[-1]);
+        bytes.extend_from_slice(&4i32.to_be_bytes());
+        bytes.extend_from_slice(&packet);
+
+        let (rest, parsed) = symbology_layer(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert!(matches!(parsed[..], [SymPacketData::LinkedVector(_)]));
+    }
+
+    #[test]
+    fn a_negative_layer_length_fails() {
+        let mut bytes = hw(&[-1]);
+        bytes.extend_from_slice(&(-4i32).to_be_bytes());
+        assert!(symbology_layer(&bytes).is_err());
+    }
+}
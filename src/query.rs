@@ -0,0 +1,282 @@
+//! Point and transect queries against the first radial layer of a product.
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_field::decode_level;
+use crate::{BeamHeight, CellMask, Error, LatLon, Radar, Sweep, EARTH_RADIUS};
+
+/// Where a query landed: the bin, and where that bin is.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+pub struct BinSample {
+    /// Index of the radial in its packet.
+    pub radial: usize,
+    /// Index of the bin along the radial.
+    pub bin: usize,
+    /// Azimuth of the centre of the radial, degrees clockwise from north.
+    pub azimuth: f64,
+    /// Slant range to the centre of the bin, metres.
+    pub range: f64,
+    /// Heights of the beam at the bin, metres above sea level.
+    pub height: BeamHeight,
+    /// The bin's raw data level.
+    pub level: u8,
+}
+
+/// What a product holds at a point.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum PointValue {
+    /// A measurement, in the units of the product's level scaling.
+    Value { value: f32, bin: BinSample },
+    /// The bin is below the product's threshold.
+    BelowThreshold(BinSample),
+    /// The bin is range folded.
+    RangeFolded(BinSample),
+    /// The bin holds some other flag, or a level that decodes to nothing.
+    NoData(BinSample),
+    /// The point is outside every radial, or nearer or further than the
+    /// product's bins reach.
+    OutsideCoverage,
+}
+
+impl PointValue {
+    /// The measurement, if there is one.
+    pub fn value(&self) -> Option<f32> {
+        match self {
+            PointValue::Value { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The bin the point fell in, unless it was outside coverage.
+    pub fn bin(&self) -> Option<&BinSample> {
+        match self {
+            PointValue::Value { bin, .. }
+            | PointValue::BelowThreshold(bin)
+            | PointValue::RangeFolded(bin)
+            | PointValue::NoData(bin) => Some(bin),
+            PointValue::OutsideCoverage => None,
+        }
+    }
+}
+
+/// One point of a [`Radar::transect`].
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct TransectPoint {
+    pub position: LatLon,
+    /// Distance along the transect from its start, metres.
+    pub distance: f64,
+    pub value: PointValue,
+}
+
+impl Sweep {
+    /// What the sweep holds at `point`.
+    pub fn value_at(&self, point: LatLon) -> PointValue {
+        let Some((radial, bin)) = self.geometry.bin_at(point) else {
+            return PointValue::OutsideCoverage;
+        };
+        let Some(level) = self.levels.get(radial).and_then(|levels| levels.get(bin)).copied() else {
+            return PointValue::OutsideCoverage;
+        };
+        let span = self.geometry.radials[radial];
+        let sample = BinSample {
+            radial,
+            bin,
+            azimuth: span.azimuth + span.width / 2.0,
+            range: self.geometry.bin_range(bin),
+            height: self.geometry.site.beam(self.geometry.bin_range(bin), span.elevation),
+            level,
+        };
+
+        match decode_level(level as i32, self.scaling.as_ref()) {
+            (value, CellMask::Valid) => PointValue::Value { value, bin: sample },
+            (_, CellMask::BelowThreshold) => PointValue::BelowThreshold(sample),
+            (_, CellMask::RangeFolded) => PointValue::RangeFolded(sample),
+            (_, CellMask::NoData) => PointValue::NoData(sample),
+        }
+    }
+
+    /// What the sweep holds at `n` points evenly spaced along the great
+    /// circle from `start` to `end`, both included.
+    ///
+    /// Endpoints that are antipodal, or within [`ANTIPODAL_TOLERANCE`] of it,
+    /// have no one great circle between them, so every point is
+    /// [`PointValue::OutsideCoverage`] and sits at `start`, or at `end` for
+    /// the last, with its distance measured along any half circle.
+    pub fn transect(&self, start: LatLon, end: LatLon, n: usize) -> Vec<TransectPoint> {
+        let (from, to) = (to_vector(start), to_vector(end));
+        let angle = dot(from, to).clamp(-1.0, 1.0).acos();
+        let antipodal = std::f64::consts::PI - angle < ANTIPODAL_TOLERANCE;
+        (0..n)
+            .map(|i| {
+                let fraction = if n > 1 { i as f64 / (n - 1) as f64 } else { 0.0 };
+                if antipodal {
+                    return TransectPoint {
+                        position: if n > 1 && i == n - 1 { end } else { start },
+                        distance: fraction * angle * EARTH_RADIUS,
+                        value: PointValue::OutsideCoverage,
+                    };
+                }
+                let position = if angle == 0.0 {
+                    start
+                } else {
+                    // Spherical linear interpolation between the endpoints.
+                    let a = ((1.0 - fraction) * angle).sin() / angle.sin();
+                    let b = (fraction * angle).sin() / angle.sin();
+                    to_lat_lon([
+                        a * from[0] + b * to[0],
+                        a * from[1] + b * to[1],
+                        a * from[2] + b * to[2],
+                    ])
+                };
+                TransectPoint {
+                    position,
+                    distance: fraction * angle * EARTH_RADIUS,
+                    value: self.value_at(position),
+                }
+            })
+            .collect()
+    }
+}
+
+/// How close to antipodal, in radians of arc, two transect endpoints may be
+/// before [`Sweep::transect`] stops interpolating between them: about 6 m.
+pub const ANTIPODAL_TOLERANCE: f64 = 1e-6;
+
+fn to_vector(point: LatLon) -> [f64; 3] {
+    let (latitude, longitude) = (point.latitude.to_radians(), point.longitude.to_radians());
+    [latitude.cos() * longitude.cos(), latitude.cos() * longitude.sin(), latitude.sin()]
+}
+
+fn to_lat_lon(v: [f64; 3]) -> LatLon {
+    LatLon {
+        latitude: v[2].atan2(v[0].hypot(v[1])).to_degrees(),
+        longitude: v[1].atan2(v[0]).to_degrees(),
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+impl Radar {
+    /// What the first radial layer holds at `latitude`, `longitude`: the
+    /// decoded value, or why there is none, with the bin's azimuth, range
+    /// and beam height.
+    ///
+    /// Decodes the layer for each call; use [`Radar::sweep`] and
+    /// [`Sweep::value_at`] to query one product many times.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::sweep`].
+    pub fn value_at(&self, latitude: f64, longitude: f64) -> Result<PointValue, Error> {
+        Ok(self.sweep()?.value_at(LatLon { latitude, longitude }))
+    }
+
+    /// What the first radial layer holds at `n` points evenly spaced along
+    /// the great circle from `start` to `end`, both included.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::sweep`].
+    pub fn transect(&self, start: LatLon, end: LatLon, n: usize) -> Result<Vec<TransectPoint>, Error> {
+        Ok(self.sweep()?.transect(start, end, n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(bytes: &[u8]) -> Radar {
+        Radar::from_vec(bytes.to_vec()).unwrap().1
+    }
+
+    #[test]
+    fn a_point_reads_back_the_bin_it_falls_in() {
+        let radar = load(include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"));
+        let sweep = radar.sweep().unwrap();
+        let scaling = sweep.scaling.clone().unwrap();
+
+        let (radial, bin) = (0..sweep.levels.len())
+            .flat_map(|r| (0..sweep.geometry.num_bins).map(move |b| (r, b)))
+            .find(|&(r, b)| scaling.value(sweep.levels[r][b]).is_some())
+            .unwrap();
+        let centre = sweep.geometry.bin_centre(radial, bin).unwrap();
+        let found = radar.value_at(centre.latitude, centre.longitude).unwrap();
+
+        let sample = found.bin().unwrap();
+        assert_eq!((sample.radial, sample.bin), (radial, bin));
+        assert_eq!(found.value(), scaling.value(sweep.levels[radial][bin]));
+        assert_eq!(sample.range, sweep.geometry.bin_range(bin));
+        assert!(sample.height.bottom < sample.height.centre && sample.height.centre < sample.height.top);
+    }
+
+    #[test]
+    fn flags_are_reported_as_outcomes() {
+        let radar = load(include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"));
+        let mut sweep = radar.sweep().unwrap();
+        let centre = sweep.geometry.bin_centre(10, 20).unwrap();
+
+        sweep.levels[10][20] = 0;
+        assert!(matches!(sweep.value_at(centre), PointValue::BelowThreshold(b) if b.level == 0));
+        sweep.levels[10][20] = 1;
+        assert!(matches!(sweep.value_at(centre), PointValue::RangeFolded(_)));
+
+        let far = sweep.geometry.site.project(0.0, 1_000_000.0);
+        assert_eq!(sweep.value_at(far), PointValue::OutsideCoverage);
+        assert_eq!(sweep.value_at(far).bin(), None);
+    }
+
+    #[test]
+    fn threshold_table_products_report_their_codes() {
+        let radar = load(include_bytes!("../data/sn_DS.p20-r_kmkx.last"));
+        let sweep = radar.sweep().unwrap();
+        let scaling = sweep.scaling.as_ref().unwrap();
+        for (radial, levels) in sweep.levels.iter().enumerate().take(20) {
+            for (bin, level) in levels.iter().enumerate() {
+                let found = sweep.value_at(sweep.geometry.bin_centre(radial, bin).unwrap());
+                match scaling.value(*level) {
+                    Some(value) => assert_eq!(found.value(), Some(value)),
+                    None => assert!(!matches!(found, PointValue::Value { .. } | PointValue::OutsideCoverage)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_transect_samples_evenly_between_its_ends() {
+        let radar = load(include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last"));
+        let site = radar.sweep().unwrap().geometry.site;
+        let start = site.project(270.0, 50_000.0);
+        let end = site.project(90.0, 50_000.0);
+
+        let transect = radar.transect(start, end, 11).unwrap();
+        assert_eq!(transect.len(), 11);
+        assert_eq!(transect[0].position, start);
+        assert!((transect[10].position.latitude - end.latitude).abs() < 1e-9);
+        assert!((transect[10].position.longitude - end.longitude).abs() < 1e-9);
+        assert!((transect[10].distance - 100_000.0).abs() < 50.0);
+        // The midpoint is over the radar, in the first bin.
+        assert_eq!(transect[5].value.bin().map(|b| b.bin), Some(0));
+
+        let beyond = radar.transect(site.project(0.0, 300_000.0), site.project(0.0, 400_000.0), 3).unwrap();
+        assert!(beyond.iter().all(|p| p.value == PointValue::OutsideCoverage));
+        assert_eq!(radar.transect(start, end, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn antipodal_endpoints_are_outside_coverage() {
+        let radar = load(include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last"));
+        let site = radar.sweep().unwrap().geometry.site;
+        let start = LatLon { latitude: site.latitude, longitude: site.longitude };
+        let end = LatLon { latitude: -site.latitude, longitude: site.longitude - 180.0 };
+
+        let transect = radar.transect(start, end, 5).unwrap();
+        assert!(transect.iter().all(|p| p.value == PointValue::OutsideCoverage));
+        assert_eq!(transect[0].position, start);
+        assert_eq!(transect[2].position, start);
+        assert_eq!(transect[4].position, end);
+        assert!((transect[4].distance - std::f64::consts::PI * EARTH_RADIUS).abs() < 1.0);
+    }
+}
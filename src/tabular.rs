@@ -0,0 +1,441 @@
+//! A flat "one row per bin" table of a radial product, for pandas, DuckDB and
+//! the like.
+//!
+//! [`Radar::bin_rows`] walks the first radial packet bin by bin, straight
+//! from the parsed radials, so a super-resolution product is not decoded into
+//! a second full-size array on the way out. [`Radar::write_bins_csv`] writes
+//! the rows as CSV and, with the `arrow` feature, [`Radar::write_bins_arrow`]
+//! as an Apache Arrow IPC stream.
+
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_field::decode_level;
+use crate::{
+    CellMask, DigitalRadialPacket, Error, GenericComponent, LevelScaling, RadialComponent, RadialGeometry,
+    RadialPacket, Radar, SymPacketData,
+};
+
+/// The table's columns, in order.
+pub const BIN_COLUMNS: [&str; 8] = [
+    "azimuth",
+    "range_km",
+    "lat",
+    "lon",
+    "beam_height_m",
+    "raw_level",
+    "value",
+    "flag",
+];
+
+/// Why a bin is flagged.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinFlag {
+    /// Below the product's threshold; there is no value.
+    BelowThreshold,
+    /// Range folded; there is no value.
+    RangeFolded,
+    /// Product 135's echo top reached the highest elevation scanned; the
+    /// value is a lower bound.
+    Topped,
+    /// Any other flag, a level that decodes to nothing, or a bin past the
+    /// end of a short radial.
+    NoData,
+}
+
+impl BinFlag {
+    /// The flag as it is written in the `flag` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinFlag::BelowThreshold => "below_threshold",
+            BinFlag::RangeFolded => "range_folded",
+            BinFlag::Topped => "topped",
+            BinFlag::NoData => "no_data",
+        }
+    }
+}
+
+/// One bin of a radial product.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct BinRow {
+    /// Index of the radial in its packet.
+    pub radial: usize,
+    /// Index of the bin along the radial.
+    pub bin: usize,
+    /// Azimuth of the centre of the radial, degrees clockwise from north.
+    pub azimuth: f64,
+    /// Slant range to the centre of the bin, kilometres.
+    pub range_km: f64,
+    /// Latitude of the point below the bin's centre, degrees.
+    pub latitude: f64,
+    /// Longitude of the point below the bin's centre, degrees.
+    pub longitude: f64,
+    /// Height of the beam centre at the bin, metres above sea level.
+    pub beam_height_m: f64,
+    /// The bin's raw data level.
+    pub raw_level: i32,
+    /// The measurement, in the units of the product's level scaling.
+    pub value: Option<f32>,
+    pub flag: Option<BinFlag>,
+}
+
+/// The packet [`BinRows`] reads levels from.
+enum Levels<'a> {
+    Runs(&'a RadialPacket),
+    Bytes(&'a DigitalRadialPacket),
+    Generic(&'a RadialComponent),
+}
+
+/// Iterator over the bins of a radial packet, radial by radial, as
+/// [`Radar::bin_rows`] returns it.
+pub struct BinRows<'a> {
+    levels: Levels<'a>,
+    geometry: RadialGeometry,
+    scaling: Option<LevelScaling>,
+    radial: usize,
+    bin: usize,
+    /// The current radial's levels, for run-length encoded packets.
+    expanded: Vec<i32>,
+}
+
+impl<'a> BinRows<'a> {
+    fn new(levels: Levels<'a>, geometry: RadialGeometry, scaling: Option<LevelScaling>) -> Self {
+        BinRows {
+            levels,
+            geometry,
+            scaling,
+            radial: 0,
+            bin: 0,
+            expanded: Vec::new(),
+        }
+    }
+
+    /// Where the bins are.
+    pub fn geometry(&self) -> &RadialGeometry {
+        &self.geometry
+    }
+
+    /// The level of the current bin, `None` past the end of a short radial.
+    fn level(&mut self) -> Option<i32> {
+        let (radial, bin) = (self.radial, self.bin);
+        match self.levels {
+            Levels::Runs(packet) => {
+                if bin == 0 {
+                    self.expanded.clear();
+                    self.expanded.extend(
+                        packet.radials[radial]
+                            .data
+                            .iter()
+                            .flat_map(|run| std::iter::repeat_n(run.color as i32, run.run as usize)),
+                    );
+                }
+                self.expanded.get(bin).copied()
+            }
+            Levels::Bytes(packet) => packet.radials[radial].data.get(bin).map(|level| *level as i32),
+            Levels::Generic(component) => component.radials[radial].data.get(bin).copied(),
+        }
+    }
+}
+
+impl Iterator for BinRows<'_> {
+    type Item = BinRow;
+
+    fn next(&mut self) -> Option<BinRow> {
+        if self.bin >= self.geometry.num_bins {
+            self.radial += 1;
+            self.bin = 0;
+        }
+        let span = *self.geometry.radials.get(self.radial)?;
+        if self.geometry.num_bins == 0 {
+            return None;
+        }
+
+        let (level, (value, mask)) = match self.level() {
+            Some(level) => (level, decode_level(level, self.scaling.as_ref())),
+            None => (0, (f32::NAN, CellMask::NoData)),
+        };
+        let topped = self
+            .scaling
+            .as_ref()
+            .zip(u8::try_from(level).ok())
+            .and_then(|(scaling, byte)| scaling.is_topped(byte))
+            .unwrap_or(false);
+        let flag = match mask {
+            CellMask::Valid if topped => Some(BinFlag::Topped),
+            CellMask::Valid => None,
+            CellMask::BelowThreshold => Some(BinFlag::BelowThreshold),
+            CellMask::RangeFolded => Some(BinFlag::RangeFolded),
+            CellMask::NoData => Some(BinFlag::NoData),
+        };
+
+        let site = &self.geometry.site;
+        let azimuth = (span.azimuth + span.width / 2.0).rem_euclid(360.0);
+        let range = self.geometry.bin_range(self.bin);
+        let position = site.locate(azimuth, range, span.elevation);
+        let row = BinRow {
+            radial: self.radial,
+            bin: self.bin,
+            azimuth,
+            range_km: range / 1000.0,
+            latitude: position.latitude,
+            longitude: position.longitude,
+            beam_height_m: site.height + site.beam_height(range, span.elevation),
+            raw_level: level,
+            value: (mask == CellMask::Valid).then_some(value),
+            flag,
+        };
+        self.bin += 1;
+        Some(row)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let bins = self.geometry.num_bins;
+        let remaining = if bins == 0 {
+            0
+        } else {
+            (self.geometry.radials.len() * bins).saturating_sub(self.radial * bins + self.bin)
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+impl Radar {
+    /// The bins of the first radial packet, in layer order: a Radial Data
+    /// packet (`AF1F`), a Digital Radial Data Array (code 16), or a generic
+    /// product's Radial Component.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::NoSymbologyData`] if there is no symbology block, and
+    /// [`Error::NoRadialData`] if no layer holds a radial packet.
+    pub fn bin_rows(&self) -> Result<BinRows<'_>, Error> {
+        let symbology = self.symbology.as_ref().ok_or(Error::NoSymbologyData)?;
+        let pd = &self.product_description;
+        let scaling = pd.level_scaling();
+        symbology
+            .layers
+            .iter()
+            .flatten()
+            .find_map(|packet| match packet {
+                SymPacketData::RadialDataAF1F(packet) => {
+                    let geometry = RadialGeometry::from_radial_packet(packet, pd);
+                    Some(BinRows::new(Levels::Runs(packet), geometry, scaling.clone()))
+                }
+                SymPacketData::DigitalRadialDataArray(packet) => {
+                    let geometry = RadialGeometry::from_digital_radial_packet(packet, pd);
+                    Some(BinRows::new(Levels::Bytes(packet), geometry, scaling.clone()))
+                }
+                SymPacketData::GenericData(packet) => {
+                    let components = match (&packet.product_description, &packet.external_description) {
+                        (Some(description), _) => &description.components,
+                        (None, Some(description)) => &description.components,
+                        (None, None) => return None,
+                    };
+                    let component = components.iter().find_map(|component| match component {
+                        GenericComponent::Radial(radial) => Some(radial),
+                        _ => None,
+                    })?;
+                    let geometry = RadialGeometry::from_radial_component(component, pd);
+                    Some(BinRows::new(Levels::Generic(component), geometry, scaling.clone()))
+                }
+                _ => None,
+            })
+            .ok_or(Error::NoRadialData)
+    }
+
+    /// Writes [`Radar::bin_rows`] to `writer` as CSV, with a header line of
+    /// [`BIN_COLUMNS`]. `value` and `flag` are empty where there is none.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::bin_rows`], or [`Error::Io`] if writing fails.
+    pub fn write_bins_csv<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let rows = self.bin_rows()?;
+        let mut out = std::io::BufWriter::new(writer);
+        writeln!(out, "{}", BIN_COLUMNS.join(","))?;
+        for row in rows {
+            write!(
+                out,
+                "{:.3},{:.3},{:.5},{:.5},{:.1},{},",
+                row.azimuth, row.range_km, row.latitude, row.longitude, row.beam_height_m, row.raw_level
+            )?;
+            if let Some(value) = row.value {
+                write!(out, "{value}")?;
+            }
+            writeln!(out, ",{}", row.flag.map_or("", |flag| flag.as_str()))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Writes [`Radar::bin_rows`] to `writer` as an Apache Arrow IPC stream,
+    /// in record batches of up to [`ARROW_BATCH_ROWS`] rows.
+    ///
+    /// The columns are [`BIN_COLUMNS`]: `raw_level` is `int32`, `value` a
+    /// nullable `float32`, `flag` a nullable `utf8`, and the rest `float64`.
+    ///
+    /// # Errors
+    ///
+    /// As [`Radar::bin_rows`], or [`Error::Arrow`] if writing fails.
+    #[cfg(feature = "arrow")]
+    pub fn write_bins_arrow<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut rows = self.bin_rows()?.peekable();
+        let schema = std::sync::Arc::new(arrow::schema());
+        let mut stream = arrow_ipc::writer::StreamWriter::try_new(writer, &schema)?;
+        while rows.peek().is_some() {
+            let batch = arrow::batch(&schema, rows.by_ref().take(ARROW_BATCH_ROWS))?;
+            stream.write(&batch)?;
+        }
+        stream.finish()?;
+        Ok(())
+    }
+}
+
+/// Rows per record batch of [`Radar::write_bins_arrow`].
+#[cfg(feature = "arrow")]
+pub const ARROW_BATCH_ROWS: usize = 65_536;
+
+#[cfg(feature = "arrow")]
+mod arrow {
+    use std::sync::Arc;
+
+    use arrow_array::builder::{Float32Builder, Float64Builder, Int32Builder, StringBuilder};
+    use arrow_array::{ArrayRef, RecordBatch};
+    use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+
+    use super::{BinRow, BIN_COLUMNS};
+
+    pub(super) fn schema() -> Schema {
+        let types = [
+            DataType::Float64,
+            DataType::Float64,
+            DataType::Float64,
+            DataType::Float64,
+            DataType::Float64,
+            DataType::Int32,
+            DataType::Float32,
+            DataType::Utf8,
+        ];
+        let fields: Vec<Field> = BIN_COLUMNS
+            .iter()
+            .zip(types)
+            .map(|(name, data_type)| {
+                let nullable = matches!(*name, "value" | "flag");
+                Field::new(*name, data_type, nullable)
+            })
+            .collect();
+        Schema::new(fields)
+    }
+
+    pub(super) fn batch(schema: &SchemaRef, rows: impl Iterator<Item = BinRow>) -> Result<RecordBatch, ArrowError> {
+        let mut floats: [Float64Builder; 5] = Default::default();
+        let mut levels = Int32Builder::new();
+        let mut values = Float32Builder::new();
+        let mut flags = StringBuilder::new();
+        for row in rows {
+            let columns = [row.azimuth, row.range_km, row.latitude, row.longitude, row.beam_height_m];
+            for (builder, value) in floats.iter_mut().zip(columns) {
+                builder.append_value(value);
+            }
+            levels.append_value(row.raw_level);
+            values.append_option(row.value);
+            flags.append_option(row.flag.map(|flag| flag.as_str()));
+        }
+        let mut columns: Vec<ArrayRef> = floats.iter_mut().map(|builder| Arc::new(builder.finish()) as ArrayRef).collect();
+        columns.push(Arc::new(levels.finish()));
+        columns.push(Arc::new(values.finish()));
+        columns.push(Arc::new(flags.finish()));
+        RecordBatch::try_new(schema.clone(), columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldCoordinates, MessageCode, ProductBuilder};
+
+    const P20: &[u8] = include_bytes!("../data/sn_DS.p20-r_kmkx.last");
+    const P99: &[u8] = include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last");
+
+    fn load(bytes: &[u8]) -> Radar {
+        Radar::from_vec(bytes.to_vec()).unwrap().1
+    }
+
+    #[test]
+    fn rows_match_the_decoded_field() {
+        for bytes in [P20, P99] {
+            let radar = load(bytes);
+            let field = radar.data_field().unwrap();
+            let rows = radar.bin_rows().unwrap();
+            assert_eq!(rows.size_hint(), (field.rows * field.cols, Some(field.rows * field.cols)));
+
+            let mut count = 0;
+            for row in rows {
+                let index = row.radial * field.cols + row.bin;
+                assert_eq!(row.raw_level, field.levels[index]);
+                assert_eq!(row.value, field.value(row.radial, row.bin));
+                let expected = match field.mask[index] {
+                    CellMask::Valid => None,
+                    CellMask::BelowThreshold => Some(BinFlag::BelowThreshold),
+                    CellMask::RangeFolded => Some(BinFlag::RangeFolded),
+                    CellMask::NoData => Some(BinFlag::NoData),
+                };
+                assert_eq!(row.flag, expected);
+                count += 1;
+            }
+            assert_eq!(count, field.rows * field.cols);
+        }
+    }
+
+    #[test]
+    fn rows_are_located_at_bin_centres() {
+        let radar = load(P99);
+        let rows: Vec<BinRow> = radar.bin_rows().unwrap().take(3).collect();
+        let FieldCoordinates::Polar(geometry) = radar.data_field().unwrap().coordinates else {
+            panic!("p99 is radial");
+        };
+        let centre = geometry.bin_centre(0, 2).unwrap();
+        assert_eq!((rows[2].latitude, rows[2].longitude), (centre.latitude, centre.longitude));
+        assert_eq!(rows[2].range_km * 1000.0, geometry.bin_range(2));
+        assert_eq!(rows[2].beam_height_m, geometry.bin_height(0, 2).unwrap().centre);
+        let span = geometry.radials[0];
+        assert_eq!(rows[0].azimuth, span.azimuth + span.width / 2.0);
+    }
+
+    #[test]
+    fn csv_has_a_header_and_one_line_per_bin() {
+        let radar = load(P20);
+        let mut out = Vec::new();
+        radar.write_bins_csv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("azimuth,range_km,lat,lon,beam_height_m,raw_level,value,flag"));
+        let lines: Vec<&str> = lines.collect();
+        let field = radar.data_field().unwrap();
+        assert_eq!(lines.len(), field.rows * field.cols);
+        assert!(lines.iter().all(|line| line.split(',').count() == 8));
+        // Level 0 of product 20 is the "ND" threshold code.
+        assert_eq!(lines[0], "50.500,1.000,42.97372,-88.54152,320.3,0,,no_data");
+        assert_eq!(lines[1], "50.500,3.000,42.98516,-88.52254,338.2,8,0,");
+    }
+
+    #[test]
+    fn topped_echo_tops_keep_their_value() {
+        // Data mask 0x7F, scale 1, offset 2, topped mask 0x80.
+        let builder = ProductBuilder::new(MessageCode::EnhancedEchoTops, "KMKX")
+            .with_thresholds([0x7F, 1, 2, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut packet = DigitalRadialPacket::from_values(&[vec![0.0; 3]], &builder.level_scaling().unwrap());
+        packet.radials[0].data = vec![0, 40, 0x80 | 40];
+        let radar = builder
+            .with_layer([SymPacketData::DigitalRadialDataArray(packet)])
+            .build()
+            .unwrap();
+
+        let rows: Vec<BinRow> = radar.bin_rows().unwrap().collect();
+        let flags: Vec<Option<BinFlag>> = rows.iter().map(|row| row.flag).collect();
+        assert_eq!(flags, vec![Some(BinFlag::BelowThreshold), None, Some(BinFlag::Topped)]);
+        assert_eq!(rows[1].value, Some(38.0));
+        assert_eq!(rows[2].value, Some(38.0));
+    }
+}
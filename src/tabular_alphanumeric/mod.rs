@@ -14,7 +14,12 @@ use nom::{
     },
     IResult,
 };
-use tracing::{debug, error, warn};
+use tracing::debug;
+
+use crate::diagnostics::{failure, report, Severity};
+use crate::encode::{latin1_bytes, len_i16, len_i32, put_i16, Encode};
+use crate::error_r::Error;
+use crate::product_symbology::latin1_string;
 
 use crate::message_header::{message_header, MessageHeader};
 use crate::product_description::{product_description, ProductDescription};
@@ -54,17 +59,18 @@ pub struct TabularBlock {
 /// Fails if the block ID is not 3, if the embedded second header blocks are
 /// malformed, or if a line's character count runs past the end of the input.
 pub fn tabular_alphanumeric(input: &[u8]) -> IResult<&[u8], TabularBlock> {
+    let start = input;
     let (input, divider) = nom_i16(Big)(input)?;
     if divider != -1 {
-        error!("Tabular alphanumeric block divider should be -1 but found {divider}");
+        report(
+            Severity::Error,
+            start,
+            format!("Tabular alphanumeric block divider should be -1 but found {divider}"),
+        );
     }
     let (input, id) = nom_i16(Big)(input)?;
     if id != 3 {
-        error!("Tabular alphanumeric block should have ID=3 but found {id}");
-        return Err(nom::Err::Failure(nom::error::Error::new(
-            input,
-            nom::error::ErrorKind::Fail,
-        )));
+        return Err(failure(input, format!("Tabular alphanumeric block should have ID=3 but found {id}")));
     }
     let (input, block_length) = nom_i32(Big)(input)?;
 
@@ -74,9 +80,14 @@ pub fn tabular_alphanumeric(input: &[u8]) -> IResult<&[u8], TabularBlock> {
     let (input, product_description) = product_description(input)?;
 
     // A second divider separates the repeated headers from the data.
+    let data_start = input;
     let (input, data_divider) = nom_i16(Big)(input)?;
     if data_divider != -1 {
-        error!("Tabular data divider should be -1 but found {data_divider}");
+        report(
+            Severity::Error,
+            data_start,
+            format!("Tabular data divider should be -1 but found {data_divider}"),
+        );
     }
 
     let (mut input, num_pages) = nom_i16(Big)(input)?;
@@ -85,11 +96,7 @@ pub fn tabular_alphanumeric(input: &[u8]) -> IResult<&[u8], TabularBlock> {
     let page_count = match usize::try_from(num_pages) {
         Ok(n) => n,
         Err(_) => {
-            error!("Tabular alphanumeric block declares {num_pages} pages");
-            return Err(nom::Err::Failure(nom::error::Error::new(
-                input,
-                nom::error::ErrorKind::Fail,
-            )));
+            return Err(failure(input, format!("Tabular alphanumeric block declares {num_pages} pages")));
         }
     };
 
@@ -129,30 +136,58 @@ fn tabular_page(mut input: &[u8]) -> IResult<&[u8], TabularPage> {
         let len = match usize::try_from(num_chars) {
             Ok(n) => n,
             Err(_) => {
-                error!("Tabular line declares {num_chars} characters");
-                return Err(nom::Err::Failure(nom::error::Error::new(
-                    rest,
-                    nom::error::ErrorKind::Fail,
-                )));
+                return Err(failure(rest, format!("Tabular line declares {num_chars} characters")));
             }
         };
         let (rest, body) = nom::bytes::complete::take(len)(rest)?;
 
         // Character data is 8-bit ASCII; when the MSB is set the remaining 7
-        // bits denote a special symbol rather than a character, so decode
-        // lossily instead of rejecting the line.
-        lines.push(String::from_utf8_lossy(body).into_owned());
+        // bits denote a special symbol rather than a character, so keep each
+        // byte as a character instead of rejecting the line.
+        lines.push(latin1_string(body));
         input = rest;
 
         if lines.len() > MAX_LINES_PER_PAGE {
-            warn!(
-                "Tabular page exceeded the documented maximum of {MAX_LINES_PER_PAGE} lines; \
-                 continuing to the end-of-page flag"
+            report(
+                Severity::Warning,
+                input,
+                format!(
+                    "Tabular page exceeded the documented maximum of {MAX_LINES_PER_PAGE} lines; \
+                     continuing to the end-of-page flag"
+                ),
             );
         }
     }
 }
 
+/// Writes the block with `block_length`, `num_pages` and every line length
+/// recomputed. The repeated message header and product description are
+/// written as stored.
+impl Encode for TabularBlock {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let start = out.len();
+        put_i16(out, self.divider);
+        put_i16(out, self.id);
+        let length_at = out.len();
+        out.extend_from_slice(&[0; 4]);
+        self.message_header.encode(out)?;
+        self.product_description.encode(out)?;
+        put_i16(out, -1);
+        put_i16(out, len_i16("Tabular alphanumeric page count", self.pages.len())?);
+        for page in &self.pages {
+            for line in &page.lines {
+                let bytes = latin1_bytes(line);
+                put_i16(out, len_i16("Tabular line", bytes.len())?);
+                out.extend_from_slice(&bytes);
+            }
+            put_i16(out, -1);
+        }
+        let block_length = len_i32("Tabular alphanumeric block", out.len() - start)?;
+        out[length_at..length_at + 4].copy_from_slice(&block_length.to_be_bytes());
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,15 +1,32 @@
 use serde::{Deserialize, Serialize};
 use nom::{
-    bytes::complete::{tag, take},
-    character::complete::digit1,
-    combinator::map_res,
+    bytes::complete::{tag, take_while, take_while_m_n},
+    character::complete::{digit1, space0},
+    combinator::{map_res, opt, recognize},
+    sequence::preceded,
     IResult, Parser,
 };
 
-/// The WMO/AWIPS text header that precedes every NEXRAD Level 3 product
-/// message.
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+use crate::encode::{put_fixed, Encode};
+use crate::error_r::Error;
+
+/// The WMO/AWIPS text header that precedes a NEXRAD Level 3 product message
+/// as it is distributed.
+///
+/// The general form is an optional SOH and sequence number line (as archive
+/// files from NCEI carry), a WMO abbreviated heading
+/// `T1T2A1A2ii CCCC YYGGgg [BBB]`, and an optional AWIPS product id line.
+/// Feeds that strip the header entirely start at the message header, and
+/// [`crate::Radar::text_header`] is `None` for those.
+///
+/// Two headers are equal when their fields are: [`TextHeader::raw`] is left
+/// out of the comparison.
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TextHeader {
+    /// Sequence number from a leading `SOH` line, e.g. `"123"`.
+    pub sequence: Option<String>,
+    /// WMO data type designator `T1T2A1A2`, e.g. `SDUS` or `NXUS`.
+    pub designator: String,
     /// WMO product category code, e.g. `73` in `SDUS73`.
     pub xx: usize,
     /// Originating radar site identifier, e.g. `KMKX`.
@@ -17,9 +34,59 @@ pub struct TextHeader {
     /// Six digit `DDHHMM` issuance timestamp, as raw text (day/hour/minute
     /// of month, UTC).
     pub timestamp: String,
-    /// First three characters of the six character AWIPS product id.
+    /// The heading's `BBB` indicator for a delayed, corrected or amended
+    /// product, e.g. `RRA` or `CCA`.
+    pub indicator: Option<String>,
+    /// The AWIPS product id line, when present.
+    pub awips: Option<AwipsId>,
+    /// The line ending the heading was read with, `"\r\r\n"` as products
+    /// are transmitted or `"\n"` / `"\r\n"` from files that have been
+    /// through a text tool. A header written from its fields ends every line
+    /// with it.
+    #[serde(default = "transmitted_line_end")]
+    pub line_end: String,
+    /// The bytes the header was read from, `None` for one that was built or
+    /// read back through serde. They are written back unchanged, spacing and
+    /// line endings included, as long as the fields still match them.
+    #[serde(skip)]
+    pub raw: Option<Vec<u8>>,
+}
+
+impl PartialEq for TextHeader {
+    fn eq(&self, other: &Self) -> bool {
+        let TextHeader {
+            sequence,
+            designator,
+            xx,
+            location,
+            timestamp,
+            indicator,
+            awips,
+            line_end,
+            raw: _,
+        } = self;
+        *sequence == other.sequence
+            && *designator == other.designator
+            && *xx == other.xx
+            && *location == other.location
+            && *timestamp == other.timestamp
+            && *indicator == other.indicator
+            && *awips == other.awips
+            && *line_end == other.line_end
+    }
+}
+
+/// The `\r\r\n` line ending products are transmitted with.
+fn transmitted_line_end() -> String {
+    "\r\r\n".to_string()
+}
+
+/// The AWIPS product id that follows the WMO heading, e.g. `N0ZMKX`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AwipsId {
+    /// First three characters: the product category, e.g. `N0Z`.
     pub aaa: String,
-    /// Last three characters of the six character AWIPS product id.
+    /// The rest: the site without its leading letter, e.g. `MKX`.
     pub bbb: String,
 }
 
@@ -29,18 +96,67 @@ fn ascii_string(bytes: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
     Ok(std::str::from_utf8(bytes)?.to_owned())
 }
 
-/// Parses the text header block that begins every NEXRAD Level 3 file.
+/// A line ending: the `\r\r\n` products are transmitted with, or a plain
+/// `\r\n` or `\n` from files that have been through a text tool.
+fn line_end(input: &[u8]) -> IResult<&[u8], String> {
+    map_res(
+        recognize((take_while(|b| b == b'\r'), tag("\n".as_bytes()))),
+        ascii_string,
+    )
+    .parse(input)
+}
+
+/// The `SOH` line and sequence number line that open a WMO bulletin.
+fn sequence_line(input: &[u8]) -> IResult<&[u8], String> {
+    let (input, _) = tag([0x01].as_slice())(input)?;
+    let (input, _) = line_end(input)?;
+    let (input, _) = space0(input)?;
+    let (input, sequence) = map_res(digit1, ascii_string).parse(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = line_end(input)?;
+    Ok((input, sequence))
+}
+
+/// An AWIPS product id line: four to six letters and digits.
+fn awips_line(input: &[u8]) -> IResult<&[u8], AwipsId> {
+    let (input, id) = map_res(
+        take_while_m_n(4, 6, |b: u8| b.is_ascii_alphanumeric()),
+        ascii_string,
+    )
+    .parse(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = line_end(input)?;
+    let (aaa, bbb) = id.split_at(3);
+    Ok((
+        input,
+        AwipsId {
+            aaa: aaa.to_string(),
+            bbb: bbb.to_string(),
+        },
+    ))
+}
+
+/// Parses the text header that begins a NEXRAD Level 3 file.
 ///
-/// Format: `SDUS<xx> <location> <DDHHMM>\r\r\n<aaa><bbb>\r\r\n`
+/// Format: `[SOH\r\r\nnnn\r\r\n]T1T2A1A2<xx> <location> <DDHHMM>[ BBB]\r\r\n[<aaa><bbb>\r\r\n]`
 ///
-/// Never panics: malformed or truncated input yields a `nom` parse error
-/// instead.
+/// Fails, rather than guessing, on anything that is not such a header, which
+/// is how [`crate::Radar::from_vec`] tells a file that starts at the message
+/// header. Never panics: malformed or truncated input yields a `nom` parse
+/// error instead.
 pub fn text_header(input: &[u8]) -> IResult<&[u8], TextHeader> {
-    // remove SDUS from input
-    let (input, _) = tag("SDUS".as_bytes())(input)?;
+    let start = input;
+    let (input, sequence) = opt(sequence_line).parse(input)?;
+
+    // data type designator, four capital letters
+    let (input, designator) = map_res(
+        take_while_m_n(4, 4, |b: u8| b.is_ascii_uppercase()),
+        ascii_string,
+    )
+    .parse(input)?;
 
-    // grab the digits after SDUS (at least one digit is required, since an
-    // empty match here cannot be parsed as a number)
+    // grab the digits after the designator (at least one digit is required,
+    // since an empty match here cannot be parsed as a number)
     let (input, xx) = map_res(digit1, |bytes: &[u8]| -> Result<usize, Box<dyn std::error::Error>> {
         Ok(std::str::from_utf8(bytes)?.parse()?)
     }).parse(input)?;
@@ -49,36 +165,92 @@ pub fn text_header(input: &[u8]) -> IResult<&[u8], TextHeader> {
     let (input, _) = tag(" ".as_bytes())(input)?;
 
     // location
-    let (input, location) = map_res(take(4usize), ascii_string).parse(input)?;
+    let (input, location) = map_res(
+        take_while_m_n(4, 4, |b: u8| b.is_ascii_alphanumeric()),
+        ascii_string,
+    )
+    .parse(input)?;
 
     // space
     let (input, _) = tag(" ".as_bytes())(input)?;
 
     // date
-    let (input, timestamp) = map_res(take(6usize), ascii_string).parse(input)?;
-
-    // line breaks
-    let (input, _) = tag([0x0D, 0x0D, 0x0A].as_slice())(input)?;
+    let (input, timestamp) = map_res(
+        take_while_m_n(6, 6, |b: u8| b.is_ascii_digit()),
+        ascii_string,
+    )
+    .parse(input)?;
 
-    // other bits
-    let (input, aaa) = map_res(take(3usize), ascii_string).parse(input)?;
-    let (input, bbb) = map_res(take(3usize), ascii_string).parse(input)?;
+    // delayed / corrected / amended indicator
+    let (input, indicator) = opt(preceded(
+        tag(" ".as_bytes()),
+        map_res(take_while_m_n(3, 3, |b: u8| b.is_ascii_uppercase()), ascii_string),
+    ))
+    .parse(input)?;
 
     // line breaks
-    let (input, _) = tag([0x0D, 0x0D, 0x0A].as_slice())(input)?;
+    let (input, _) = space0(input)?;
+    let (input, line_end) = line_end(input)?;
+
+    // AWIPS id, or straight on to the binary message
+    let (input, awips) = opt(awips_line).parse(input)?;
+    let raw = start[..start.len() - input.len()].to_vec();
 
     Ok((
         input,
         TextHeader {
+            sequence,
+            designator,
             xx,
             location,
             timestamp,
-            aaa,
-            bbb,
+            indicator,
+            awips,
+            line_end,
+            raw: Some(raw),
         },
     ))
 }
 
+/// Writes back the bytes the header was read from while its fields still
+/// match them. Otherwise the header is written from its fields in the form
+/// of [`text_header`], with `ii` as at least two digits, single spaces, and
+/// every line ended with [`TextHeader::line_end`].
+impl Encode for TextHeader {
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        if let Some(raw) = &self.raw {
+            if text_header(raw).is_ok_and(|(rest, read)| rest.is_empty() && read == *self) {
+                out.extend_from_slice(raw);
+                return Ok(());
+            }
+        }
+        let line_end = self.line_end.as_bytes();
+        if let Some(sequence) = &self.sequence {
+            out.push(0x01);
+            out.extend_from_slice(line_end);
+            out.extend_from_slice(sequence.as_bytes());
+            out.push(b' ');
+            out.extend_from_slice(line_end);
+        }
+        put_fixed(out, "WMO data type designator", self.designator.as_bytes(), 4)?;
+        out.extend_from_slice(format!("{:02} ", self.xx).as_bytes());
+        put_fixed(out, "Text header location", self.location.as_bytes(), 4)?;
+        out.push(b' ');
+        put_fixed(out, "Text header timestamp", self.timestamp.as_bytes(), 6)?;
+        if let Some(indicator) = &self.indicator {
+            out.push(b' ');
+            put_fixed(out, "WMO heading indicator", indicator.as_bytes(), 3)?;
+        }
+        out.extend_from_slice(line_end);
+        if let Some(awips) = &self.awips {
+            put_fixed(out, "AWIPS product category", awips.aaa.as_bytes(), 3)?;
+            out.extend_from_slice(awips.bbb.as_bytes());
+            out.extend_from_slice(line_end);
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,18 +265,98 @@ mod tests {
         assert_eq!(
             header,
             TextHeader {
+                sequence: None,
+                designator: "SDUS".to_string(),
                 xx: 73,
                 location: "KMKX".to_string(),
                 timestamp: "091253".to_string(),
-                aaa: "N0Z".to_string(),
-                bbb: "MKX".to_string(),
+                indicator: None,
+                awips: Some(AwipsId {
+                    aaa: "N0Z".to_string(),
+                    bbb: "MKX".to_string(),
+                }),
+                line_end: "\r\r\n".to_string(),
+                raw: None,
             }
         );
+        assert_eq!(header.raw.as_deref(), Some(SAMPLE_HEADER));
+    }
+
+    #[test]
+    fn encodes_back_to_the_same_bytes() {
+        for bytes in [
+            SAMPLE_HEADER,
+            b"\x01\r\r\n123 \r\r\nSDUS53 KMKX 162134 RRA\r\r\nDHRMKX\r\r\n",
+            b"NXUS63 KMKX 091253\r\r\n",
+            b"SDUS73 KMKX 091253\r\nN0ZMKX\r\n",
+            b"\x01\n123 \nSDUS53 KMKX 162134\nDHRMKX\n",
+            b"\x01\r\r\n 042 \r\r\nSDUS53 KMKX 162134\r\r\nDHRMKX\r\r\n",
+            b"SDUS5 KMKX 091253\r\r\n",
+            b"SDUS053 KMKX 091253  \r\r\nN0ZMKX \r\r\n",
+            b"\x01\r\r\n123\r\nSDUS73 KMKX 091253\nN0ZMKX\r\r\n",
+        ] {
+            let (rest, header) = text_header(bytes).unwrap();
+            assert!(rest.is_empty());
+            let mut out = Vec::new();
+            header.encode(&mut out).unwrap();
+            assert_eq!(out, bytes);
+        }
+    }
+
+    /// Once a field is edited the header is written afresh, every line with
+    /// the heading's line ending.
+    #[test]
+    fn an_edited_header_is_written_from_its_fields() {
+        let (_, mut header) = text_header(b"\x01\r\r\n 042 \r\r\nSDUS5 KMKX 091253 \nN0ZMKX\r\n").unwrap();
+        header.location = "KGRB".to_string();
+        let mut out = Vec::new();
+        header.encode(&mut out).unwrap();
+        assert_eq!(out, b"\x01\n042 \nSDUS05 KGRB 091253\nN0ZMKX\n");
+
+        header.raw = None;
+        header.location = "KMKX".to_string();
+        out.clear();
+        header.encode(&mut out).unwrap();
+        assert_eq!(out, b"\x01\n042 \nSDUS05 KMKX 091253\nN0ZMKX\n");
+    }
+
+    #[test]
+    fn reads_a_leading_soh_and_sequence_number() {
+        let input = b"\x01\r\r\n 042 \r\r\nSDUS53 KMKX 162134\r\r\nDHRMKX\r\r\n\x00\x20";
+        let (rest, header) = text_header(input).unwrap();
+        assert_eq!(header.sequence.as_deref(), Some("042"));
+        assert_eq!(header.awips.unwrap().aaa, "DHR");
+        assert_eq!(rest, [0x00, 0x20]);
+    }
+
+    #[test]
+    fn accepts_any_wmo_designator_and_an_indicator() {
+        let (_, header) = text_header(b"SXUS21 KOUN 011200 CCA\r\r\nN0QOUN\r\r\n").unwrap();
+        assert_eq!(header.designator, "SXUS");
+        assert_eq!(header.xx, 21);
+        assert_eq!(header.indicator.as_deref(), Some("CCA"));
+    }
+
+    /// The message header that follows a header with no AWIPS line starts
+    /// with a zero byte, so it is never mistaken for one.
+    #[test]
+    fn the_awips_line_is_optional() {
+        let (rest, header) = text_header(b"NXUS63 KMKX 091253\r\n\x00\x14").unwrap();
+        assert!(header.awips.is_none());
+        assert_eq!(rest, [0x00, 0x14]);
+    }
+
+    #[test]
+    fn accepts_plain_line_endings() {
+        let (rest, header) = text_header(b"SDUS73 KMKX 091253\nN0ZMKX\n").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(header.awips.unwrap().bbb, "MKX");
     }
 
     #[test]
-    fn rejects_input_missing_the_sdus_tag() {
-        assert!(text_header(b"XDUS73 KMKX 091253\r\r\nN0ZMKX\r\r\n").is_err());
+    fn rejects_a_designator_that_is_not_four_capitals() {
+        assert!(text_header(b"Sdus73 KMKX 091253\r\r\nN0ZMKX\r\r\n").is_err());
+        assert!(text_header(&[0x00, 0x14, 0x4B, 0x2D]).is_err());
     }
 
     #[test]
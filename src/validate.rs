@@ -0,0 +1,252 @@
+//! Consistency checks across the blocks of a product: the declared message
+//! length, block count, block offsets and block lengths against what was
+//! actually found, and the tabular block's repeated headers against the
+//! primary ones.
+//!
+//! The parser tolerates all of these, since a product with a wrong length
+//! field usually still decodes; [`Radar::validate`] is for callers that want
+//! to reject such files anyway, such as an ingest QC gate.
+
+use crate::diagnostics::{Block, Diagnostic, Severity};
+use crate::{Radar, MESSAGE_HEADERS_LEN};
+
+/// Where a parsed product's blocks were found, and how many bytes each took.
+///
+/// Recorded by [`Radar::from_vec_with`] for [`Radar::validate`]; `None` in
+/// [`Radar::layout`] for a product that was built rather than parsed, whose
+/// lengths and offsets [`Radar::to_vec`] computes as it writes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// Length of the text header, 0 if there was none.
+    pub text_header_len: usize,
+    /// Bytes from the start of the message header to the end of the file, as
+    /// received (so compressed, if the product was).
+    pub message_len: usize,
+    /// Bytes after the product description, once decompressed.
+    pub payload_len: usize,
+    /// Bytes the symbology block consumed, when it was parsed.
+    pub symbology_len: Option<usize>,
+    /// Bytes the graphic alphanumeric block consumed, when it was parsed.
+    pub graphic_len: Option<usize>,
+    /// Bytes the tabular alphanumeric block consumed, when it was parsed.
+    pub tabular_len: Option<usize>,
+}
+
+impl Radar {
+    /// Checks that the product's length and offset fields agree with its
+    /// contents, returning a [`Diagnostic`] for each disagreement.
+    ///
+    /// For a parsed product this covers:
+    ///
+    /// - the message header's length against the size of the message;
+    /// - its block count against the blocks present;
+    /// - that each block offset is past the product description, inside the
+    ///   message, in symbology, graphic, tabular order, and that no block runs
+    ///   into the next;
+    /// - that each block's declared length is what parsing it consumed, and
+    ///   that every block with an offset could be parsed;
+    /// - that the message header and product description repeated inside the
+    ///   tabular block identify the same product as the primary ones.
+    ///
+    /// Only the last applies to a product without a [`Layout`]: one built
+    /// with [`crate::ProductBuilder`], or deserialized, since serde does not
+    /// carry [`Radar::layout`] (parse the original bytes again to check
+    /// those). Every finding
+    /// is a [`Severity::Error`] except the block count, which RPGs are known
+    /// to fill in loosely, so a QC gate can reject on any error:
+    ///
+    /// ```no_run
+    /// # let bytes = Vec::new();
+    /// use radar::{Radar, Severity};
+    ///
+    /// let (_, radar) = Radar::from_vec(bytes)?;
+    /// if radar.validate().iter().any(|d| d.severity == Severity::Error) {
+    ///     // quarantine the file
+    /// }
+    /// # Ok::<(), radar::Error>(())
+    /// ```
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut findings = Vec::new();
+        if let Some(layout) = &self.layout {
+            self.validate_layout(layout, &mut findings);
+        }
+        self.validate_tabular_headers(&mut findings);
+        findings
+    }
+
+    fn validate_layout(&self, layout: &Layout, findings: &mut Vec<Diagnostic>) {
+        let message_start = layout.text_header_len;
+        let message_header = &self.message_header;
+        if message_header.length as i64 != layout.message_len as i64 {
+            findings.push(finding(
+                Severity::Error,
+                Block::Header,
+                Some(message_start),
+                format!(
+                    "Message header gives a length of {} bytes but the message is {}",
+                    message_header.length, layout.message_len
+                ),
+            ));
+        }
+
+        let blocks = 2 + [self.symbology.is_some(), self.graphic.is_some(), self.tabular.is_some()]
+            .iter()
+            .filter(|present| **present)
+            .count() as i16;
+        if message_header.nblocks != blocks {
+            findings.push(finding(
+                Severity::Warning,
+                Block::Header,
+                Some(message_start),
+                format!(
+                    "Message header counts {} blocks but the message has {blocks}",
+                    message_header.nblocks
+                ),
+            ));
+        }
+
+        let pd = &self.product_description;
+        let declared = [
+            (
+                Block::Symbology,
+                pd.offset_symbology,
+                self.symbology.as_ref().map(|b| b.header.block_length),
+                layout.symbology_len,
+            ),
+            (
+                Block::Graphic,
+                pd.offset_graphic,
+                self.graphic.as_ref().map(|b| b.block_length),
+                layout.graphic_len,
+            ),
+            (
+                Block::Tabular,
+                pd.offset_tabular,
+                self.tabular.as_ref().map(|b| b.block_length),
+                layout.tabular_len,
+            ),
+        ];
+
+        // Where the previous block ends, in bytes from the message header.
+        let mut previous_end: Option<(Block, i64)> = None;
+        let payload_end = (MESSAGE_HEADERS_LEN + layout.payload_len) as i64;
+        for (block, offset, block_length, consumed) in declared {
+            if offset == 0 {
+                continue;
+            }
+            let start = offset as i64 * 2;
+            let at = usize::try_from(start).ok().map(|start| message_start + start);
+            if start < MESSAGE_HEADERS_LEN as i64 {
+                findings.push(finding(
+                    Severity::Error,
+                    block,
+                    None,
+                    format!("Offset {offset} falls inside the message header and product description"),
+                ));
+                continue;
+            }
+            if start >= payload_end {
+                findings.push(finding(
+                    Severity::Error,
+                    block,
+                    None,
+                    format!("Offset {offset} is past the end of the {payload_end} byte message"),
+                ));
+                continue;
+            }
+            if let Some((previous, end)) = previous_end {
+                if start < end {
+                    findings.push(finding(
+                        Severity::Error,
+                        block,
+                        at,
+                        format!("Offset {offset} is inside the {previous}, which runs to byte {end}"),
+                    ));
+                }
+            }
+
+            let Some(block_length) = block_length else {
+                findings.push(finding(
+                    Severity::Error,
+                    block,
+                    at,
+                    format!("Offset {offset} is given but no block could be parsed there"),
+                ));
+                continue;
+            };
+            if let Some(consumed) = consumed.filter(|c| *c as i64 != block_length as i64) {
+                findings.push(finding(
+                    Severity::Error,
+                    block,
+                    at,
+                    format!("Block length is {block_length} bytes but the block is {consumed}"),
+                ));
+            }
+            let end = start + block_length.max(0) as i64;
+            if end > payload_end {
+                findings.push(finding(
+                    Severity::Error,
+                    block,
+                    at,
+                    format!("Block length {block_length} runs past the end of the {payload_end} byte message"),
+                ));
+            }
+            previous_end = Some((block, end));
+        }
+    }
+
+    fn validate_tabular_headers(&self, findings: &mut Vec<Diagnostic>) {
+        let Some(tabular) = &self.tabular else {
+            return;
+        };
+        let (primary, repeated) = (&self.product_description, &tabular.product_description);
+        let mut differing = Vec::new();
+        if tabular.message_header.code != self.message_header.code {
+            differing.push("message code");
+        }
+        for (field, same) in [
+            ("product code", primary.product_code == repeated.product_code),
+            (
+                "radar position",
+                (primary.latitude, primary.longitude, primary.height)
+                    == (repeated.latitude, repeated.longitude, repeated.height),
+            ),
+            (
+                "volume scan time",
+                (primary.vol_scan_date, primary.vol_scan_time)
+                    == (repeated.vol_scan_date, repeated.vol_scan_time),
+            ),
+            (
+                "product generation time",
+                (primary.product_date, primary.product_time)
+                    == (repeated.product_date, repeated.product_time),
+            ),
+        ] {
+            if !same {
+                differing.push(field);
+            }
+        }
+        if !differing.is_empty() {
+            findings.push(finding(
+                Severity::Error,
+                Block::Tabular,
+                None,
+                format!(
+                    "The tabular block's repeated headers disagree with the primary ones on {}",
+                    differing.join(", ")
+                ),
+            ));
+        }
+    }
+}
+
+fn finding(severity: Severity, block: Block, offset: Option<usize>, message: String) -> Diagnostic {
+    Diagnostic {
+        severity,
+        block,
+        layer: None,
+        packet_code: None,
+        offset,
+        message,
+    }
+}
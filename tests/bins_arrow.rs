@@ -0,0 +1,51 @@
+//! Reads the Arrow IPC stream `Radar::write_bins_arrow` writes back with
+//! `arrow-ipc`'s own reader. Only built with the `arrow` feature.
+#![cfg(feature = "arrow")]
+
+use std::io::Cursor;
+
+use arrow_array::{Array, Float32Array, Float64Array, Int32Array, StringArray};
+use arrow_ipc::reader::StreamReader;
+use arrow_schema::DataType;
+use radar::{BinRow, Radar, ARROW_BATCH_ROWS, BIN_COLUMNS};
+
+const P99: &[u8] = include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last");
+
+#[test]
+fn the_stream_reads_back_row_for_row() {
+    let radar = Radar::from_vec(P99.to_vec()).unwrap().1;
+    let mut bytes = Vec::new();
+    radar.write_bins_arrow(&mut bytes).unwrap();
+    let expected: Vec<BinRow> = radar.bin_rows().unwrap().collect();
+
+    let reader = StreamReader::try_new(Cursor::new(bytes), None).unwrap();
+    let schema = reader.schema();
+    let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+    assert_eq!(names, BIN_COLUMNS);
+    assert_eq!(schema.field(5).data_type(), &DataType::Int32);
+    assert!(schema.field(6).is_nullable());
+
+    let batches: Vec<_> = reader.map(Result::unwrap).collect();
+    assert_eq!(batches.len(), expected.len().div_ceil(ARROW_BATCH_ROWS));
+    assert!(batches.iter().all(|batch| batch.num_rows() <= ARROW_BATCH_ROWS));
+
+    let mut rows = expected.iter();
+    for batch in &batches {
+        let column = |i: usize| batch.column(i).as_any().downcast_ref::<Float64Array>().unwrap().clone();
+        let (azimuth, range, lat, lon, height) = (column(0), column(1), column(2), column(3), column(4));
+        let levels = batch.column(5).as_any().downcast_ref::<Int32Array>().unwrap();
+        let values = batch.column(6).as_any().downcast_ref::<Float32Array>().unwrap();
+        let flags = batch.column(7).as_any().downcast_ref::<StringArray>().unwrap();
+        for i in 0..batch.num_rows() {
+            let row = rows.next().unwrap();
+            assert_eq!(azimuth.value(i), row.azimuth);
+            assert_eq!(range.value(i), row.range_km);
+            assert_eq!((lat.value(i), lon.value(i)), (row.latitude, row.longitude));
+            assert_eq!(height.value(i), row.beam_height_m);
+            assert_eq!(levels.value(i), row.raw_level);
+            assert_eq!(values.is_valid(i).then(|| values.value(i)), row.value);
+            assert_eq!(flags.is_valid(i).then(|| flags.value(i)), row.flag.map(|flag| flag.as_str()));
+        }
+    }
+    assert!(rows.next().is_none());
+}
@@ -0,0 +1,106 @@
+//! Integration test for decompression: halfword 51 decides whether the data
+//! after the product description is bzip2, halfwords 52-53 are checked
+//! against what it decompresses to, and a corrupt or mislabelled payload is
+//! reported as such rather than as a packet that fails to parse.
+
+use std::io::Write;
+
+use bzip2::write::BzEncoder;
+use radar::{EncodeOptions, Error, ParseOptions, Radar, Severity};
+
+const DHR: &[u8] = include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last");
+
+/// Where the data after the product description starts in the fixture.
+const PAYLOAD: usize = 150;
+/// Byte offset of halfword 51 in the fixture: the 30 byte text header, then
+/// halfwords counted from the message header.
+const HALFWORD_51: usize = 30 + 50 * 2;
+
+fn parse(bytes: Vec<u8>) -> Result<radar::Parsed, Error> {
+    Radar::from_vec_with(bytes, &ParseOptions::default())
+}
+
+fn uncompressed_payload() -> Vec<u8> {
+    let (_, radar) = Radar::from_vec(DHR.to_vec()).unwrap();
+    let bytes = radar.to_vec_with(&EncodeOptions::new().with_compression(false)).unwrap();
+    bytes[PAYLOAD..].to_vec()
+}
+
+fn bzip2(data: &[u8]) -> Vec<u8> {
+    let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::new(1));
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn a_compressed_fixture_decompresses_without_diagnostics() {
+    let parsed = parse(DHR.to_vec()).unwrap();
+    assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+    assert!(parsed.radar.symbology.is_some());
+}
+
+#[test]
+fn every_stream_of_a_multi_stream_payload_is_read() {
+    let payload = uncompressed_payload();
+    let (first, second) = payload.split_at(payload.len() / 2);
+    let mut bytes = DHR[..PAYLOAD].to_vec();
+    bytes.extend(bzip2(first));
+    bytes.extend(bzip2(second));
+
+    let parsed = parse(bytes).unwrap();
+    assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+    let (_, original) = Radar::from_vec(DHR.to_vec()).unwrap();
+    assert_eq!(parsed.radar.symbology, original.symbology);
+}
+
+#[test]
+fn a_truncated_download_is_a_decompression_error() {
+    let bytes = DHR[..DHR.len() - 200].to_vec();
+    assert!(matches!(parse(bytes), Err(Error::Decompress(_))));
+}
+
+#[test]
+fn claiming_compression_over_uncompressed_data_is_an_error() {
+    let mut bytes = DHR[..PAYLOAD].to_vec();
+    bytes.extend(uncompressed_payload());
+    assert_eq!(bytes[HALFWORD_51..HALFWORD_51 + 2], [0, 1]);
+    assert!(matches!(parse(bytes), Err(Error::NotBzip2)));
+}
+
+#[test]
+fn an_undefined_compression_method_is_an_error() {
+    let mut bytes = DHR.to_vec();
+    bytes[HALFWORD_51 + 1] = 7;
+    assert!(matches!(parse(bytes), Err(Error::CompressionMethod(7))));
+}
+
+#[test]
+fn a_size_mismatch_is_reported() {
+    let mut bytes = DHR.to_vec();
+    // Halfword 53, the low half of the uncompressed size.
+    bytes[HALFWORD_51 + 5] ^= 0x01;
+    let parsed = parse(bytes.clone()).unwrap();
+    let mismatch = parsed
+        .diagnostics
+        .iter()
+        .find(|d| d.message.contains("halfwords 52-53"))
+        .expect("a size mismatch diagnostic");
+    assert_eq!(mismatch.severity, Severity::Error);
+    assert_eq!(mismatch.offset, Some(PAYLOAD));
+
+    assert!(matches!(
+        Radar::from_vec_with(bytes, &ParseOptions::new().strict()),
+        Err(Error::Strict(_))
+    ));
+}
+
+/// With halfword 51 cleared, the data is taken as is rather than sniffed.
+#[test]
+fn an_uncompressed_product_with_compression_halfwords_is_read_as_is() {
+    let (_, radar) = Radar::from_vec(DHR.to_vec()).unwrap();
+    let bytes = radar.to_vec_with(&EncodeOptions::new().with_compression(false)).unwrap();
+    assert_eq!(bytes[HALFWORD_51..HALFWORD_51 + 2], [0, 0]);
+    let parsed = parse(bytes).unwrap();
+    assert!(parsed.diagnostics.is_empty());
+    assert_eq!(parsed.radar.symbology, radar.symbology);
+}
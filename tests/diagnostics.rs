@@ -0,0 +1,88 @@
+//! Integration test for `Radar::from_vec_with`: the anomalies a lenient parse
+//! tolerates come back as `Diagnostic`s placed by block and file offset, and
+//! `ParseOptions` decides which of them fail the parse instead.
+
+use radar::{Block, Error, MessageCode, ParseOptions, Radar, Severity};
+
+fn sample_bytes() -> Vec<u8> {
+    include_bytes!("../data/sn_DS.p20-r_kmkx.last").to_vec()
+}
+
+/// File offset of the product description divider: the 30 byte text header
+/// plus the 18 byte message header.
+const DESCRIPTION_DIVIDER: usize = 48;
+
+/// File offset of the product version byte (halfword 54, high byte).
+const VERSION: usize = 136;
+
+fn sample_with_bad_divider() -> Vec<u8> {
+    let mut bytes = sample_bytes();
+    bytes[DESCRIPTION_DIVIDER..DESCRIPTION_DIVIDER + 2].copy_from_slice(&7i16.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn a_clean_file_has_no_diagnostics() {
+    let parsed = Radar::from_vec_with(sample_bytes(), &ParseOptions::default()).unwrap();
+    assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+    assert!(parsed.radar.symbology.is_some());
+}
+
+#[test]
+fn a_bad_divider_is_reported_with_its_block_and_offset() {
+    let parsed = Radar::from_vec_with(sample_with_bad_divider(), &ParseOptions::default())
+        .expect("a lenient parse tolerates a divider mismatch");
+
+    assert_eq!(parsed.diagnostics.len(), 1);
+    let diagnostic = &parsed.diagnostics[0];
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.block, Block::Header);
+    assert_eq!(diagnostic.offset, Some(DESCRIPTION_DIVIDER));
+    assert!(diagnostic.message.contains("found 7"), "{}", diagnostic.message);
+    assert!(parsed.radar.symbology.is_some(), "the rest of the file still parses");
+}
+
+#[test]
+fn from_vec_still_tolerates_what_from_vec_with_reports() {
+    assert!(Radar::from_vec(sample_with_bad_divider()).is_ok());
+}
+
+#[test]
+fn strict_mode_rejects_the_first_error() {
+    let err = Radar::from_vec_with(sample_with_bad_divider(), &ParseOptions::new().strict())
+        .unwrap_err();
+    match err {
+        Error::Strict(diagnostic) => assert_eq!(diagnostic.offset, Some(DESCRIPTION_DIVIDER)),
+        other => panic!("expected Error::Strict, got {other:?}"),
+    }
+}
+
+#[test]
+fn unvalidated_products_are_a_warning_unless_rejected() {
+    let mut bytes = sample_bytes();
+    bytes[30..32].copy_from_slice(&37i16.to_be_bytes());
+    bytes[60..62].copy_from_slice(&37i16.to_be_bytes());
+
+    let parsed = Radar::from_vec_with(bytes.clone(), &ParseOptions::new().strict())
+        .expect("a warning does not fail a strict parse");
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].severity, Severity::Warning);
+
+    let err = Radar::from_vec_with(bytes, &ParseOptions::new().reject_unvalidated()).unwrap_err();
+    assert!(matches!(err, Error::UnvalidatedProduct(MessageCode::CompositeReflectivity0p54Nmi)), "{err:?}");
+}
+
+#[test]
+fn newer_versions_are_rejected_unless_allowed() {
+    let mut bytes = sample_bytes();
+    bytes[VERSION] = 99;
+
+    let err = Radar::from_vec_with(bytes.clone(), &ParseOptions::default()).unwrap_err();
+    assert!(matches!(err, Error::SupportedVersion(99, Some(_))), "{err:?}");
+
+    let parsed = Radar::from_vec_with(bytes, &ParseOptions::new().allow_newer_versions())
+        .expect("newer versions parse when allowed");
+    assert_eq!(parsed.radar.product_description.version, 99);
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].severity, Severity::Warning);
+}
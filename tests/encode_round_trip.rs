@@ -0,0 +1,113 @@
+//! Integration test for `Radar::to_vec`: every fixture in `data/` encodes back
+//! to the bytes it was parsed from, compressed or not, and a product whose
+//! blocks are edited or added still encodes to a file that parses back to it.
+
+use radar::{
+    EncodeOptions, GraphicBlock, GraphicPage, PacketCode, Radar, SymPacketData, TabularBlock,
+    TabularPage, TextPacket,
+};
+
+const FIXTURES: [&[u8]; 4] = [
+    include_bytes!("../data/sn_DS.p20-r_kmkx.last"),
+    include_bytes!("../data/sn_DC.radar_DS.56rm1_KMKX.last"),
+    include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last"),
+    include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"),
+];
+
+fn parse(bytes: Vec<u8>) -> Radar {
+    Radar::from_vec(bytes).expect("file should parse").1
+}
+
+#[test]
+fn every_fixture_encodes_to_its_own_bytes() {
+    for (i, fixture) in FIXTURES.iter().enumerate() {
+        let encoded = parse(fixture.to_vec()).to_vec().expect("fixture should encode");
+        assert_eq!(encoded.len(), fixture.len(), "fixture {i} length");
+        assert!(encoded == *fixture, "fixture {i} differs from its encoding");
+    }
+}
+
+#[test]
+fn compression_can_be_switched_either_way() {
+    for fixture in FIXTURES {
+        let radar = parse(fixture.to_vec());
+        for compress in [false, true] {
+            let options = EncodeOptions::new().with_compression(compress);
+            let bytes = radar.to_vec_with(&options).unwrap();
+            let reparsed = parse(bytes);
+            assert_eq!(reparsed.symbology, radar.symbology);
+        }
+    }
+}
+
+#[test]
+fn added_alphanumeric_blocks_are_located_by_the_new_offsets() {
+    let mut radar = parse(FIXTURES[0].to_vec());
+    radar.graphic = Some(GraphicBlock {
+        divider: -1,
+        id: 2,
+        pages: vec![GraphicPage {
+            page_number: 1,
+            packets: vec![SymPacketData::TextAndSpecialSymbol8(TextPacket {
+                packet_code: PacketCode::TextAndSpecialSymbol8,
+                color_level: Some(3),
+                i_coord: 10,
+                j_coord: 20,
+                text: "STORM ID  A1".into(),
+                ..Default::default()
+            })],
+            ..Default::default()
+        }],
+        ..Default::default()
+    });
+    radar.tabular = Some(TabularBlock {
+        divider: -1,
+        id: 3,
+        block_length: 0,
+        message_header: radar.message_header.clone(),
+        product_description: radar.product_description.clone(),
+        num_pages: 0,
+        pages: vec![TabularPage {
+            lines: vec!["STORM TOTAL RAINFALL".into(), "\u{b0}DEGREES".into()],
+        }],
+    });
+
+    let reparsed = parse(radar.to_vec().unwrap());
+
+    let graphic = reparsed.graphic.expect("graphic block should be found");
+    assert_eq!(graphic.num_pages, 1);
+    match &graphic.pages[0].packets[..] {
+        [SymPacketData::TextAndSpecialSymbol8(text)] => {
+            assert_eq!(text.text, "STORM ID  A1");
+            assert_eq!(text.color_level, Some(3));
+            // Recomputed from what was written, not taken from the struct.
+            assert_eq!(text.length, 6 + 12);
+        }
+        other => panic!("expected one text packet, got {other:?}"),
+    }
+    assert!(graphic.pages[0].undecoded.is_empty());
+
+    let tabular = reparsed.tabular.expect("tabular block should be found");
+    assert_eq!(tabular.pages, radar.tabular.unwrap().pages);
+    assert_eq!(reparsed.symbology, radar.symbology);
+}
+
+#[test]
+fn an_edited_radial_is_written_with_its_new_length() {
+    let mut radar = parse(FIXTURES[0].to_vec());
+    let symbology = radar.symbology.as_mut().unwrap();
+    let SymPacketData::RadialDataAF1F(packet) = &mut symbology.layers[0][0] else {
+        panic!("expected a radial packet");
+    };
+    packet.radials[0].data.truncate(3);
+    let expected = packet.radials[0].data.clone();
+
+    let reparsed = parse(radar.to_vec().unwrap());
+    let SymPacketData::RadialDataAF1F(packet) = &reparsed.symbology.unwrap().layers[0][0] else {
+        panic!("expected a radial packet");
+    };
+    // Three runs are padded with an empty fourth to fill two halfwords.
+    assert_eq!(packet.radials[0].header.num_halfwords, 2);
+    assert_eq!(packet.radials[0].data[..3], expected[..]);
+    assert_eq!(packet.radials.len(), 360);
+}
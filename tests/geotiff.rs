@@ -0,0 +1,186 @@
+//! Reads the GeoTIFFs `Radar::to_geotiff` writes back with the `tiff` crate,
+//! checking the pixels, the georeferencing tags and the GeoKeys that GDAL
+//! uses to pick the CRS.
+
+use std::io::Cursor;
+
+use radar::{
+    Error, GeoTiffOptions, Grid, MessageCode, PrecipArrayHeader, PrecipArrayPacket, ProductBuilder, Radar, RasterPacket,
+    RasterPacketHeader, Resampling, Run, Site, SymPacketData,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+const P20: &[u8] = include_bytes!("../data/sn_DS.p20-r_kmkx.last");
+
+fn parse(bytes: &[u8]) -> Radar {
+    Radar::from_vec(bytes.to_vec()).expect("file should parse").1
+}
+
+struct GeoTiff {
+    width: u32,
+    height: u32,
+    pixels: DecodingResult,
+    pixel_scale: Vec<f64>,
+    tiepoint: Vec<f64>,
+    /// `(key, location, count, value)` for each GeoKey.
+    keys: Vec<[u16; 4]>,
+    doubles: Vec<f64>,
+    nodata: Option<String>,
+}
+
+impl GeoTiff {
+    fn read(bytes: Vec<u8>) -> GeoTiff {
+        let mut decoder = Decoder::new(Cursor::new(bytes)).expect("should be a TIFF");
+        let (width, height) = decoder.dimensions().unwrap();
+        let directory = decoder.get_tag_u16_vec(Tag::GeoKeyDirectoryTag).expect("GeoKey directory");
+        let keys = directory[4..].chunks_exact(4).map(|k| [k[0], k[1], k[2], k[3]]).collect();
+        GeoTiff {
+            width,
+            height,
+            pixel_scale: decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag).unwrap(),
+            tiepoint: decoder.get_tag_f64_vec(Tag::ModelTiepointTag).unwrap(),
+            keys,
+            doubles: decoder.get_tag_f64_vec(Tag::GeoDoubleParamsTag).unwrap_or_default(),
+            nodata: decoder.get_tag_ascii_string(Tag::GdalNodata).ok(),
+            pixels: decoder.read_image().expect("pixels should decode"),
+        }
+    }
+
+    fn key(&self, id: u16) -> Option<[u16; 4]> {
+        self.keys.iter().find(|k| k[0] == id).copied()
+    }
+
+    fn double_key(&self, id: u16) -> f64 {
+        let key = self.key(id).expect("key present");
+        assert_eq!(key[1], 34736, "key {id} should be in the double parameters");
+        self.doubles[key[3] as usize]
+    }
+}
+
+#[test]
+fn a_radial_product_is_gridded_about_the_radar_by_default() {
+    let radar = parse(P20);
+    let tiff = GeoTiff::read(radar.to_geotiff(&GeoTiffOptions::new()).unwrap());
+
+    // 230 two-kilometre bins, gridded at 2 km out to 460 km.
+    assert_eq!((tiff.width, tiff.height), (460, 460));
+    assert_eq!(tiff.pixel_scale, vec![2000.0, 2000.0, 0.0]);
+    assert_eq!(tiff.tiepoint, vec![0.0, 0.0, 0.0, -460_000.0, 460_000.0, 0.0]);
+    assert_eq!(tiff.nodata.as_deref(), Some("-9999"));
+
+    assert_eq!(tiff.key(1024).unwrap()[3], 1, "projected model");
+    assert_eq!(tiff.key(3075).unwrap()[3], 12, "azimuthal equidistant");
+    assert_eq!(tiff.key(3076).unwrap()[3], 9001, "metres");
+    let site = Site::from_description(&radar.product_description);
+    assert_eq!(tiff.double_key(3089), site.latitude);
+    assert_eq!(tiff.double_key(3088), site.longitude);
+
+    let DecodingResult::F32(values) = tiff.pixels else {
+        panic!("values should be float32");
+    };
+    let gridded = radar.grid(&Grid::cartesian(460.0, 2.0).unwrap(), Resampling::Nearest).unwrap();
+    for (written, expected) in values.iter().zip(&gridded.values) {
+        if expected.is_nan() {
+            assert_eq!(*written, -9999.0);
+        } else {
+            assert_eq!(written, expected);
+        }
+    }
+    assert!(values.iter().any(|v| *v > 0.0), "some echo should be written");
+}
+
+#[test]
+fn latitude_longitude_grids_are_written_in_epsg_4326() {
+    let radar = parse(P20);
+    let grid = Grid::lat_lon(42.0, -90.0, 44.0, -87.0, 0.05).unwrap();
+    let options = GeoTiffOptions::new()
+        .with_grid(grid, Resampling::BilinearPolar)
+        .with_nodata(-32768.0);
+    let tiff = GeoTiff::read(radar.to_geotiff(&options).unwrap());
+
+    assert_eq!((tiff.width, tiff.height), (60, 40));
+    assert_eq!(tiff.tiepoint[3..5], [-90.0, 44.0]);
+    assert_eq!(tiff.pixel_scale[0], 0.05);
+    assert_eq!(tiff.key(1024).unwrap()[3], 2, "geographic model");
+    assert_eq!(tiff.key(2048).unwrap()[3], 4326);
+    assert_eq!(tiff.nodata.as_deref(), Some("-32768"));
+}
+
+#[test]
+fn levels_are_written_as_uint8_without_nodata() {
+    let radar = parse(P20);
+    let tiff = GeoTiff::read(radar.to_geotiff(&GeoTiffOptions::new().with_levels()).unwrap());
+    let DecodingResult::U8(levels) = tiff.pixels else {
+        panic!("levels should be uint8");
+    };
+    assert_eq!(levels.len(), 460 * 460);
+    assert!(levels.iter().any(|l| *l > 0));
+    assert_eq!(tiff.nodata, None);
+}
+
+#[test]
+fn rasters_are_written_on_their_own_cells() {
+    let run = |run, level| Run { run, level };
+    let raster = RasterPacket {
+        header: RasterPacketHeader {
+            packet_code: 0xBA0Fu16 as i16,
+            packet_code_2: 0x8000u16 as i16,
+            packet_code_3: 0x00C0,
+            packing_descriptor: 2,
+            i_start: -8,
+            j_start: -4,
+            x_scale_int: 4,
+            y_scale_int: 2,
+            num_rows: 2,
+            ..Default::default()
+        },
+        rows: vec![vec![run(2, 3), run(1, 7)], vec![run(3, 1)]],
+    };
+    let bytes = ProductBuilder::new(MessageCode::EchoTops, "KMKX")
+        .with_layer([SymPacketData::RasterData(raster)])
+        .to_vec()
+        .unwrap();
+    let tiff = GeoTiff::read(parse(&bytes).to_geotiff(&GeoTiffOptions::new().with_levels()).unwrap());
+
+    assert_eq!((tiff.width, tiff.height), (3, 2));
+    // I -8, J -4: 2 km west and 1 km north of the radar.
+    assert_eq!(tiff.tiepoint[3..5], [-2000.0, 1000.0]);
+    assert_eq!(tiff.pixel_scale[..2], [1000.0, 500.0]);
+    assert_eq!(tiff.key(3075).unwrap()[3], 12);
+    let DecodingResult::U8(levels) = tiff.pixels else {
+        panic!("levels should be uint8");
+    };
+    assert_eq!(levels, vec![3, 3, 7, 1, 1, 1]);
+
+    // A grid only applies to radial products; the raster keeps its cells.
+    let gridded = GeoTiffOptions::new()
+        .with_levels()
+        .with_grid(Grid::cartesian(10.0, 1.0).unwrap(), Resampling::Nearest);
+    let tiff = GeoTiff::read(parse(&bytes).to_geotiff(&gridded).unwrap());
+    assert_eq!((tiff.width, tiff.height), (3, 2));
+}
+
+#[test]
+fn precipitation_arrays_are_not_georeferenced() {
+    let run = |run, level| Run { run, level };
+    let precip = PrecipArrayPacket {
+        header: PrecipArrayHeader {
+            packet_code: 17,
+            num_boxes: 4,
+            num_rows: 2,
+            ..Default::default()
+        },
+        rows: vec![vec![run(4, 10)], vec![run(4, 20)]],
+    };
+    let bytes = ProductBuilder::new(MessageCode::HourlyDigitalPrecipitationArray, "KMKX")
+        .with_position(42.968, -88.551, 1022)
+        .with_layer([SymPacketData::DigitalPrecipitationDataArray(precip)])
+        .to_vec()
+        .unwrap();
+
+    // The 1/4 LFM grid is polar stereographic about an origin the product
+    // does not give.
+    let result = parse(&bytes).to_geotiff(&GeoTiffOptions::new());
+    assert!(matches!(result, Err(Error::LfmGrid)), "{result:?}");
+}
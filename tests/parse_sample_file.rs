@@ -16,11 +16,15 @@ fn parses_the_bundled_fixture_end_to_end() {
     // description followed by one fully-consumed symbology block.
     assert!(leftover.is_empty(), "expected no leftover bytes, found {}", leftover.len());
 
-    assert_eq!(radar.text_header.location, "KMKX");
-    assert_eq!(radar.text_header.aaa, "N0Z");
-    assert_eq!(radar.text_header.bbb, "MKX");
+    let text_header = radar.text_header.as_ref().expect("fixture has a text header");
+    assert_eq!(text_header.designator, "SDUS");
+    assert_eq!(text_header.location, "KMKX");
+    let awips = text_header.awips.as_ref().expect("fixture has an AWIPS line");
+    assert_eq!(awips.aaa, "N0Z");
+    assert_eq!(awips.bbb, "MKX");
 
     assert_eq!(radar.message_header.code, MessageCode::BaseReflectivity20);
+    assert!(radar.validated, "product 20 is on the validated list");
     assert_eq!(radar.product_description.product_code, 20);
     assert_eq!(radar.product_description.offset_symbology, 60);
     assert_eq!(radar.product_description.offset_graphic, 0);
@@ -31,8 +35,9 @@ fn parses_the_bundled_fixture_end_to_end() {
     let symbology = radar.symbology.expect("product declares a symbology block");
     assert_eq!(symbology.header.layers, 1);
     assert_eq!(symbology.layers.len(), 1);
+    assert_eq!(symbology.layers[0].len(), 1, "the layer holds a single packet");
 
-    match &symbology.layers[0] {
+    match &symbology.layers[0][0] {
         SymPacketData::RadialDataAF1F(packet) => {
             assert_eq!(packet.header.num_radials, 360);
             assert_eq!(packet.header.num_bins, 230);
@@ -54,10 +59,39 @@ fn plot_succeeds_on_the_bundled_fixture() {
         .expect("plotting a real, fully-parsed product should not fail");
 }
 
+/// The fixture with its message code and product code rewritten to `code`.
+fn sample_bytes_as_product(code: i16) -> Vec<u8> {
+    let mut bytes = sample_bytes();
+    bytes[30..32].copy_from_slice(&code.to_be_bytes()); // message header code
+    bytes[60..62].copy_from_slice(&code.to_be_bytes()); // product description code
+    bytes
+}
+
+/// Composite reflectivity (37) is not on the validated list, but every packet
+/// it uses has a parser, so it parses and is flagged rather than rejected.
+#[test]
+fn parses_an_unvalidated_product_and_flags_it() {
+    let (_, radar) = Radar::from_vec(sample_bytes_as_product(37))
+        .expect("an unvalidated product should still parse");
+
+    assert_eq!(radar.message_header.code, MessageCode::CompositeReflectivity0p54Nmi);
+    assert!(!radar.validated);
+    assert!(radar.symbology.is_some());
+}
+
+/// Table II control messages are not products, and are still rejected.
+#[test]
+fn rejects_a_message_code_that_is_not_a_product() {
+    let err = Radar::from_vec(sample_bytes_as_product(2)).unwrap_err();
+    assert!(matches!(err, radar::Error::ProductType(MessageCode::GeneralStatus)));
+}
+
 #[test]
 fn rejects_a_file_that_is_too_short_to_be_nexrad_level_3() {
     let err = Radar::from_vec(vec![0u8; 10]).unwrap_err();
-    assert!(matches!(err, radar::Error::TooShort { expected: 150, actual: 10 }));
+    // Ten zero bytes are no text header, so the message header would start
+    // at once.
+    assert!(matches!(err, radar::Error::TooShort { expected: 120, actual: 10 }));
 }
 
 #[test]
@@ -70,3 +104,72 @@ fn rejects_a_file_that_is_not_nexrad_level_3_at_all() {
 fn rejects_an_empty_file_instead_of_panicking() {
     assert!(Radar::from_vec(Vec::new()).is_err());
 }
+
+/// A corrupted packet fails with the block, layer, packet code and file
+/// offset where parsing stopped, not just a formatted `nom` error.
+#[test]
+fn a_corrupt_packet_names_where_parsing_failed() {
+    let mut bytes = sample_bytes();
+    // The first radial's RLE halfword count: 150 header bytes, a 10 byte
+    // symbology header, a 6 byte layer header and the 14 byte AF1F header.
+    bytes[180..182].copy_from_slice(&(-1i16).to_be_bytes());
+
+    let err = Radar::from_vec(bytes).unwrap_err();
+    let radar::Error::Parse(parse) = err else {
+        panic!("expected a parse error, got {err:?}");
+    };
+    assert_eq!(parse.block, Some(radar::Block::Symbology));
+    assert_eq!(parse.layer, Some(0));
+    assert_eq!(parse.packet_code, Some(radar::PacketCode::RadialDataAF1F));
+    // Just past the 6 byte radial header.
+    assert_eq!(parse.offset, Some(186));
+    assert_eq!(parse.detail, "Radial declares an invalid RLE halfword count (-1)");
+}
+
+#[test]
+fn a_truncated_file_reports_running_out_of_data() {
+    let mut bytes = sample_bytes();
+    bytes.truncate(200);
+
+    let err = Radar::from_vec(bytes).unwrap_err();
+    let radar::Error::Parse(parse) = err else {
+        panic!("expected a parse error, got {err:?}");
+    };
+    assert_eq!(parse.block, Some(radar::Block::Symbology));
+    assert!(parse.detail.starts_with("expected more data"), "{}", parse.detail);
+}
+
+/// Feeds that strip the WMO header start straight at the message header.
+#[test]
+fn parses_a_file_with_no_text_header() {
+    let bytes = sample_bytes();
+    let (_, with_header) = Radar::from_vec(bytes.clone()).unwrap();
+    let parsed = Radar::from_vec_with(bytes[30..].to_vec(), &Default::default()).unwrap();
+
+    assert!(parsed.radar.text_header.is_none());
+    assert_eq!(parsed.radar.symbology, with_header.symbology);
+    assert!(parsed
+        .diagnostics
+        .iter()
+        .any(|d| d.offset == Some(0) && d.message.contains("No WMO/AWIPS text header")));
+    // Nothing is invented on the way back out.
+    assert_eq!(parsed.radar.to_vec().unwrap(), bytes[30..]);
+}
+
+/// An NCEI archive file: SOH and sequence number, a different WMO heading,
+/// and no AWIPS line.
+#[test]
+fn parses_a_soh_prefixed_file_with_another_heading() {
+    let bytes = sample_bytes();
+    let mut archived = b"\x01\r\r\n123 \r\r\nNXUS63 KMKX 091253\r\r\n".to_vec();
+    archived.extend_from_slice(&bytes[30..]);
+
+    let (leftover, radar) = Radar::from_vec(archived.clone()).unwrap();
+    assert!(leftover.is_empty());
+    let text_header = radar.text_header.as_ref().unwrap();
+    assert_eq!(text_header.sequence.as_deref(), Some("123"));
+    assert_eq!(text_header.designator, "NXUS");
+    assert!(text_header.awips.is_none());
+    assert_eq!(radar.message_header.code, MessageCode::BaseReflectivity20);
+    assert_eq!(radar.to_vec().unwrap(), archived);
+}
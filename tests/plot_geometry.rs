@@ -350,3 +350,382 @@ fn digital_data_array_levels_map_to_distinct_colors() {
     assert_ne!(b, c);
     assert_ne!(a, c);
 }
+
+/// Overwrites the index of first range bin and the I/J centre of sweep in the
+/// packet header of a synthetic file, which the builders leave at 0.
+fn with_packet_offsets(mut file: Vec<u8>, first_bin: i16, i_center: i16, j_center: i16) -> Vec<u8> {
+    // 150 header bytes, then the 10-byte block header and 6-byte layer header;
+    // the packet code comes first, then the three fields.
+    let packet = 166;
+    file[packet + 2..packet + 4].copy_from_slice(&first_bin.to_be_bytes());
+    file[packet + 6..packet + 8].copy_from_slice(&i_center.to_be_bytes());
+    file[packet + 8..packet + 10].copy_from_slice(&j_center.to_be_bytes());
+    file
+}
+
+fn true_scale(km: f64) -> radar::PlotOptions {
+    radar::PlotOptions::new().without_panel().with_max_range_km(km)
+}
+
+/// At true scale, 230 one-kilometre bins out of a 460 km image radius fill
+/// exactly half of it.
+#[test]
+fn true_scale_draws_bins_at_their_range() {
+    let rendered = Rendered::with_options(synthetic_af1f_file(230, &[(0, 11)]), &true_scale(460.0));
+
+    assert_eq!(rendered.sample_at_azimuth(0.5, 0.45), [0xFF, 0xFF, 0x00]);
+    assert_eq!(rendered.sample_at_azimuth(0.5, 0.55), BACKGROUND);
+}
+
+/// A packet whose first bin is 115 starts 115 km out, not at the radar.
+#[test]
+fn true_scale_applies_the_index_of_first_range_bin() {
+    let file = with_packet_offsets(synthetic_af1f_file(230, &[(0, 11)]), 115, 0, 0);
+    let rendered = Rendered::with_options(file, &true_scale(460.0));
+
+    assert_eq!(rendered.sample_at_azimuth(0.5, 0.2), BACKGROUND);
+    for frac in [0.3, 0.5, 0.7] {
+        assert_eq!(rendered.sample_at_azimuth(0.5, frac), [0xFF, 0xFF, 0x00], "at {frac}");
+    }
+    assert_eq!(rendered.sample_at_azimuth(0.5, 0.8), BACKGROUND);
+}
+
+/// Product 99's 0.25 km bins cover a quarter of the range of product 56's
+/// 1 km bins, so at one scale its 230 bins stop a quarter of the way out.
+#[test]
+fn true_scale_products_with_different_bin_sizes_line_up() {
+    let fine = Rendered::with_options(synthetic_packet16_file(99, 230, &[(0, 200)]), &true_scale(230.0));
+    let coarse = Rendered::with_options(synthetic_af1f_file(230, &[(0, 11)]), &true_scale(230.0));
+
+    assert_ne!(fine.sample_at_azimuth(0.5, 0.2), BACKGROUND);
+    assert_eq!(fine.sample_at_azimuth(0.5, 0.3), BACKGROUND);
+    assert_eq!(coarse.sample_at_azimuth(0.5, 0.3), [0xFF, 0xFF, 0x00]);
+    assert_eq!(coarse.sample_at_azimuth(0.5, 0.95), [0xFF, 0xFF, 0x00]);
+}
+
+/// The I/J centre of sweep is in 1/4 km units, I increasing east: I = 400 moves
+/// the radar 100 km right of the image centre.
+#[test]
+fn true_scale_centres_the_sweep_on_its_i_j_centre() {
+    let file = with_packet_offsets(synthetic_af1f_file(230, &[(2700, 3)]), 0, 400, 0);
+    let rendered = Rendered::with_options(file, &true_scale(230.0));
+
+    let (xc, yc) = rendered.center();
+    let r_max = rendered.img.height() as f64 * 0.458;
+    // 50 km west of the radar, which is 50 km east of the image centre.
+    let x = xc + (50.0 / 230.0 * r_max).round() as i64;
+    let p = rendered.img.get_pixel(x as u32, yc as u32);
+    assert_eq!([p[0], p[1], p[2]], [0x32, 0x00, 0x96]);
+}
+
+/// Rings are drawn at their true range; 50 km rings on a 100 km plot put the
+/// first one halfway out.
+#[test]
+fn range_rings_are_drawn_at_their_range() {
+    let options = true_scale(100.0)
+        .with_range_rings(radar::RangeRings::new(50.0, radar::DistanceUnits::Kilometres).with_spoke_spacing(0.0));
+    let rendered = Rendered::with_options(synthetic_af1f_file(230, &[(1800, 9)]), &options);
+
+    let (xc, yc) = rendered.center();
+    let r = (rendered.img.height() as f64 * 0.458 * 0.5).round() as i64;
+    let ring = (yc - r - 1..=yc - r + 1).any(|y| {
+        let p = rendered.img.get_pixel(xc as u32, y as u32);
+        [p[0], p[1], p[2]] == [150, 150, 150]
+    });
+    assert!(ring, "the 50 km ring should cross north halfway out");
+    assert_eq!(rendered.sample_at_azimuth(45.0, 0.25), BACKGROUND);
+}
+
+/// A digital reflectivity sweep of 80 one-kilometre bins in layer 0, with a
+/// 40 km line due north of the radar in layer 1.
+fn sweep_with_overlay() -> Vec<u8> {
+    use radar::{DigitalRadialPacket, MessageCode, Point, ProductBuilder, UnlinkedVectorPacket, Vector};
+
+    let builder = ProductBuilder::new(MessageCode::DigitalHybridScanReflectivity, "KMKX")
+        .with_linear_scaling(0.5, -32.0);
+    let scaling = builder.level_scaling().unwrap();
+    let values = vec![vec![30.0; 80]; 360];
+    builder
+        .with_layer([DigitalRadialPacket::from_values(&values, &scaling).into()])
+        .with_layer([UnlinkedVectorPacket {
+            value: None,
+            vectors: vec![Vector {
+                begin: Point { i: 0, j: 0 },
+                end: Point { i: 0, j: -160 },
+            }],
+        }
+        .into()])
+        .to_vec()
+        .unwrap()
+}
+
+impl Rendered {
+    /// Whether any pixel within one of `(x, y)` is `color`.
+    fn has_near(&self, (x, y): (i64, i64), color: [u8; 3]) -> bool {
+        (x - 1..=x + 1).any(|x| {
+            (y - 1..=y + 1).any(|y| {
+                let p = self.img.get_pixel(x as u32, y as u32);
+                [p[0], p[1], p[2]] == color
+            })
+        })
+    }
+}
+
+const ANNOTATION: [u8; 3] = [255, 255, 255];
+
+/// Every layer is drawn, annotations over data: the line in layer 1 shows on
+/// top of the sweep in layer 0.
+#[test]
+fn annotation_layers_are_drawn_over_data_layers() {
+    let rendered = Rendered::of(sweep_with_overlay());
+    let (xc, yc) = rendered.center();
+    // 80 km of bins fill the circle, so the 40 km line ends halfway out.
+    let r = rendered.img.height() as f64 * 0.458;
+
+    assert!(rendered.has_near((xc, yc - (0.25 * r) as i64), ANNOTATION));
+    assert!(!rendered.has_near((xc, yc - (0.75 * r) as i64), ANNOTATION));
+    assert_ne!(rendered.sample_at_azimuth(90.0, 0.5), BACKGROUND);
+}
+
+#[test]
+fn hidden_layers_are_left_out() {
+    let without_overlay = Rendered::with_options(
+        sweep_with_overlay(),
+        &radar::PlotOptions::new().without_panel().with_hidden_layer(1),
+    );
+    let (xc, yc) = without_overlay.center();
+    let r = without_overlay.img.height() as f64 * 0.458;
+    assert!(!without_overlay.has_near((xc, yc - (0.25 * r) as i64), ANNOTATION));
+    assert_ne!(without_overlay.sample_at_azimuth(90.0, 0.5), BACKGROUND);
+
+    let without_data = Rendered::with_options(
+        sweep_with_overlay(),
+        &radar::PlotOptions::new().without_panel().with_hidden_layer(0),
+    );
+    assert!(without_data.has_near((xc, yc - (0.25 * r) as i64), ANNOTATION));
+    assert_eq!(without_data.sample_at_azimuth(90.0, 0.5), BACKGROUND);
+}
+
+/// An echo tops product (41) whose `BA0F` raster spans 100 km either side of
+/// the radar in 1 km cells, a different table colour in each quadrant.
+fn quadrant_raster() -> Vec<u8> {
+    use radar::{MessageCode, ProductBuilder, RasterPacket, RasterPacketHeader, Run};
+
+    // Runs are 4-bit, so each half row is ten runs of ten cells.
+    let half = |level| vec![Run { run: 10, level }; 10];
+    let row = |west: u16, east: u16| [half(west), half(east)].concat();
+    let rows = (0..200)
+        .map(|r| if r < 100 { row(11, 3) } else { row(9, 13) })
+        .collect();
+    let packet = RasterPacket {
+        header: RasterPacketHeader {
+            packet_code: 0xBA0Fu16 as i16,
+            packet_code_2: 0x8000u16 as i16,
+            packet_code_3: 0x00C0,
+            i_start: -400,
+            j_start: -400,
+            x_scale_int: 4,
+            y_scale_int: 4,
+            num_rows: 200,
+            packing_descriptor: 2,
+            ..Default::default()
+        },
+        rows,
+    };
+    ProductBuilder::new(MessageCode::EchoTops, "KMKX")
+        .with_layer([packet.into()])
+        .to_vec()
+        .unwrap()
+}
+
+/// Raster rows run north to south from J start and columns west to east from
+/// I start, coloured by the product's table.
+#[test]
+fn raster_packets_are_drawn_from_their_start_and_scale() {
+    let rendered = Rendered::of(quadrant_raster());
+
+    // The raster's 100 km half-width fills the circle.
+    assert_eq!(rendered.sample_at_azimuth(315.0, 0.5), [0xFF, 0xFF, 0x00]);
+    assert_eq!(rendered.sample_at_azimuth(45.0, 0.5), [0x00, 0xE0, 0xFF]);
+    assert_eq!(rendered.sample_at_azimuth(225.0, 0.5), [0x00, 0xEF, 0x00]);
+    assert_eq!(rendered.sample_at_azimuth(135.0, 0.5), [0xFF, 0x00, 0x00]);
+}
+
+#[test]
+fn raster_packets_honour_true_scale() {
+    let rendered = Rendered::with_options(quadrant_raster(), &true_scale(200.0));
+
+    assert_eq!(rendered.sample_at_azimuth(359.5, 0.45), [0xFF, 0xFF, 0x00]);
+    assert_eq!(rendered.sample_at_azimuth(359.5, 0.55), BACKGROUND);
+}
+
+/// Packet 33's 8-bit levels go through the product's level scaling and ramp,
+/// as a digital radial array's do.
+#[test]
+fn digital_raster_arrays_are_coloured_like_digital_radials() {
+    use radar::{DigitalRasterHeader, DigitalRasterPacket, MessageCode, ProductBuilder};
+
+    let builder = ProductBuilder::new(MessageCode::DigitalHybridScanReflectivity, "KMKX")
+        .with_linear_scaling(0.5, -32.0);
+    let level = builder.level_scaling().unwrap().level(30.0);
+    let packet = DigitalRasterPacket {
+        header: DigitalRasterHeader {
+            packet_code: 33,
+            i_start: -320,
+            j_start: -320,
+            i_scale: 4,
+            j_scale: 4,
+            num_cells: 160,
+            num_rows: 160,
+        },
+        rows: vec![vec![level; 160]; 160],
+    };
+    let raster = Rendered::of(builder.with_layer([packet.into()]).to_vec().unwrap());
+    let radial = Rendered::of(sweep_with_overlay());
+
+    let color = raster.sample_at_azimuth(90.0, 0.5);
+    assert_ne!(color, BACKGROUND);
+    assert_eq!(color, radial.sample_at_azimuth(90.0, 0.5));
+}
+
+/// Packet 17's 1/4 LFM boxes are drawn centred on the radar.
+#[test]
+fn precipitation_arrays_are_drawn_about_the_radar() {
+    use radar::{MessageCode, PrecipArrayHeader, PrecipArrayPacket, ProductBuilder, Run, SymPacketData};
+
+    let packet = PrecipArrayPacket {
+        header: PrecipArrayHeader { packet_code: 17, num_boxes: 13, num_rows: 13, ..Default::default() },
+        rows: (0..13)
+            .map(|r| vec![Run { run: 13, level: if r == 6 { 200 } else { 0 } }])
+            .collect(),
+    };
+    let file = ProductBuilder::new(MessageCode::DigitalStormTotalPrecipitation, "KMKX")
+        .with_linear_scaling(0.01, 0.0)
+        .with_layer([SymPacketData::DigitalPrecipitationDataArray(packet)])
+        .to_vec()
+        .unwrap();
+    let rendered = Rendered::of(file);
+
+    // The middle row of boxes runs east-west through the radar.
+    let middle = rendered.sample_at_azimuth(90.0, 0.5);
+    assert_eq!(rendered.sample_at_azimuth(270.0, 0.5), middle);
+    assert_ne!(rendered.sample_at_azimuth(0.0, 0.5), middle);
+    assert_ne!(rendered.sample_at_azimuth(0.0, 0.5), BACKGROUND);
+}
+
+/// A product holding nothing but one overlay layer, which the plot scales to
+/// the default 230 km.
+fn overlay_only(packets: Vec<radar::SymPacketData>) -> Vec<u8> {
+    radar::ProductBuilder::new(radar::MessageCode::StormTrackingInformation, "KMKX")
+        .with_layer(packets)
+        .to_vec()
+        .unwrap()
+}
+
+#[test]
+fn tvs_and_mesocyclone_symbols_are_drawn_in_their_colours() {
+    use radar::{CircleSymbol, PointSymbol, SpecialSymbolPacket};
+
+    let rendered = Rendered::of(overlay_only(vec![
+        SpecialSymbolPacket::Position {
+            packet_code: 12,
+            symbols: vec![PointSymbol { i_position: 0, j_position: 0 }],
+        }
+        .into(),
+        // A 10 km mesocyclone 50 km east of the radar.
+        SpecialSymbolPacket::Mesocyclone {
+            packet_code: 3,
+            symbols: vec![CircleSymbol { i_position: 200, j_position: 0, radius: 40 }],
+        }
+        .into(),
+    ]));
+    let (xc, yc) = rendered.center();
+    let per_km = rendered.img.height() as f64 * 0.458 / 230.0;
+
+    assert!(rendered.has_near((xc, yc), [255, 0, 0]), "the TVS triangle sits on its point");
+    let east_edge = xc + (60.0 * per_km).round() as i64;
+    assert!(rendered.has_near((east_edge, yc), [255, 255, 0]), "the circle has a 10 km radius");
+    assert!(!rendered.has_near((xc + (50.0 * per_km) as i64, yc), [255, 255, 0]));
+}
+
+/// SCIT forecast data holds its track as a nested linked vector packet.
+#[test]
+fn scit_tracks_are_drawn_from_their_nested_packets() {
+    use radar::SpecialSymbolPacket;
+
+    // Packet 6: code, byte length, start I/J, one end I/J.
+    let mut nested = Vec::new();
+    for value in [6i16, 8, 0, 0, 0, 400] {
+        nested.extend_from_slice(&value.to_be_bytes());
+    }
+    let rendered = Rendered::of(overlay_only(vec![SpecialSymbolPacket::ScitData { packet_code: 23, nested }.into()]));
+    let (xc, yc) = rendered.center();
+    let per_km = rendered.img.height() as f64 * 0.458 / 230.0;
+
+    // The track runs 100 km due south.
+    for km in [20.0, 50.0, 80.0] {
+        assert!(rendered.has_near((xc, yc + (km * per_km) as i64), [255, 255, 255]), "at {km} km");
+    }
+    assert!(!rendered.has_near((xc, yc - (50.0 * per_km) as i64), [255, 255, 255]));
+}
+
+fn parsed(file: Vec<u8>) -> radar::Radar {
+    radar::Radar::from_vec(file).expect("synthetic file should parse").1
+}
+
+#[test]
+fn in_memory_rendering_matches_the_written_png() {
+    let file = synthetic_af1f_file(10, &[(0, 5), (900, 12)]);
+    let options = radar::PlotOptions::new().with_image_size(300);
+    let written = Rendered::with_options(file.clone(), &options.clone().without_panel());
+    let radar = parsed(file);
+
+    let rgba = radar.render_rgba(&options.clone().without_panel()).expect("render");
+    assert_eq!(rgba.dimensions(), written.img.dimensions());
+    assert!(rgba.pixels().all(|p| p[3] == u8::MAX), "opaque unless asked otherwise");
+    assert_eq!(image::DynamicImage::ImageRgba8(rgba).to_rgb8(), written.img);
+
+    let png = radar.render_png_bytes(&options).expect("png bytes");
+    let decoded = image::load_from_memory(&png).expect("bytes should decode as a png");
+    assert_eq!(decoded.height(), 300);
+    assert!(decoded.width() > 300, "the panel is included");
+}
+
+#[test]
+fn transparent_background_leaves_only_the_data_opaque() {
+    let radar = parsed(synthetic_af1f_file(10, &[(900, 12)]));
+    let options = radar::PlotOptions::new()
+        .without_panel()
+        .with_image_size(300)
+        .with_transparent_background();
+    let rgba = radar.render_rgba(&options).expect("render");
+
+    let (xc, yc) = (150u32, 150u32);
+    assert_eq!(rgba.get_pixel(xc - 60, yc)[3], 0, "background should be transparent");
+    let wedge = rgba.get_pixel(xc + 60, yc);
+    assert_eq!(wedge[3], u8::MAX, "data should be opaque");
+
+    let written = Rendered::with_options(
+        synthetic_af1f_file(10, &[(900, 12)]),
+        &radar::PlotOptions::new().without_panel().with_image_size(300),
+    );
+    let expected = written.img.get_pixel(xc + 60, yc);
+    assert_eq!(&wedge.0[..3], &expected.0[..], "data keeps its colour");
+}
+
+#[test]
+fn svg_output_draws_the_same_plot_as_vectors() {
+    let radar = parsed(synthetic_af1f_file(10, &[(0, 5)]));
+    let options = radar::PlotOptions::new().with_image_size(300);
+
+    let svg = radar.render_svg(&options).expect("svg");
+    assert!(svg.starts_with("<svg"), "should be an SVG document: {}", &svg[..40]);
+    assert!(svg.contains("height=\"300\""), "the SVG should be the requested size");
+    assert!(svg.contains("<polygon"), "bins should be drawn as polygons");
+    assert!(svg.contains("<rect"), "the radar image background is filled");
+
+    let transparent = radar
+        .render_svg(&options.without_panel().with_transparent_background())
+        .expect("svg");
+    assert!(!transparent.contains("<rect"), "no background should be drawn");
+}
@@ -0,0 +1,124 @@
+//! Integration test for `ProductBuilder`: products assembled from a field of
+//! values and a few overlay packets encode to files that parse back to the
+//! same values.
+
+use chrono::{TimeZone, Utc};
+use radar::{
+    DigitalRadialPacket, LinkedVectorPacket, MessageCode, Point, PointSymbol, ProductBuilder,
+    Radar, RadialPacket, SpecialSymbolPacket, SymPacketData, TextPacket,
+};
+
+fn parse(bytes: Vec<u8>) -> Radar {
+    Radar::from_vec(bytes).expect("built file should parse").1
+}
+
+/// A field that rises by 1 dBZ per bin out to 60 dBZ, with no data beyond.
+fn field(radials: usize) -> Vec<Vec<f32>> {
+    (0..radials)
+        .map(|_| (0..80).map(|bin| if bin < 60 { bin as f32 } else { f32::NAN }).collect())
+        .collect()
+}
+
+#[test]
+fn a_built_digital_product_decodes_to_the_field_it_was_given() {
+    let builder = ProductBuilder::new(MessageCode::DigitalHybridScanReflectivity, "KMKX")
+        .with_awips_id(53, "DHR")
+        .with_position(42.968, -88.551, 1022)
+        .with_times(
+            Utc.with_ymd_and_hms(2022, 9, 16, 21, 30, 0).unwrap(),
+            Utc.with_ymd_and_hms(2022, 9, 16, 21, 34, 0).unwrap(),
+        )
+        .with_linear_scaling(0.5, -32.0);
+    let scaling = builder.level_scaling().unwrap();
+    let values = field(360);
+
+    let radar = parse(
+        builder
+            .with_layer([DigitalRadialPacket::from_values(&values, &scaling).into()])
+            .with_layer([
+                TextPacket::new(40, -40, "CELL A1", Some(5)).into(),
+                LinkedVectorPacket {
+                    value: None,
+                    start: Point { i: 0, j: 0 },
+                    ends: vec![Point { i: 100, j: 0 }, Point { i: 100, j: 100 }],
+                }
+                .into(),
+                SpecialSymbolPacket::Position {
+                    packet_code: 12,
+                    symbols: vec![PointSymbol { i_position: -8, j_position: 16 }],
+                }
+                .into(),
+            ])
+            .to_vec()
+            .unwrap(),
+    );
+
+    let text_header = radar.text_header.as_ref().unwrap();
+    assert_eq!(text_header.location, "KMKX");
+    assert_eq!(text_header.timestamp, "162134");
+    assert_eq!(radar.message_header.code, MessageCode::DigitalHybridScanReflectivity);
+    assert_eq!(radar.message_header.datetime.to_string(), "2022-09-16 21:34:00 UTC");
+    assert!(radar.validated);
+
+    let decoded = radar.product_description.level_scaling().unwrap();
+    let symbology = radar.symbology.unwrap();
+    let SymPacketData::DigitalRadialDataArray(packet) = &symbology.layers[0][0] else {
+        panic!("expected a digital radial packet");
+    };
+    assert_eq!(packet.radials.len(), 360);
+    for radial in &packet.radials {
+        for (bin, level) in radial.data.iter().enumerate() {
+            let want = values[0][bin];
+            match decoded.value(*level) {
+                Some(got) => assert_eq!(got, want, "bin {bin}"),
+                None => assert!(want.is_nan(), "bin {bin} lost its value"),
+            }
+        }
+    }
+
+    match &symbology.layers[1][..] {
+        [SymPacketData::TextAndSpecialSymbol8(text), SymPacketData::LinkedVector(line), SymPacketData::SpecialGraphicSymbol(symbol)] =>
+        {
+            assert_eq!((text.text.as_str(), text.color_level), ("CELL A1", Some(5)));
+            assert_eq!(line.ends.len(), 2);
+            assert_eq!(symbol.packet_code(), 12);
+        }
+        other => panic!("unexpected overlay layer {other:?}"),
+    }
+}
+
+#[test]
+fn a_built_threshold_table_product_uses_run_length_radials() {
+    // Level 0 is below threshold, then 5 dBZ steps from 5 dBZ.
+    let mut thresholds = [0i16; 16];
+    thresholds[0] = 0x8001u16 as i16;
+    for (level, threshold) in thresholds.iter_mut().enumerate().skip(1) {
+        *threshold = level as i16 * 5;
+    }
+    let builder = ProductBuilder::new(MessageCode::BaseReflectivity19, "KMKX")
+        .with_thresholds(thresholds)
+        .with_elevation_number(1);
+    let scaling = builder.level_scaling().unwrap();
+
+    let radar = parse(
+        builder
+            .with_layer([RadialPacket::from_values(&field(360), &scaling).into()])
+            .to_vec()
+            .unwrap(),
+    );
+
+    let SymPacketData::RadialDataAF1F(packet) = &radar.symbology.unwrap().layers[0][0] else {
+        panic!("expected an AF1F packet");
+    };
+    let levels: Vec<u8> = packet.radials[0]
+        .data
+        .iter()
+        .flat_map(|run| std::iter::repeat_n(run.color, run.run as usize))
+        .collect();
+    assert_eq!(levels.len(), 80);
+    assert_eq!(levels[4], 0);
+    assert_eq!(levels[5], 1);
+    assert_eq!(levels[59], 11);
+    assert_eq!(levels[79], 0);
+    assert_eq!(radar.product_description.elevation_num, 1);
+}
@@ -0,0 +1,140 @@
+//! Integration test for `Radar::validate`: the bundled products are
+//! internally consistent, and a file whose length, offset or block length
+//! fields disagree with its contents is flagged, even though it still parses.
+
+use radar::{Block, EncodeOptions, MessageCode, Radar, Severity, TabularBlock};
+
+const FIXTURES: [&[u8]; 4] = [
+    include_bytes!("../data/sn_DS.p20-r_kmkx.last"),
+    include_bytes!("../data/sn_DC.radar_DS.56rm1_KMKX.last"),
+    include_bytes!("../data/sn_DC.radar_DS.32dhr_KMKX.last"),
+    include_bytes!("../data/sn_DC.radar_DS.p99v0_KMKX.last"),
+];
+
+/// An uncompressed fixture, so that block fields can be edited in place.
+const VELOCITY: &[u8] = include_bytes!("../data/sn_DC.radar_DS.56rm1_KMKX.last");
+/// Byte offset of the message length (halfwords 5-6) in the fixture, after
+/// its 30 byte text header.
+const MESSAGE_LENGTH: usize = 30 + 8;
+/// Byte offset of the symbology block offset (halfwords 55-56).
+const SYMBOLOGY_OFFSET: usize = 30 + 54 * 2;
+/// Byte offset of the graphic block offset (halfwords 57-58).
+const GRAPHIC_OFFSET: usize = 30 + 56 * 2;
+/// Byte offset of the symbology block's length, after its divider and id.
+const SYMBOLOGY_LENGTH: usize = 150 + 4;
+
+fn edited(at: usize, value: i32) -> Vec<u8> {
+    let mut bytes = VELOCITY.to_vec();
+    bytes[at..at + 4].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+fn errors(bytes: Vec<u8>) -> Vec<(Block, String)> {
+    let (_, radar) = Radar::from_vec(bytes).unwrap();
+    radar
+        .validate()
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| (d.block, d.message))
+        .collect()
+}
+
+#[test]
+fn the_bundled_fixtures_are_consistent() {
+    for fixture in FIXTURES {
+        let (_, radar) = Radar::from_vec(fixture.to_vec()).unwrap();
+        assert!(radar.layout.is_some());
+        assert_eq!(radar.validate(), vec![]);
+    }
+}
+
+/// The layout does not survive serde, and is no part of a product's
+/// equality, so a round trip still compares equal.
+#[test]
+fn a_serde_round_trip_has_no_layout_but_is_equal() {
+    let (_, radar) = Radar::from_vec(FIXTURES[0].to_vec()).unwrap();
+    let json = serde_json::to_string(&radar).unwrap();
+    let back: Radar = serde_json::from_str(&json).unwrap();
+    assert!(back.layout.is_none());
+    assert_eq!(back, radar);
+    assert_eq!(back.validate(), vec![]);
+}
+
+#[test]
+fn an_encoded_product_is_consistent() {
+    for fixture in FIXTURES {
+        let (_, radar) = Radar::from_vec(fixture.to_vec()).unwrap();
+        let bytes = radar.to_vec_with(&EncodeOptions::new().with_compression(false)).unwrap();
+        let (_, radar) = Radar::from_vec(bytes).unwrap();
+        assert_eq!(radar.validate(), vec![]);
+    }
+}
+
+#[test]
+fn a_wrong_message_length_is_an_error() {
+    let length = VELOCITY.len() as i32 - 30;
+    let errors = errors(edited(MESSAGE_LENGTH, length + 2));
+
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].0, Block::Header);
+    assert!(errors[0].1.contains(&format!("{} bytes but the message is {length}", length + 2)));
+}
+
+#[test]
+fn a_symbology_offset_inside_the_headers_is_an_error() {
+    let errors = errors(edited(SYMBOLOGY_OFFSET, 10));
+
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].0, Block::Symbology);
+    assert!(errors[0].1.contains("inside the message header"), "{}", errors[0].1);
+}
+
+#[test]
+fn a_graphic_offset_past_the_end_is_an_error() {
+    let errors = errors(edited(GRAPHIC_OFFSET, VELOCITY.len() as i32));
+
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].0, Block::Graphic);
+    assert!(errors[0].1.contains("past the end"), "{}", errors[0].1);
+}
+
+#[test]
+fn a_graphic_offset_inside_the_symbology_block_is_an_error() {
+    // The symbology data there does not parse as a graphic block either, so
+    // that is reported too.
+    let errors = errors(edited(GRAPHIC_OFFSET, 60 + 8));
+
+    assert!(errors.iter().all(|(block, _)| *block == Block::Graphic), "{errors:?}");
+    assert!(errors.iter().any(|(_, m)| m.contains("inside the symbology")), "{errors:?}");
+}
+
+#[test]
+fn a_wrong_symbology_block_length_is_an_error() {
+    let (_, radar) = Radar::from_vec(VELOCITY.to_vec()).unwrap();
+    let length = radar.symbology.unwrap().header.block_length;
+    let errors = errors(edited(SYMBOLOGY_LENGTH, length - 2));
+
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].0, Block::Symbology);
+    assert!(errors[0].1.contains(&format!("{} bytes but the block is {length}", length - 2)));
+}
+
+#[test]
+fn a_tabular_block_for_another_product_is_an_error() {
+    let (_, mut radar) = Radar::from_vec(VELOCITY.to_vec()).unwrap();
+    let mut message_header = radar.message_header.clone();
+    message_header.code = MessageCode::BaseReflectivity19;
+    radar.tabular = Some(TabularBlock {
+        divider: -1,
+        id: 3,
+        block_length: 0,
+        message_header,
+        product_description: radar.product_description.clone(),
+        num_pages: 0,
+        pages: Vec::new(),
+    });
+
+    let tabular: Vec<_> = radar.validate().into_iter().filter(|d| d.block == Block::Tabular).collect();
+    assert_eq!(tabular.len(), 1, "{tabular:?}");
+    assert!(tabular[0].message.ends_with("on message code"), "{}", tabular[0].message);
+}